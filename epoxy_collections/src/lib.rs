@@ -7,4 +7,7 @@ pub use base_collection::{ReactiveCollection, ReadonlyReactiveCollection};
 pub use mutations::*;
 pub use reactive_container_item::ReactiveContainerItem;
 pub use reactive_hash_map::reactive_hash_map::ReactiveHashMap;
+pub use reactive_hash_map::reactive_hash_map_query::{Query, QueryChange, QueryResult};
+#[cfg(feature = "sled")]
+pub use reactive_hash_map::reactive_hash_map_sled::{SledLoadError, SledWriteError};
 pub use reactive_hash_map::readonly_reactive_hash_map::ReadonlyReactiveHashMap;
@@ -1,3 +1,7 @@
 pub mod reactive_hash_map;
+pub mod reactive_hash_map_aggregations;
 pub mod reactive_hash_map_operators;
+pub mod reactive_hash_map_query;
+#[cfg(feature = "sled")]
+pub mod reactive_hash_map_sled;
 pub mod readonly_reactive_hash_map;
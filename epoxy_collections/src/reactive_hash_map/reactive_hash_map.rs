@@ -1,5 +1,7 @@
 use super::readonly_reactive_hash_map::ReadonlyReactiveHashMap;
-use crate::base_collection::{ReactiveCollectionInternal, ReadonlyReactiveCollectionInternal};
+use crate::base_collection::{
+    ReactiveCollectionInternal, ReadonlyReactiveCollection, ReadonlyReactiveCollectionInternal,
+};
 use crate::mutations::Mutation::{Property, Subproperty};
 use crate::mutations::{Mutation, PropertyMutation, SubpropertyMutation};
 use crate::reactive_container_item::ReactiveContainerItem;
@@ -470,3 +472,21 @@ where
         }
     }
 }
+
+impl<KeyType, ValueType> epoxy_streams::Observable<Mutation> for ReactiveHashMap<KeyType, ValueType>
+where
+    KeyType: Hash,
+    KeyType: Eq,
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: ReactiveContainerItem,
+    ValueType: Sync,
+    ValueType: Send,
+    ValueType: 'static,
+{
+    fn changes(&self) -> epoxy_streams::Stream<Mutation> {
+        self.get_mutation_stream()
+    }
+}
@@ -0,0 +1,298 @@
+use super::reactive_hash_map::ReactiveHashMap;
+use crate::base_collection::ReadonlyReactiveCollection;
+use crate::mutations::Mutation;
+use crate::mutations::Mutation::{Property, Subproperty};
+use crate::reactive_container_item::ReactiveContainerItem;
+use epoxy_streams::{ReactiveValue, ReadonlyReactiveValue, Stream};
+use std::cmp::Eq;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::{Add, Sub};
+use std::sync::{Arc, Mutex};
+
+fn mutation_key<KeyType: Clone + 'static>(mutation: &Mutation) -> KeyType {
+    match mutation {
+        Property(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+        Subproperty(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+        _ => panic!("Invalid mutation type for ReactiveHashMap"),
+    }
+}
+
+/// Keeps an aggregate up to date from the mutations of a map. `update` is subscribed to the
+/// mutations before `init` takes the initial snapshot, and both run with `state` locked, so a
+/// concurrent mutation is part of the snapshot, seen by `update` afterwards, or both. Since the
+/// collection is written before its mutations are emitted, `update` has to read the entries it
+/// needs from the live map rather than trust the mutation, which also makes seeing a mutation
+/// twice harmless.
+fn aggregate<S, R, U, I>(
+    mutations: Stream<Mutation>,
+    state: S,
+    update: U,
+    init: I,
+) -> ReadonlyReactiveValue<R>
+where
+    S: Send + 'static,
+    R: Clone + Send + Sync + 'static,
+    U: Fn(&mut S, &Mutation) -> Option<R> + Send + Sync + 'static,
+    I: FnOnce(&mut S) -> R,
+{
+    let state = Arc::new(Mutex::new(state));
+    let update_state = state.clone();
+    let updates = mutations
+        .map(move |mutation| update(&mut update_state.lock().unwrap(), mutation))
+        .some_values();
+    let mut state = state.lock().unwrap();
+    let initial = init(&mut state);
+    updates.to_reactive_value_with_default(initial)
+}
+
+impl<KeyType, ValueType> ReactiveHashMap<KeyType, ValueType>
+where
+    KeyType: Hash,
+    KeyType: Eq,
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: ReactiveContainerItem,
+    ValueType: Sync,
+    ValueType: Send,
+    ValueType: 'static,
+{
+    /// Returns a ReadonlyReactiveValue that contains the number of entries in the map. The length
+    /// is updated from each insertion and removal, rather than by counting the whole map again.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::ReactiveHashMap;
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let hash_map: ReactiveHashMap<i8, i8> = ReactiveHashMap::new();
+    /// let len = hash_map.reactive_len();
+    /// assert_eq!(*len.get(), 0);
+    ///
+    /// hash_map.insert(1, 10);
+    /// hash_map.insert(2, 20);
+    /// hash_map.insert(1, 100);
+    /// assert_eq!(*len.get(), 2);
+    ///
+    /// hash_map.remove(2);
+    /// assert_eq!(*len.get(), 1);
+    /// ```
+    pub fn reactive_len(&self) -> ReadonlyReactiveValue<usize> {
+        let map_ref = self.clone_internal();
+        aggregate(
+            self.get_mutation_stream(),
+            0,
+            move |len, mutation| {
+                if let Subproperty(_) = mutation {
+                    return None;
+                }
+                let new_len = map_ref.live_len();
+                if new_len == *len {
+                    return None;
+                }
+                *len = new_len;
+                Some(new_len)
+            },
+            |len| {
+                *len = self.live_len();
+                *len
+            },
+        )
+    }
+
+    fn live_len(&self) -> usize {
+        self.internal
+            .map
+            .internal
+            .base
+            .collection
+            .read()
+            .unwrap()
+            .len()
+    }
+
+    /// Returns a ReadonlyReactiveValue that contains the sum of all values in the map. Each change
+    /// adjusts the sum by the difference between the old and the new value of one entry, so
+    /// keeping the sum up to date does not depend on the size of the map. The old values are
+    /// copies kept by the sum itself, which is why `ValueType` has to be `Copy`. Note that for
+    /// floating point values this can slowly accumulate rounding errors.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::ReactiveHashMap;
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let hash_map: ReactiveHashMap<&'static str, i32> = ReactiveHashMap::new();
+    /// hash_map.insert("apples", 3);
+    /// let sum = hash_map.reactive_sum();
+    /// assert_eq!(*sum.get(), 3);
+    ///
+    /// hash_map.insert("pears", 4);
+    /// assert_eq!(*sum.get(), 7);
+    ///
+    /// hash_map.insert("apples", 10);
+    /// assert_eq!(*sum.get(), 14);
+    ///
+    /// hash_map.remove("pears");
+    /// assert_eq!(*sum.get(), 10);
+    /// ```
+    pub fn reactive_sum(&self) -> ReadonlyReactiveValue<ValueType>
+    where
+        ValueType: Add<Output = ValueType>,
+        ValueType: Sub<Output = ValueType>,
+        ValueType: Default,
+        ValueType: Copy,
+    {
+        let map_ref = self.clone_internal();
+        aggregate(
+            self.get_mutation_stream(),
+            (ValueType::default(), HashMap::new()),
+            move |(sum, summed_values), mutation| {
+                let key = mutation_key::<KeyType>(mutation);
+                let new_value = map_ref.get(&key).map(|value| *value);
+                let old_value = match new_value {
+                    Some(new_value) => summed_values.insert(key, new_value),
+                    None => summed_values.remove(&key),
+                };
+                if let Some(old_value) = old_value {
+                    *sum = *sum - old_value;
+                }
+                if let Some(new_value) = new_value {
+                    *sum = *sum + new_value;
+                }
+                Some(*sum)
+            },
+            |(sum, summed_values)| {
+                let collection = self.internal.map.internal.base.collection.read();
+                for (key, value) in collection.unwrap().iter() {
+                    summed_values.insert(key.clone(), **value);
+                    *sum = *sum + **value;
+                }
+                *sum
+            },
+        )
+    }
+
+    /// Returns a ReadonlyReactiveValue that contains the number of values in the map that pass a
+    /// test function. When an entry changes, only that entry is tested again.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::ReactiveHashMap;
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let hash_map: ReactiveHashMap<i8, i8> = ReactiveHashMap::new();
+    /// hash_map.insert(1, 10);
+    /// let large_values = hash_map.reactive_count(|value| *value > 50);
+    /// assert_eq!(*large_values.get(), 0);
+    ///
+    /// hash_map.insert(2, 100);
+    /// hash_map.insert(3, 90);
+    /// assert_eq!(*large_values.get(), 2);
+    ///
+    /// hash_map.insert(2, 20);
+    /// assert_eq!(*large_values.get(), 1);
+    /// ```
+    pub fn reactive_count<F>(&self, filter_function: F) -> ReadonlyReactiveValue<usize>
+    where
+        F: Fn(&ValueType) -> bool,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let filter_function = Arc::new(filter_function);
+        let init_filter_function = filter_function.clone();
+        let map_ref = self.clone_internal();
+        aggregate(
+            self.get_mutation_stream(),
+            HashSet::new(),
+            move |matches, mutation| {
+                let key = mutation_key::<KeyType>(mutation);
+                let is_match = match map_ref.get(&key) {
+                    Some(value) => filter_function(&*value),
+                    None => false,
+                };
+                let changed = if is_match {
+                    matches.insert(key)
+                } else {
+                    matches.remove(&key)
+                };
+                if changed {
+                    Some(matches.len())
+                } else {
+                    None
+                }
+            },
+            |matches| {
+                let collection = self.internal.map.internal.base.collection.read();
+                for (key, value) in collection.unwrap().iter() {
+                    if init_filter_function(value) {
+                        matches.insert(key.clone());
+                    }
+                }
+                matches.len()
+            },
+        )
+    }
+
+    /// Returns a ReadonlyReactiveValue that is true whenever at least one value in the map passes
+    /// a test function. See `reactive_count`.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::ReactiveHashMap;
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let hash_map: ReactiveHashMap<i8, i8> = ReactiveHashMap::new();
+    /// let has_negative = hash_map.reactive_any(|value| *value < 0);
+    /// assert_eq!(*has_negative.get(), false);
+    ///
+    /// hash_map.insert(1, -10);
+    /// assert_eq!(*has_negative.get(), true);
+    ///
+    /// hash_map.remove(1);
+    /// assert_eq!(*has_negative.get(), false);
+    /// ```
+    pub fn reactive_any<F>(&self, filter_function: F) -> ReadonlyReactiveValue<bool>
+    where
+        F: Fn(&ValueType) -> bool,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let count = self.reactive_count(filter_function);
+        <dyn ReactiveValue<usize>>::map(&count, |count| *count > 0)
+    }
+
+    /// Returns a ReadonlyReactiveValue that is true whenever every value in the map passes a test
+    /// function (including when the map is empty). See `reactive_count`.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::ReactiveHashMap;
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let hash_map: ReactiveHashMap<i8, i8> = ReactiveHashMap::new();
+    /// let all_positive = hash_map.reactive_all(|value| *value > 0);
+    /// assert_eq!(*all_positive.get(), true);
+    ///
+    /// hash_map.insert(1, 10);
+    /// hash_map.insert(2, -10);
+    /// assert_eq!(*all_positive.get(), false);
+    ///
+    /// hash_map.insert(2, 20);
+    /// assert_eq!(*all_positive.get(), true);
+    /// ```
+    pub fn reactive_all<F>(&self, filter_function: F) -> ReadonlyReactiveValue<bool>
+    where
+        F: Fn(&ValueType) -> bool,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let failures = self.reactive_count(move |value| !filter_function(value));
+        <dyn ReactiveValue<usize>>::map(&failures, |count| *count == 0)
+    }
+}
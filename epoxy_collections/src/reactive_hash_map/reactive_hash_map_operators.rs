@@ -6,6 +6,7 @@ use crate::reactive_container_item::ReactiveContainerItem;
 use std::cmp::Eq;
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::sync::Arc;
 
 impl<KeyType, ValueType> ReactiveHashMap<KeyType, ValueType>
 where
@@ -97,4 +98,109 @@ where
 
         readonly_mapped_data
     }
+
+    /// Creates a new ReadonlyReactiveHashMap that contains the keys present in both this map and
+    /// `other`, where each value is the result of running both values through a joiner function.
+    /// The joined map is updated key-by-key as either side changes, so a change to one entry only
+    /// re-runs the joiner function for that key.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::ReactiveHashMap;
+    ///
+    /// let prices: ReactiveHashMap<&'static str, u32> = ReactiveHashMap::new();
+    /// let quantities: ReactiveHashMap<&'static str, u32> = ReactiveHashMap::new();
+    /// prices.insert("apple", 3);
+    /// prices.insert("pear", 4);
+    /// quantities.insert("apple", 10);
+    ///
+    /// let totals = prices.join(&quantities, |price, quantity| price * quantity);
+    /// assert_eq!(*totals.get(&"apple").unwrap(), 30);
+    /// assert_eq!(totals.get(&"pear"), None);
+    ///
+    /// quantities.insert("pear", 5);
+    /// assert_eq!(*totals.get(&"pear").unwrap(), 20);
+    ///
+    /// prices.insert("apple", 2);
+    /// assert_eq!(*totals.get(&"apple").unwrap(), 20);
+    ///
+    /// quantities.remove("apple");
+    /// assert_eq!(totals.get(&"apple"), None);
+    /// ```
+    pub fn join<OtherType, U, F>(
+        &self,
+        other: &ReactiveHashMap<KeyType, OtherType>,
+        joiner_fn: F,
+    ) -> ReadonlyReactiveHashMap<KeyType, U>
+    where
+        OtherType: ReactiveContainerItem,
+        OtherType: Send,
+        OtherType: Sync,
+        OtherType: 'static,
+        U: ReactiveContainerItem,
+        U: Send,
+        U: Sync,
+        U: Clone,
+        U: 'static,
+        F: Fn(&ValueType, &OtherType) -> U,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let joined_data;
+        {
+            let original_data = self.internal.map.internal.base.collection.read().unwrap();
+            let mut original_joined_data: HashMap<KeyType, U> = HashMap::new();
+            for (key, value) in original_data.iter() {
+                if let Some(other_value) = other.get(key) {
+                    original_joined_data.insert(key.clone(), joiner_fn(value, &*other_value));
+                }
+            }
+
+            joined_data = ReactiveHashMap::copy_of(&original_joined_data);
+        }
+
+        let readonly_joined_data = joined_data.as_readonly();
+
+        let left = self.clone_internal();
+        let right = other.clone_internal();
+        let update_key = Arc::new(move |key: &KeyType| match (left.get(key), right.get(key)) {
+            (Some(value), Some(other_value)) => {
+                joined_data.insert(key.clone(), joiner_fn(&*value, &*other_value));
+            }
+            _ => {
+                joined_data.remove(key.clone());
+            }
+        });
+
+        let update_from_left = update_key.clone();
+        let left_subscription = self
+            .get_mutation_stream()
+            .map(|mutation| match mutation {
+                Property(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+                Subproperty(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+                _ => panic!("Invalid mutation type for ReactiveHashMap"),
+            })
+            .subscribe(move |key| update_from_left(&key));
+        let right_subscription = other
+            .get_mutation_stream()
+            .map(|mutation| match mutation {
+                Property(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+                Subproperty(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+                _ => panic!("Invalid mutation type for ReactiveHashMap"),
+            })
+            .subscribe(move |key| update_key(&key));
+
+        {
+            let mut extra_fields = readonly_joined_data
+                .internal
+                .base
+                .extra_fields
+                .write()
+                .unwrap();
+            *extra_fields = Some(Box::new((left_subscription, right_subscription)));
+        }
+
+        readonly_joined_data
+    }
 }
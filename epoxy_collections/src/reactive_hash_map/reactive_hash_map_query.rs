@@ -0,0 +1,380 @@
+use super::reactive_hash_map::ReactiveHashMap;
+use crate::base_collection::ReadonlyReactiveCollection;
+use crate::mutations::Mutation::{Property, Subproperty};
+use crate::reactive_container_item::ReactiveContainerItem;
+use std::cmp::{Eq, Ordering};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+type FilterFn<ValueType> = Arc<dyn Fn(&ValueType) -> bool + Send + Sync>;
+type CompareFn<ValueType> = Arc<dyn Fn(&ValueType, &ValueType) -> Ordering + Send + Sync>;
+
+/// A change to the rows of a `QueryResult`.
+#[derive(Debug, PartialEq)]
+pub enum QueryChange<KeyType, ValueType> {
+    /// An entry became part of the results.
+    Added { key: KeyType, value: Arc<ValueType> },
+
+    /// An entry that is part of the results changed, and may have moved to a different position.
+    Updated { key: KeyType, value: Arc<ValueType> },
+
+    /// An entry is no longer part of the results.
+    Removed { key: KeyType },
+}
+
+impl<KeyType: Clone, ValueType> Clone for QueryChange<KeyType, ValueType> {
+    fn clone(&self) -> Self {
+        match self {
+            QueryChange::Added { key, value } => QueryChange::Added {
+                key: key.clone(),
+                value: value.clone(),
+            },
+            QueryChange::Updated { key, value } => QueryChange::Updated {
+                key: key.clone(),
+                value: value.clone(),
+            },
+            QueryChange::Removed { key } => QueryChange::Removed { key: key.clone() },
+        }
+    }
+}
+
+/// Describes a query over a ReactiveHashMap. Created by `ReactiveHashMap::query`, and turned into
+/// a live `QueryResult` by `run`.
+pub struct Query<KeyType, ValueType>
+where
+    KeyType: Hash,
+    KeyType: Eq,
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: ReactiveContainerItem,
+    ValueType: Sync,
+    ValueType: Send,
+    ValueType: 'static,
+{
+    map: ReactiveHashMap<KeyType, ValueType>,
+    filters: Vec<FilterFn<ValueType>>,
+    order: Option<CompareFn<ValueType>>,
+    limit: Option<usize>,
+}
+
+struct QueryState<KeyType, ValueType> {
+    filters: Vec<FilterFn<ValueType>>,
+    order: Option<CompareFn<ValueType>>,
+    limit: usize,
+
+    // Every entry that passes the filters, in order. The results are the first `limit` of these.
+    matches: Vec<(KeyType, Arc<ValueType>)>,
+}
+
+impl<KeyType, ValueType> QueryState<KeyType, ValueType>
+where
+    KeyType: Eq,
+    KeyType: Clone,
+{
+    fn is_match(&self, value: &ValueType) -> bool {
+        self.filters.iter().all(|filter| filter(value))
+    }
+
+    fn insert_match(&mut self, key: KeyType, value: Arc<ValueType>) -> usize {
+        let index = match &self.order {
+            // Entries that compare equal stay in the order they were added.
+            Some(order) => self
+                .matches
+                .partition_point(|(_, other)| order(other, &value) != Ordering::Greater),
+            None => self.matches.len(),
+        };
+        self.matches.insert(index, (key, value));
+        index
+    }
+
+    /// Updates the entry for a key, and returns how that changed the results.
+    fn update(
+        &mut self,
+        key: &KeyType,
+        value: Option<Arc<ValueType>>,
+    ) -> Vec<QueryChange<KeyType, ValueType>> {
+        let old_index = self.matches.iter().position(|(other, _)| other == key);
+        if let Some(old_index) = old_index {
+            self.matches.remove(old_index);
+        }
+        let new_index = match value {
+            Some(value) if self.is_match(&value) => Some(self.insert_match(key.clone(), value)),
+            _ => None,
+        };
+
+        let was_result = old_index.is_some_and(|index| index < self.limit);
+        let is_result = new_index.is_some_and(|index| index < self.limit);
+        let mut changes = vec![];
+        match (was_result, is_result) {
+            (true, true) => changes.push(QueryChange::Updated {
+                key: key.clone(),
+                value: self.matches[new_index.unwrap()].1.clone(),
+            }),
+            (true, false) => {
+                changes.push(QueryChange::Removed { key: key.clone() });
+                // The first entry past the limit moves up to take its place.
+                if self.matches.len() >= self.limit {
+                    let (key, value) = &self.matches[self.limit - 1];
+                    changes.push(QueryChange::Added {
+                        key: key.clone(),
+                        value: value.clone(),
+                    });
+                }
+            }
+            (false, true) => {
+                // The last result gets pushed past the limit.
+                if self.matches.len() > self.limit {
+                    let (key, _) = &self.matches[self.limit];
+                    changes.push(QueryChange::Removed { key: key.clone() });
+                }
+                changes.push(QueryChange::Added {
+                    key: key.clone(),
+                    value: self.matches[new_index.unwrap()].1.clone(),
+                });
+            }
+            (false, false) => {}
+        }
+        changes
+    }
+}
+
+/// The live results of a `Query`. Each change to the map only re-runs the filters for the entry
+/// that changed, and moves that one entry to its new position, rather than running the whole
+/// query again.
+pub struct QueryResult<KeyType, ValueType> {
+    state: Arc<Mutex<QueryState<KeyType, ValueType>>>,
+    change_sink: Arc<epoxy_streams::Sink<QueryChange<KeyType, ValueType>>>,
+
+    #[allow(dead_code)]
+    subscription: epoxy_streams::Subscription<KeyType>,
+}
+
+impl<KeyType, ValueType> ReactiveHashMap<KeyType, ValueType>
+where
+    KeyType: Hash,
+    KeyType: Eq,
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: ReactiveContainerItem,
+    ValueType: Sync,
+    ValueType: Send,
+    ValueType: 'static,
+{
+    /// Starts building a query over the entries of this map, which can be narrowed down with
+    /// `filter`, sorted with `order_by` and cut off with `limit`. Calling `run` on the query
+    /// returns a result set that stays up to date as the map changes.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::{QueryChange, ReactiveHashMap};
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::sync::Arc;
+    ///
+    /// let scores: ReactiveHashMap<&'static str, u32> = ReactiveHashMap::new();
+    /// scores.insert("ann", 40);
+    /// scores.insert("bob", 75);
+    /// scores.insert("cat", 90);
+    ///
+    /// let top_two = scores
+    ///     .query()
+    ///     .filter(|score| *score >= 50)
+    ///     .order_by_desc(|score| *score)
+    ///     .limit(2)
+    ///     .run();
+    /// let changes = ReactiveCache::from_stream(top_two.changes());
+    /// assert_eq!(top_two.keys(), vec!["cat", "bob"]);
+    ///
+    /// scores.insert("ann", 80);
+    /// assert_eq!(top_two.keys(), vec!["cat", "ann"]);
+    /// assert_eq!(
+    ///     changes.get_cloned(),
+    ///     vec![
+    ///         QueryChange::Removed { key: "bob" },
+    ///         QueryChange::Added {
+    ///             key: "ann",
+    ///             value: Arc::new(80)
+    ///         },
+    ///     ]
+    /// );
+    ///
+    /// scores.remove("cat");
+    /// assert_eq!(top_two.keys(), vec!["ann", "bob"]);
+    /// ```
+    pub fn query(&self) -> Query<KeyType, ValueType> {
+        Query {
+            map: self.clone_internal(),
+            filters: vec![],
+            order: None,
+            limit: None,
+        }
+    }
+}
+
+impl<KeyType, ValueType> Query<KeyType, ValueType>
+where
+    KeyType: Hash,
+    KeyType: Eq,
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: ReactiveContainerItem,
+    ValueType: Sync,
+    ValueType: Send,
+    ValueType: 'static,
+{
+    /// Only includes the values that pass a test function. Calling this more than once requires
+    /// values to pass every test.
+    pub fn filter<F>(mut self, filter_function: F) -> Query<KeyType, ValueType>
+    where
+        F: Fn(&ValueType) -> bool,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.filters.push(Arc::new(filter_function));
+        self
+    }
+
+    /// Sorts the results by a key taken from each value, from lowest to highest. Without an
+    /// order, results are in no particular order.
+    pub fn order_by<SortKey, F>(mut self, sort_key: F) -> Query<KeyType, ValueType>
+    where
+        SortKey: Ord,
+        F: Fn(&ValueType) -> SortKey,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.order = Some(Arc::new(move |a, b| sort_key(a).cmp(&sort_key(b))));
+        self
+    }
+
+    /// Sorts the results by a key taken from each value, from highest to lowest.
+    pub fn order_by_desc<SortKey, F>(mut self, sort_key: F) -> Query<KeyType, ValueType>
+    where
+        SortKey: Ord,
+        F: Fn(&ValueType) -> SortKey,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.order = Some(Arc::new(move |a, b| sort_key(b).cmp(&sort_key(a))));
+        self
+    }
+
+    /// Only includes the first `count` values that pass the filters.
+    pub fn limit(mut self, count: usize) -> Query<KeyType, ValueType> {
+        self.limit = Some(count);
+        self
+    }
+
+    /// Runs the query, returning results that are kept up to date until they are dropped.
+    pub fn run(self) -> QueryResult<KeyType, ValueType> {
+        let mut state = QueryState {
+            filters: self.filters,
+            order: self.order,
+            limit: self.limit.unwrap_or(usize::MAX),
+            matches: vec![],
+        };
+        {
+            let original_data = self
+                .map
+                .internal
+                .map
+                .internal
+                .base
+                .collection
+                .read()
+                .unwrap();
+            for (key, value) in original_data.iter() {
+                if state.is_match(value) {
+                    state.insert_match(key.clone(), value.clone());
+                }
+            }
+        }
+
+        let state = Arc::new(Mutex::new(state));
+        let change_sink = Arc::new(epoxy_streams::Sink::new());
+
+        let subscription_state = state.clone();
+        let subscription_sink = change_sink.clone();
+        let map_ref = self.map.clone_internal();
+        let subscription = self
+            .map
+            .get_mutation_stream()
+            .map(|mutation| match mutation {
+                Property(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+                Subproperty(mutation) => (*mutation.key.downcast_ref::<KeyType>().unwrap()).clone(),
+                _ => panic!("Invalid mutation type for ReactiveHashMap"),
+            })
+            .subscribe(move |key| {
+                let changes = subscription_state
+                    .lock()
+                    .unwrap()
+                    .update(&key, map_ref.get(&key));
+                for change in changes {
+                    subscription_sink.emit(change);
+                }
+            });
+
+        QueryResult {
+            state,
+            change_sink,
+            subscription,
+        }
+    }
+}
+
+impl<KeyType, ValueType> QueryResult<KeyType, ValueType>
+where
+    KeyType: Clone,
+{
+    /// Returns the current results, in order.
+    pub fn rows(&self) -> Vec<(KeyType, Arc<ValueType>)> {
+        let state = self.state.lock().unwrap();
+        let count = state.matches.len().min(state.limit);
+        state.matches[..count].to_vec()
+    }
+
+    /// Returns the keys of the current results, in order.
+    pub fn keys(&self) -> Vec<KeyType> {
+        self.rows().into_iter().map(|(key, _value)| key).collect()
+    }
+
+    /// Returns the number of current results.
+    pub fn len(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.matches.len().min(state.limit)
+    }
+
+    /// Returns true if no entries currently match the query.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a stream of the entries that join, change within or leave the results.
+    pub fn changes(&self) -> epoxy_streams::Stream<QueryChange<KeyType, ValueType>> {
+        self.change_sink.get_stream()
+    }
+}
+
+impl<KeyType, ValueType> epoxy_streams::Observable<QueryChange<KeyType, ValueType>>
+    for QueryResult<KeyType, ValueType>
+where
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: Send,
+    ValueType: Sync,
+    ValueType: 'static,
+{
+    fn changes(&self) -> epoxy_streams::Stream<QueryChange<KeyType, ValueType>> {
+        self.change_sink.get_stream()
+    }
+}
@@ -0,0 +1,221 @@
+use super::reactive_hash_map::ReactiveHashMap;
+use crate::base_collection::ReadonlyReactiveCollection;
+use crate::mutations::Mutation::Property;
+use crate::mutations::{Mutation, PropertyMutation};
+use crate::reactive_container_item::ReactiveContainerItem;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cmp::Eq;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// An error that occurred while loading a ReactiveHashMap from a sled tree.
+#[derive(Debug)]
+pub enum SledLoadError {
+    /// The tree could not be read.
+    Sled(sled::Error),
+
+    /// A key or value in the tree could not be deserialized into the map's types.
+    Serialization(serde_json::Error),
+}
+
+impl fmt::Display for SledLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SledLoadError::Sled(err) => write!(f, "Could not read sled tree: {}", err),
+            SledLoadError::Serialization(err) => write!(f, "Invalid entry in sled tree: {}", err),
+        }
+    }
+}
+
+impl Error for SledLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SledLoadError::Sled(err) => Some(err),
+            SledLoadError::Serialization(err) => Some(err),
+        }
+    }
+}
+
+impl From<sled::Error> for SledLoadError {
+    fn from(err: sled::Error) -> SledLoadError {
+        SledLoadError::Sled(err)
+    }
+}
+
+impl From<serde_json::Error> for SledLoadError {
+    fn from(err: serde_json::Error) -> SledLoadError {
+        SledLoadError::Serialization(err)
+    }
+}
+
+/// An error that occurred while writing an entry of a sled-backed ReactiveHashMap back to its
+/// tree. The entry is still changed in the map itself. See `ReactiveHashMap::take_sled_errors`.
+#[derive(Debug)]
+pub enum SledWriteError {
+    /// The tree could not be written.
+    Sled(sled::Error),
+
+    /// The key or value could not be serialized.
+    Serialization(serde_json::Error),
+}
+
+impl fmt::Display for SledWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SledWriteError::Sled(err) => write!(f, "Could not write sled tree: {}", err),
+            SledWriteError::Serialization(err) => write!(f, "Could not serialize entry: {}", err),
+        }
+    }
+}
+
+impl Error for SledWriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SledWriteError::Sled(err) => Some(err),
+            SledWriteError::Serialization(err) => Some(err),
+        }
+    }
+}
+
+impl From<sled::Error> for SledWriteError {
+    fn from(err: sled::Error) -> SledWriteError {
+        SledWriteError::Sled(err)
+    }
+}
+
+impl From<serde_json::Error> for SledWriteError {
+    fn from(err: serde_json::Error) -> SledWriteError {
+        SledWriteError::Serialization(err)
+    }
+}
+
+// Kept in the map's extra fields for as long as the map is backed by a tree.
+struct SledBinding {
+    errors: Arc<Mutex<Vec<SledWriteError>>>,
+
+    #[allow(dead_code)]
+    subscription: epoxy_streams::Subscription<Mutation>,
+}
+
+fn write_entry<KeyType, ValueType>(
+    tree: &sled::Tree,
+    mutation: &PropertyMutation,
+) -> Result<(), SledWriteError>
+where
+    KeyType: Serialize + 'static,
+    ValueType: Serialize + 'static,
+{
+    let key = serde_json::to_vec(mutation.key.downcast_ref::<KeyType>().unwrap())?;
+    match &mutation.new_value {
+        Some(value) => {
+            let value = serde_json::to_vec(value.downcast_ref::<ValueType>().unwrap())?;
+            tree.insert(key, value)?;
+        }
+        None => {
+            tree.remove(key)?;
+        }
+    }
+    Ok(())
+}
+
+impl<KeyType, ValueType> ReactiveHashMap<KeyType, ValueType>
+where
+    KeyType: Hash,
+    KeyType: Eq,
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: ReactiveContainerItem,
+    ValueType: Sync,
+    ValueType: Send,
+    ValueType: 'static,
+{
+    /// Creates a ReactiveHashMap that is backed by a sled tree. The map starts out with the
+    /// entries already stored in the tree, and every insertion and removal is written back to it,
+    /// with keys and values stored as JSON. Otherwise the map behaves exactly like one created
+    /// with `new`. Writes go through sled's own buffering, so call `flush` on the tree when they
+    /// have to be on disk.
+    ///
+    /// The tree should not be modified by anything else while the map is in use. An entry that
+    /// can not be written is still changed in the map, and the error is kept for
+    /// `take_sled_errors`.
+    ///
+    /// Requires the `sled` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_collections::ReactiveHashMap;
+    ///
+    /// let db = sled::Config::new().temporary(true).open().unwrap();
+    /// {
+    ///     let settings: ReactiveHashMap<String, u32> =
+    ///         ReactiveHashMap::open_sled(db.open_tree("settings").unwrap()).unwrap();
+    ///     settings.insert("volume".to_string(), 7);
+    ///     settings.insert("brightness".to_string(), 3);
+    ///     settings.remove("brightness".to_string());
+    /// }
+    ///
+    /// let settings: ReactiveHashMap<String, u32> =
+    ///     ReactiveHashMap::open_sled(db.open_tree("settings").unwrap()).unwrap();
+    /// assert_eq!(*settings.get(&"volume".to_string()).unwrap(), 7);
+    /// assert!(!settings.contains_key(&"brightness".to_string()));
+    /// assert!(settings.take_sled_errors().is_empty());
+    /// ```
+    pub fn open_sled(tree: sled::Tree) -> Result<ReactiveHashMap<KeyType, ValueType>, SledLoadError>
+    where
+        KeyType: Serialize,
+        KeyType: DeserializeOwned,
+        ValueType: Serialize,
+        ValueType: DeserializeOwned,
+    {
+        let map = ReactiveHashMap::new();
+        {
+            let mut collection = map.internal.map.internal.base.collection.write().unwrap();
+            for entry in tree.iter() {
+                let (key, value) = entry?;
+                collection.insert(
+                    serde_json::from_slice(&key)?,
+                    Arc::new(serde_json::from_slice(&value)?),
+                );
+            }
+        }
+
+        // Nested mutations are not written, since values that can be serialized do not have any.
+        let errors = Arc::new(Mutex::new(vec![]));
+        let errors_write = errors.clone();
+        let subscription = map.get_mutation_stream().subscribe(move |mutation| {
+            if let Property(mutation) = &*mutation {
+                if let Err(err) = write_entry::<KeyType, ValueType>(&tree, mutation) {
+                    errors_write.lock().unwrap().push(err);
+                }
+            }
+        });
+
+        {
+            let mut extra_fields = map.internal.map.internal.base.extra_fields.write().unwrap();
+            *extra_fields = Some(Box::new(SledBinding {
+                errors,
+                subscription,
+            }));
+        }
+
+        Ok(map)
+    }
+
+    /// Returns any errors that occurred while writing entries back to the sled tree since the
+    /// last call. Always empty for maps that were not created with `open_sled`.
+    pub fn take_sled_errors(&self) -> Vec<SledWriteError> {
+        let extra_fields = self.internal.map.internal.base.extra_fields.read().unwrap();
+        match extra_fields
+            .as_ref()
+            .and_then(|fields| fields.downcast_ref::<SledBinding>())
+        {
+            Some(binding) => std::mem::take(&mut *binding.errors.lock().unwrap()),
+            None => vec![],
+        }
+    }
+}
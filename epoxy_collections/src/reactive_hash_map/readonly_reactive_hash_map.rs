@@ -238,3 +238,22 @@ where
         }
     }
 }
+
+impl<KeyType, ValueType> epoxy_streams::Observable<Mutation>
+    for ReadonlyReactiveHashMap<KeyType, ValueType>
+where
+    KeyType: Hash,
+    KeyType: Eq,
+    KeyType: Clone,
+    KeyType: Send,
+    KeyType: Sync,
+    KeyType: 'static,
+    ValueType: ReactiveContainerItem,
+    ValueType: Sync,
+    ValueType: Send,
+    ValueType: 'static,
+{
+    fn changes(&self) -> epoxy_streams::Stream<Mutation> {
+        self.get_mutation_stream()
+    }
+}
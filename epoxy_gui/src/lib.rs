@@ -0,0 +1,23 @@
+//! # Epoxy GUI
+//!
+//! Most GUI toolkits only allow their widgets to be touched from the UI thread, while epoxy
+//! streams can emit from any thread. This crate bridges the two with a `MainThreadDispatcher`,
+//! which queues the values emitted by a stream and hands them to callbacks when the UI thread
+//! calls `run_pending`. Callbacks do not need to be `Send`, so they can hold on to widgets
+//! directly.
+//!
+//! Deliveries are conflated per subscription: if a stream emits several times before the UI
+//! thread gets around to running the queue, the callback is only called once, with the latest
+//! value. This keeps slow frames from building up a backlog of stale updates.
+//!
+//! The dispatcher wakes the UI thread with a callback whenever new values are queued. With the
+//! `winit` feature, `MainThreadDispatcher::for_event_loop` does this by sending a user event
+//! through a winit `EventLoopProxy`. Other toolkits can pass their own wake function to
+//! `MainThreadDispatcher::new` (for example one that calls `glib::idle_add_once`).
+extern crate epoxy_streams;
+#[cfg(feature = "winit")]
+extern crate winit;
+
+mod main_thread_dispatcher;
+
+pub use main_thread_dispatcher::{MainThreadDispatcher, MainThreadSubscription};
@@ -0,0 +1,217 @@
+use epoxy_streams::{Stream, Subscription};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::{Rc, Weak};
+use std::sync::{Arc, Mutex};
+
+type Deliverers = RefCell<HashMap<usize, Rc<dyn Fn()>>>;
+
+struct DispatchQueue {
+    ready: Mutex<VecDeque<usize>>,
+    wake: Box<dyn Fn() + Send + Sync>,
+}
+
+impl DispatchQueue {
+    fn push(&self, id: usize) {
+        let was_empty = {
+            let mut ready = match self.ready.lock() {
+                Ok(ready) => ready,
+                Err(err) => panic!("Dispatch queue mutex poisoned: {}", err),
+            };
+            ready.push_back(id);
+            ready.len() == 1
+        };
+
+        // The UI thread drains the whole queue at once, so it only needs to be woken up once
+        // for every batch of deliveries.
+        if was_empty {
+            (self.wake)();
+        }
+    }
+
+    fn take_ready(&self) -> VecDeque<usize> {
+        let mut ready = match self.ready.lock() {
+            Ok(ready) => ready,
+            Err(err) => panic!("Dispatch queue mutex poisoned: {}", err),
+        };
+        std::mem::take(&mut *ready)
+    }
+}
+
+/// Delivers stream values to callbacks on the thread that owns the dispatcher, which is usually
+/// the UI thread of a GUI application. See the crate documentation for an overview.
+///
+/// The dispatcher itself is not `Send`, so it should be created on the UI thread and stay there.
+///
+/// # Examples
+/// ```
+/// use epoxy_gui::MainThreadDispatcher;
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// let needs_redraw = Arc::new(AtomicBool::new(false));
+/// let wake_flag = needs_redraw.clone();
+/// let dispatcher = MainThreadDispatcher::new(move || wake_flag.store(true, Ordering::SeqCst));
+///
+/// // Widgets usually can't leave the UI thread, so they are modelled with an Rc here.
+/// let label = Rc::new(RefCell::new(String::new()));
+/// let label_ref = label.clone();
+///
+/// let sink = epoxy_streams::Sink::new();
+/// let _subscription = dispatcher.subscribe(&sink.get_stream(), move |progress: Arc<u32>| {
+///     *label_ref.borrow_mut() = format!("{}%", progress);
+/// });
+///
+/// std::thread::spawn(move || {
+///     for progress in 1..=100 {
+///         sink.emit(progress);
+///     }
+/// })
+/// .join()
+/// .unwrap();
+///
+/// // The worker thread woke the UI thread, which now receives only the latest progress.
+/// assert!(needs_redraw.load(Ordering::SeqCst));
+/// assert_eq!(dispatcher.run_pending(), 1);
+/// assert_eq!(*label.borrow(), "100%");
+/// ```
+pub struct MainThreadDispatcher {
+    queue: Arc<DispatchQueue>,
+    deliverers: Rc<Deliverers>,
+    next_id: RefCell<usize>,
+}
+
+/// Keeps a subscription created with `MainThreadDispatcher::subscribe` alive. Dropping it
+/// unsubscribes from the stream and discards any value that has not been delivered yet.
+pub struct MainThreadSubscription<T> {
+    id: usize,
+    deliverers: Weak<Deliverers>,
+    #[allow(dead_code)]
+    subscription: Subscription<T>,
+}
+
+impl MainThreadDispatcher {
+    /// Creates a dispatcher that calls `wake` whenever values are ready to be delivered. The wake
+    /// function is called from whichever thread the stream emitted on, and should arrange for
+    /// `run_pending` to be called on the UI thread soon.
+    pub fn new<F>(wake: F) -> MainThreadDispatcher
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        MainThreadDispatcher {
+            queue: Arc::new(DispatchQueue {
+                ready: Mutex::new(VecDeque::new()),
+                wake: Box::new(wake),
+            }),
+            deliverers: Rc::new(RefCell::new(HashMap::new())),
+            next_id: RefCell::new(0),
+        }
+    }
+
+    /// Creates a dispatcher that wakes a winit event loop by sending `wake_event` through its
+    /// proxy. The application should call `run_pending` when it receives that event, usually from
+    /// `ApplicationHandler::user_event`.
+    ///
+    /// Nothing happens if the event loop has already exited.
+    #[cfg(feature = "winit")]
+    pub fn for_event_loop<E>(
+        proxy: winit::event_loop::EventLoopProxy<E>,
+        wake_event: E,
+    ) -> MainThreadDispatcher
+    where
+        E: Clone + Send + 'static,
+    {
+        let proxy = Mutex::new(proxy);
+        let wake_event = Mutex::new(wake_event);
+        MainThreadDispatcher::new(move || {
+            let wake_event = match wake_event.lock() {
+                Ok(wake_event) => wake_event.clone(),
+                Err(err) => panic!("Wake event mutex poisoned: {}", err),
+            };
+            let proxy = match proxy.lock() {
+                Ok(proxy) => proxy,
+                Err(err) => panic!("Event loop proxy mutex poisoned: {}", err),
+            };
+            let _ = proxy.send_event(wake_event);
+        })
+    }
+
+    /// Subscribes to a stream, calling `callback` with its values from within `run_pending`.
+    /// Values that arrive before the previous one was delivered replace it, so the callback
+    /// always sees the latest value.
+    pub fn subscribe<T, F>(&self, stream: &Stream<T>, callback: F) -> MainThreadSubscription<T>
+    where
+        T: Send + Sync + 'static,
+        F: Fn(Arc<T>) + 'static,
+    {
+        let id = {
+            let mut next_id = self.next_id.borrow_mut();
+            *next_id += 1;
+            *next_id
+        };
+        let latest: Arc<Mutex<Option<Arc<T>>>> = Arc::new(Mutex::new(None));
+
+        let latest_for_delivery = latest.clone();
+        self.deliverers.borrow_mut().insert(
+            id,
+            Rc::new(move || {
+                let value = match latest_for_delivery.lock() {
+                    Ok(mut latest) => latest.take(),
+                    Err(err) => panic!("Latest value mutex poisoned: {}", err),
+                };
+                if let Some(value) = value {
+                    callback(value);
+                }
+            }),
+        );
+
+        let queue = self.queue.clone();
+        let subscription = stream.subscribe(move |value| {
+            let replaced = match latest.lock() {
+                Ok(mut latest) => latest.replace(value),
+                Err(err) => panic!("Latest value mutex poisoned: {}", err),
+            };
+            if replaced.is_none() {
+                queue.push(id);
+            }
+        });
+
+        MainThreadSubscription {
+            id,
+            deliverers: Rc::downgrade(&self.deliverers),
+            subscription,
+        }
+    }
+
+    /// Delivers every value that is waiting in the queue, and returns the number of callbacks
+    /// that were called. This must be called on the thread that created the dispatcher. Values
+    /// emitted while the callbacks run are left for the next call.
+    pub fn run_pending(&self) -> usize {
+        let mut delivered = 0;
+        for id in self.queue.take_ready() {
+            // The map is not borrowed while the callback runs, so that callbacks can subscribe
+            // and unsubscribe.
+            let deliverer = self.deliverers.borrow().get(&id).cloned();
+            if let Some(deliverer) = deliverer {
+                deliverer();
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+
+    /// Returns the number of active subscriptions.
+    pub fn count_subscriptions(&self) -> usize {
+        self.deliverers.borrow().len()
+    }
+}
+
+impl<T> Drop for MainThreadSubscription<T> {
+    fn drop(&mut self) {
+        if let Some(deliverers) = self.deliverers.upgrade() {
+            deliverers.borrow_mut().remove(&self.id);
+        }
+    }
+}
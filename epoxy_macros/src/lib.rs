@@ -3,6 +3,7 @@ extern crate proc_macro2;
 extern crate proc_macro_hack;
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
 extern crate syn;
 #[macro_use]
 extern crate quote;
@@ -31,12 +32,14 @@ lazy_static! {
         m.insert("bool");
         m.insert("char");
         m.insert("str");
+        m.insert("None");
         m
     };
 }
 
 #[proc_macro_hack]
-pub fn computed(block_stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
+pub fn computed(input_stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let (captured_idents, block_stream) = split_capture_list(input_stream);
     let wrapped_block_stream = proc_macro::TokenStream::from(proc_macro::TokenTree::from(
         proc_macro::Group::new(proc_macro::Delimiter::Brace, block_stream),
     ));
@@ -49,8 +52,20 @@ pub fn computed(block_stream: proc_macro::TokenStream) -> proc_macro::TokenStrea
     let value_tokens_1 = external_vars
         .idents
         .difference(&external_vars.local_idents)
-        .into_iter()
-        .filter(|ident| !EXCEMPT_IDENTIFIERS.contains(&*format!("{}", ident)));
+        .filter(|ident| !EXCEMPT_IDENTIFIERS.contains(&*format!("{}", ident)))
+        .filter(|ident| !captured_idents.contains(ident));
+
+    // Values that are only read through get_untracked() are captured, but not subscribed to.
+    let untracked_tokens_1 = external_vars
+        .untracked_idents
+        .difference(&external_vars.idents)
+        .filter(|ident| !external_vars.local_idents.contains(ident))
+        .filter(|ident| !captured_idents.contains(ident));
+    let untracked_tokens_2 = untracked_tokens_1.clone();
+
+    // Values listed in the capture list are plain values, which are captured as they are.
+    let captured_tokens_1 = captured_idents.iter();
+    let captured_tokens_2 = captured_idents.iter();
 
     let value_tokens_2 = value_tokens_1.clone();
     let value_tokens_3 = value_tokens_1.clone();
@@ -58,32 +73,62 @@ pub fn computed(block_stream: proc_macro::TokenStream) -> proc_macro::TokenStrea
     let value_tokens_5 = value_tokens_1.clone();
     let value_tokens_6 = value_tokens_1.clone();
     let value_tokens_7 = value_tokens_1.clone();
+    let value_tokens_8 = value_tokens_1.clone();
+    let value_tokens_9 = value_tokens_1.clone();
 
     proc_macro::TokenStream::from(quote! {
         {
             #(let #value_tokens_1 = #value_tokens_2.clone();
             )*
+            #(let #untracked_tokens_1 = #untracked_tokens_2.clone();
+            )*
+            #(let #captured_tokens_1 = #captured_tokens_2.clone();
+            )*
 
-            let initial = {
+            let __epoxy_stats_recorder = epoxy_streams::ComputedStatsRecorder::new(vec![
+                #((stringify!(#value_tokens_8), #value_tokens_9.as_stream().id())),*
+            ]);
+            let initial = __epoxy_stats_recorder.record(|| epoxy_streams::evaluate_derivation(|| {
                 #(let #value_tokens_3 = &*#value_tokens_4.get();
                 )*
                 #compute_fn_body
-            };
+            }));
 
+            let __epoxy_map_stats_recorder = __epoxy_stats_recorder.clone();
             epoxy_streams::merge(vec![
                 #(#value_tokens_5.as_stream().map(|_| ())),*
-            ]).map(move |_| {
+            ]).map(move |_| __epoxy_map_stats_recorder.record(|| epoxy_streams::evaluate_derivation(|| {
                 #(let #value_tokens_6 = &*#value_tokens_7.get();
                 )*
                 #compute_fn_body
-            }).to_reactive_value_with_default(initial)
+            }))).to_reactive_value_with_default(initial).with_stats_recorder(__epoxy_stats_recorder)
         }
     })
 }
 
+/// Splits an explicit capture list, like the `[config]` in `computed!([config] value * config.scale)`,
+/// off the front of the macro input. Variables in the capture list are cloned into the computed
+/// value as plain values, instead of being treated as ReactiveValues.
+fn split_capture_list(
+    input_stream: proc_macro::TokenStream,
+) -> (Vec<syn::Ident>, proc_macro::TokenStream) {
+    let mut tokens = input_stream.clone().into_iter();
+    if let Some(proc_macro::TokenTree::Group(group)) = tokens.next() {
+        let rest: proc_macro::TokenStream = tokens.collect();
+        if group.delimiter() == proc_macro::Delimiter::Bracket && !rest.is_empty() {
+            let parser = syn::punctuated::Punctuated::<syn::Ident, Token![,]>::parse_terminated;
+            if let Ok(idents) = syn::parse::Parser::parse(parser, group.stream()) {
+                return (idents.into_iter().collect(), rest);
+            }
+        }
+    }
+    (vec![], input_stream)
+}
+
 struct ExternalVarVisitor {
     pub idents: HashSet<syn::Ident>,
     pub local_idents: HashSet<syn::Ident>,
+    pub untracked_idents: HashSet<syn::Ident>,
 }
 
 impl ExternalVarVisitor {
@@ -91,8 +136,40 @@ impl ExternalVarVisitor {
         ExternalVarVisitor {
             idents: HashSet::new(),
             local_idents: HashSet::new(),
+            untracked_idents: HashSet::new(),
+        }
+    }
+}
+
+/// Returns the identifier of an expression that consists of nothing but a single variable name.
+fn expr_as_ident(expr: &syn::Expr) -> Option<&syn::Ident> {
+    match expr {
+        syn::Expr::Path(expr_path) if expr_path.qself.is_none() => {
+            let segments = &expr_path.path.segments;
+            if segments.len() == 1 && expr_path.path.leading_colon.is_none() {
+                Some(&segments[0].ident)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Collects the variables bound by a pattern, such as the `value` in `Ok(value) => ...`.
+struct PatternBindingVisitor<'a> {
+    bindings: &'a mut HashSet<syn::Ident>,
+}
+
+impl<'a, 'ast> syn::visit::Visit<'ast> for PatternBindingVisitor<'a> {
+    fn visit_pat_ident(&mut self, pat_ident: &'ast syn::PatIdent) {
+        self.bindings.insert(pat_ident.ident.clone());
+        if let Some((_, subpat)) = &pat_ident.subpat {
+            self.visit_pat(subpat);
         }
     }
+
+    fn visit_path(&mut self, _path: &'ast syn::Path) {}
 }
 
 impl<'ast> syn::visit::Visit<'ast> for ExternalVarVisitor {
@@ -100,12 +177,62 @@ impl<'ast> syn::visit::Visit<'ast> for ExternalVarVisitor {
         self.idents.insert(ident.clone());
     }
 
+    // Only single-segment paths can refer to captured variables. Longer paths like
+    // `i32::MAX` or `Ordering::Less` name items.
+    fn visit_expr_path(&mut self, expr_path: &'ast syn::ExprPath) {
+        if let Some(ident) = expr_as_ident(&syn::Expr::Path(expr_path.clone())) {
+            self.idents.insert(ident.clone());
+        }
+    }
+
+    // The callee of a call is a function or a constructor like `Ok` or `Some`, not a variable.
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        match &*call.func {
+            syn::Expr::Path(_) => {}
+            func => self.visit_expr(func),
+        }
+        for arg in call.args.iter() {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if call.method == "get_untracked" {
+            if let Some(ident) = expr_as_ident(&call.receiver) {
+                self.untracked_idents.insert(ident.clone());
+                for arg in call.args.iter() {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+        }
+        self.visit_expr(&call.receiver);
+        for arg in call.args.iter() {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_field(&mut self, field: &'ast syn::ExprField) {
+        self.visit_expr(&field.base);
+    }
+
+    fn visit_macro(&mut self, _mac: &'ast syn::Macro) {}
+
+    fn visit_type(&mut self, _ty: &'ast syn::Type) {}
+
+    fn visit_pat(&mut self, pat: &'ast syn::Pat) {
+        let mut bindings = PatternBindingVisitor {
+            bindings: &mut self.local_idents,
+        };
+        bindings.visit_pat(pat);
+    }
+
     fn visit_local(&mut self, local: &'ast syn::Local) {
         for pat in local.pats.iter() {
-            match pat {
-                syn::Pat::Ident(pat_ident) => self.local_idents.insert(pat_ident.ident.clone()),
-                _ => false,
-            };
+            self.visit_pat(pat);
+        }
+        if let Some((_, init)) = &local.init {
+            self.visit_expr(init);
         }
     }
 }
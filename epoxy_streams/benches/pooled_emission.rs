@@ -0,0 +1,72 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use epoxy_streams::{ArcPool, Sink};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const FRAME_SIZE: usize = 64 * 1024;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn fill_frame(frame: &mut Vec<u8>) {
+    frame.resize(FRAME_SIZE, 7);
+}
+
+fn pooled_emission(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pooled_emission");
+
+    group.bench_function("allocated", |b| {
+        let sink: Sink<Vec<u8>> = Sink::new();
+        let _subscription = sink.get_stream().subscribe(|frame| {
+            black_box(frame[0]);
+        });
+        b.iter(|| {
+            let mut frame = Vec::with_capacity(FRAME_SIZE);
+            fill_frame(&mut frame);
+            sink.emit(frame);
+        });
+    });
+
+    group.bench_function("pooled", |b| {
+        let pool = ArcPool::new(4, || Vec::with_capacity(FRAME_SIZE));
+        let sink: Sink<Vec<u8>> = Sink::new();
+        let _subscription = sink.get_stream().subscribe(|frame| {
+            black_box(frame[0]);
+        });
+
+        // Once the pool is warm, emitting must not touch the allocator at all.
+        sink.emit_pooled(&pool, fill_frame);
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for _ in 0..1000 {
+            sink.emit_pooled(&pool, fill_frame);
+        }
+        let steady_state_allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+        assert_eq!(
+            steady_state_allocations, 0,
+            "pooled emission allocated {} times",
+            steady_state_allocations
+        );
+
+        b.iter(|| sink.emit_pooled(&pool, fill_frame));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, pooled_emission);
+criterion_main!(benches);
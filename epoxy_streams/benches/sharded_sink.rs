@@ -0,0 +1,68 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use epoxy_streams::{ShardedSink, Sink};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+const EMITS_PER_THREAD: usize = 10_000;
+
+fn run_producers<F>(n_threads: usize, emit: F)
+where
+    F: Fn(usize) + Clone + Send + 'static,
+{
+    let handles: Vec<_> = (0..n_threads)
+        .map(|_| {
+            let emit = emit.clone();
+            thread::spawn(move || {
+                for val in 0..EMITS_PER_THREAD {
+                    emit(val);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+fn multi_producer_emit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("multi_producer_emit");
+    for n_threads in [1, 4, 16] {
+        group.bench_with_input(BenchmarkId::new("Sink", n_threads), &n_threads, |b, &n| {
+            let sink: Sink<usize> = Sink::new();
+            let received = Arc::new(AtomicUsize::new(0));
+            let received_write = received.clone();
+            let _subscription = sink.get_stream().subscribe(move |_| {
+                received_write.fetch_add(1, Ordering::Relaxed);
+            });
+            b.iter(|| {
+                let producer = sink.producer();
+                run_producers(n, move |val| {
+                    let _ = producer.emit(val);
+                });
+            });
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("ShardedSink", n_threads),
+            &n_threads,
+            |b, &n| {
+                let sink: ShardedSink<usize> = ShardedSink::new(n);
+                let received = Arc::new(AtomicUsize::new(0));
+                let received_write = received.clone();
+                let _subscription = sink.get_stream().subscribe(move |_| {
+                    received_write.fetch_add(1, Ordering::Relaxed);
+                });
+                b.iter(|| {
+                    let producer = sink.clone();
+                    run_producers(n, move |val| producer.emit(val));
+                    sink.flush();
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, multi_producer_emit);
+criterion_main!(benches);
@@ -0,0 +1,64 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use epoxy_streams::{Sink, Stream, Subscription};
+
+fn emit_throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("emit_throughput");
+    for n_subscribers in [1, 10, 100, 1000] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(n_subscribers),
+            &n_subscribers,
+            |b, &n| {
+                let sink: Sink<u64> = Sink::new();
+                let stream = sink.get_stream();
+                let _subscriptions: Vec<Subscription<u64>> = (0..n)
+                    .map(|_| {
+                        stream.subscribe(|val| {
+                            black_box(*val);
+                        })
+                    })
+                    .collect();
+                b.iter(|| sink.emit(black_box(1)));
+            },
+        );
+    }
+    group.finish();
+}
+
+fn subscription_churn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("subscription_churn");
+    for n_existing in [0, 100] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(n_existing),
+            &n_existing,
+            |b, &n| {
+                let sink: Sink<u64> = Sink::new();
+                let stream = sink.get_stream();
+                let _existing: Vec<Subscription<u64>> =
+                    (0..n).map(|_| stream.subscribe(|_| {})).collect();
+                b.iter(|| drop(stream.subscribe(|_| {})));
+            },
+        );
+    }
+    group.finish();
+}
+
+fn operator_chain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("operator_chain");
+    for depth in [1, 10, 50] {
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, &depth| {
+            let sink: Sink<u64> = Sink::new();
+            let mut stream: Stream<u64> = sink.get_stream();
+            for _ in 0..depth {
+                stream = stream.map(|val| val + 1).filter(|val| *val > 0);
+            }
+            let _subscription = stream.subscribe(|val| {
+                black_box(*val);
+            });
+            b.iter(|| sink.emit(black_box(1)));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, emit_throughput, subscription_churn, operator_chain);
+criterion_main!(benches);
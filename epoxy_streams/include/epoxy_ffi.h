@@ -0,0 +1,47 @@
+/*
+ * C interface to epoxy_streams Sinks and Subscriptions. Requires the `ffi` feature.
+ *
+ * Every handle must be freed exactly once with its matching free function. Passing a null handle
+ * to any function does nothing. Callbacks run on the thread that emitted the value, while the sink
+ * is locked, so they must not emit from the same sink or free its subscriptions.
+ */
+#ifndef EPOXY_FFI_H
+#define EPOXY_FFI_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct EpoxySubscription EpoxySubscription;
+
+typedef void (*EpoxyCompleteCallback)(void *user_data);
+
+void epoxy_subscription_free(EpoxySubscription *subscription);
+
+#define EPOXY_DECLARE_SINK(suffix, Name, value_type)                                              \
+    typedef struct EpoxySink##Name EpoxySink##Name;                                               \
+    typedef void (*EpoxyValueCallback##Name)(void *user_data, value_type value);                  \
+    EpoxySink##Name *epoxy_sink_##suffix##_new(void);                                             \
+    void epoxy_sink_##suffix##_emit(const EpoxySink##Name *sink, value_type value);               \
+    EpoxySubscription *epoxy_sink_##suffix##_subscribe(const EpoxySink##Name *sink,               \
+                                                       EpoxyValueCallback##Name on_value,         \
+                                                       EpoxyCompleteCallback on_complete,         \
+                                                       void *user_data);                          \
+    void epoxy_sink_##suffix##_close(const EpoxySink##Name *sink);                                \
+    void epoxy_sink_##suffix##_free(EpoxySink##Name *sink);
+
+EPOXY_DECLARE_SINK(i32, I32, int32_t)
+EPOXY_DECLARE_SINK(i64, I64, int64_t)
+EPOXY_DECLARE_SINK(f64, F64, double)
+EPOXY_DECLARE_SINK(bool, Bool, bool)
+
+#undef EPOXY_DECLARE_SINK
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* EPOXY_FFI_H */
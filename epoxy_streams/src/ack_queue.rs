@@ -0,0 +1,296 @@
+use super::scheduler::Scheduler;
+use super::{Stream, Subscription};
+use std::collections::{BTreeMap, VecDeque};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+type AckListener<T> = Arc<dyn Fn(Arc<T>, Ack<T>) + Send + Sync>;
+
+struct AckState<T> {
+    consumers: BTreeMap<u64, AckListener<T>>,
+    next_consumer_id: u64,
+
+    // The consumer that received the last delivery. Deliveries go to the consumers in turn.
+    last_consumer_id: u64,
+
+    // Values that arrived while there were no consumers.
+    waiting: VecDeque<Arc<T>>,
+
+    // Values that have been delivered but not acknowledged yet, by delivery id. A value gets a new
+    // delivery id every time it is delivered.
+    in_flight: BTreeMap<u64, (u64, Arc<T>)>,
+    next_delivery_id: u64,
+}
+
+struct AckShared<T> {
+    state: Mutex<AckState<T>>,
+    scheduler: Arc<dyn Scheduler>,
+    redeliver_after: Duration,
+}
+
+/// Distributes the values of a stream over a set of consumers, so that each value is handled by
+/// one consumer rather than broadcast to all of them. Every delivery comes with an `Ack` handle,
+/// and a value that is not acknowledged within the redelivery timeout is delivered again, to the
+/// next consumer in line. Values are also redelivered right away when the consumer handling them
+/// gives up with `Ack::nack` or is dropped, and values that arrive while there are no consumers
+/// wait for the first one to join. This turns a fire-and-forget stream into reliable work
+/// distribution.
+///
+/// Consumers take turns receiving values. Listeners are called on the thread that emitted the
+/// value, or on the stream's scheduler for redeliveries, so slow work should be handed off to
+/// another thread along with its `Ack`.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::scheduler::ManualScheduler;
+/// use epoxy_streams::{Ack, AckQueue};
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+///
+/// let scheduler = Arc::new(ManualScheduler::new());
+/// let stream_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+/// let stream = stream_host.get_stream().with_scheduler(scheduler.clone());
+/// let queue = AckQueue::new(&stream, Duration::from_secs(30));
+///
+/// // This worker takes jobs but never finishes them.
+/// let stuck: Arc<Mutex<Vec<Ack<&str>>>> = Default::default();
+/// let stuck_write = stuck.clone();
+/// let _stuck_worker = queue.subscribe(move |_job, ack| stuck_write.lock().unwrap().push(ack));
+///
+/// let done = Arc::new(Mutex::new(vec![]));
+/// let done_write = done.clone();
+/// let _worker = queue.subscribe(move |job, ack| {
+///     done_write.lock().unwrap().push(*job);
+///     ack.ack();
+/// });
+///
+/// stream_host.emit("resize image");
+/// stream_host.emit("send email");
+/// assert_eq!(*done.lock().unwrap(), vec!["send email"]);
+/// assert_eq!(queue.count_in_flight(), 1);
+///
+/// scheduler.advance(Duration::from_secs(30));
+/// assert_eq!(*done.lock().unwrap(), vec!["send email", "resize image"]);
+/// assert_eq!(queue.count_in_flight(), 0);
+///
+/// // Acknowledging after the value was redelivered has no effect.
+/// assert!(!stuck.lock().unwrap().pop().unwrap().ack());
+/// ```
+pub struct AckQueue<T> {
+    shared: Arc<AckShared<T>>,
+
+    #[allow(dead_code)]
+    subscription: Subscription<T>,
+}
+
+/// Acknowledges a value delivered by an `AckQueue`.
+pub struct Ack<T> {
+    shared: Weak<AckShared<T>>,
+    delivery_id: u64,
+}
+
+/// A consumer of an `AckQueue`. Dropping it stops deliveries to its listener, and redelivers the
+/// values it has not acknowledged to the other consumers.
+pub struct AckConsumer<T> {
+    leave: Option<Box<dyn FnOnce() + Send + Sync>>,
+    marker: PhantomData<T>,
+}
+
+impl<T> AckShared<T> {
+    fn lock_state(&self) -> MutexGuard<'_, AckState<T>> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(err) => panic!("AckQueue mutex poisoned: {}", err),
+        }
+    }
+}
+
+impl<T> AckState<T> {
+    // Returns the consumer whose turn it is, skipping `avoid` unless it is the only consumer left.
+    fn next_consumer(&self, avoid: Option<u64>) -> Option<(u64, AckListener<T>)> {
+        let mut in_turn = self
+            .consumers
+            .range(self.last_consumer_id + 1..)
+            .chain(self.consumers.iter())
+            .take(self.consumers.len());
+        let first = in_turn.next();
+        match first {
+            Some((id, _)) if Some(*id) == avoid => in_turn.next().or(first),
+            _ => first,
+        }
+        .map(|(id, listener)| (*id, listener.clone()))
+    }
+}
+
+// Delivers each value to the next consumer in turn. Values that are being redelivered pass the
+// consumer that failed to handle them as `avoid`.
+fn deliver<T: Send + Sync + 'static>(
+    shared: &Arc<AckShared<T>>,
+    values: Vec<Arc<T>>,
+    avoid: Option<u64>,
+) {
+    for value in values {
+        let (listener, delivery_id) = {
+            let mut state = shared.lock_state();
+            let next_consumer = state.next_consumer(avoid);
+            let (consumer_id, listener) = match next_consumer {
+                Some(consumer) => consumer,
+                None => {
+                    state.waiting.push_back(value);
+                    continue;
+                }
+            };
+            let delivery_id = state.next_delivery_id;
+            state.next_delivery_id += 1;
+            state.last_consumer_id = consumer_id;
+            state
+                .in_flight
+                .insert(delivery_id, (consumer_id, value.clone()));
+            (listener, delivery_id)
+        };
+
+        let weak_shared = Arc::downgrade(shared);
+        shared.scheduler.schedule_after(
+            shared.redeliver_after,
+            Box::new(move || {
+                if let Some(shared) = weak_shared.upgrade() {
+                    let expired = shared.lock_state().in_flight.remove(&delivery_id);
+                    if let Some((consumer_id, value)) = expired {
+                        deliver(&shared, vec![value], Some(consumer_id));
+                    }
+                }
+            }),
+        );
+
+        listener(
+            value,
+            Ack {
+                shared: Arc::downgrade(shared),
+                delivery_id,
+            },
+        );
+    }
+}
+
+// Removes a consumer, and redelivers the values it had not acknowledged to the others.
+fn leave<T: Send + Sync + 'static>(shared: &Arc<AckShared<T>>, consumer_id: u64) {
+    let abandoned = {
+        let mut state = shared.lock_state();
+        state.consumers.remove(&consumer_id);
+        let delivery_ids: Vec<u64> = state
+            .in_flight
+            .iter()
+            .filter(|(_, (owner, _))| *owner == consumer_id)
+            .map(|(delivery_id, _)| *delivery_id)
+            .collect();
+        delivery_ids
+            .into_iter()
+            .filter_map(|delivery_id| state.in_flight.remove(&delivery_id))
+            .map(|(_, value)| value)
+            .collect()
+    };
+    deliver(shared, abandoned, None);
+}
+
+impl<T: Send + Sync + 'static> AckQueue<T> {
+    /// Creates a queue of the values emitted by `source`. Values that are not acknowledged within
+    /// `redeliver_after`, as measured by the source's scheduler, are delivered again.
+    pub fn new(source: &Stream<T>, redeliver_after: Duration) -> AckQueue<T> {
+        let shared = Arc::new(AckShared {
+            state: Mutex::new(AckState {
+                consumers: BTreeMap::new(),
+                next_consumer_id: 0,
+                last_consumer_id: 0,
+                waiting: VecDeque::new(),
+                in_flight: BTreeMap::new(),
+                next_delivery_id: 0,
+            }),
+            scheduler: source.scheduler(),
+            redeliver_after,
+        });
+
+        let weak_shared = Arc::downgrade(&shared);
+        let subscription = source.subscribe(move |val| {
+            if let Some(shared) = weak_shared.upgrade() {
+                deliver(&shared, vec![val], None);
+            }
+        });
+
+        AckQueue {
+            shared,
+            subscription,
+        }
+    }
+
+    /// Adds a consumer. Values that have been waiting for a consumer are delivered to it right
+    /// away.
+    pub fn subscribe<F>(&self, listener: F) -> AckConsumer<T>
+    where
+        F: Fn(Arc<T>, Ack<T>) + Send + Sync + 'static,
+    {
+        let (id, waiting) = {
+            let mut state = self.shared.lock_state();
+            state.next_consumer_id += 1;
+            let id = state.next_consumer_id;
+            state.consumers.insert(id, Arc::new(listener));
+            (id, state.waiting.drain(..).collect())
+        };
+        deliver(&self.shared, waiting, None);
+
+        let weak_shared = Arc::downgrade(&self.shared);
+        AckConsumer {
+            leave: Some(Box::new(move || {
+                if let Some(shared) = weak_shared.upgrade() {
+                    leave(&shared, id);
+                }
+            })),
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of values that have been delivered but not acknowledged yet.
+    pub fn count_in_flight(&self) -> usize {
+        self.shared.lock_state().in_flight.len()
+    }
+
+    /// Returns the number of values waiting for a consumer to join.
+    pub fn count_waiting(&self) -> usize {
+        self.shared.lock_state().waiting.len()
+    }
+}
+
+impl<T: Send + Sync + 'static> Ack<T> {
+    /// Marks the value as handled, so it will not be delivered again. Returns false if the value
+    /// had already been redelivered (or the queue dropped), in which case another consumer is now
+    /// responsible for it.
+    pub fn ack(self) -> bool {
+        match self.shared.upgrade() {
+            Some(shared) => shared
+                .lock_state()
+                .in_flight
+                .remove(&self.delivery_id)
+                .is_some(),
+            None => false,
+        }
+    }
+
+    /// Gives the value back, so it is delivered to the next consumer right away instead of after
+    /// the redelivery timeout.
+    pub fn nack(self) {
+        if let Some(shared) = self.shared.upgrade() {
+            let returned = shared.lock_state().in_flight.remove(&self.delivery_id);
+            if let Some((consumer_id, value)) = returned {
+                deliver(&shared, vec![value], Some(consumer_id));
+            }
+        }
+    }
+}
+
+impl<T> Drop for AckConsumer<T> {
+    fn drop(&mut self) {
+        if let Some(leave) = self.leave.take() {
+            leave();
+        }
+    }
+}
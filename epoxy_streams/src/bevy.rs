@@ -0,0 +1,174 @@
+//! Integration with the Bevy game engine. `EpoxyPlugin` runs epoxy's scheduled work at the start
+//! of every frame, `EpoxyAppExt::insert_reactive_resource` mirrors a ReactiveValue into an ECS
+//! resource (so systems can use Bevy's change detection on it), and `EpoxyAppExt::event_stream`
+//! turns Bevy events into a Stream.
+//!
+//! Requires the `bevy` feature.
+//!
+//! # Examples
+//! ```
+//! use bevy_app::App;
+//! use bevy_ecs::event::{Event, Events};
+//! use epoxy_streams::bevy::{EpoxyAppExt, EpoxyPlugin, ReactiveResource};
+//! use epoxy_streams::{ReactiveCache, ReactiveValue};
+//!
+//! #[derive(Event, Clone, Debug, PartialEq)]
+//! struct Scored(u32);
+//!
+//! let score = ReactiveValue::new(0_u32);
+//! let mut app = App::new();
+//! app.add_plugins(EpoxyPlugin)
+//!     .insert_reactive_resource(&score);
+//! let scored = ReactiveCache::from_stream(app.event_stream::<Scored>());
+//!
+//! score.set(10);
+//! app.world_mut().resource_mut::<Events<Scored>>().send(Scored(10));
+//! app.update();
+//!
+//! assert_eq!(**app.world().resource::<ReactiveResource<u32>>(), 10);
+//! assert_eq!(scored.get_cloned(), vec![Scored(10)]);
+//! ```
+use super::config;
+use super::scheduler::TickScheduler;
+use super::{ReactiveValue, Sink, Stream, Subscription};
+use bevy_app::{App, First, Last, Plugin, PreUpdate};
+use bevy_ecs::event::{Event, EventReader};
+use bevy_ecs::system::{Res, ResMut, Resource};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+/// Makes a `TickScheduler` the default epoxy scheduler, and ticks it in Bevy's `First` schedule.
+/// Delayed and deferred stream work (debounces, timeouts, etc) then runs on the main thread at
+/// the start of each frame, instead of on epoxy's background thread.
+///
+/// The default scheduler is global, so this affects every stream in the process.
+pub struct EpoxyPlugin;
+
+/// The scheduler installed by `EpoxyPlugin`.
+#[derive(Resource, Clone)]
+pub struct EpoxyScheduler(pub Arc<TickScheduler>);
+
+impl Plugin for EpoxyPlugin {
+    fn build(&self, app: &mut App) {
+        let scheduler = Arc::new(TickScheduler::new());
+        config::set_default_scheduler(scheduler.clone());
+        app.insert_resource(EpoxyScheduler(scheduler))
+            .add_systems(First, tick_scheduler);
+    }
+}
+
+fn tick_scheduler(scheduler: Res<EpoxyScheduler>) {
+    scheduler.0.tick();
+}
+
+/// An ECS resource that mirrors the value of a ReactiveValue, see
+/// `EpoxyAppExt::insert_reactive_resource`. The resource is only marked as changed on frames
+/// where the ReactiveValue changed.
+#[derive(Resource)]
+pub struct ReactiveResource<T: Send + Sync + 'static> {
+    value: Arc<T>,
+}
+
+impl<T: Send + Sync + 'static> ReactiveResource<T> {
+    /// Returns the value as an Arc, without copying it.
+    pub fn get_rc(&self) -> Arc<T> {
+        self.value.clone()
+    }
+}
+
+impl<T: Send + Sync + 'static> Deref for ReactiveResource<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[derive(Resource)]
+struct ReactiveResourceSource<T: Send + Sync + 'static> {
+    latest: Arc<Mutex<Option<Arc<T>>>>,
+    _subscription: Subscription<T>,
+}
+
+fn sync_reactive_resource<T: Send + Sync + 'static>(
+    source: Res<ReactiveResourceSource<T>>,
+    mut resource: ResMut<ReactiveResource<T>>,
+) {
+    let latest = match source.latest.lock() {
+        Ok(mut latest) => latest.take(),
+        Err(err) => panic!("Reactive resource mutex poisoned: {}", err),
+    };
+    if let Some(value) = latest {
+        resource.value = value;
+    }
+}
+
+#[derive(Resource)]
+struct EventSink<E: Event> {
+    sink: Sink<E>,
+}
+
+fn forward_events<E: Event + Clone>(mut reader: EventReader<E>, sink: Res<EventSink<E>>) {
+    for event in reader.read() {
+        sink.sink.emit(event.clone());
+    }
+}
+
+/// Adds epoxy integrations to a Bevy `App`.
+pub trait EpoxyAppExt {
+    /// Inserts a `ReactiveResource<T>` that follows the given ReactiveValue. Changes are copied
+    /// into the resource in the `PreUpdate` schedule, so every system in a frame sees the same
+    /// value.
+    fn insert_reactive_resource<T>(&mut self, value: &dyn ReactiveValue<T>) -> &mut Self
+    where
+        T: Send + Sync + 'static;
+
+    /// Returns a Stream that emits every `E` event sent in the app. Events are emitted in the
+    /// `Last` schedule of the frame they were sent in. Registers the event type if necessary.
+    fn event_stream<E>(&mut self) -> Stream<E>
+    where
+        E: Event + Clone;
+}
+
+impl EpoxyAppExt for App {
+    fn insert_reactive_resource<T>(&mut self, value: &dyn ReactiveValue<T>) -> &mut App
+    where
+        T: Send + Sync + 'static,
+    {
+        let latest = Arc::new(Mutex::new(None));
+        let latest_ref = latest.clone();
+        let subscription = value.as_stream().subscribe(move |value| {
+            match latest_ref.lock() {
+                Ok(mut latest) => *latest = Some(value),
+                Err(err) => panic!("Reactive resource mutex poisoned: {}", err),
+            };
+        });
+
+        let is_new = !self.world().contains_resource::<ReactiveResource<T>>();
+        self.insert_resource(ReactiveResource { value: value.get() })
+            .insert_resource(ReactiveResourceSource {
+                latest,
+                _subscription: subscription,
+            });
+        if is_new {
+            self.add_systems(PreUpdate, sync_reactive_resource::<T>);
+        }
+        self
+    }
+
+    fn event_stream<E>(&mut self) -> Stream<E>
+    where
+        E: Event + Clone,
+    {
+        if let Some(event_sink) = self.world().get_resource::<EventSink<E>>() {
+            return event_sink.sink.get_stream();
+        }
+
+        let sink = Sink::new();
+        let stream = sink.get_stream();
+        self.add_event::<E>()
+            .insert_resource(EventSink { sink })
+            .add_systems(Last, forward_events::<E>);
+        stream
+    }
+}
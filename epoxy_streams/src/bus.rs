@@ -0,0 +1,98 @@
+//! A central event bus that connects components without them knowing about each other. Events are
+//! routed by their type: anything published as a `UserLoggedIn` reaches every stream returned by
+//! `subscribe::<UserLoggedIn>()`, and nothing else.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::bus::EventBus;
+//! use epoxy_streams::ReactiveCache;
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct UserLoggedIn(String);
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct CartUpdated(u32);
+//!
+//! let bus = EventBus::new();
+//! let logins = ReactiveCache::from_stream(bus.subscribe::<UserLoggedIn>());
+//! let carts = ReactiveCache::from_stream(bus.subscribe::<CartUpdated>());
+//!
+//! bus.publish(UserLoggedIn("keaton".to_string()));
+//! bus.publish(CartUpdated(3));
+//! bus.publish(CartUpdated(4));
+//!
+//! assert_eq!(logins.get_cloned(), vec![UserLoggedIn("keaton".to_string())]);
+//! assert_eq!(carts.get_cloned(), vec![CartUpdated(3), CartUpdated(4)]);
+//! ```
+use super::sync::Mutex;
+use super::{Sink, Stream};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::any::{Any, TypeId};
+
+type SinkMap = BTreeMap<TypeId, Box<dyn Any + Send + Sync>>;
+
+/// Routes events to subscribers by type. See the module documentation.
+///
+/// Handles are cheap to clone, and every clone publishes to and subscribes from the same bus.
+pub struct EventBus {
+    sinks: Arc<Mutex<SinkMap>>,
+}
+
+impl Clone for EventBus {
+    fn clone(&self) -> Self {
+        EventBus {
+            sinks: Arc::clone(&self.sinks),
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus {
+            sinks: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Sends an event to every subscriber of its type. Events that nobody has subscribed to are
+    /// dropped.
+    pub fn publish<E: Send + Sync + 'static>(&self, event: E) {
+        self.publish_rc(Arc::new(event))
+    }
+
+    /// Same logic as `publish`, but takes an existing Arc pointer.
+    pub fn publish_rc<E: Send + Sync + 'static>(&self, event: Arc<E>) {
+        // Look up the stream first so that subscribers can use the bus while handling the event.
+        let stream = {
+            let sinks = self.sinks.lock();
+            match sinks.get(&TypeId::of::<E>()) {
+                Some(sink) => Self::downcast::<E>(&**sink).get_stream(),
+                None => return,
+            }
+        };
+        stream.emit_rc(event);
+    }
+
+    /// Returns a stream of every event of type `E` published to this bus from now on.
+    pub fn subscribe<E: Send + Sync + 'static>(&self) -> Stream<E> {
+        let mut sinks = self.sinks.lock();
+        let sink = sinks
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Sink::<E>::new()));
+        Self::downcast::<E>(&**sink).get_stream()
+    }
+
+    fn downcast<E: 'static>(sink: &(dyn Any + Send + Sync)) -> &Sink<E> {
+        match sink.downcast_ref::<Sink<E>>() {
+            Some(sink) => sink,
+            None => unreachable!("Event bus sinks are keyed by their event type"),
+        }
+    }
+}
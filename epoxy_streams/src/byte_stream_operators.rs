@@ -0,0 +1,196 @@
+use super::{Stream, Subscription};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+struct ByteFramingFields {
+    // Bytes that have been received but do not form a complete record yet.
+    buffer: Vec<u8>,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Vec<u8>>>,
+}
+
+impl Stream<Vec<u8>> {
+    /// Splits a stream of byte buffers (for example reads from a socket or file) into records
+    /// separated by `delimiter`. Records can span any number of buffers, and a single buffer can
+    /// contain any number of records. The delimiter is not included in the emitted records. When
+    /// the stream completes, any bytes after the last delimiter are emitted as a final record.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let records = stream_host.get_stream().split(0);
+    /// let cache = ReactiveCache::from_stream(records);
+    ///
+    /// stream_host.emit(b"ab\0c".to_vec());
+    /// stream_host.emit(b"d\0\0e".to_vec());
+    /// assert_eq!(cache.get_cloned(), vec![b"ab".to_vec(), b"cd".to_vec(), vec![]]);
+    ///
+    /// stream_host.close();
+    /// assert_eq!(cache.get_cloned().back(), Some(&b"e".to_vec()));
+    /// ```
+    pub fn split(&self, delimiter: u8) -> Stream<Vec<u8>> {
+        self.frame_bytes(
+            move |buffer, bytes| {
+                let mut records = vec![];
+                for (index, segment) in bytes.split(|byte| *byte == delimiter).enumerate() {
+                    if index > 0 {
+                        records.push(core::mem::take(buffer));
+                    }
+                    buffer.extend_from_slice(segment);
+                }
+                records
+            },
+            |buffer| buffer,
+        )
+    }
+
+    /// Splits a stream of byte buffers into lines of text. Lines end with `\n` or `\r\n`, neither
+    /// of which is included in the emitted strings. Invalid UTF-8 is replaced with U+FFFD rather
+    /// than ending the stream. When the stream completes, any text after the last line break is
+    /// emitted as a final line.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let lines = ReactiveCache::from_stream(stream_host.get_stream().lines());
+    ///
+    /// stream_host.emit(b"GET / HTTP/1.1\r\nHost: exa".to_vec());
+    /// stream_host.emit(b"mple.com\r\n\r\n".to_vec());
+    /// assert_eq!(
+    ///     lines.get_cloned(),
+    ///     vec!["GET / HTTP/1.1", "Host: example.com", ""]
+    /// );
+    /// ```
+    pub fn lines(&self) -> Stream<String> {
+        self.split(b'\n').map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            String::from_utf8_lossy(line).into_owned()
+        })
+    }
+
+    /// Decodes a stream of byte buffers as UTF-8 text. A character whose bytes are spread across
+    /// several buffers is held back until it is complete, so every emitted string contains only
+    /// whole characters. Invalid UTF-8 is replaced with U+FFFD. Buffers that do not complete any
+    /// characters do not produce an emission.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let text = ReactiveCache::from_stream(stream_host.get_stream().decode_utf8());
+    ///
+    /// let bytes = "añb".as_bytes();
+    /// stream_host.emit(bytes[..2].to_vec());
+    /// stream_host.emit(bytes[2..].to_vec());
+    /// assert_eq!(text.get_cloned(), vec!["a", "ñb"]);
+    /// ```
+    pub fn decode_utf8(&self) -> Stream<String> {
+        self.frame_bytes(
+            |buffer, bytes| {
+                buffer.extend_from_slice(bytes);
+                let text = decode_complete_utf8(buffer);
+                if text.is_empty() {
+                    vec![]
+                } else {
+                    vec![text]
+                }
+            },
+            // An incomplete character at the end of the stream is never going to be completed.
+            |buffer| String::from_utf8_lossy(&buffer).into_owned(),
+        )
+    }
+
+    /// Shared implementation of the byte framing operators. `process` receives the buffered
+    /// bytes and the new bytes, and returns the completed records. `finish` turns whatever is
+    /// left in the buffer (if anything) into a final record when the stream completes.
+    fn frame_bytes<U, P, F>(&self, process: P, finish: F) -> Stream<U>
+    where
+        U: Send,
+        U: Sync,
+        U: 'static,
+        P: Fn(&mut Vec<u8>, &[u8]) -> Vec<U>,
+        P: Send,
+        P: Sync,
+        P: 'static,
+        F: Fn(Vec<u8>) -> U,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let derived_stream = self.derive_with_fields(ByteFramingFields {
+            buffer: vec![],
+            subscription: None,
+        });
+        let subscription_stream_ref = derived_stream.clone();
+        let completion_stream_ref = derived_stream.clone();
+
+        let subscription = self.subscribe_with_completion(
+            move |bytes| {
+                let mut records = vec![];
+                subscription_stream_ref.mutate_extra_fields(|fields: &mut ByteFramingFields| {
+                    records = process(&mut fields.buffer, &bytes);
+                });
+                for record in records {
+                    subscription_stream_ref.emit_rc(Arc::new(record));
+                }
+            },
+            move || {
+                let mut buffer = vec![];
+                completion_stream_ref.mutate_extra_fields(|fields: &mut ByteFramingFields| {
+                    buffer = core::mem::take(&mut fields.buffer);
+                });
+                if !buffer.is_empty() {
+                    completion_stream_ref.emit_rc(Arc::new(finish(buffer)));
+                }
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut ByteFramingFields| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
+
+/// Removes and decodes every complete character at the start of `buffer`, leaving behind only an
+/// incomplete character at the very end (if there is one).
+fn decode_complete_utf8(buffer: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    let mut consumed = 0;
+    loop {
+        match core::str::from_utf8(&buffer[consumed..]) {
+            Ok(valid) => {
+                text.push_str(valid);
+                consumed = buffer.len();
+                break;
+            }
+            Err(err) => {
+                let valid_end = consumed + err.valid_up_to();
+                // The bytes were checked by from_utf8 above.
+                text.push_str(core::str::from_utf8(&buffer[consumed..valid_end]).unwrap());
+                match err.error_len() {
+                    Some(invalid_len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        consumed = valid_end + invalid_len;
+                    }
+                    None => {
+                        consumed = valid_end;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    buffer.drain(..consumed);
+    text
+}
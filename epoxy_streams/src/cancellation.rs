@@ -0,0 +1,174 @@
+use super::sync::Mutex;
+use super::Stream;
+use alloc::boxed::Box;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+type CancelCallback = Box<dyn FnOnce() + Send>;
+
+struct TokenState {
+    is_cancelled: bool,
+    callbacks: Vec<CancelCallback>,
+    children: Vec<Arc<TokenImpl>>,
+}
+
+struct TokenImpl {
+    state: Mutex<TokenState>,
+    parent: Option<Weak<TokenImpl>>,
+}
+
+/// Ties the lifetime of subscriptions to an explicit cancellation, rather than to the scope of
+/// each Subscription object. Subscriptions created with `Stream::subscribe_until` are owned by the
+/// token, and are dropped when it is cancelled (or when every handle to the token is dropped).
+///
+/// Tokens form a tree. Cancelling a token cancels all of its children, so a component can hand a
+/// child token to each of its sub-components and tear the whole tree down with a single call.
+///
+/// Handles are cheap to clone, and every clone refers to the same token.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::CancellationToken;
+///
+/// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+/// let stream = stream_host.get_stream();
+///
+/// let component = CancellationToken::new();
+/// let sub_component = component.child_token();
+/// stream.subscribe_until(&component, |_| {});
+/// stream.subscribe_until(&sub_component, |_| {});
+/// assert_eq!(stream.count_subscribers(), 2);
+///
+/// component.cancel();
+/// assert!(sub_component.is_cancelled());
+/// assert_eq!(stream.count_subscribers(), 0);
+/// ```
+pub struct CancellationToken {
+    pointer: Arc<TokenImpl>,
+}
+
+impl Clone for CancellationToken {
+    fn clone(&self) -> Self {
+        CancellationToken {
+            pointer: Arc::clone(&self.pointer),
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        CancellationToken::new()
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::new_with_parent(None)
+    }
+
+    fn new_with_parent(parent: Option<Weak<TokenImpl>>) -> CancellationToken {
+        CancellationToken {
+            pointer: Arc::new(TokenImpl {
+                state: Mutex::new(TokenState {
+                    is_cancelled: false,
+                    callbacks: Vec::new(),
+                    children: Vec::new(),
+                }),
+                parent,
+            }),
+        }
+    }
+
+    /// Creates a token that is cancelled along with this one. Cancelling the child does not
+    /// affect this token. The child is kept alive by this token until one of them is cancelled,
+    /// so it is fine to drop the returned handle once it has been used. If this token has already
+    /// been cancelled, the child is created cancelled.
+    pub fn child_token(&self) -> CancellationToken {
+        let child = CancellationToken::new_with_parent(Some(Arc::downgrade(&self.pointer)));
+        let mut state = self.pointer.state.lock();
+        if state.is_cancelled {
+            drop(state);
+            child.cancel();
+        } else {
+            state.children.push(Arc::clone(&child.pointer));
+        }
+        child
+    }
+
+    /// Cancels the token and all of its children, dropping the subscriptions they own and running
+    /// their `on_cancel` callbacks. Does nothing if the token has already been cancelled.
+    pub fn cancel(&self) {
+        cancel_token(&self.pointer);
+    }
+
+    /// Returns true once the token, or any of its ancestors, has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.pointer.state.lock().is_cancelled
+    }
+
+    /// Runs the given function when the token is cancelled, or right away if it already has been.
+    pub fn on_cancel<F>(&self, callback: F)
+    where
+        F: FnOnce(),
+        F: Send,
+        F: 'static,
+    {
+        let mut state = self.pointer.state.lock();
+        if state.is_cancelled {
+            drop(state);
+            callback();
+        } else {
+            state.callbacks.push(Box::new(callback));
+        }
+    }
+}
+
+fn cancel_token(token: &Arc<TokenImpl>) {
+    let (callbacks, children) = {
+        let mut state = token.state.lock();
+        if state.is_cancelled {
+            return;
+        }
+        state.is_cancelled = true;
+        (
+            core::mem::take(&mut state.callbacks),
+            core::mem::take(&mut state.children),
+        )
+    };
+
+    // Callbacks run without holding the lock, since they usually drop subscriptions, and a
+    // subscriber might be holding on to the token as well.
+    for child in children {
+        cancel_token(&child);
+    }
+    for callback in callbacks {
+        callback();
+    }
+
+    if let Some(parent) = token.parent.as_ref().and_then(Weak::upgrade) {
+        parent
+            .state
+            .lock()
+            .children
+            .retain(|child| !Arc::ptr_eq(child, token));
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Same as `subscribe`, except that the subscription is owned by the given token and lasts
+    /// until the token is cancelled. Nothing is subscribed if the token has already been
+    /// cancelled.
+    pub fn subscribe_until<F>(&self, token: &CancellationToken, listener: F)
+    where
+        F: Fn(Arc<T>),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        if token.is_cancelled() {
+            return;
+        }
+        let subscription = self.subscribe(listener);
+        token.on_cancel(move || drop(subscription));
+    }
+}
@@ -0,0 +1,299 @@
+//! Saving and restoring the state of stateful operators, so that a long-running pipeline can pick
+//! up where it left off after the application restarts. The state of an operator is whatever it
+//! has accumulated from the values it has seen so far, like the running result of `scan`, the
+//! latest value per key of `compact_latest` or the open session of `session_window`.
+//!
+//! `Stream::checkpoint_state` returns the state of a single operator as JSON, and
+//! `Stream::restore_state` puts it back. A `Checkpointer` does the same for every operator in a
+//! pipeline at once, and produces a `Checkpoint` that can be saved to a file. Checkpoints record a
+//! position as well, which is meant to be the number of input values the state reflects. Combined
+//! with a `journal::Journal` as the input, restoring the checkpoint and then calling
+//! `Journal::replay_from` with that position brings the pipeline back to exactly where it was.
+//!
+//! The operators that support checkpoints are `scan` (and `buffer`, which is built on it),
+//! `compact_latest` and `session_window`. A restored session closes once the session gap has
+//! passed without a new value, counted from the restore, or when the stream completes.
+//!
+//! Snapshots read each operator in turn, so they should be taken while no values are flowing
+//! through the pipeline, for example on the thread that feeds it, in between two values.
+//! Otherwise a value can be reflected in the state of one operator but not yet in the next.
+//!
+//! Requires the `checkpoint` feature.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::checkpoint::Checkpointer;
+//! use epoxy_streams::{ReactiveCache, Sink};
+//!
+//! let stream_host: Sink<i32> = Sink::new();
+//! let total = stream_host.get_stream().scan(|acc, val| acc + *val, 0);
+//! let checkpointer = Checkpointer::new();
+//! checkpointer.register("total", &total).unwrap();
+//!
+//! stream_host.emit(5);
+//! stream_host.emit(10);
+//! let checkpoint = checkpointer.snapshot(2).unwrap();
+//!
+//! // After a restart, build the same pipeline and restore it before emitting new values.
+//! let stream_host: Sink<i32> = Sink::new();
+//! let total = stream_host.get_stream().scan(|acc, val| acc + *val, 0);
+//! let totals = ReactiveCache::from_stream(total.clone());
+//! let checkpointer = Checkpointer::new();
+//! checkpointer.register("total", &total).unwrap();
+//! checkpointer.restore(&checkpoint).unwrap();
+//!
+//! assert_eq!(checkpoint.position, 2);
+//! stream_host.emit(1);
+//! assert_eq!(totals.get_cloned(), vec![16]);
+//! ```
+use super::streams::StreamImpl;
+use super::{CheckpointError, Stream};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type SaveState<T> = dyn Fn(&StreamImpl<T>) -> Vec<Arc<T>> + Send + Sync;
+type RestoreState<T> = dyn Fn(&mut StreamImpl<T>, Vec<Arc<T>>) + Send + Sync;
+
+// Reads and replaces the state of a stateful operator. The state is expressed as values of the
+// stream's own type, so it can be serialized whenever the stream's values can.
+pub(crate) struct StateHooks<T> {
+    save: Box<SaveState<T>>,
+    restore: Box<RestoreState<T>>,
+}
+
+type SaveEntry = Box<dyn Fn() -> Option<Result<Value, CheckpointError>> + Send + Sync>;
+type RestoreEntry = Box<dyn Fn(Value) -> Result<(), CheckpointError> + Send + Sync>;
+
+struct CheckpointEntry {
+    save: SaveEntry,
+    restore: RestoreEntry,
+}
+
+/// The state of every stream registered with a `Checkpointer`, by name.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The position passed to `Checkpointer::snapshot`.
+    pub position: u64,
+    pub states: BTreeMap<String, Value>,
+}
+
+impl Checkpoint {
+    /// Writes the checkpoint to a file as JSON. The file is replaced in a single step, so a crash
+    /// while saving leaves the previous checkpoint intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Reads a checkpoint written by `save`. Returns None if the file does not exist, as is the
+    /// case the first time an application starts.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Checkpoint>, CheckpointError> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Takes and restores checkpoints of a set of named streams, usually every stateful operator in
+/// a pipeline. Registered streams are not kept alive by the Checkpointer, and are left out of
+/// snapshots once they have been dropped.
+pub struct Checkpointer {
+    entries: Mutex<BTreeMap<String, CheckpointEntry>>,
+}
+
+impl Checkpointer {
+    pub fn new() -> Checkpointer {
+        Checkpointer {
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn lock_entries(&self) -> MutexGuard<'_, BTreeMap<String, CheckpointEntry>> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(err) => panic!("Checkpointer mutex poisoned: {}", err),
+        }
+    }
+
+    /// Adds a stream to every checkpoint under the given name, replacing any stream previously
+    /// registered under that name. Returns `CheckpointError::NotCheckpointable` if the stream was
+    /// not created by an operator that supports checkpoints.
+    pub fn register<T>(&self, name: &str, stream: &Stream<T>) -> Result<(), CheckpointError>
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        if stream.pointer.lock().state_hooks.is_none() {
+            return Err(CheckpointError::NotCheckpointable);
+        }
+        let save_pointer = Arc::downgrade(&stream.pointer);
+        let restore_pointer = Arc::downgrade(&stream.pointer);
+        self.lock_entries().insert(
+            name.to_string(),
+            CheckpointEntry {
+                save: Box::new(move || {
+                    save_pointer
+                        .upgrade()
+                        .map(|pointer| Stream { pointer }.checkpoint_state())
+                }),
+                restore: Box::new(move |state| match restore_pointer.upgrade() {
+                    Some(pointer) => Stream { pointer }.restore_state(state),
+                    None => Ok(()),
+                }),
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns the state of every registered stream that is still alive, along with `position`.
+    /// See the module documentation for when to call this.
+    pub fn snapshot(&self, position: u64) -> Result<Checkpoint, CheckpointError> {
+        let entries = self.lock_entries();
+        let mut states = BTreeMap::new();
+        for (name, entry) in entries.iter() {
+            if let Some(state) = (entry.save)() {
+                states.insert(name.clone(), state?);
+            }
+        }
+        Ok(Checkpoint { position, states })
+    }
+
+    /// Restores the state of every registered stream from the checkpoint. Streams that are not in
+    /// the checkpoint, like operators that were added since it was taken, keep their current
+    /// state.
+    pub fn restore(&self, checkpoint: &Checkpoint) -> Result<(), CheckpointError> {
+        let entries = self.lock_entries();
+        for (name, entry) in entries.iter() {
+            if let Some(state) = checkpoint.states.get(name) {
+                (entry.restore)(state.clone())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Checkpointer {
+    fn default() -> Self {
+        Checkpointer::new()
+    }
+}
+
+impl<T: 'static> Stream<T> {
+    /// Makes the state of a stateful operator available to checkpoints. `save` returns the state
+    /// held in the operator's fields, and `restore` replaces it.
+    pub(crate) fn set_state_hooks<F, S, R>(&self, save: S, restore: R)
+    where
+        F: 'static,
+        S: Fn(&F) -> Vec<Arc<T>> + Send + Sync + 'static,
+        R: Fn(&mut F, Vec<Arc<T>>) + Send + Sync + 'static,
+    {
+        let hooks = StateHooks {
+            save: Box::new(move |stream_impl: &StreamImpl<T>| {
+                match stream_impl
+                    .extra_fields
+                    .as_ref()
+                    .and_then(|fields| fields.downcast_ref::<F>())
+                {
+                    Some(fields) => save(fields),
+                    None => Vec::new(),
+                }
+            }),
+            restore: Box::new(move |stream_impl: &mut StreamImpl<T>, values| {
+                if let Some(fields) = stream_impl
+                    .extra_fields
+                    .as_mut()
+                    .and_then(|fields| fields.downcast_mut::<F>())
+                {
+                    restore(fields, values);
+                }
+            }),
+        };
+        self.pointer.lock().state_hooks = Some(Arc::new(hooks));
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> Stream<T> {
+    /// Returns the state of the operator that created this stream, as JSON. Returns
+    /// `CheckpointError::NotCheckpointable` for streams whose operator does not support
+    /// checkpoints (see the `checkpoint` module).
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::Sink;
+    ///
+    /// let stream_host: Sink<(&'static str, i32)> = Sink::new();
+    /// let latest = stream_host.get_stream().compact_latest();
+    /// stream_host.emit(("a", 1));
+    /// stream_host.emit(("b", 2));
+    /// stream_host.emit(("a", 3));
+    ///
+    /// let state = latest.checkpoint_state().unwrap();
+    /// assert_eq!(state.to_string(), r#"[["a",3],["b",2]]"#);
+    /// assert!(stream_host.get_stream().checkpoint_state().is_err());
+    /// ```
+    pub fn checkpoint_state(&self) -> Result<Value, CheckpointError> {
+        let values = {
+            let stream_impl = self.pointer.lock();
+            match &stream_impl.state_hooks {
+                Some(hooks) => (hooks.save)(&*stream_impl),
+                None => return Err(CheckpointError::NotCheckpointable),
+            }
+        };
+        let values: Vec<&T> = values.iter().map(|value| &**value).collect();
+        Ok(serde_json::to_value(values)?)
+    }
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> Stream<T> {
+    /// Replaces the state of the operator that created this stream with one returned by
+    /// `checkpoint_state`. Nothing is emitted right away, the restored state only affects the
+    /// values that the operator emits from then on. A restored `session_window` session ends once
+    /// the session gap has passed, even if no new value arrives.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::scheduler::ManualScheduler;
+    /// use epoxy_streams::{ReactiveCache, Sink};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let scheduler = Arc::new(ManualScheduler::new());
+    /// let stream_host: Sink<String> = Sink::new();
+    /// let sessions = stream_host
+    ///     .get_stream()
+    ///     .with_scheduler(scheduler.clone())
+    ///     .session_window(Duration::from_secs(60));
+    /// let cache = ReactiveCache::from_stream(sessions.clone());
+    ///
+    /// sessions.restore_state(serde_json::json!([["home", "search"]])).unwrap();
+    /// assert!(cache.get_cloned().is_empty());
+    ///
+    /// scheduler.advance(Duration::from_secs(60));
+    /// let restored: Vec<String> = cache.get_cloned()[0].iter().map(|page| (**page).clone()).collect();
+    /// assert_eq!(restored, vec!["home".to_string(), "search".to_string()]);
+    /// ```
+    pub fn restore_state(&self, state: Value) -> Result<(), CheckpointError> {
+        let values: Vec<T> = serde_json::from_value(state)?;
+        let mut stream_impl = self.pointer.lock();
+        let hooks = match &stream_impl.state_hooks {
+            Some(hooks) => hooks.clone(),
+            None => return Err(CheckpointError::NotCheckpointable),
+        };
+        (hooks.restore)(
+            &mut *stream_impl,
+            values.into_iter().map(Arc::new).collect(),
+        );
+        Ok(())
+    }
+}
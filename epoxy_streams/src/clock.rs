@@ -0,0 +1,157 @@
+//! Clocks tell epoxy what time it is. Operators that measure time (such as `rate_limit` or the
+//! watchdog) read it from their scheduler, which asks the application's clock by default (see
+//! `config::set_clock`). Code that stamps values with a date, like `recording`, asks the clock
+//! for wall-clock time.
+//!
+//! Replacing the clock with a `TestClock` makes timestamps and measured durations reproducible.
+//! The default schedulers measure their delays with the clock too, so timers only come due as the
+//! `TestClock` is advanced, and fire shortly after on the scheduler's thread. For full control
+//! over when timers run as well, use `runtime::manual`.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::clock::{Clock, TestClock};
+//! use epoxy_streams::config;
+//! use std::sync::Arc;
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! let clock = Arc::new(TestClock::starting_at(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+//! config::set_clock(clock.clone());
+//!
+//! let start = config::clock().now();
+//! clock.advance(Duration::from_secs(90));
+//! assert_eq!(config::clock().now() - start, Duration::from_secs(90));
+//! assert_eq!(
+//!     config::clock().wall_time(),
+//!     UNIX_EPOCH + Duration::from_secs(1_700_000_090)
+//! );
+//! config::reset_clock();
+//! ```
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+    /// Returns a time that never goes backwards, for measuring how much time has passed.
+    fn now(&self) -> Instant;
+
+    /// Returns the current date and time, for timestamps that are stored or shown to people.
+    fn wall_time(&self) -> SystemTime;
+}
+
+/// Reads the time from the operating system. Its wall-clock time follows any adjustments made
+/// to the system clock, so it can jump backwards. This is the default clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Reads the system's wall-clock time once, and from then on derives it from the monotonic
+/// clock. Timestamps taken from it always increase, even if the system clock is adjusted, at the
+/// cost of drifting away from the system clock over very long runs.
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicClock {
+    start: Instant,
+    start_wall_time: SystemTime,
+}
+
+impl MonotonicClock {
+    /// Creates a clock anchored to the current system time.
+    pub fn new() -> MonotonicClock {
+        MonotonicClock {
+            start: Instant::now(),
+            start_wall_time: SystemTime::now(),
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> MonotonicClock {
+        MonotonicClock::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.start_wall_time + self.start.elapsed()
+    }
+}
+
+#[derive(Debug)]
+struct TestClockState {
+    elapsed: Duration,
+    wall_time: SystemTime,
+}
+
+/// A clock that stands still until it is told to move, for tests.
+#[derive(Debug)]
+pub struct TestClock {
+    start: Instant,
+    state: Mutex<TestClockState>,
+}
+
+impl TestClock {
+    /// Creates a clock that starts at the current time.
+    pub fn new() -> TestClock {
+        TestClock::starting_at(SystemTime::now())
+    }
+
+    /// Creates a clock whose wall-clock time starts at `wall_time`.
+    pub fn starting_at(wall_time: SystemTime) -> TestClock {
+        TestClock {
+            start: Instant::now(),
+            state: Mutex::new(TestClockState {
+                elapsed: Duration::ZERO,
+                wall_time,
+            }),
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, TestClockState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(err) => panic!("Test clock mutex poisoned: {}", err),
+        }
+    }
+
+    /// Moves both the monotonic and the wall-clock time forward.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.lock_state();
+        state.elapsed += duration;
+        state.wall_time += duration;
+    }
+
+    /// Changes the wall-clock time without moving the monotonic time, like a user adjusting the
+    /// system clock. The new time may be earlier than the current one.
+    pub fn set_wall_time(&self, wall_time: SystemTime) {
+        self.lock_state().wall_time = wall_time;
+    }
+}
+
+impl Default for TestClock {
+    fn default() -> TestClock {
+        TestClock::new()
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Instant {
+        self.start + self.lock_state().elapsed
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.lock_state().wall_time
+    }
+}
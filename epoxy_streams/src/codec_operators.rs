@@ -0,0 +1,144 @@
+//! Operators that convert between byte buffers and typed values with serde. Each wire format is
+//! behind its own feature: `json`, `bincode` and `msgpack`.
+//!
+//! Decoding failures are emitted as `Err` values instead of ending the stream, so one malformed
+//! message does not take down the whole pipeline. Each buffer is expected to hold exactly one
+//! message; use framing operators such as `Stream::split` or `Stream::lines` first if messages
+//! can span several reads.
+use super::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+impl Stream<Vec<u8>> {
+    /// Parses every buffer as a JSON document.
+    ///
+    /// Requires the `json` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let numbers = stream_host.get_stream().decode_json::<Vec<i32>>();
+    /// let cache = ReactiveCache::from_stream(numbers.map(|result| result.is_ok()));
+    ///
+    /// stream_host.emit(b"[1, 2, 3]".to_vec());
+    /// stream_host.emit(b"[1, 2,".to_vec());
+    /// assert_eq!(cache.get_cloned(), vec![true, false]);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn decode_json<T>(&self) -> Stream<Result<T, serde_json::Error>>
+    where
+        T: DeserializeOwned,
+        T: 'static,
+    {
+        self.map(|bytes| serde_json::from_slice(bytes))
+    }
+
+    /// Decodes every buffer with bincode's default configuration.
+    ///
+    /// Requires the `bincode` feature.
+    #[cfg(feature = "bincode")]
+    pub fn decode_bincode<T>(&self) -> Stream<Result<T, bincode::Error>>
+    where
+        T: DeserializeOwned,
+        T: 'static,
+    {
+        self.map(|bytes| bincode::deserialize(bytes))
+    }
+
+    /// Decodes every buffer as a MessagePack value.
+    ///
+    /// Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn decode_msgpack<T>(&self) -> Stream<Result<T, rmp_serde::decode::Error>>
+    where
+        T: DeserializeOwned,
+        T: 'static,
+    {
+        self.map(|bytes| rmp_serde::from_slice(bytes))
+    }
+}
+
+impl<T> Stream<T>
+where
+    T: Serialize,
+    T: Send,
+    T: Sync,
+    T: 'static,
+{
+    /// Serializes every value as a JSON document. Serialization only fails for values that JSON
+    /// can not represent, such as maps with non-string keys.
+    ///
+    /// Requires the `json` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<(String, u32)> = epoxy_streams::Sink::new();
+    /// let json = stream_host
+    ///     .get_stream()
+    ///     .encode_json()
+    ///     .map(|result| result.as_ref().unwrap().clone());
+    /// let cache = ReactiveCache::from_stream(json);
+    ///
+    /// stream_host.emit(("temperature".to_string(), 21));
+    /// assert_eq!(cache.get_cloned(), vec![br#"["temperature",21]"#.to_vec()]);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn encode_json(&self) -> Stream<Result<Vec<u8>, serde_json::Error>> {
+        self.map(|value| serde_json::to_vec(value))
+    }
+
+    /// Serializes every value with bincode's default configuration.
+    ///
+    /// Requires the `bincode` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<u64> = epoxy_streams::Sink::new();
+    /// let round_trip = stream_host
+    ///     .get_stream()
+    ///     .encode_bincode()
+    ///     .map(|result| result.as_ref().unwrap().clone())
+    ///     .decode_bincode::<u64>()
+    ///     .map(|result| *result.as_ref().unwrap());
+    /// let cache = ReactiveCache::from_stream(round_trip);
+    ///
+    /// stream_host.emit(1 << 40);
+    /// assert_eq!(cache.get_cloned(), vec![1 << 40]);
+    /// ```
+    #[cfg(feature = "bincode")]
+    pub fn encode_bincode(&self) -> Stream<Result<Vec<u8>, bincode::Error>> {
+        self.map(|value| bincode::serialize(value))
+    }
+
+    /// Serializes every value as MessagePack. Structs are written as arrays, which is compact but
+    /// means both sides need to agree on the field order.
+    ///
+    /// Requires the `msgpack` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<String>> = epoxy_streams::Sink::new();
+    /// let round_trip = stream_host
+    ///     .get_stream()
+    ///     .encode_msgpack()
+    ///     .map(|result| result.as_ref().unwrap().clone())
+    ///     .decode_msgpack::<Vec<String>>()
+    ///     .map(|result| result.as_ref().unwrap().clone());
+    /// let cache = ReactiveCache::from_stream(round_trip);
+    ///
+    /// stream_host.emit(vec!["a".to_string(), "b".to_string()]);
+    /// assert_eq!(cache.get_cloned(), vec![vec!["a".to_string(), "b".to_string()]]);
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn encode_msgpack(&self) -> Stream<Result<Vec<u8>, rmp_serde::encode::Error>> {
+        self.map(|value| rmp_serde::to_vec(value))
+    }
+}
@@ -0,0 +1,92 @@
+use super::streams::StreamImpl;
+use super::{Stream, Subscription};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+struct CompactLatestFields<K, V> {
+    latest: BTreeMap<K, Arc<(K, V)>>,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<(K, V)>>,
+}
+
+impl<K, V> Stream<(K, V)>
+where
+    K: Ord + Clone + Send + Sync + 'static,
+    V: Send + Sync + 'static,
+{
+    /// Returns a stream of key-value updates that remembers the latest value for each key. Every
+    /// new subscriber first receives that snapshot, one value per key in key order, and then the
+    /// updates that follow, so late subscribers end up with the same state as early ones without
+    /// having to see every update that came before. This holds even after the stream completes.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<(&str, i32)> = epoxy_streams::Sink::new();
+    /// let compacted = stream_host.get_stream().compact_latest();
+    /// let early = ReactiveCache::from_stream(compacted.clone());
+    ///
+    /// stream_host.emit(("b", 1));
+    /// stream_host.emit(("a", 2));
+    /// stream_host.emit(("b", 3));
+    ///
+    /// let late = ReactiveCache::from_stream(compacted.clone());
+    /// stream_host.emit(("c", 4));
+    ///
+    /// assert_eq!(early.get_cloned(), vec![("b", 1), ("a", 2), ("b", 3), ("c", 4)]);
+    /// assert_eq!(late.get_cloned(), vec![("a", 2), ("b", 3), ("c", 4)]);
+    /// ```
+    pub fn compact_latest(&self) -> Stream<(K, V)> {
+        let derived_stream = self.derive_with_fields(CompactLatestFields::<K, V> {
+            latest: BTreeMap::new(),
+            subscription: None,
+        });
+        derived_stream.set_replay(|stream_impl: &StreamImpl<(K, V)>| {
+            match stream_impl
+                .extra_fields
+                .as_ref()
+                .and_then(|fields| fields.downcast_ref::<CompactLatestFields<K, V>>())
+            {
+                Some(fields) => fields.latest.values().cloned().collect(),
+                None => Vec::new(),
+            }
+        });
+
+        #[cfg(feature = "checkpoint")]
+        derived_stream.set_state_hooks(
+            |fields: &CompactLatestFields<K, V>| fields.latest.values().cloned().collect(),
+            |fields: &mut CompactLatestFields<K, V>, values| {
+                fields.latest = values.into_iter().map(|val| (val.0.clone(), val)).collect();
+            },
+        );
+
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                if let Some(pointer) = weak_stream_ref.upgrade() {
+                    Stream { pointer }.mutate_extra_fields_and_emit(
+                        |fields: &mut CompactLatestFields<K, V>| {
+                            fields.latest.insert(val.0.clone(), val.clone());
+                            Some(val)
+                        },
+                    );
+                }
+            },
+            move || {
+                if let Some(pointer) = weak_completion_ref.upgrade() {
+                    Stream { pointer }.complete();
+                }
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut CompactLatestFields<K, V>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
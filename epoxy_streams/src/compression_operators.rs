@@ -0,0 +1,219 @@
+//! Operators that compress and decompress streams of byte buffers. Each format is behind its own
+//! feature: `zstd` and `gzip`.
+//!
+//! The operators keep one compression context for the lifetime of the stream, so later buffers
+//! can refer back to data in earlier ones. The compressor flushes after every buffer, which means
+//! each compressed buffer can be decompressed as soon as it arrives (this is what network bridges
+//! need) at the cost of a somewhat worse compression ratio for very small buffers. Use `buffer`
+//! or `chunk_by` to batch small buffers together first if that matters.
+//!
+//! Errors are emitted as `Err` values. The compression context can not be used after an error,
+//! so the derived stream completes right after emitting one.
+use super::{Stream, Subscription};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// A compressor or decompressor that writes its output into a Vec.
+trait ByteCodec: Write {
+    fn output(&mut self) -> &mut Vec<u8>;
+
+    fn finish_output(self: Box<Self>) -> io::Result<Vec<u8>>;
+
+    fn process(&mut self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        self.write_all(bytes)?;
+        self.flush()?;
+        Ok(std::mem::take(self.output()))
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl ByteCodec for zstd::stream::write::Encoder<'static, Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+
+    fn finish_output(self: Box<Self>) -> io::Result<Vec<u8>> {
+        self.finish()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl ByteCodec for zstd::stream::write::Decoder<'static, Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+
+    fn finish_output(mut self: Box<Self>) -> io::Result<Vec<u8>> {
+        self.flush()?;
+        Ok(self.into_inner())
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl ByteCodec for flate2::write::GzEncoder<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+
+    fn finish_output(self: Box<Self>) -> io::Result<Vec<u8>> {
+        self.finish()
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl ByteCodec for flate2::write::GzDecoder<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+
+    fn finish_output(self: Box<Self>) -> io::Result<Vec<u8>> {
+        self.finish()
+    }
+}
+
+type SharedCodec = Arc<Mutex<Option<Box<dyn ByteCodec + Send>>>>;
+
+struct CodecStreamFields {
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Vec<u8>>>,
+}
+
+/// Runs `bytes` through the codec and emits the result. Does nothing if the codec has already
+/// failed.
+fn emit_processed(codec: &SharedCodec, stream: &Stream<io::Result<Vec<u8>>>, bytes: &[u8]) {
+    let result = match codec.lock() {
+        Ok(mut codec) => match codec.as_mut() {
+            Some(active_codec) => {
+                let result = active_codec.process(bytes);
+                if result.is_err() {
+                    *codec = None;
+                }
+                result
+            }
+            None => return,
+        },
+        Err(err) => panic!("Codec mutex poisoned: {}", err),
+    };
+    let failed = result.is_err();
+    if !matches!(result, Ok(ref output) if output.is_empty()) {
+        stream.emit_rc(Arc::new(result));
+    }
+    if failed {
+        stream.complete();
+    }
+}
+
+impl Stream<Vec<u8>> {
+    /// Compresses a stream of byte buffers with zstd at the given compression level (1-22, or 0
+    /// for zstd's default). The zstd frame is closed when the source stream completes.
+    ///
+    /// Requires the `zstd` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let round_trip = stream_host
+    ///     .get_stream()
+    ///     .compress_zstd(3)
+    ///     .map(|result| result.as_ref().unwrap().clone())
+    ///     .decompress_zstd()
+    ///     .map(|result| result.as_ref().unwrap().clone());
+    /// let cache = ReactiveCache::from_stream(round_trip);
+    ///
+    /// stream_host.emit(b"hello ".to_vec());
+    /// stream_host.emit(b"world".to_vec());
+    /// assert_eq!(cache.get_cloned(), vec![b"hello ".to_vec(), b"world".to_vec()]);
+    /// ```
+    #[cfg(feature = "zstd")]
+    pub fn compress_zstd(&self, level: i32) -> Stream<io::Result<Vec<u8>>> {
+        // zstd clamps out of range levels, so this can only fail if zstd runs out of memory.
+        match zstd::stream::write::Encoder::new(vec![], level) {
+            Ok(encoder) => self.pipe_through_codec(Box::new(encoder)),
+            Err(err) => panic!("Could not create zstd encoder: {}", err),
+        }
+    }
+
+    /// Decompresses a stream of zstd compressed buffers, such as the output of `compress_zstd`.
+    /// Output is emitted as soon as it can be decoded, so a buffer does not need to contain a
+    /// whole zstd frame.
+    ///
+    /// Requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    pub fn decompress_zstd(&self) -> Stream<io::Result<Vec<u8>>> {
+        match zstd::stream::write::Decoder::new(vec![]) {
+            Ok(decoder) => self.pipe_through_codec(Box::new(decoder)),
+            Err(err) => panic!("Could not create zstd decoder: {}", err),
+        }
+    }
+
+    /// Compresses a stream of byte buffers as a gzip file, at the given compression level (0-9).
+    /// The gzip trailer is written when the source stream completes.
+    ///
+    /// Requires the `gzip` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let round_trip = stream_host
+    ///     .get_stream()
+    ///     .compress_gzip(6)
+    ///     .map(|result| result.as_ref().unwrap().clone())
+    ///     .decompress_gzip()
+    ///     .map(|result| result.as_ref().unwrap().clone());
+    /// let cache = ReactiveCache::from_stream(round_trip);
+    ///
+    /// stream_host.emit(b"line 1\n".to_vec());
+    /// stream_host.emit(b"line 2\n".to_vec());
+    /// stream_host.close();
+    /// let output: Vec<u8> = cache.get_cloned().into_iter().flatten().collect();
+    /// assert_eq!(output, b"line 1\nline 2\n".to_vec());
+    /// ```
+    #[cfg(feature = "gzip")]
+    pub fn compress_gzip(&self, level: u32) -> Stream<io::Result<Vec<u8>>> {
+        let encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::new(level));
+        self.pipe_through_codec(Box::new(encoder))
+    }
+
+    /// Decompresses a stream of gzip compressed buffers, such as the output of `compress_gzip`.
+    ///
+    /// Requires the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    pub fn decompress_gzip(&self) -> Stream<io::Result<Vec<u8>>> {
+        self.pipe_through_codec(Box::new(flate2::write::GzDecoder::new(vec![])))
+    }
+
+    fn pipe_through_codec(&self, codec: Box<dyn ByteCodec + Send>) -> Stream<io::Result<Vec<u8>>> {
+        let derived_stream = self.derive_with_fields(CodecStreamFields { subscription: None });
+        let codec: SharedCodec = Arc::new(Mutex::new(Some(codec)));
+
+        let subscription_stream_ref = derived_stream.clone();
+        let subscription_codec_ref = codec.clone();
+        let completion_stream_ref = derived_stream.clone();
+        let subscription = self.subscribe_with_completion(
+            move |bytes| emit_processed(&subscription_codec_ref, &subscription_stream_ref, &bytes),
+            move || {
+                let codec = match codec.lock() {
+                    Ok(mut codec) => codec.take(),
+                    Err(err) => panic!("Codec mutex poisoned: {}", err),
+                };
+                if let Some(codec) = codec {
+                    match codec.finish_output() {
+                        Ok(output) if output.is_empty() => {}
+                        result => completion_stream_ref.emit_rc(Arc::new(result)),
+                    }
+                }
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut CodecStreamFields| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
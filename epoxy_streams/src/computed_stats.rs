@@ -0,0 +1,93 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A value that a `computed!` expression depends on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComputedDependency {
+    /// The name of the variable that holds the dependency in the computed expression.
+    pub name: &'static str,
+
+    /// The id of the dependency's stream, see `Stream::id`. Two computed values that depend on
+    /// the same ReactiveValue report the same id for it.
+    pub stream_id: usize,
+}
+
+/// Statistics about a value created by `computed!`, see `ReadonlyReactiveValue::stats`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComputedStats {
+    /// The number of times the value has been recomputed because a dependency changed. The
+    /// initial computation is not included.
+    pub recompute_count: u64,
+
+    /// How long the most recent computation took, including the initial one.
+    pub last_duration: Duration,
+
+    /// How long all computations took together, including the initial one.
+    pub total_duration: Duration,
+
+    /// The ReactiveValues the computed value depends on.
+    pub dependencies: Vec<ComputedDependency>,
+}
+
+/// Collects `ComputedStats` while a computed value evaluates. `computed!` creates one of these for
+/// every computed value, there is usually no need to use it directly.
+#[derive(Clone)]
+pub struct ComputedStatsRecorder {
+    stats: Arc<Mutex<Option<ComputedStats>>>,
+    dependencies: Arc<Vec<ComputedDependency>>,
+}
+
+impl ComputedStatsRecorder {
+    /// Creates a recorder for a computed value with the given dependencies, as pairs of a variable
+    /// name and a stream id.
+    pub fn new(dependencies: Vec<(&'static str, usize)>) -> ComputedStatsRecorder {
+        ComputedStatsRecorder {
+            stats: Arc::new(Mutex::new(None)),
+            dependencies: Arc::new(
+                dependencies
+                    .into_iter()
+                    .map(|(name, stream_id)| ComputedDependency { name, stream_id })
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Runs one computation of the computed value and records how long it took.
+    pub fn record<R, F>(&self, computation: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let start = Instant::now();
+        let result = computation();
+        let duration = start.elapsed();
+
+        let mut stats = match self.stats.lock() {
+            Ok(stats) => stats,
+            Err(err) => panic!("Computed stats mutex poisoned: {}", err),
+        };
+        match &mut *stats {
+            Some(stats) => {
+                stats.recompute_count += 1;
+                stats.last_duration = duration;
+                stats.total_duration += duration;
+            }
+            None => {
+                *stats = Some(ComputedStats {
+                    recompute_count: 0,
+                    last_duration: duration,
+                    total_duration: duration,
+                    dependencies: (*self.dependencies).clone(),
+                })
+            }
+        }
+        result
+    }
+
+    /// Returns the statistics recorded so far, or None if nothing has been computed yet.
+    pub fn stats(&self) -> Option<ComputedStats> {
+        match self.stats.lock() {
+            Ok(stats) => stats.clone(),
+            Err(err) => panic!("Computed stats mutex poisoned: {}", err),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use super::{Stream, Subscription};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+struct MapConcurrentFields<T, U> {
+    // Values waiting for a free slot, with their sequence numbers.
+    queue: VecDeque<(u64, Arc<T>)>,
+    in_flight: usize,
+    next_sequence: u64,
+
+    // Results that finished before an earlier value's result, keyed by sequence number.
+    results: BTreeMap<u64, Arc<U>>,
+    next_to_emit: u64,
+    is_emitting: bool,
+    is_source_complete: bool,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+impl<T, U> MapConcurrentFields<T, U> {
+    fn is_drained(&self) -> bool {
+        self.is_source_complete && self.in_flight == 0 && self.queue.is_empty()
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Like `map`, but runs `map_function` on the stream's scheduler for up to `concurrency`
+    /// values at a time. Results are still emitted in the order the values arrived, so a slow
+    /// value holds back the results of the values after it. Values that arrive while every slot
+    /// is busy wait in a queue.
+    ///
+    /// The default scheduler runs everything on a single thread, so to actually spread the work
+    /// across cores pass a thread pool scheduler (such as `RayonScheduler`) to
+    /// `Stream::with_scheduler` first. If `map_function` panics, no further results are emitted.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::scheduler::ManualScheduler;
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::sync::Arc;
+    ///
+    /// let scheduler = Arc::new(ManualScheduler::new());
+    /// let stream_host: epoxy_streams::Sink<u64> = epoxy_streams::Sink::new();
+    /// let squares = stream_host
+    ///     .get_stream()
+    ///     .with_scheduler(scheduler.clone())
+    ///     .map_concurrent(2, |value| value * value);
+    /// let cache = ReactiveCache::from_stream(squares);
+    ///
+    /// for value in 1..=5 {
+    ///     stream_host.emit(value);
+    /// }
+    /// assert_eq!(cache.get_cloned(), Vec::<u64>::new());
+    ///
+    /// scheduler.run_until_idle();
+    /// assert_eq!(cache.get_cloned(), vec![1, 4, 9, 16, 25]);
+    /// ```
+    pub fn map_concurrent<U, F>(&self, concurrency: usize, map_function: F) -> Stream<U>
+    where
+        U: Send,
+        U: Sync,
+        U: 'static,
+        F: Fn(&T) -> U,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let derived_stream = self.derive_with_fields(MapConcurrentFields::<T, U> {
+            queue: VecDeque::new(),
+            in_flight: 0,
+            next_sequence: 0,
+            results: BTreeMap::new(),
+            next_to_emit: 0,
+            is_emitting: false,
+            is_source_complete: false,
+            subscription: None,
+        });
+        let map_function = Arc::new(map_function);
+        let concurrency = concurrency.max(1);
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                stream_ref.mutate_extra_fields(|fields: &mut MapConcurrentFields<T, U>| {
+                    let sequence = fields.next_sequence;
+                    fields.next_sequence += 1;
+                    fields.queue.push_back((sequence, val));
+                });
+                start_concurrent_maps(&stream_ref, concurrency, &map_function);
+            },
+            move || {
+                if let Some(pointer) = weak_completion_ref.upgrade() {
+                    let stream_ref = Stream { pointer };
+                    let mut is_drained = false;
+                    stream_ref.mutate_extra_fields(|fields: &mut MapConcurrentFields<T, U>| {
+                        fields.is_source_complete = true;
+                        is_drained = fields.is_drained() && !fields.is_emitting;
+                    });
+                    if is_drained {
+                        stream_ref.complete();
+                    }
+                }
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut MapConcurrentFields<T, U>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
+
+/// Schedules queued values until every slot is in use.
+fn start_concurrent_maps<T, U, F>(stream: &Stream<U>, concurrency: usize, map_function: &Arc<F>)
+where
+    T: 'static + Send + Sync,
+    U: 'static + Send + Sync,
+    F: Fn(&T) -> U + Send + Sync + 'static,
+{
+    let mut started = vec![];
+    stream.mutate_extra_fields(|fields: &mut MapConcurrentFields<T, U>| {
+        while fields.in_flight < concurrency {
+            match fields.queue.pop_front() {
+                Some(entry) => {
+                    fields.in_flight += 1;
+                    started.push(entry);
+                }
+                None => break,
+            }
+        }
+    });
+
+    let scheduler = stream.scheduler();
+    for (sequence, value) in started {
+        let weak_stream_ref = Arc::downgrade(&stream.pointer);
+        let map_function = map_function.clone();
+        scheduler.schedule(Box::new(move || {
+            let result = Arc::new(map_function(&value));
+            let stream_ref = match weak_stream_ref.upgrade() {
+                Some(pointer) => Stream { pointer },
+                None => return,
+            };
+            stream_ref.mutate_extra_fields(|fields: &mut MapConcurrentFields<T, U>| {
+                fields.in_flight -= 1;
+                fields.results.insert(sequence, result);
+            });
+            emit_ready_results::<T, U>(&stream_ref);
+            start_concurrent_maps(&stream_ref, concurrency, &map_function);
+        }));
+    }
+}
+
+/// Emits every result whose predecessors have all been emitted. Only one thread emits at a time,
+/// which keeps the results in order even when tasks finish on different threads.
+fn emit_ready_results<T, U>(stream: &Stream<U>)
+where
+    T: 'static + Send + Sync,
+    U: 'static + Send + Sync,
+{
+    let mut is_emitter = false;
+    stream.mutate_extra_fields(|fields: &mut MapConcurrentFields<T, U>| {
+        if !fields.is_emitting {
+            fields.is_emitting = true;
+            is_emitter = true;
+        }
+    });
+    if !is_emitter {
+        return;
+    }
+
+    loop {
+        let mut ready = vec![];
+        let mut is_finished = false;
+        stream.mutate_extra_fields(|fields: &mut MapConcurrentFields<T, U>| {
+            while let Some(result) = fields.results.remove(&fields.next_to_emit) {
+                ready.push(result);
+                fields.next_to_emit += 1;
+            }
+            if ready.is_empty() {
+                fields.is_emitting = false;
+                is_finished = fields.is_drained();
+            }
+        });
+        if ready.is_empty() {
+            if is_finished {
+                stream.complete();
+            }
+            return;
+        }
+        for result in ready {
+            stream.emit_rc(result);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Process-wide settings that control how epoxy behaves. Libraries built on epoxy should leave
+//! these alone, so that the host application stays in control of its threading policy.
+use super::clock::{Clock, SystemClock};
+use super::scheduler::{shared_thread_scheduler, shared_worker_scheduler, Scheduler};
+use std::sync::{Arc, RwLock};
+
+static DEFAULT_SCHEDULER: RwLock<Option<Arc<dyn Scheduler>>> = RwLock::new(None);
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+/// Sets the scheduler used by time-based operators on every stream that does not override it
+/// with `Stream::with_scheduler`. Operators that are already waiting on a timer will finish on
+/// the scheduler they started with.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::config;
+/// use epoxy_streams::scheduler::DedicatedThreadScheduler;
+/// use std::sync::Arc;
+///
+/// let scheduler = Arc::new(DedicatedThreadScheduler::new());
+/// config::set_default_scheduler(scheduler.clone());
+/// config::reset_default_scheduler();
+/// ```
+pub fn set_default_scheduler(scheduler: Arc<dyn Scheduler>) {
+    match DEFAULT_SCHEDULER.write() {
+        Ok(mut default_scheduler) => *default_scheduler = Some(scheduler),
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
+
+/// Restores the default scheduler to epoxy's own background thread.
+pub fn reset_default_scheduler() {
+    match DEFAULT_SCHEDULER.write() {
+        Ok(mut default_scheduler) => *default_scheduler = None,
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
+
+/// Returns the scheduler that time-based operators use when their stream does not override it.
+pub fn default_scheduler() -> Arc<dyn Scheduler> {
+    configured_scheduler().unwrap_or_else(shared_thread_scheduler)
+}
+
+/// Returns the scheduler that runs slow user code (see `Stream::subscribe_with_mode` and
+/// `ReactiveValue::from_poll`) when there is no override. This is the default scheduler if the
+/// application has set one, and otherwise a worker thread that is separate from the one that
+/// runs epoxy's timers.
+pub(crate) fn worker_scheduler() -> Arc<dyn Scheduler> {
+    configured_scheduler().unwrap_or_else(shared_worker_scheduler)
+}
+
+fn configured_scheduler() -> Option<Arc<dyn Scheduler>> {
+    match DEFAULT_SCHEDULER.read() {
+        Ok(default_scheduler) => default_scheduler.clone(),
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
+
+/// Sets the clock that epoxy reads the time from, see the `clock` module. Schedulers without a
+/// clock of their own (every scheduler except `ManualScheduler`) report its time from
+/// `Scheduler::now`.
+pub fn set_clock(clock: Arc<dyn Clock>) {
+    match CLOCK.write() {
+        Ok(mut current_clock) => *current_clock = Some(clock),
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
+
+/// Restores the clock to a `SystemClock`.
+pub fn reset_clock() {
+    match CLOCK.write() {
+        Ok(mut current_clock) => *current_clock = None,
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
+
+/// Returns the clock that epoxy reads the time from.
+pub fn clock() -> Arc<dyn Clock> {
+    match CLOCK.read() {
+        Ok(current_clock) => match &*current_clock {
+            Some(clock) => clock.clone(),
+            None => Arc::new(SystemClock),
+        },
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
+
+/// What happens when a ReactiveValue is set while a `computed!` expression is being evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReentrantWritePolicy {
+    /// `set` panics with a `ReentrantWriteError`, and `try_set` returns `SetError::Reentrant`.
+    /// This is the default, because such writes usually come from a computed expression that
+    /// updates one of its own dependencies, which would otherwise recurse forever.
+    Error,
+
+    /// The write is queued, and applied once the computed expression (and the propagation turn
+    /// that triggered it) has finished.
+    Defer,
+}
+
+static REENTRANT_WRITE_POLICY: RwLock<ReentrantWritePolicy> =
+    RwLock::new(ReentrantWritePolicy::Error);
+
+/// Sets what happens to writes made while a `computed!` expression is being evaluated.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::config::{self, ReentrantWritePolicy};
+/// use epoxy_streams::ReactiveValue;
+///
+/// config::set_reentrant_write_policy(ReentrantWritePolicy::Defer);
+///
+/// let log_length = ReactiveValue::new(0);
+/// let doubled = epoxy_streams::evaluate_derivation(|| {
+///     log_length.set(1);
+///     assert_eq!(*log_length.get(), 0); // Not applied yet.
+///     2
+/// });
+/// assert_eq!(doubled, 2);
+/// assert_eq!(*log_length.get(), 1);
+/// ```
+pub fn set_reentrant_write_policy(policy: ReentrantWritePolicy) {
+    match REENTRANT_WRITE_POLICY.write() {
+        Ok(mut current_policy) => *current_policy = policy,
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
+
+/// Returns what happens to writes made while a `computed!` expression is being evaluated.
+pub fn reentrant_write_policy() -> ReentrantWritePolicy {
+    match REENTRANT_WRITE_POLICY.read() {
+        Ok(policy) => *policy,
+        Err(err) => panic!("Config mutex poisoned: {}", err),
+    }
+}
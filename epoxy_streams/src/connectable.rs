@@ -0,0 +1,206 @@
+use super::stream_combinators::forward_into;
+use super::sync::Mutex;
+use super::{Stream, Subscription};
+use alloc::sync::{Arc, Weak};
+
+/// A stream whose subscribers can be attached before any values flow. Created with
+/// `Stream::publish`.
+///
+/// Nothing subscribes to the original stream until `connect` is called, so sources that start
+/// work when they get their first subscriber (like the ones created with `Stream::defer`) stay
+/// idle until then. This lets an application wire up all of its pipelines during startup and
+/// then turn them on at once.
+pub struct ConnectableStream<T> {
+    source: Stream<T>,
+    stream: Stream<T>,
+    connection: Arc<Mutex<Option<Weak<Subscription<T>>>>>,
+}
+
+struct RefCountFields<T> {
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+
+    #[allow(dead_code)]
+    connection: Option<Connection<T>>,
+}
+
+/// Keeps a ConnectableStream connected to its source. Dropping the last clone of the connection
+/// disconnects it.
+pub struct Connection<T> {
+    #[allow(dead_code)]
+    subscription: Arc<Subscription<T>>,
+}
+
+impl<T> Clone for Connection<T> {
+    fn clone(&self) -> Self {
+        Connection {
+            subscription: Arc::clone(&self.subscription),
+        }
+    }
+}
+
+impl<T> Clone for ConnectableStream<T> {
+    fn clone(&self) -> Self {
+        ConnectableStream {
+            source: self.source.clone(),
+            stream: self.stream.clone(),
+            connection: Arc::clone(&self.connection),
+        }
+    }
+}
+
+impl<T: 'static + Send + Sync> ConnectableStream<T> {
+    /// Returns the stream that subscribers should attach to. It only emits while connected.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.stream.clone()
+    }
+
+    /// Connects to the source stream, if it is not connected already, and returns a handle that
+    /// keeps the connection open. Calling `connect` again while connected returns another
+    /// handle to the same connection.
+    pub fn connect(&self) -> Connection<T> {
+        let mut connection = self.connection.lock();
+        if let Some(subscription) = connection.as_ref().and_then(Weak::upgrade) {
+            return Connection { subscription };
+        }
+        let subscription = Arc::new(forward_into(&self.source, &self.stream));
+        *connection = Some(Arc::downgrade(&subscription));
+        Connection { subscription }
+    }
+
+    /// Returns a stream that connects when it gets its first subscriber, and disconnects again
+    /// once its last subscriber is dropped. A later subscriber starts a fresh connection.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Sink};
+    ///
+    /// let stream_host: Sink<i32> = Sink::new();
+    /// let published = stream_host.get_stream().publish();
+    /// let shared = published.ref_count();
+    /// assert!(!published.is_connected());
+    ///
+    /// let first = ReactiveCache::from_stream(shared.clone());
+    /// let second = shared.subscribe(|_| {});
+    /// assert!(published.is_connected());
+    ///
+    /// stream_host.emit(1);
+    /// drop(first);
+    /// assert!(published.is_connected());
+    ///
+    /// drop(second);
+    /// assert!(!published.is_connected());
+    /// ```
+    pub fn ref_count(&self) -> Stream<T> {
+        let connectable = self.clone();
+        Stream::defer(move || {
+            let derived_stream = Stream::new_with_fields(RefCountFields::<T> {
+                subscription: None,
+                connection: None,
+            });
+
+            // Only weak references point back at the derived stream, so dropping the subscriber
+            // drops it, and with it this subscriber's share of the connection.
+            let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+            let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+            let subscription = connectable.stream.subscribe_with_completion(
+                move |val| {
+                    if let Some(pointer) = weak_stream_ref.upgrade() {
+                        Stream { pointer }.emit_rc(val);
+                    }
+                },
+                move || {
+                    if let Some(pointer) = weak_completion_ref.upgrade() {
+                        Stream { pointer }.complete();
+                    }
+                },
+            );
+            let connection = connectable.connect();
+
+            derived_stream.mutate_extra_fields(move |fields: &mut RefCountFields<T>| {
+                fields.subscription = Some(subscription);
+                fields.connection = Some(connection);
+            });
+            derived_stream
+        })
+    }
+
+    /// Returns true while a connection handle is alive.
+    pub fn is_connected(&self) -> bool {
+        match &*self.connection.lock() {
+            Some(subscription) => subscription.strong_count() > 0,
+            None => false,
+        }
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Returns a ConnectableStream that mirrors this stream, but only once it is connected.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Sink, Stream};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: Sink<i32> = Sink::new();
+    /// let source = stream_host.get_stream();
+    /// let opened = Arc::new(AtomicUsize::new(0));
+    /// let opened_write = opened.clone();
+    /// let lazy = Stream::defer(move || {
+    ///     opened_write.fetch_add(1, Ordering::SeqCst);
+    ///     source.clone()
+    /// });
+    ///
+    /// let published = lazy.publish();
+    /// let cache = ReactiveCache::from_stream(published.get_stream());
+    /// stream_host.emit(1);
+    /// assert_eq!(opened.load(Ordering::SeqCst), 0);
+    ///
+    /// let connection = published.connect();
+    /// stream_host.emit(2);
+    /// assert_eq!(opened.load(Ordering::SeqCst), 1);
+    ///
+    /// drop(connection);
+    /// stream_host.emit(3);
+    /// assert_eq!(cache.get_cloned(), vec![2]);
+    /// ```
+    pub fn publish(&self) -> ConnectableStream<T> {
+        ConnectableStream {
+            source: self.clone(),
+            stream: Stream::new(),
+            connection: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns a stream that shares a single subscription to this stream between all of its
+    /// subscribers, for as long as it has any. Shorthand for `publish().ref_count()`.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Sink, Stream};
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: Sink<i32> = Sink::new();
+    /// let source = stream_host.get_stream();
+    /// let opened = Arc::new(AtomicUsize::new(0));
+    /// let opened_write = opened.clone();
+    /// let shared = Stream::defer(move || {
+    ///     opened_write.fetch_add(1, Ordering::SeqCst);
+    ///     source.clone()
+    /// })
+    /// .share();
+    ///
+    /// let first = ReactiveCache::from_stream(shared.clone());
+    /// let second = ReactiveCache::from_stream(shared.clone());
+    /// stream_host.emit(1);
+    ///
+    /// assert_eq!(opened.load(Ordering::SeqCst), 1);
+    /// assert_eq!(first.get_cloned(), vec![1]);
+    /// assert_eq!(second.get_cloned(), vec![1]);
+    /// ```
+    pub fn share(&self) -> Stream<T> {
+        self.publish().ref_count()
+    }
+}
@@ -0,0 +1,267 @@
+#[cfg(feature = "std")]
+use super::sync::Mutex;
+use super::{Observable, Stream};
+#[cfg(feature = "std")]
+use super::{ReactiveCache, ReactiveValue, ReadonlyReactiveValue};
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use alloc::sync::Weak;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+#[cfg(feature = "futures")]
+use core::pin::Pin;
+#[cfg(feature = "std")]
+use std::sync::mpsc::Receiver;
+
+#[cfg(feature = "std")]
+type StreamPointer<T> = Mutex<super::streams::StreamImpl<T>>;
+
+/// Conversion into a Stream, so functions and operators can accept anything that produces values
+/// (another stream, a reactive value, a collection of values or a channel) without the caller
+/// converting it first.
+///
+/// Collections of values, like a `Vec` or an `Option`, become streams that emit every value to
+/// each subscriber as soon as it subscribes, and then complete. Channels and async streams start
+/// being read when the first subscriber arrives, and complete when they run out of values. Like
+/// all streams, values that are emitted while nothing is subscribed are lost, and operators such
+/// as `map` subscribe as soon as they are created. Derive from a stream created from values only
+/// right before subscribing to the result, or pass it to an operator that subscribes later on, like
+/// `concat`.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{IntoStream, ReactiveCache, Sink};
+/// use std::sync::mpsc;
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let stream_host: Sink<i32> = Sink::new();
+/// let merged = epoxy_streams::merge(vec![
+///     receiver.into_stream(),
+///     (&stream_host).into_stream(),
+/// ]);
+/// let cache = ReactiveCache::from_stream(merged.concat(vec![100, 101]));
+///
+/// stream_host.emit(1);
+/// sender.send(2).unwrap();
+/// drop(sender);
+/// stream_host.close();
+///
+/// // The channel is read on another thread.
+/// # while cache.get_cloned().len() < 4 { std::thread::yield_now(); }
+/// assert_eq!(cache.get_cloned(), vec![1, 2, 100, 101]);
+/// ```
+pub trait IntoStream<T> {
+    /// Returns a stream of the values that this produces.
+    fn into_stream(self) -> Stream<T>;
+}
+
+/// Conversion from a Stream into something that keeps track of its values, like a ReactiveCache
+/// or a ReactiveValue. See `Stream::collect`.
+pub trait FromStream<T> {
+    /// Creates a value that follows `stream`.
+    fn from_stream(stream: Stream<T>) -> Self;
+}
+
+impl<T> IntoStream<T> for Stream<T> {
+    fn into_stream(self) -> Stream<T> {
+        self
+    }
+}
+
+impl<T, O> IntoStream<T> for &O
+where
+    T: Send + Sync + 'static,
+    O: Observable<T>,
+{
+    fn into_stream(self) -> Stream<T> {
+        self.changes()
+    }
+}
+
+impl<T: Send + Sync + 'static> IntoStream<T> for Vec<T> {
+    fn into_stream(self) -> Stream<T> {
+        Stream::from_values(self)
+    }
+}
+
+impl<T: Send + Sync + 'static, const N: usize> IntoStream<T> for [T; N] {
+    fn into_stream(self) -> Stream<T> {
+        Stream::from_values(self)
+    }
+}
+
+impl<T: Send + Sync + 'static> IntoStream<T> for Option<T> {
+    fn into_stream(self) -> Stream<T> {
+        Stream::from_values(self)
+    }
+}
+
+impl<T: Send + Sync + 'static> FromIterator<T> for Stream<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Stream<T> {
+        Stream::from_values(iter)
+    }
+}
+
+/// Reads values from the channel on a new thread.
+#[cfg(feature = "std")]
+impl<T: Send + Sync + 'static> IntoStream<T> for Receiver<T> {
+    fn into_stream(self) -> Stream<T> {
+        start_on_first_subscription(move |weak_pointer| {
+            std::thread::spawn(move || {
+                for value in self {
+                    match weak_pointer.upgrade() {
+                        Some(pointer) => Stream { pointer }.emit_rc(Arc::new(value)),
+                        None => return,
+                    }
+                }
+                if let Some(pointer) = weak_pointer.upgrade() {
+                    Stream { pointer }.complete();
+                }
+            });
+        })
+    }
+}
+
+/// Polls the async stream on the tokio runtime that `into_stream` is called from, and panics when
+/// called outside of one.
+///
+/// Requires the `futures` feature.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{IntoStream, ReactiveCache};
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+///
+/// struct Countdown(u32);
+///
+/// impl futures_core::Stream for Countdown {
+///     type Item = u32;
+///
+///     fn poll_next(mut self: Pin<&mut Self>, _context: &mut Context<'_>) -> Poll<Option<u32>> {
+///         self.0 = self.0.saturating_sub(1);
+///         Poll::Ready(Some(self.0).filter(|count| *count > 0))
+///     }
+/// }
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// runtime.block_on(async {
+///     let source: Pin<Box<dyn futures_core::Stream<Item = u32> + Send>> = Box::pin(Countdown(4));
+///     let stream = source.into_stream();
+///     let cache = ReactiveCache::from_stream(stream.clone());
+///
+///     while !stream.is_complete() {
+///         tokio::task::yield_now().await;
+///     }
+///     assert_eq!(cache.get_cloned(), vec![3, 2, 1]);
+/// });
+/// ```
+#[cfg(feature = "futures")]
+impl<T: Send + Sync + 'static> IntoStream<T>
+    for Pin<Box<dyn futures_core::Stream<Item = T> + Send>>
+{
+    fn into_stream(mut self) -> Stream<T> {
+        let handle = tokio::runtime::Handle::current();
+        start_on_first_subscription(move |weak_pointer| {
+            handle.spawn(async move {
+                while let Some(value) =
+                    std::future::poll_fn(|context| self.as_mut().poll_next(context)).await
+                {
+                    match weak_pointer.upgrade() {
+                        Some(pointer) => Stream { pointer }.emit_rc(Arc::new(value)),
+                        None => return,
+                    }
+                }
+                if let Some(pointer) = weak_pointer.upgrade() {
+                    Stream { pointer }.complete();
+                }
+            });
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Send + Sync + 'static> FromStream<T> for ReactiveCache<T> {
+    fn from_stream(stream: Stream<T>) -> ReactiveCache<T> {
+        ReactiveCache::from_stream(stream)
+    }
+}
+
+/// Starts out with the default value of `T`.
+#[cfg(feature = "std")]
+impl<T: Default + Send + Sync + 'static> FromStream<T> for ReadonlyReactiveValue<T> {
+    fn from_stream(stream: Stream<T>) -> ReadonlyReactiveValue<T> {
+        <dyn ReactiveValue<T>>::from_stream(stream)
+    }
+}
+
+// Calls `start` once the stream gets its first subscriber. The replay runs under the stream lock
+// before that subscriber is added, so anything `start` sets off can not emit until it is.
+#[cfg(feature = "std")]
+fn start_on_first_subscription<T, F>(start: F) -> Stream<T>
+where
+    T: Send + Sync + 'static,
+    F: FnOnce(Weak<StreamPointer<T>>) + Send + 'static,
+{
+    let stream = Stream::new();
+    let weak_pointer = Arc::downgrade(&stream.pointer);
+    let start = Mutex::new(Some(start));
+    stream.set_replay(move |_| {
+        if let Some(start) = start.lock().take() {
+            start(weak_pointer.clone());
+        }
+        Vec::new()
+    });
+    stream
+}
+
+impl<T: Send + Sync + 'static> Stream<T> {
+    /// Returns a stream that emits `values` to each subscriber as soon as it subscribes, and then
+    /// completes. See `IntoStream` for how this interacts with operators.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Stream};
+    ///
+    /// let stream = Stream::from_values(vec![1, 2, 3]);
+    /// let first = ReactiveCache::from_stream(stream.clone());
+    /// let second = ReactiveCache::from_stream(stream.clone());
+    ///
+    /// assert_eq!(first.get_cloned(), vec![1, 2, 3]);
+    /// assert_eq!(second.get_cloned(), vec![1, 2, 3]);
+    /// assert!(stream.is_complete());
+    /// ```
+    pub fn from_values<I: IntoIterator<Item = T>>(values: I) -> Stream<T> {
+        let values: Vec<Arc<T>> = values.into_iter().map(Arc::new).collect();
+        let stream = Stream::new();
+        stream.set_replay(move |_| values.clone());
+        stream.complete();
+        stream
+    }
+
+    /// Returns a stream that emits `value` to each subscriber as soon as it subscribes, and then
+    /// completes.
+    pub fn once(value: T) -> Stream<T> {
+        Stream::from_values(Some(value))
+    }
+
+    /// Converts this stream into anything that implements `FromStream`, like a ReactiveCache or a
+    /// ReactiveValue.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, ReactiveValue, ReadonlyReactiveValue, Sink};
+    ///
+    /// let stream_host: Sink<i32> = Sink::new();
+    /// let cache: ReactiveCache<i32> = stream_host.get_stream().collect();
+    /// let latest: ReadonlyReactiveValue<i32> = stream_host.get_stream().collect();
+    ///
+    /// stream_host.emit(4);
+    /// stream_host.emit(5);
+    /// assert_eq!(cache.get_cloned(), vec![4, 5]);
+    /// assert_eq!(*latest.get(), 5);
+    /// ```
+    pub fn collect<C: FromStream<T>>(&self) -> C {
+        C::from_stream(self.clone())
+    }
+}
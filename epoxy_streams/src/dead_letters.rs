@@ -0,0 +1,155 @@
+//! Values that streams drop on purpose, like values emitted after a Sink was closed or values
+//! that a rate limit turned away, are normally gone without a trace. Dead letters make that loss
+//! visible. Every stream reports the values it drops on its own `Stream::dead_letters`, and a
+//! summary of every dead letter from every stream is emitted by `global`, which makes it easy to
+//! count or log losses across a whole pipeline.
+//!
+//! Values are dropped for one of the reasons in `DeadLetterReason`:
+//! * `SinkClosed`: the value was emitted after the Sink (or the producer or permit it was emitted
+//!   through) was closed or dropped. Reported on the Sink's stream.
+//! * `RateLimited`: `Stream::rate_limit` with `RateLimitOverflow::Drop` turned the value away.
+//!   Reported on the rate limited stream.
+//! * `Superseded`: a subscriber using `DeliveryMode::Latest` or `DeliveryMode::Conflated` skipped
+//!   the value because a newer one arrived first. Reported on the subscribed stream.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::dead_letters::{self, DeadLetterReason};
+//! use epoxy_streams::ReactiveCache;
+//!
+//! let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+//! let stream = stream_host.get_stream();
+//! let dropped = ReactiveCache::from_stream(stream.dead_letters().map(|letter| *letter.value));
+//! let everywhere = ReactiveCache::from_stream(dead_letters::global());
+//!
+//! stream_host.emit(1);
+//! stream_host.close();
+//! stream_host.emit(2);
+//!
+//! assert_eq!(dropped.get_cloned(), vec![2]);
+//! let report = everywhere.get_cloned()[0];
+//! assert_eq!(report.reason, DeadLetterReason::SinkClosed);
+//! assert_eq!(report.type_name, "i32");
+//! ```
+use super::{Sink, Stream};
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "std")]
+static GLOBAL: OnceLock<Sink<DeadLetterReport>> = OnceLock::new();
+
+/// Why a value became a dead letter. See the module documentation for when each one is used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    /// The value was emitted after its Sink was closed.
+    SinkClosed,
+
+    /// A rate limit turned the value away.
+    RateLimited,
+
+    /// A subscriber skipped the value in favor of a newer one.
+    Superseded,
+}
+
+/// A value that a stream dropped, emitted from `Stream::dead_letters`.
+#[derive(Debug)]
+pub struct DeadLetter<T> {
+    pub reason: DeadLetterReason,
+    pub value: Arc<T>,
+}
+
+impl<T> Clone for DeadLetter<T> {
+    fn clone(&self) -> Self {
+        DeadLetter {
+            reason: self.reason,
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// Describes a dead letter from any stream, emitted from `global`. The value itself is only
+/// available from the stream that dropped it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeadLetterReport {
+    pub reason: DeadLetterReason,
+
+    /// The type of the dropped value, as returned by `core::any::type_name`.
+    pub type_name: &'static str,
+}
+
+/// Returns a stream that describes every value that any stream drops.
+#[cfg(feature = "std")]
+pub fn global() -> Stream<DeadLetterReport> {
+    GLOBAL.get_or_init(Sink::new).get_stream()
+}
+
+// Lets a stream hold the Sink of its dead letters without naming its type, which would otherwise
+// make the type of every stream depend on the type of a stream of its own dead letters.
+pub(crate) trait DeadLetterTarget<T>: Send + Sync {
+    fn report(&self, letter: DeadLetter<T>);
+    fn get_stream(&self) -> Stream<DeadLetter<T>>;
+}
+
+impl<T: Send + Sync + 'static> DeadLetterTarget<T> for Sink<DeadLetter<T>> {
+    fn report(&self, letter: DeadLetter<T>) {
+        self.emit(letter)
+    }
+
+    fn get_stream(&self) -> Stream<DeadLetter<T>> {
+        Sink::get_stream(self)
+    }
+}
+
+impl<T> Stream<T> {
+    /// Emits a value that the stream dropped from its dead letters, and reports it globally.
+    pub(crate) fn report_dead_letter(&self, value: Arc<T>, reason: DeadLetterReason) {
+        let target = self.pointer.lock().dead_letters.clone();
+        if let Some(target) = target {
+            target.report(DeadLetter { reason, value });
+        }
+        report_global::<T>(reason);
+    }
+}
+
+impl<T: Send + Sync + 'static> Stream<T> {
+    /// Returns a stream of the values that this stream drops, along with the reason each one was
+    /// dropped. See the `dead_letters` module for when values are dropped. Unlike the stream
+    /// itself, the stream of dead letters does not complete when its Sink is closed, since values
+    /// emitted after that are exactly the ones it reports.
+    pub fn dead_letters(&self) -> Stream<DeadLetter<T>> {
+        self.dead_letter_target().get_stream()
+    }
+
+    /// Returns a function that reports dead letters for this stream. Unlike `report_dead_letter`
+    /// it does not lock the stream, so it can be called from the stream's own subscribers.
+    #[cfg(feature = "std")]
+    pub(crate) fn dead_letter_reporter(&self) -> impl Fn(Arc<T>, DeadLetterReason) {
+        let target = self.dead_letter_target();
+        move |value, reason| {
+            target.report(DeadLetter { reason, value });
+            report_global::<T>(reason);
+        }
+    }
+
+    fn dead_letter_target(&self) -> Arc<dyn DeadLetterTarget<T>> {
+        let mut stream_impl = self.pointer.lock();
+        stream_impl
+            .dead_letters
+            .get_or_insert_with(|| Arc::new(Sink::<DeadLetter<T>>::new()))
+            .clone()
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn report_global<T>(reason: DeadLetterReason) {
+    if let Some(sink) = GLOBAL.get() {
+        sink.emit(DeadLetterReport {
+            reason,
+            type_name: core::any::type_name::<T>(),
+        });
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn report_global<T>(_reason: DeadLetterReason) {}
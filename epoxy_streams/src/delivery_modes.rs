@@ -0,0 +1,143 @@
+use super::dead_letters::DeadLetterReason;
+use super::{Stream, Subscription};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+/// How values are delivered to a subscriber created with `Stream::subscribe_with_mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryMode {
+    /// Every value is delivered synchronously, as it is emitted. This is how `subscribe` works.
+    Every,
+
+    /// Values are delivered on the stream's scheduler, or on a worker thread of epoxy's own if
+    /// neither the stream nor the application has chosen one, so that a slow subscriber never
+    /// holds up the timers of time-based operators. If several values arrive while the subscriber
+    /// is busy (or before the scheduler gets to it), only the newest one is delivered.
+    Latest,
+
+    /// Same as `Latest`, but each delivery reports how many values it stands for, including the
+    /// ones that were skipped.
+    Conflated,
+}
+
+/// A value delivered to a subscriber created with `Stream::subscribe_with_mode`.
+pub struct Delivery<T> {
+    pub value: Arc<T>,
+
+    /// The number of values this delivery stands for. Always 1 unless the subscriber uses
+    /// `DeliveryMode::Conflated`.
+    pub count: usize,
+}
+
+struct ConflationState<T> {
+    pending: Option<(Arc<T>, usize)>,
+    is_scheduled: bool,
+}
+
+fn lock_state<T>(state: &Mutex<ConflationState<T>>) -> MutexGuard<'_, ConflationState<T>> {
+    match state.lock() {
+        Ok(state) => state,
+        Err(err) => panic!("Delivery mutex poisoned: {}", err),
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Same as `subscribe`, but lets the subscriber choose how values are delivered. Slow
+    /// subscribers, like ones that render a frame for every value, should usually use
+    /// `DeliveryMode::Latest` so that they never fall behind the stream.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::DeliveryMode;
+    /// use std::sync::{Arc, Mutex};
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let received = Arc::new(Mutex::new(vec![]));
+    /// let received_write = received.clone();
+    ///
+    /// let _subscription = stream_host.get_stream().subscribe_with_mode(
+    ///     DeliveryMode::Conflated,
+    ///     move |delivery| {
+    ///         received_write.lock().unwrap().push((*delivery.value, delivery.count));
+    ///         // A slow subscriber.
+    ///         std::thread::sleep(Duration::from_millis(100));
+    ///     },
+    /// );
+    ///
+    /// stream_host.emit(1);
+    /// std::thread::sleep(Duration::from_millis(30));
+    ///
+    /// // These arrive while the subscriber is still busy with the first value.
+    /// stream_host.emit(2);
+    /// stream_host.emit(3);
+    /// stream_host.emit(4);
+    /// std::thread::sleep(Duration::from_millis(300));
+    /// assert_eq!(*received.lock().unwrap(), vec![(1, 1), (4, 3)]);
+    /// ```
+    pub fn subscribe_with_mode<F>(&self, mode: DeliveryMode, listener: F) -> Subscription<T>
+    where
+        F: Fn(Delivery<T>),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        if mode == DeliveryMode::Every {
+            return self.subscribe(move |value| listener(Delivery { value, count: 1 }));
+        }
+
+        let listener = Arc::new(listener);
+        let state = Arc::new(Mutex::new(ConflationState {
+            pending: None,
+            is_scheduled: false,
+        }));
+        let scheduler = self.worker_scheduler();
+
+        // The scheduled task only holds a weak reference, so nothing is delivered after the
+        // subscription has been dropped.
+        let report_dead_letter = self.dead_letter_reporter();
+        self.subscribe(move |value| {
+            let (should_schedule, superseded) = {
+                let mut state = lock_state(&state);
+                let (superseded, count) = match state.pending.take() {
+                    Some((superseded, count)) => (Some(superseded), count + 1),
+                    None => (None, 1),
+                };
+                state.pending = Some((value, count));
+                let should_schedule = !state.is_scheduled;
+                state.is_scheduled = true;
+                (should_schedule, superseded)
+            };
+            if let Some(superseded) = superseded {
+                report_dead_letter(superseded, DeadLetterReason::Superseded);
+            }
+            if !should_schedule {
+                return;
+            }
+
+            let weak_state: Weak<_> = Arc::downgrade(&state);
+            let listener = listener.clone();
+            scheduler.schedule(Box::new(move || loop {
+                let state = match weak_state.upgrade() {
+                    Some(state) => state,
+                    None => return,
+                };
+                let pending = {
+                    let mut state = lock_state(&state);
+                    let pending = state.pending.take();
+                    state.is_scheduled = pending.is_some();
+                    pending
+                };
+                match pending {
+                    Some((value, count)) => listener(Delivery {
+                        value,
+                        count: match mode {
+                            DeliveryMode::Conflated => count,
+                            _ => 1,
+                        },
+                    }),
+                    None => return,
+                }
+            }));
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use super::config::{self, ReentrantWritePolicy};
+use super::propagation::{after_propagation, is_propagating};
+use super::ReentrantWriteError;
+use std::cell::{Cell, RefCell};
+
+type DeferredWrite = Box<dyn FnOnce()>;
+
+thread_local! {
+    static EVALUATION_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static DEFERRED_WRITES: RefCell<Vec<DeferredWrite>> = const { RefCell::new(vec![]) };
+}
+
+struct EvaluationGuard;
+
+impl Drop for EvaluationGuard {
+    fn drop(&mut self) {
+        let depth = EVALUATION_DEPTH.with(|depth| {
+            depth.set(depth.get() - 1);
+            depth.get()
+        });
+        if depth > 0 {
+            return;
+        }
+        for write in DEFERRED_WRITES.with(|writes| writes.take()) {
+            // The evaluation usually runs inside a propagation turn, in which case the write waits
+            // for the turn to finish so it can not feed back into the values being propagated.
+            if is_propagating() {
+                after_propagation(write);
+            } else {
+                write();
+            }
+        }
+    }
+}
+
+/// Runs the body of a derived value, such as a `computed!` expression. While it runs, setting a
+/// ReactiveValue on the same thread is treated as a bug: by default `set` panics with a
+/// `ReentrantWriteError`, because a computed value that updates one of its own dependencies
+/// would otherwise recompute forever. Use `config::set_reentrant_write_policy` to queue such
+/// writes until the evaluation has finished instead.
+///
+/// `computed!` calls this automatically. Hand-written derivations (for example the function
+/// passed to `ReactiveValue::map`) can call it to get the same protection.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{ReactiveValue, SetError};
+///
+/// let counter = ReactiveValue::new(0);
+/// let result = epoxy_streams::evaluate_derivation(|| counter.try_set(1));
+/// assert_eq!(result, Err(SetError::Reentrant));
+/// assert_eq!(*counter.get(), 0);
+/// ```
+pub fn evaluate_derivation<R, F>(derivation: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    EVALUATION_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let _guard = EvaluationGuard;
+    derivation()
+}
+
+/// Decides what to do with a write that is about to happen. Returns `Ok(true)` if the write
+/// should happen now, `Ok(false)` if it has been queued by `defer_write`, and an error if it is
+/// not allowed.
+pub(crate) fn check_write<F>(defer_write: F) -> Result<bool, ReentrantWriteError>
+where
+    F: FnOnce() + 'static,
+{
+    if EVALUATION_DEPTH.with(|depth| depth.get()) == 0 {
+        return Ok(true);
+    }
+    match config::reentrant_write_policy() {
+        ReentrantWritePolicy::Error => Err(ReentrantWriteError),
+        ReentrantWritePolicy::Defer => {
+            DEFERRED_WRITES.with(|writes| writes.borrow_mut().push(Box::new(defer_write)));
+            Ok(false)
+        }
+    }
+}
+
+/// Returns an error if the current thread is evaluating a derivation. Used by writes that can
+/// not be deferred because their caller needs the result.
+pub(crate) fn check_immediate_write() -> Result<(), ReentrantWriteError> {
+    if EVALUATION_DEPTH.with(|depth| depth.get()) == 0 {
+        Ok(())
+    } else {
+        Err(ReentrantWriteError)
+    }
+}
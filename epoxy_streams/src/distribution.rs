@@ -0,0 +1,131 @@
+use super::streams::StreamImpl;
+use super::sync::Mutex;
+use super::{Stream, Subscription};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+type WeakOutput<T> = Weak<Mutex<StreamImpl<T>>>;
+
+struct Distributor<T> {
+    outputs: Vec<WeakOutput<T>>,
+    next: usize,
+}
+
+struct DistributedFields<T> {
+    #[allow(dead_code)]
+    distributor: Arc<Mutex<Distributor<T>>>,
+
+    #[allow(dead_code)]
+    subscription: Arc<Subscription<T>>,
+}
+
+impl<T> Distributor<T> {
+    // Returns the next output in turn that is still alive and has subscribers, so values are not
+    // handed to outputs that nobody is listening to.
+    fn next_output(&mut self) -> Option<Stream<T>> {
+        for _ in 0..self.outputs.len() {
+            let index = self.next;
+            self.next = (self.next + 1) % self.outputs.len();
+            if let Some(pointer) = self.outputs[index].upgrade() {
+                let output = Stream { pointer };
+                if output.count_subscribers() > 0 {
+                    return Some(output);
+                }
+            }
+        }
+        None
+    }
+
+    fn live_outputs(&self) -> Vec<Stream<T>> {
+        self.outputs
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|pointer| Stream { pointer })
+            .collect()
+    }
+}
+
+impl<T: Send + Sync + 'static> Stream<T> {
+    /// Splits this stream into `n` streams that take turns receiving its values, rather than each
+    /// receiving all of them. This spreads the work of consuming a busy stream across several
+    /// workers without each of them having to filter out the values meant for the others.
+    ///
+    /// Outputs that have been dropped, or that have no subscribers, are skipped, so a worker that
+    /// goes away does not leave a gap in the rotation. Values that arrive while none of the outputs
+    /// have subscribers are discarded. Every output completes when this stream completes.
+    ///
+    /// Values are handed out as they arrive, regardless of whether the worker that gets one is
+    /// still busy with the last. Use an `AckQueue` when values need to be acknowledged, and
+    /// redelivered to another worker if they are not.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let mut outputs = stream_host.get_stream().distribute(3);
+    /// let first = ReactiveCache::from_stream(outputs.remove(0));
+    /// let second = ReactiveCache::from_stream(outputs.remove(0));
+    /// let third = ReactiveCache::from_stream(outputs.remove(0));
+    ///
+    /// stream_host.emit_many(vec![1, 2, 3, 4, 5]);
+    /// assert_eq!(first.get_cloned(), vec![1, 4]);
+    /// assert_eq!(second.get_cloned(), vec![2, 5]);
+    /// assert_eq!(third.get_cloned(), vec![3]);
+    ///
+    /// // Once a worker goes away the others share its values.
+    /// drop(third);
+    /// stream_host.emit_many(vec![6, 7, 8]);
+    /// assert_eq!(first.get_cloned(), vec![1, 4, 6, 8]);
+    /// assert_eq!(second.get_cloned(), vec![2, 5, 7]);
+    /// ```
+    pub fn distribute(&self, n: usize) -> Vec<Stream<T>> {
+        let distributor = Arc::new(Mutex::new(Distributor {
+            outputs: Vec::new(),
+            next: 0,
+        }));
+
+        // The subscription only holds weak references, since the outputs own it.
+        let weak_distributor = Arc::downgrade(&distributor);
+        let weak_completion_distributor = Arc::downgrade(&distributor);
+        let subscription = Arc::new(self.subscribe_with_completion(
+            move |value| {
+                let output = match weak_distributor.upgrade() {
+                    Some(distributor) => distributor.lock().next_output(),
+                    None => return,
+                };
+                if let Some(output) = output {
+                    output.emit_rc(value);
+                }
+            },
+            move || {
+                let outputs = match weak_completion_distributor.upgrade() {
+                    Some(distributor) => distributor.lock().live_outputs(),
+                    None => return,
+                };
+                for output in outputs {
+                    output.complete();
+                }
+            },
+        ));
+
+        let outputs: Vec<Stream<T>> = (0..n)
+            .map(|_| {
+                self.derive_with_fields(DistributedFields {
+                    distributor: distributor.clone(),
+                    subscription: subscription.clone(),
+                })
+            })
+            .collect();
+        distributor.lock().outputs = outputs
+            .iter()
+            .map(|output| Arc::downgrade(&output.pointer))
+            .collect();
+        if self.is_complete() {
+            for output in &outputs {
+                output.complete();
+            }
+        }
+        outputs
+    }
+}
@@ -0,0 +1,249 @@
+//! A C interface to Sinks and Subscriptions, so that an application written in C or C++ can
+//! drive streams built in Rust. The declarations are in `include/epoxy_ffi.h`, and the functions
+//! are exported when this crate is linked into a `staticlib` or `cdylib`.
+//!
+//! Sinks are available for `int32_t`, `int64_t`, `double` and `bool` values, with one set of
+//! functions each. For `int32_t` values:
+//! * `epoxy_sink_i32_new` creates a sink and returns a handle to it.
+//! * `epoxy_sink_i32_emit` emits a value from the sink.
+//! * `epoxy_sink_i32_subscribe` calls a C function with every value the sink emits, and optionally
+//!   another one when the sink is closed. Both are passed the `user_data` pointer given when
+//!   subscribing. Returns a subscription handle.
+//! * `epoxy_sink_i32_close` closes the sink, see `Sink::close`.
+//! * `epoxy_sink_i32_free` frees the sink handle, which also closes the sink.
+//!
+//! The `i64`, `f64` and `bool` versions work the same way.
+//!
+//! Every handle must be freed exactly once, with the matching `free` function, and must not be
+//! used afterwards. Subscriptions are freed with `epoxy_subscription_free`, which unsubscribes.
+//! Subscriptions can outlive the sink they were created from, in which case they will simply not
+//! be called again. Passing a null handle to any function does nothing.
+//!
+//! Callbacks run on whichever thread emitted the value, while the sink is locked, so they must not
+//! emit from the same sink or free its subscriptions.
+//!
+//! Rust code can build on a sink created from C through `epoxy_sink_i32_stream` and its versions
+//! for the other types, which return the sink's stream.
+//!
+//! Requires the `ffi` feature.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::epoxy_ffi::*;
+//! use std::ffi::c_void;
+//! use std::sync::atomic::{AtomicI32, Ordering};
+//!
+//! extern "C" fn add_to_total(user_data: *mut c_void, value: i32) {
+//!     let total = unsafe { &*(user_data as *const AtomicI32) };
+//!     total.fetch_add(value, Ordering::SeqCst);
+//! }
+//!
+//! let total = AtomicI32::new(0);
+//! unsafe {
+//!     let sink = epoxy_sink_i32_new();
+//!     let subscription = epoxy_sink_i32_subscribe(
+//!         sink,
+//!         add_to_total,
+//!         None,
+//!         &total as *const AtomicI32 as *mut c_void,
+//!     );
+//!     epoxy_sink_i32_emit(sink, 2);
+//!     epoxy_sink_i32_emit(sink, 3);
+//!
+//!     epoxy_subscription_free(subscription);
+//!     epoxy_sink_i32_emit(sink, 100);
+//!     epoxy_sink_i32_free(sink);
+//! }
+//! assert_eq!(total.load(Ordering::SeqCst), 5);
+//! ```
+use super::{Sink, Stream, Subscription};
+use std::any::Any;
+use std::ffi::c_void;
+use std::ptr;
+
+/// Called when a sink closes, with the `user_data` pointer given when subscribing.
+pub type EpoxyCompleteCallback = extern "C" fn(user_data: *mut c_void);
+
+/// A subscription created from C, freed with `epoxy_subscription_free`.
+pub struct EpoxySubscription {
+    #[allow(dead_code)]
+    subscription: Box<dyn Any + Send>,
+}
+
+// The user data pointer is owned by the C application, which is responsible for making it safe to
+// use from whichever thread emits values.
+#[derive(Clone, Copy)]
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+/// Unsubscribes and frees a subscription created by one of the `subscribe` functions.
+///
+/// # Safety
+/// `subscription` must be null or a handle returned by a `subscribe` function that has not been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn epoxy_subscription_free(subscription: *mut EpoxySubscription) {
+    if !subscription.is_null() {
+        drop(Box::from_raw(subscription));
+    }
+}
+
+macro_rules! ffi_sink {
+    (
+        $value_type:ty,
+        $sink_name:ident,
+        $callback_name:ident,
+        $new_fn:ident,
+        $emit_fn:ident,
+        $subscribe_fn:ident,
+        $close_fn:ident,
+        $free_fn:ident,
+        $stream_fn:ident
+    ) => {
+        /// A sink created from C, freed with its `free` function.
+        pub struct $sink_name {
+            sink: Sink<$value_type>,
+        }
+
+        /// Called with every value emitted by a sink, along with the `user_data` pointer given
+        /// when subscribing.
+        pub type $callback_name = extern "C" fn(user_data: *mut c_void, value: $value_type);
+
+        /// Creates a sink. The returned handle must be freed with the matching `free` function.
+        #[no_mangle]
+        pub extern "C" fn $new_fn() -> *mut $sink_name {
+            Box::into_raw(Box::new($sink_name { sink: Sink::new() }))
+        }
+
+        /// Emits a value from the sink.
+        ///
+        /// # Safety
+        /// `sink` must be null or a live handle returned by the matching `new` function.
+        #[no_mangle]
+        pub unsafe extern "C" fn $emit_fn(sink: *const $sink_name, value: $value_type) {
+            if let Some(sink) = sink.as_ref() {
+                sink.sink.emit(value);
+            }
+        }
+
+        /// Calls `on_value` with every value the sink emits from now on, and `on_complete` (if
+        /// not null) once the sink closes. Returns a subscription handle, which must be freed
+        /// with `epoxy_subscription_free`, or null if `sink` is null.
+        ///
+        /// # Safety
+        /// `sink` must be null or a live handle returned by the matching `new` function.
+        /// `user_data` is passed to the callbacks as is, and must stay valid until the
+        /// subscription is freed.
+        #[no_mangle]
+        pub unsafe extern "C" fn $subscribe_fn(
+            sink: *const $sink_name,
+            on_value: $callback_name,
+            on_complete: Option<EpoxyCompleteCallback>,
+            user_data: *mut c_void,
+        ) -> *mut EpoxySubscription {
+            let sink = match sink.as_ref() {
+                Some(sink) => sink,
+                None => return ptr::null_mut(),
+            };
+            let user_data = UserData(user_data);
+            let subscription: Subscription<$value_type> =
+                sink.sink.get_stream().subscribe_with_completion(
+                    move |val| {
+                        let user_data = user_data;
+                        on_value(user_data.0, *val)
+                    },
+                    move || {
+                        let user_data = user_data;
+                        if let Some(on_complete) = on_complete {
+                            on_complete(user_data.0);
+                        }
+                    },
+                );
+            Box::into_raw(Box::new(EpoxySubscription {
+                subscription: Box::new(subscription),
+            }))
+        }
+
+        /// Closes the sink, see `Sink::close`. The handle still has to be freed.
+        ///
+        /// # Safety
+        /// `sink` must be null or a live handle returned by the matching `new` function.
+        #[no_mangle]
+        pub unsafe extern "C" fn $close_fn(sink: *const $sink_name) {
+            if let Some(sink) = sink.as_ref() {
+                sink.sink.close();
+            }
+        }
+
+        /// Closes and frees the sink.
+        ///
+        /// # Safety
+        /// `sink` must be null or a handle returned by the matching `new` function that has not
+        /// been freed yet.
+        #[no_mangle]
+        pub unsafe extern "C" fn $free_fn(sink: *mut $sink_name) {
+            if !sink.is_null() {
+                drop(Box::from_raw(sink));
+            }
+        }
+
+        /// Returns the stream of a sink created from C, so that Rust code can apply operators to
+        /// it. Returns None if `sink` is null.
+        ///
+        /// # Safety
+        /// `sink` must be null or a live handle returned by the matching `new` function.
+        pub unsafe fn $stream_fn(sink: *const $sink_name) -> Option<Stream<$value_type>> {
+            sink.as_ref().map(|sink| sink.sink.get_stream())
+        }
+    };
+}
+
+ffi_sink!(
+    i32,
+    EpoxySinkI32,
+    EpoxyValueCallbackI32,
+    epoxy_sink_i32_new,
+    epoxy_sink_i32_emit,
+    epoxy_sink_i32_subscribe,
+    epoxy_sink_i32_close,
+    epoxy_sink_i32_free,
+    epoxy_sink_i32_stream
+);
+
+ffi_sink!(
+    i64,
+    EpoxySinkI64,
+    EpoxyValueCallbackI64,
+    epoxy_sink_i64_new,
+    epoxy_sink_i64_emit,
+    epoxy_sink_i64_subscribe,
+    epoxy_sink_i64_close,
+    epoxy_sink_i64_free,
+    epoxy_sink_i64_stream
+);
+
+ffi_sink!(
+    f64,
+    EpoxySinkF64,
+    EpoxyValueCallbackF64,
+    epoxy_sink_f64_new,
+    epoxy_sink_f64_emit,
+    epoxy_sink_f64_subscribe,
+    epoxy_sink_f64_close,
+    epoxy_sink_f64_free,
+    epoxy_sink_f64_stream
+);
+
+ffi_sink!(
+    bool,
+    EpoxySinkBool,
+    EpoxyValueCallbackBool,
+    epoxy_sink_bool_new,
+    epoxy_sink_bool_emit,
+    epoxy_sink_bool_subscribe,
+    epoxy_sink_bool_close,
+    epoxy_sink_bool_free,
+    epoxy_sink_bool_stream
+);
@@ -0,0 +1,260 @@
+use core::error::Error;
+use core::fmt;
+
+/// Error returned when interacting with a stream whose Sink has been closed or dropped, meaning that the
+/// stream will never emit again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamClosed;
+
+impl fmt::Display for StreamClosed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The stream's Sink has been closed or dropped")
+    }
+}
+
+impl Error for StreamClosed {}
+
+/// Error returned when reading a ReactiveValue whose lock was poisoned by a thread that panicked
+/// while updating it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValuePoisoned;
+
+impl fmt::Display for ValuePoisoned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "The ReactiveValue lock was poisoned")
+    }
+}
+
+impl Error for ValuePoisoned {}
+
+/// Reported by `Stream::assert_ordered` when a sequenced value does not arrive in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderViolation {
+    /// One or more sequence numbers were skipped.
+    Gap { expected: u64, received: u64 },
+
+    /// A value arrived after a value with a higher sequence number.
+    OutOfOrder { expected: u64, received: u64 },
+}
+
+impl fmt::Display for OrderViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderViolation::Gap { expected, received } => write!(
+                f,
+                "Expected sequence number {} but received {}, skipping {} value(s)",
+                expected,
+                received,
+                received - expected
+            ),
+            OrderViolation::OutOfOrder { expected, received } => write!(
+                f,
+                "Expected sequence number {} but received {}, which arrived out of order",
+                expected, received
+            ),
+        }
+    }
+}
+
+impl Error for OrderViolation {}
+
+/// Returned when a request made with a `Requester` does not get a response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestError {
+    /// Nothing was subscribed to the Responder's stream when the request was sent.
+    NoResponder,
+
+    /// The Responder did not answer before the Requester's timeout elapsed.
+    TimedOut,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::NoResponder => write!(f, "No responder was listening for the request"),
+            RequestError::TimedOut => write!(f, "The request timed out before it was answered"),
+        }
+    }
+}
+
+impl Error for RequestError {}
+
+/// Returned when a message cannot be delivered to a `Mailbox`. Contains the message that was not
+/// delivered.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MailboxError<T> {
+    /// The mailbox's queue is full. Only returned by `try_send`.
+    Full(T),
+
+    /// The mailbox has shut down, or its handler panicked.
+    Closed(T),
+}
+
+impl<T> fmt::Debug for MailboxError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MailboxError::Full(_) => write!(f, "Full(..)"),
+            MailboxError::Closed(_) => write!(f, "Closed(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for MailboxError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MailboxError::Full(_) => write!(f, "The mailbox queue is full"),
+            MailboxError::Closed(_) => write!(f, "The mailbox has shut down"),
+        }
+    }
+}
+
+impl<T> Error for MailboxError<T> {}
+
+/// Returned when emitting through an `EmitPermit` fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermitError {
+    /// The permit has been revoked by its `PermitRevoker`.
+    Revoked,
+
+    /// The Sink that minted the permit has been closed or dropped.
+    StreamClosed,
+}
+
+impl fmt::Display for PermitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PermitError::Revoked => write!(f, "The emit permit has been revoked"),
+            PermitError::StreamClosed => write!(f, "The stream's Sink has been closed or dropped"),
+        }
+    }
+}
+
+impl Error for PermitError {}
+
+/// Error raised when a ReactiveValue is set while a `computed!` expression is being evaluated,
+/// see `config::set_reentrant_write_policy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReentrantWriteError;
+
+impl fmt::Display for ReentrantWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "A ReactiveValue was set while a computed value was being evaluated, which can \
+             recurse forever if the computed value depends on it"
+        )
+    }
+}
+
+impl Error for ReentrantWriteError {}
+
+/// Error returned by `WriteableReactiveValue::try_set` when the value can not be set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetError {
+    /// The value was set while a `computed!` expression was being evaluated, see
+    /// `ReentrantWriteError`.
+    Reentrant,
+
+    /// The value has been frozen with `WriteableReactiveValue::freeze`.
+    Frozen,
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetError::Reentrant => ReentrantWriteError.fmt(f),
+            SetError::Frozen => write!(f, "Attempted to set a ReactiveValue after it was frozen"),
+        }
+    }
+}
+
+impl Error for SetError {}
+
+impl From<ReentrantWriteError> for SetError {
+    fn from(_: ReentrantWriteError) -> SetError {
+        SetError::Reentrant
+    }
+}
+
+/// Returned when checkpointing or restoring the state of a stream fails.
+#[cfg(feature = "checkpoint")]
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// The stream was not created by an operator whose state can be checkpointed.
+    NotCheckpointable,
+
+    /// The state could not be converted to or from JSON.
+    Json(serde_json::Error),
+
+    /// A checkpoint file could not be read or written.
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "checkpoint")]
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::NotCheckpointable => {
+                write!(f, "The stream's operator does not support checkpoints")
+            }
+            CheckpointError::Json(err) => write!(f, "Invalid checkpoint state: {}", err),
+            CheckpointError::Io(err) => write!(f, "Could not access the checkpoint file: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl Error for CheckpointError {}
+
+#[cfg(feature = "checkpoint")]
+impl From<serde_json::Error> for CheckpointError {
+    fn from(err: serde_json::Error) -> CheckpointError {
+        CheckpointError::Json(err)
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl From<std::io::Error> for CheckpointError {
+    fn from(err: std::io::Error) -> CheckpointError {
+        CheckpointError::Io(err)
+    }
+}
+
+/// Returned when a `pipeline_config::PipelineConfig` can not be turned into a pipeline.
+#[cfg(feature = "pipeline_config")]
+#[derive(Debug)]
+pub enum PipelineConfigError {
+    /// A stage names a filter or map function that was not registered.
+    UnknownFunction(String),
+
+    /// A stage has a parameter that is out of range, like sampling every 0th value.
+    InvalidParameter(String),
+
+    /// The configuration is not valid JSON, or does not describe a pipeline.
+    Json(serde_json::Error),
+}
+
+#[cfg(feature = "pipeline_config")]
+impl fmt::Display for PipelineConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineConfigError::UnknownFunction(name) => {
+                write!(f, "No function named \"{}\" was registered", name)
+            }
+            PipelineConfigError::InvalidParameter(message) => {
+                write!(f, "Invalid stage parameter: {}", message)
+            }
+            PipelineConfigError::Json(err) => write!(f, "Invalid pipeline config: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "pipeline_config")]
+impl Error for PipelineConfigError {}
+
+#[cfg(feature = "pipeline_config")]
+impl From<serde_json::Error> for PipelineConfigError {
+    fn from(err: serde_json::Error) -> PipelineConfigError {
+        PipelineConfigError::Json(err)
+    }
+}
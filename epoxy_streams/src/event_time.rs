@@ -0,0 +1,291 @@
+use super::sync::Mutex;
+use super::{Stream, Subscription};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// A value tagged with the time at which the event it describes happened, as opposed to the time
+/// at which it was emitted or recorded (see `Timestamped`). See `Stream::assign_timestamps`.
+///
+/// Event times are measured from whatever epoch the application picks, like the Unix epoch or the
+/// start of a recording, as long as every value in a stream uses the same one.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EventTimed<T> {
+    pub event_time: Duration,
+    pub value: Arc<T>,
+}
+
+impl<T> Clone for EventTimed<T> {
+    fn clone(&self) -> Self {
+        EventTimed {
+            event_time: self.event_time,
+            value: self.value.clone(),
+        }
+    }
+}
+
+/// The values whose event times fall within `start..end`, emitted from `EventTimeWindows::windows`.
+/// Values are in event time order, with values that share an event time in the order they arrived.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EventTimeWindow<T> {
+    pub start: Duration,
+    pub end: Duration,
+    pub values: Vec<Arc<T>>,
+}
+
+/// The streams produced by `Stream::window_event_time`. The streams stay connected to the source
+/// for as long as any of them are alive.
+pub struct EventTimeWindows<T> {
+    windows: Stream<EventTimeWindow<T>>,
+    late_data: Stream<EventTimed<T>>,
+    watermarks: Stream<Duration>,
+}
+
+impl<T> EventTimeWindows<T> {
+    /// Returns the stream of windows, emitted as soon as the watermark passes their end.
+    pub fn windows(&self) -> Stream<EventTimeWindow<T>> {
+        self.windows.clone()
+    }
+
+    /// Returns the stream of values that arrived after their window was emitted.
+    pub fn late_data(&self) -> Stream<EventTimed<T>> {
+        self.late_data.clone()
+    }
+
+    /// Returns the stream of watermarks, emitted every time the watermark moves forward. Every
+    /// value that arrives from then on with an earlier event time is late.
+    pub fn watermarks(&self) -> Stream<Duration> {
+        self.watermarks.clone()
+    }
+}
+
+struct EventTimeFields<T> {
+    #[allow(dead_code)]
+    subscription: Option<Arc<Subscription<EventTimed<T>>>>,
+}
+
+struct WindowState<T> {
+    // Windows that have not been emitted yet, by start time. Each value is kept with its
+    // event time so the window can be sorted when it is emitted.
+    open: BTreeMap<Duration, Vec<(Duration, Arc<T>)>>,
+    watermark: Option<Duration>,
+}
+
+impl<T> WindowState<T> {
+    // Removes the windows that end at or before the watermark (or every window if there is
+    // none), in order.
+    fn take_closed(&mut self, size: Duration) -> Vec<EventTimeWindow<T>> {
+        let still_open = match self.watermark {
+            Some(watermark) => self.open.split_off(&window_start(watermark, size)),
+            None => BTreeMap::new(),
+        };
+        let closed = core::mem::replace(&mut self.open, still_open);
+        closed
+            .into_iter()
+            .map(|(start, mut values)| {
+                values.sort_by_key(|(event_time, _)| *event_time);
+                EventTimeWindow {
+                    start,
+                    end: start + size,
+                    values: values.into_iter().map(|(_, value)| value).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn window_start(event_time: Duration, size: Duration) -> Duration {
+    let size_nanos = size.as_nanos().max(1);
+    Duration::from_nanos((event_time.as_nanos() / size_nanos * size_nanos) as u64)
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Returns a stream that tags every value with the event time returned by `timestamp`, for
+    /// use with `window_event_time`.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Sink};
+    /// use std::time::Duration;
+    ///
+    /// struct Reading {
+    ///     taken_at_ms: u64,
+    /// }
+    ///
+    /// let stream_host: Sink<Reading> = Sink::new();
+    /// let timestamped = stream_host
+    ///     .get_stream()
+    ///     .assign_timestamps(|reading| Duration::from_millis(reading.taken_at_ms));
+    /// let cache = ReactiveCache::from_stream(timestamped.map(|val| val.event_time));
+    ///
+    /// stream_host.emit(Reading { taken_at_ms: 1500 });
+    /// assert_eq!(cache.get_cloned(), vec![Duration::from_millis(1500)]);
+    /// ```
+    pub fn assign_timestamps<F>(&self, timestamp: F) -> Stream<EventTimed<T>>
+    where
+        F: Fn(&T) -> Duration + Send + Sync + 'static,
+    {
+        self.map_rc(move |value| {
+            Arc::new(EventTimed {
+                event_time: timestamp(&value),
+                value,
+            })
+        })
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<EventTimed<T>> {
+    /// Groups values into consecutive windows of event time, each `size` long, regardless of the
+    /// order in which the values arrive. Windows start at multiples of `size`.
+    ///
+    /// Since values can arrive out of order, a window can not be emitted as soon as a later value
+    /// arrives. Instead the operator keeps a watermark, which trails the latest event time seen so
+    /// far by `allowed_lateness`, and emits a window once the watermark reaches its end. Values
+    /// that arrive for a window that was already emitted are sent to the late data stream
+    /// instead, so they can be handled separately rather than being lost. Windows that are still
+    /// open when the source completes are emitted right away.
+    ///
+    /// Windows with no values are never emitted.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Sink, EventTimed};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: Sink<EventTimed<&str>> = Sink::new();
+    /// let windowed = stream_host
+    ///     .get_stream()
+    ///     .window_event_time(Duration::from_secs(10), Duration::from_secs(5));
+    /// let windows = ReactiveCache::from_stream(windowed.windows().map(|window| {
+    ///     let values: Vec<&str> = window.values.iter().map(|value| **value).collect();
+    ///     (window.start.as_secs(), values)
+    /// }));
+    /// let late = ReactiveCache::from_stream(windowed.late_data().map(|late| *late.value));
+    ///
+    /// let emit = |secs, value| {
+    ///     stream_host.emit(EventTimed {
+    ///         event_time: Duration::from_secs(secs),
+    ///         value: Arc::new(value),
+    ///     })
+    /// };
+    /// emit(3, "a");
+    /// emit(12, "c");
+    /// emit(8, "b"); // Out of order, but within the allowed lateness.
+    /// emit(15, "d"); // Moves the watermark to 10, which closes the first window.
+    /// emit(9, "too late");
+    /// stream_host.close();
+    ///
+    /// assert_eq!(
+    ///     windows.get_cloned(),
+    ///     vec![(0, vec!["a", "b"]), (10, vec!["c", "d"])]
+    /// );
+    /// assert_eq!(late.get_cloned(), vec!["too late"]);
+    /// ```
+    pub fn window_event_time(
+        &self,
+        size: Duration,
+        allowed_lateness: Duration,
+    ) -> EventTimeWindows<T> {
+        let windows = self.derive_with_fields(EventTimeFields::<T> { subscription: None });
+        let late_data = self.derive_with_fields(EventTimeFields::<T> { subscription: None });
+        let watermarks = self.derive_with_fields(EventTimeFields::<T> { subscription: None });
+
+        let state = Arc::new(Mutex::new(WindowState {
+            open: BTreeMap::new(),
+            watermark: None,
+        }));
+        let completion_state = state.clone();
+        let weak_windows = Arc::downgrade(&windows.pointer);
+        let weak_late_data = Arc::downgrade(&late_data.pointer);
+        let weak_watermarks = Arc::downgrade(&watermarks.pointer);
+        let completion_windows = weak_windows.clone();
+        let completion_late_data = weak_late_data.clone();
+        let completion_watermarks = weak_watermarks.clone();
+
+        let subscription = Arc::new(self.subscribe_with_completion(
+            move |val| {
+                let mut is_late = false;
+                let mut new_watermark = None;
+                let closed = {
+                    let mut state = state.lock();
+                    let start = window_start(val.event_time, size);
+                    let watermark = state.watermark;
+                    if watermark.is_some_and(|watermark| start + size <= watermark) {
+                        is_late = true;
+                        vec![]
+                    } else {
+                        state
+                            .open
+                            .entry(start)
+                            .or_insert_with(Vec::new)
+                            .push((val.event_time, val.value.clone()));
+                        let candidate = val.event_time.saturating_sub(allowed_lateness);
+                        if watermark.is_none_or(|watermark| candidate > watermark) {
+                            state.watermark = Some(candidate);
+                            new_watermark = Some(candidate);
+                            state.take_closed(size)
+                        } else {
+                            vec![]
+                        }
+                    }
+                };
+
+                if is_late {
+                    if let Some(pointer) = weak_late_data.upgrade() {
+                        Stream { pointer }.emit_rc(val);
+                    }
+                    return;
+                }
+                if let Some(pointer) = weak_windows.upgrade() {
+                    let windows = Stream { pointer };
+                    for window in closed {
+                        windows.emit_rc(Arc::new(window));
+                    }
+                }
+                if let (Some(watermark), Some(pointer)) = (new_watermark, weak_watermarks.upgrade())
+                {
+                    Stream { pointer }.emit_rc(Arc::new(watermark));
+                }
+            },
+            move || {
+                let remaining = {
+                    let mut state = completion_state.lock();
+                    state.watermark = None;
+                    state.take_closed(size)
+                };
+                if let Some(pointer) = completion_windows.upgrade() {
+                    let windows = Stream { pointer };
+                    for window in remaining {
+                        windows.emit_rc(Arc::new(window));
+                    }
+                    windows.complete();
+                }
+                if let Some(pointer) = completion_late_data.upgrade() {
+                    Stream { pointer }.complete();
+                }
+                if let Some(pointer) = completion_watermarks.upgrade() {
+                    Stream { pointer }.complete();
+                }
+            },
+        ));
+
+        windows.mutate_extra_fields(|fields: &mut EventTimeFields<T>| {
+            fields.subscription = Some(subscription.clone());
+        });
+        late_data.mutate_extra_fields(|fields: &mut EventTimeFields<T>| {
+            fields.subscription = Some(subscription.clone());
+        });
+        watermarks.mutate_extra_fields(|fields: &mut EventTimeFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        EventTimeWindows {
+            windows,
+            late_data,
+            watermarks,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::sync::Mutex;
+use super::{Sink, Stream, Subscription};
+use alloc::sync::Arc;
+
+/// A Sink for streams that end with a final result, such as a download that emits progress
+/// updates and then finishes with a summary. The values and the result can have different types.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{FinishingSink, ReactiveCache};
+/// use std::sync::{Arc, Mutex};
+///
+/// let download: FinishingSink<u8, String> = FinishingSink::new();
+/// let progress = ReactiveCache::from_stream(download.get_stream().get_stream());
+///
+/// let summary = Arc::new(Mutex::new(None));
+/// let summary_write = summary.clone();
+/// let _on_finish = download.get_stream().on_finish(move |result| {
+///     *summary_write.lock().unwrap() = Some((*result).clone());
+/// });
+///
+/// download.emit(50);
+/// download.emit(100);
+/// download.finish("Downloaded 3 files".to_string());
+///
+/// assert_eq!(progress.get_cloned(), vec![50, 100]);
+/// assert_eq!(*summary.lock().unwrap(), Some("Downloaded 3 files".to_string()));
+/// assert!(download.get_stream().get_stream().is_complete());
+/// ```
+pub struct FinishingSink<T, R> {
+    sink: Sink<T>,
+    result: Arc<Mutex<Option<Arc<R>>>>,
+}
+
+/// The read side of a `FinishingSink`: a stream of values plus the result it finishes with.
+pub struct FinishingStream<T, R> {
+    stream: Stream<T>,
+    result: Arc<Mutex<Option<Arc<R>>>>,
+}
+
+impl<T, R> Clone for FinishingStream<T, R> {
+    fn clone(&self) -> Self {
+        FinishingStream {
+            stream: self.stream.clone(),
+            result: Arc::clone(&self.result),
+        }
+    }
+}
+
+impl<T, R> Default for FinishingSink<T, R> {
+    fn default() -> Self {
+        FinishingSink::new()
+    }
+}
+
+impl<T, R> FinishingSink<T, R> {
+    pub fn new() -> FinishingSink<T, R> {
+        FinishingSink {
+            sink: Sink::new(),
+            result: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns the stream of values and the result they finish with.
+    pub fn get_stream(&self) -> FinishingStream<T, R> {
+        FinishingStream {
+            stream: self.sink.get_stream(),
+            result: Arc::clone(&self.result),
+        }
+    }
+
+    /// Emits a value. Values emitted after `finish` are discarded.
+    pub fn emit(&self, value: T) {
+        self.sink.emit(value)
+    }
+
+    /// Same logic as `emit`, but takes an existing Arc pointer.
+    pub fn emit_rc(&self, value: Arc<T>) {
+        self.sink.emit_rc(value)
+    }
+
+    /// Completes the stream with the given result, which is passed to every `on_finish`
+    /// listener. Does nothing if the stream has already finished.
+    pub fn finish(&self, result: R) {
+        {
+            let mut current_result = self.result.lock();
+            if current_result.is_some() {
+                return;
+            }
+            *current_result = Some(Arc::new(result));
+        }
+        self.sink.close();
+    }
+}
+
+impl<T, R> FinishingStream<T, R> {
+    /// Returns the stream of values. It completes when the Sink finishes.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.stream.clone()
+    }
+
+    /// Returns the result, or None if the stream has not finished yet.
+    pub fn result(&self) -> Option<Arc<R>> {
+        self.result.lock().clone()
+    }
+
+    /// Runs the given function with the result once the stream finishes, or right away if it
+    /// already has. The function is not called if the Sink is closed or dropped without calling
+    /// `finish`.
+    pub fn on_finish<F>(&self, on_finish: F) -> Subscription<T>
+    where
+        F: FnOnce(Arc<R>),
+        F: Send,
+        F: 'static,
+        T: 'static,
+        R: Send + Sync + 'static,
+    {
+        // Registering while holding the result lock means `finish` cannot slip in between
+        // checking for a result and listening for completion.
+        let result_lock = self.result.lock();
+        if let Some(result) = result_lock.clone() {
+            drop(result_lock);
+            on_finish(result);
+            return self.stream.on_complete(|| {});
+        }
+
+        let result = Arc::clone(&self.result);
+        let subscription = self.stream.on_complete(move || {
+            let result = result.lock().clone();
+            if let Some(result) = result {
+                on_finish(result);
+            }
+        });
+        drop(result_lock);
+        subscription
+    }
+}
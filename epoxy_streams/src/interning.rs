@@ -0,0 +1,171 @@
+use super::{Stream, Subscription};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Counters that describe how well an `Interner` is working.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InternerStats {
+    /// The number of strings currently held by the interner.
+    pub entries: usize,
+
+    /// How many strings were replaced with one the interner already held.
+    pub hits: u64,
+
+    /// How many strings were not held by the interner yet.
+    pub misses: u64,
+
+    /// How many strings were dropped to stay within the interner's capacity.
+    pub evictions: u64,
+}
+
+// Lets the map be searched with a &str without allocating.
+struct InternedKey(Arc<String>);
+
+impl Borrow<str> for InternedKey {
+    fn borrow(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Hash for InternedKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_str().hash(state)
+    }
+}
+
+impl PartialEq for InternedKey {
+    fn eq(&self, other: &InternedKey) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for InternedKey {}
+
+struct InternerState {
+    capacity: usize,
+    // Maps each string to the last time it was used. `recency` holds the same entries ordered by
+    // that time, so the least recently used one can be found quickly.
+    last_used: HashMap<InternedKey, u64>,
+    recency: BTreeMap<u64, Arc<String>>,
+    clock: u64,
+    stats: InternerStats,
+}
+
+/// Replaces equal strings with a single shared copy, keeping up to `capacity` distinct strings
+/// and dropping the least recently used one when it runs out of room. Cloning an Interner returns
+/// a handle to the same set of strings, so several streams can share one. See `Stream::intern`.
+#[derive(Clone)]
+pub struct Interner {
+    state: Arc<Mutex<InternerState>>,
+}
+
+impl Interner {
+    /// Creates an interner that holds up to `capacity` distinct strings.
+    pub fn new(capacity: usize) -> Interner {
+        Interner {
+            state: Arc::new(Mutex::new(InternerState {
+                capacity: capacity.max(1),
+                last_used: HashMap::new(),
+                recency: BTreeMap::new(),
+                clock: 0,
+                stats: InternerStats::default(),
+            })),
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, InternerState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(err) => panic!("Interner mutex poisoned: {}", err),
+        }
+    }
+
+    /// Returns the shared copy of `value`, storing `value` itself if there is none yet.
+    pub fn intern_rc(&self, value: Arc<String>) -> Arc<String> {
+        let mut state = self.lock_state();
+        state.clock += 1;
+        let now = state.clock;
+
+        if let Some(last_used) = state.last_used.get_mut(value.as_str()) {
+            let previous = std::mem::replace(last_used, now);
+            let interned = state.recency.remove(&previous).unwrap();
+            state.recency.insert(now, interned.clone());
+            state.stats.hits += 1;
+            return interned;
+        }
+
+        state.stats.misses += 1;
+        if state.last_used.len() >= state.capacity {
+            let (_, evicted) = state.recency.pop_first().unwrap();
+            state.last_used.remove(evicted.as_str());
+            state.stats.evictions += 1;
+        }
+        state.last_used.insert(InternedKey(value.clone()), now);
+        state.recency.insert(now, value.clone());
+        state.stats.entries = state.last_used.len();
+        value
+    }
+
+    /// Returns the interner's counters.
+    pub fn stats(&self) -> InternerStats {
+        self.lock_state().stats
+    }
+}
+
+struct InternFields {
+    #[allow(dead_code)]
+    subscription: Option<Subscription<String>>,
+}
+
+impl Stream<String> {
+    /// Returns a stream that emits the same strings as this one, but with every string replaced
+    /// by the interner's shared copy of it. Streams of log lines, status messages or identifiers
+    /// tend to repeat the same few strings over and over, and anything downstream that holds on
+    /// to them (like a `ReactiveCache`) then only keeps one copy of each.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{Interner, ReactiveCache};
+    /// use std::sync::Arc;
+    ///
+    /// let interner = Interner::new(1000);
+    /// let stream_host: epoxy_streams::Sink<String> = epoxy_streams::Sink::new();
+    /// let cache = ReactiveCache::from_stream(stream_host.get_stream().intern(&interner));
+    ///
+    /// for _ in 0..3 {
+    ///     stream_host.emit("connection reset".to_string());
+    /// }
+    /// let lines = cache.get();
+    /// assert!(Arc::ptr_eq(&lines[0], &lines[2]));
+    ///
+    /// let stats = interner.stats();
+    /// assert_eq!((stats.entries, stats.hits, stats.misses), (1, 2, 1));
+    /// ```
+    pub fn intern(&self, interner: &Interner) -> Stream<String> {
+        let derived_stream = self.derive_with_fields(InternFields { subscription: None });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+        let interner = interner.clone();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                if let Some(pointer) = weak_stream_ref.upgrade() {
+                    Stream { pointer }.emit_rc(interner.intern_rc(val));
+                }
+            },
+            move || {
+                if let Some(pointer) = weak_completion_ref.upgrade() {
+                    Stream { pointer }.complete();
+                }
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut InternFields| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
@@ -0,0 +1,198 @@
+//! Mirrors streams between processes over Unix domain sockets. A stream published at a socket
+//! path can be subscribed to from any other process, which receives every value emitted after it
+//! connected. Values are encoded as one JSON document per line.
+//!
+//! Requires the `ipc` feature, and is only available on Unix platforms.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::{ipc, ReactiveCache, Sink};
+//! use std::time::Duration;
+//!
+//! let path = std::env::temp_dir().join(format!("epoxy-ipc-doc-{}", std::process::id()));
+//!
+//! // In the daemon process.
+//! let sink: Sink<String> = Sink::new();
+//! let _publisher = ipc::publish(&sink.get_stream(), &path).unwrap();
+//!
+//! // In the UI process.
+//! let mirror: ipc::IpcSubscriber<String> = ipc::subscribe(&path).unwrap();
+//! let cache = ReactiveCache::from_stream(mirror.get_stream());
+//! std::thread::sleep(Duration::from_millis(100));
+//!
+//! sink.emit("Hello".to_string());
+//! std::thread::sleep(Duration::from_millis(100));
+//! assert_eq!(cache.get_cloned(), vec!["Hello".to_string()]);
+//! ```
+use super::{Sink, Stream, Subscription};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// How many encoded values may be waiting to be written to one connected process before that
+/// process is considered too slow and is disconnected.
+pub const CLIENT_QUEUE_LEN: usize = 1024;
+
+/// A connected process, fed by its own writer thread.
+struct Client {
+    queue: SyncSender<Arc<[u8]>>,
+    connection: UnixStream,
+}
+
+impl Client {
+    fn connect(connection: UnixStream) -> io::Result<Client> {
+        let (queue, lines) = mpsc::sync_channel::<Arc<[u8]>>(CLIENT_QUEUE_LEN);
+        let mut writer = connection.try_clone()?;
+        thread::Builder::new()
+            .name("epoxy-ipc-writer".to_string())
+            .spawn(move || {
+                for line in lines {
+                    if writer.write_all(&line).is_err() {
+                        return;
+                    }
+                }
+            })?;
+        Ok(Client { queue, connection })
+    }
+
+    /// Queues a line for the writer thread. Returns false if the process went away or has fallen
+    /// `CLIENT_QUEUE_LEN` values behind, in which case it is disconnected.
+    fn send(&self, line: &Arc<[u8]>) -> bool {
+        match self.queue.try_send(line.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                let _ = self.connection.shutdown(Shutdown::Both);
+                false
+            }
+        }
+    }
+}
+
+/// Publishes a stream at a socket path until it is dropped. See `publish`.
+pub struct IpcPublisher<T> {
+    path: PathBuf,
+    is_shut_down: Arc<AtomicBool>,
+
+    #[allow(dead_code)]
+    subscription: Subscription<T>,
+}
+
+impl<T> Drop for IpcPublisher<T> {
+    fn drop(&mut self) {
+        self.is_shut_down.store(true, Ordering::SeqCst);
+        // Wake up the accept thread so that it notices the shutdown.
+        let _ = UnixStream::connect(&self.path);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Exposes a stream at the given socket path, so that other processes can mirror it with
+/// `subscribe`. Fails if the path is already in use. Each connected process is written to from a
+/// thread of its own, so emitting never blocks on a slow reader. A process that disconnects, or
+/// that falls more than `CLIENT_QUEUE_LEN` values behind, is dropped silently.
+pub fn publish<T, P>(stream: &Stream<T>, path: P) -> io::Result<IpcPublisher<T>>
+where
+    T: Serialize + Send + Sync + 'static,
+    P: AsRef<Path>,
+{
+    let path = path.as_ref().to_path_buf();
+    let listener = UnixListener::bind(&path)?;
+    let clients: Arc<Mutex<Vec<Client>>> = Arc::new(Mutex::new(vec![]));
+    let is_shut_down = Arc::new(AtomicBool::new(false));
+
+    let accept_clients = clients.clone();
+    let accept_is_shut_down = is_shut_down.clone();
+    thread::Builder::new()
+        .name("epoxy-ipc-publisher".to_string())
+        .spawn(move || {
+            for connection in listener.incoming() {
+                if accept_is_shut_down.load(Ordering::SeqCst) {
+                    return;
+                }
+                if let Ok(client) = connection.and_then(Client::connect) {
+                    match accept_clients.lock() {
+                        Ok(mut clients) => clients.push(client),
+                        Err(err) => panic!("IPC mutex poisoned: {}", err),
+                    }
+                }
+            }
+        })?;
+
+    let subscription = stream.subscribe(move |val| {
+        let mut line = match serde_json::to_vec(&*val) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        line.push(b'\n');
+        let line: Arc<[u8]> = line.into();
+        match clients.lock() {
+            Ok(mut clients) => clients.retain(|client| client.send(&line)),
+            Err(err) => panic!("IPC mutex poisoned: {}", err),
+        }
+    });
+
+    Ok(IpcPublisher {
+        path,
+        is_shut_down,
+        subscription,
+    })
+}
+
+/// A local mirror of a stream published by another process. See `subscribe`.
+pub struct IpcSubscriber<T> {
+    sink: Arc<Sink<T>>,
+    connection: UnixStream,
+}
+
+impl<T> IpcSubscriber<T> {
+    /// Returns the stream of values received from the publishing process. The stream completes
+    /// when the publisher goes away.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.sink.get_stream()
+    }
+}
+
+impl<T> Drop for IpcSubscriber<T> {
+    fn drop(&mut self) {
+        let _ = self.connection.shutdown(Shutdown::Both);
+    }
+}
+
+/// Connects to a stream published at the given socket path. Values are read on a background
+/// thread and re-emitted from the returned subscriber's stream. Values that cannot be decoded
+/// are skipped.
+pub fn subscribe<T, P>(path: P) -> io::Result<IpcSubscriber<T>>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+    P: AsRef<Path>,
+{
+    let connection = UnixStream::connect(path)?;
+    let reader = BufReader::new(connection.try_clone()?);
+    let sink = Arc::new(Sink::new());
+
+    let reader_sink = sink.clone();
+    thread::Builder::new()
+        .name("epoxy-ipc-subscriber".to_string())
+        .spawn(move || {
+            for line in reader.lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if let Ok(value) = serde_json::from_str(&line) {
+                    reader_sink.emit(value);
+                }
+            }
+            reader_sink.close();
+        })?;
+
+    Ok(IpcSubscriber { sink, connection })
+}
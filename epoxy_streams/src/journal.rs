@@ -0,0 +1,403 @@
+//! Event-sourced persistence for streams. A `Journal` appends every value it emits to a file,
+//! one JSON document per line, and can replay those values after the application restarts.
+//! `record` does the same for an existing stream. Clients that reconnect can catch up on the
+//! values they missed with `Journal::subscribe_from`.
+//!
+//! Requires the `journal` feature.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::journal::Journal;
+//! use epoxy_streams::ReactiveCache;
+//!
+//! let path = std::env::temp_dir().join(format!("epoxy-journal-doc-{}", std::process::id()));
+//! # let _ = std::fs::remove_file(&path);
+//!
+//! {
+//!     let journal: Journal<i32> = Journal::open(&path).unwrap();
+//!     journal.emit(1).unwrap();
+//!     journal.emit(2).unwrap();
+//! }
+//!
+//! // After a restart, subscribe first and then replay the journal before emitting new values.
+//! let journal: Journal<i32> = Journal::open(&path).unwrap();
+//! let cache = ReactiveCache::from_stream(journal.get_stream());
+//! assert_eq!(journal.replay().unwrap(), 2);
+//! journal.emit(3).unwrap();
+//! assert_eq!(cache.get_cloned(), vec![1, 2, 3]);
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+use super::{Sequenced, Sink, Stream, Subscription};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::Hash;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Opens the journal file for appending. An incomplete final line, as left behind by a crash in
+/// the middle of a write, is cut off first, so that the next entry starts on a line of its own.
+fn open_for_append(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    let complete_len = complete_length(&file)?;
+    if complete_len < file.metadata()?.len() {
+        file.set_len(complete_len)?;
+    }
+    Ok(file)
+}
+
+/// Returns the length of the file up to and including its last newline.
+fn complete_length(mut file: &File) -> io::Result<u64> {
+    let mut buffer = [0u8; 4096];
+    let mut end = file.metadata()?.len();
+    while end > 0 {
+        let start = end.saturating_sub(buffer.len() as u64);
+        let chunk = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(newline) = chunk.iter().rposition(|byte| *byte == b'\n') {
+            return Ok(start + newline as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+fn append_entry<T: Serialize>(file: &mut File, value: &T) -> io::Result<()> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    // Written with a single call so that concurrent entries never interleave.
+    file.write_all(&line)
+}
+
+/// Calls `on_entry` with every complete line of the journal file, in order. An incomplete final
+/// line, as left behind by a crash in the middle of a write, is ignored.
+fn read_entries<F>(path: &Path, mut on_entry: F) -> io::Result<u64>
+where
+    F: FnMut(u64, &str) -> io::Result<()>,
+{
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = String::new();
+    let mut count = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+            return Ok(count);
+        }
+        on_entry(count, &line)?;
+        count += 1;
+    }
+}
+
+type Compactor<T> = Box<dyn Fn(Vec<Sequenced<T>>) -> Vec<Sequenced<T>>>;
+
+/// Decides which of the missed values `Journal::subscribe_from` replays.
+pub struct Compaction<T> {
+    compact: Option<Compactor<T>>,
+}
+
+impl<T: 'static> Compaction<T> {
+    /// Replays every missed value.
+    pub fn keep_all() -> Compaction<T> {
+        Compaction { compact: None }
+    }
+
+    /// Replays only the latest missed value for each key, for journals of updates where newer
+    /// values replace older ones with the same key. The values that are kept are replayed in the
+    /// order they were written.
+    pub fn latest_per_key<K, F>(key: F) -> Compaction<T>
+    where
+        K: Hash + Eq,
+        F: Fn(&T) -> K + 'static,
+    {
+        Compaction {
+            compact: Some(Box::new(move |entries: Vec<Sequenced<T>>| {
+                let mut latest = HashMap::new();
+                for (index, entry) in entries.iter().enumerate() {
+                    latest.insert(key(&entry.value), index);
+                }
+                entries
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, entry)| latest.get(&key(&entry.value)) == Some(index))
+                    .map(|(_, entry)| entry)
+                    .collect()
+            })),
+        }
+    }
+}
+
+impl<T: 'static> Default for Compaction<T> {
+    fn default() -> Compaction<T> {
+        Compaction::keep_all()
+    }
+}
+
+struct JournalFile {
+    file: File,
+    next_sequence: u64,
+}
+
+/// A Sink whose values are appended to a journal file before they are emitted. Each entry is
+/// numbered by its position in the file, starting at 0, which subscribers can use to resume where
+/// they left off (see `subscribe_from`).
+pub struct Journal<T> {
+    path: PathBuf,
+    file: Mutex<JournalFile>,
+    sink: Sink<T>,
+    entries: Sink<Sequenced<T>>,
+}
+
+impl<T> Journal<T>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Opens the journal at the given path, creating the file if it does not exist yet. Existing
+    /// entries are kept, and are only emitted when `replay` is called. An incomplete final line,
+    /// as left behind by a crash in the middle of a write, is removed from the file.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::journal::Journal;
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let path = std::env::temp_dir().join(format!("epoxy-journal-torn-{}", std::process::id()));
+    ///
+    /// // The process crashed halfway through writing its second entry.
+    /// std::fs::write(&path, "1\n4").unwrap();
+    ///
+    /// let journal: Journal<i32> = Journal::open(&path).unwrap();
+    /// journal.emit(2).unwrap();
+    /// assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n2\n");
+    ///
+    /// let cache = ReactiveCache::from_stream(journal.get_stream());
+    /// assert_eq!(journal.replay().unwrap(), 2);
+    /// assert_eq!(cache.get_cloned(), vec![1, 2]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Journal<T>> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_for_append(&path)?;
+        let next_sequence = read_entries(&path, |_, _| Ok(()))?;
+        Ok(Journal {
+            file: Mutex::new(JournalFile {
+                file,
+                next_sequence,
+            }),
+            path,
+            sink: Sink::new(),
+            entries: Sink::new(),
+        })
+    }
+
+    fn lock_file(&self) -> MutexGuard<'_, JournalFile> {
+        match self.file.lock() {
+            Ok(file) => file,
+            Err(err) => panic!("Journal mutex poisoned: {}", err),
+        }
+    }
+
+    /// Returns the stream of replayed and newly emitted values.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.sink.get_stream()
+    }
+
+    /// Returns the sequence number that the next emitted value will get, which is also the
+    /// number of entries in the journal.
+    pub fn next_sequence(&self) -> u64 {
+        self.lock_file().next_sequence
+    }
+
+    /// Appends a value to the journal and then emits it. The value is not emitted if it could not
+    /// be written.
+    pub fn emit(&self, value: T) -> io::Result<()> {
+        let value = Arc::new(value);
+        let sequence = {
+            let mut file = self.lock_file();
+            append_entry(&mut file.file, &*value)?;
+            file.next_sequence += 1;
+            file.next_sequence - 1
+        };
+        self.sink.emit_rc(value.clone());
+        self.entries
+            .emit_rc(Arc::new(Sequenced { sequence, value }));
+        Ok(())
+    }
+
+    /// Emits every value in the journal, in the order they were written, and returns how many
+    /// were emitted. Since streams do not buffer values this should be called after the
+    /// application has subscribed to the stream. An incomplete final line, as left behind by a
+    /// crash in the middle of a write, is ignored.
+    pub fn replay(&self) -> io::Result<usize> {
+        self.replay_from(0)
+    }
+
+    /// Same as `replay`, but starts at the entry numbered `cursor`. This is how a pipeline whose
+    /// state was restored from a `checkpoint::Checkpoint` catches up on the values that arrived
+    /// after the checkpoint was taken, by passing the checkpoint's position.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::journal::Journal;
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let path = std::env::temp_dir().join(format!("epoxy-replay-doc-{}", std::process::id()));
+    /// # let _ = std::fs::remove_file(&path);
+    /// let journal: Journal<i32> = Journal::open(&path).unwrap();
+    /// journal.emit(1).unwrap();
+    /// journal.emit(2).unwrap();
+    /// journal.emit(3).unwrap();
+    ///
+    /// let cache = ReactiveCache::from_stream(journal.get_stream());
+    /// assert_eq!(journal.replay_from(1).unwrap(), 2);
+    /// assert_eq!(cache.get_cloned(), vec![2, 3]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn replay_from(&self, cursor: u64) -> io::Result<usize> {
+        let mut count = 0;
+        read_entries(&self.path, |sequence, line| {
+            if sequence >= cursor {
+                self.sink.emit(serde_json::from_str(line)?);
+                count += 1;
+            }
+            Ok(())
+        })?;
+        Ok(count)
+    }
+
+    /// Subscribes to the journal starting at the entry numbered `cursor`, which is usually one
+    /// more than the sequence number of the last value a reconnecting client received. The
+    /// entries it missed are passed to `listener` first, filtered by `compaction`, followed by
+    /// every value emitted from then on, without gaps or duplicates.
+    ///
+    /// Values emitted while the missed entries are being read wait until they are done, so
+    /// `listener` must not emit to the journal itself during that time. Values emitted
+    /// concurrently from several threads may reach `listener` out of order, which
+    /// `reorder_window` can undo.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::journal::{Compaction, Journal};
+    /// use epoxy_streams::Sequenced;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let path = std::env::temp_dir().join(format!("epoxy-resume-doc-{}", std::process::id()));
+    /// # let _ = std::fs::remove_file(&path);
+    /// let journal: Journal<(String, i32)> = Journal::open(&path).unwrap();
+    /// journal.emit(("a".to_string(), 1)).unwrap();
+    /// journal.emit(("b".to_string(), 2)).unwrap();
+    /// journal.emit(("a".to_string(), 3)).unwrap();
+    ///
+    /// // This client already received entry 0, and only cares about the latest value per key.
+    /// let received = Arc::new(Mutex::new(vec![]));
+    /// let received_write = received.clone();
+    /// let _subscription = journal
+    ///     .subscribe_from(
+    ///         1,
+    ///         Compaction::latest_per_key(|(key, _): &(String, i32)| key.clone()),
+    ///         move |entry: Arc<Sequenced<(String, i32)>>| {
+    ///             received_write
+    ///                 .lock()
+    ///                 .unwrap()
+    ///                 .push((entry.sequence, entry.value.1));
+    ///         },
+    ///     )
+    ///     .unwrap();
+    /// journal.emit(("b".to_string(), 4)).unwrap();
+    ///
+    /// assert_eq!(*received.lock().unwrap(), vec![(1, 2), (2, 3), (3, 4)]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn subscribe_from<F>(
+        &self,
+        cursor: u64,
+        compaction: Compaction<T>,
+        listener: F,
+    ) -> io::Result<Subscription<Sequenced<T>>>
+    where
+        F: Fn(Arc<Sequenced<T>>) + Send + Sync + 'static,
+    {
+        // Holding the file lock keeps new entries from being written until the listener is live.
+        let file = self.lock_file();
+        let live_from = file.next_sequence;
+
+        let mut missed = vec![];
+        read_entries(&self.path, |sequence, line| {
+            if sequence >= cursor && sequence < live_from {
+                missed.push(Sequenced {
+                    sequence,
+                    value: Arc::new(serde_json::from_str(line)?),
+                });
+            }
+            Ok(())
+        })?;
+        if let Some(compact) = compaction.compact {
+            missed = compact(missed);
+        }
+        for entry in missed {
+            listener(Arc::new(entry));
+        }
+
+        // Entries that were already written, but not emitted yet, were just replayed.
+        let subscription = self.entries.get_stream().subscribe(move |entry| {
+            if entry.sequence >= live_from.max(cursor) {
+                listener(entry);
+            }
+        });
+        drop(file);
+        Ok(subscription)
+    }
+}
+
+/// Keeps recording the values of a stream to a journal file until it is dropped. See `record`.
+pub struct JournalRecording<T> {
+    errors: Arc<Mutex<Vec<io::Error>>>,
+
+    #[allow(dead_code)]
+    subscription: Subscription<T>,
+}
+
+impl<T> JournalRecording<T> {
+    /// Returns any errors that occurred while writing to the journal since the last call.
+    pub fn take_errors(&self) -> Vec<io::Error> {
+        match self.errors.lock() {
+            Ok(mut errors) => std::mem::take(&mut *errors),
+            Err(err) => panic!("Journal mutex poisoned: {}", err),
+        }
+    }
+}
+
+/// Appends every value emitted by the stream to the journal file at the given path, in the same
+/// format used by `Journal`, until the returned recording is dropped. An incomplete final line,
+/// as left behind by a crash in the middle of a write, is removed from the file first.
+pub fn record<T, P>(stream: &Stream<T>, path: P) -> io::Result<JournalRecording<T>>
+where
+    T: Serialize + Send + Sync + 'static,
+    P: AsRef<Path>,
+{
+    let file = Mutex::new(open_for_append(path.as_ref())?);
+    let errors = Arc::new(Mutex::new(vec![]));
+    let errors_write = errors.clone();
+    let subscription = stream.subscribe(move |val| {
+        let result = match file.lock() {
+            Ok(mut file) => append_entry(&mut file, &*val),
+            Err(err) => panic!("Journal mutex poisoned: {}", err),
+        };
+        if let Err(err) = result {
+            match errors_write.lock() {
+                Ok(mut errors) => errors.push(err),
+                Err(err) => panic!("Journal mutex poisoned: {}", err),
+            }
+        }
+    });
+    Ok(JournalRecording {
+        errors,
+        subscription,
+    })
+}
@@ -1,16 +1,204 @@
+//! The streams and reactive values behind the `epoxy_frp` crate.
+//!
+//! The crate supports `no_std` environments that provide an allocator. Disabling the default
+//! `std` feature leaves Stream, Sink, Subscription and the basic stream operators, and removes
+//! everything that needs threads, clocks or the standard library locks.
+//!
+//! The default `timers` feature adds the operators that wait for time to pass, such as
+//! `debounce`, `rate_limit`, `retry_with_backoff`, `threshold_alerts` and the watchdog.
+//! Applications that never defer work can turn it off, keeping schedulers and clocks but dropping
+//! the operators built on them.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "timers")]
+mod ack_queue;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+pub mod bus;
+mod byte_stream_operators;
+mod cancellation;
+#[cfg(feature = "checkpoint")]
+pub mod checkpoint;
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(any(feature = "json", feature = "bincode", feature = "msgpack"))]
+mod codec_operators;
+mod compaction;
+#[cfg(any(feature = "zstd", feature = "gzip"))]
+mod compression_operators;
+#[cfg(feature = "std")]
+mod computed_stats;
+#[cfg(feature = "std")]
+mod concurrent_operators;
+#[cfg(feature = "std")]
+pub mod config;
+mod connectable;
+mod conversion;
+pub mod dead_letters;
+#[cfg(feature = "std")]
+mod delivery_modes;
+#[cfg(feature = "std")]
+mod derivation;
+mod distribution;
+#[cfg(feature = "ffi")]
+pub mod epoxy_ffi;
+mod errors;
+mod event_time;
+mod finishing;
+#[cfg(feature = "std")]
+mod interning;
+#[cfg(all(feature = "ipc", unix))]
+pub mod ipc;
+#[cfg(feature = "journal")]
+pub mod journal;
+#[cfg(feature = "std")]
+mod list_projection;
+#[cfg(feature = "std")]
+mod mailbox;
+#[cfg(feature = "std")]
+mod metadata;
+mod notification;
+mod observable;
+mod pipeline;
+#[cfg(feature = "pipeline_config")]
+pub mod pipeline_config;
+mod pool;
+mod producers;
+#[cfg(feature = "std")]
+mod propagation;
+#[cfg(feature = "std")]
 mod reactive_cache;
+#[cfg(feature = "std")]
 mod reactive_value;
+#[cfg(feature = "std")]
 mod reactive_value_operators;
+#[cfg(any(feature = "csv", feature = "parquet"))]
+pub mod recording;
+#[cfg(feature = "std")]
+mod request_response;
+#[cfg(feature = "timers")]
+mod resilience_operators;
+mod retention;
+#[cfg(feature = "std")]
+mod routing;
+#[cfg(feature = "std")]
+pub mod runtime;
+#[cfg(feature = "std")]
+pub mod scheduler;
+#[cfg(feature = "std")]
+pub mod scope;
+#[cfg(feature = "std")]
+mod selector;
+mod sequencing;
+#[cfg(feature = "std")]
+mod sharded_sink;
+mod slot_map;
+#[cfg(feature = "std")]
+mod spawn;
+#[cfg(feature = "std")]
+mod state_machine;
 mod stateful_operators;
 mod stateless_operators;
+#[cfg(feature = "std")]
+mod statistical_operators;
+#[cfg(feature = "std")]
+pub mod store;
 mod stream_combinators;
 mod streams;
+mod subscriber_group;
+mod sync;
+#[cfg(feature = "timers")]
+mod threshold_alerts;
+#[cfg(feature = "timers")]
+mod timed_operators;
+#[cfg(feature = "std")]
+mod validated_value;
+#[cfg(feature = "std")]
+mod value_history;
+#[cfg(feature = "std")]
+mod vec_diff;
+#[cfg(feature = "timers")]
+pub mod watchdog;
 
+#[cfg(feature = "timers")]
+pub use ack_queue::{Ack, AckConsumer, AckQueue};
+pub use cancellation::CancellationToken;
+#[cfg(feature = "std")]
+pub use computed_stats::{ComputedDependency, ComputedStats, ComputedStatsRecorder};
+pub use connectable::{ConnectableStream, Connection};
+pub use conversion::{FromStream, IntoStream};
+#[cfg(feature = "std")]
+pub use delivery_modes::{Delivery, DeliveryMode};
+#[cfg(feature = "std")]
+pub use derivation::evaluate_derivation;
+pub use errors::{
+    MailboxError, OrderViolation, PermitError, ReentrantWriteError, RequestError, SetError,
+    StreamClosed, ValuePoisoned,
+};
+#[cfg(feature = "checkpoint")]
+pub use errors::CheckpointError;
+#[cfg(feature = "pipeline_config")]
+pub use errors::PipelineConfigError;
+pub use event_time::{EventTimeWindow, EventTimeWindows, EventTimed};
+pub use finishing::{FinishingSink, FinishingStream};
+#[cfg(feature = "std")]
+pub use interning::{Interner, InternerStats};
+#[cfg(feature = "std")]
+pub use mailbox::Mailbox;
+#[cfg(feature = "std")]
+pub use metadata::{pipe_into_bidirectional, BidirectionalPipe};
+pub use notification::Notification;
+pub use observable::Observable;
+pub use pipeline::{Pipeline, PipelineBuilder, PipelineStep};
+pub use pool::{ArcPool, Recycle};
+pub use producers::{EmitPermit, PermitRevoker, SinkProducer};
+#[cfg(feature = "std")]
+pub use propagation::{read_consistent, transaction, ConsistentRead};
+#[cfg(feature = "std")]
 pub use reactive_cache::ReactiveCache;
+#[cfg(feature = "std")]
 pub use reactive_value::ReactiveValue;
+#[cfg(feature = "std")]
+pub use reactive_value::ReactiveValueReadGuard;
+#[cfg(feature = "std")]
 pub use reactive_value::ReadonlyReactiveValue;
+#[cfg(feature = "std")]
 pub use reactive_value::WriteableReactiveValue;
+#[cfg(feature = "std")]
+pub use request_response::{request_channel, PendingResponse, Request, Requester, Responder};
+#[cfg(feature = "timers")]
+pub use resilience_operators::{BreakerState, CircuitBreaker, RetryPolicy};
+pub use retention::{CountedBroadcast, RetainedPayload, RetentionReport};
+#[cfg(feature = "std")]
+pub use routing::RouterHandle;
+#[cfg(feature = "std")]
+pub use selector::{selector, Selector};
+pub use sequencing::Sequenced;
+#[cfg(feature = "std")]
+pub use spawn::{SpawnOptions, TaskGroup};
+#[cfg(feature = "std")]
+pub use sharded_sink::ShardedSink;
+#[cfg(feature = "std")]
+pub use state_machine::{InvalidTransition, StateMachine};
+pub use stateful_operators::ChunkBoundary;
 pub use stream_combinators::merge;
+#[cfg(feature = "std")]
+pub use stream_combinators::{merge_with_priority, merge_with_priority_conflated};
+pub use streams::DeliveryReport;
+pub use streams::EmitNext;
 pub use streams::Sink;
 pub use streams::Stream;
 pub use streams::Subscription;
+pub use subscriber_group::{MembershipChange, SubscriberGroup};
+#[cfg(feature = "timers")]
+pub use threshold_alerts::{ThresholdAlert, ThresholdBound, ThresholdConfig};
+#[cfg(feature = "timers")]
+pub use timed_operators::RateLimitOverflow;
+#[cfg(feature = "std")]
+pub use validated_value::ValidatedReactiveValue;
+#[cfg(feature = "std")]
+pub use value_history::{Timestamped, ValueHistory};
+#[cfg(feature = "std")]
+pub use vec_diff::VecEdit;
@@ -0,0 +1,133 @@
+use super::{ReactiveValue, ReadonlyReactiveValue, WriteableReactiveValue};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+struct ProjectedItem<T, C> {
+    value: WriteableReactiveValue<T>,
+    component: Arc<C>,
+}
+
+impl<T, C> Clone for ProjectedItem<T, C> {
+    fn clone(&self) -> Self {
+        ProjectedItem {
+            value: self.value.clone(),
+            component: Arc::clone(&self.component),
+        }
+    }
+}
+
+fn project_items<T, K, C, KF, F>(
+    list: &[T],
+    items: &mut HashMap<K, ProjectedItem<T, C>>,
+    key_function: &KF,
+    create_component: &F,
+) -> Vec<Arc<C>>
+where
+    T: 'static + Send + Sync + Clone + PartialEq,
+    K: Hash + Eq,
+    KF: Fn(&T) -> K,
+    F: Fn(ReadonlyReactiveValue<T>) -> C,
+{
+    let mut previous_items = std::mem::take(items);
+    list.iter()
+        .map(|item| {
+            let key = key_function(item);
+            let projected = match previous_items.remove(&key) {
+                Some(projected) => {
+                    projected.value.set(item.clone());
+                    projected
+                }
+                None => {
+                    let value = <dyn ReactiveValue<T>>::new_distinct(item.clone());
+                    let component = Arc::new(create_component(value.as_readonly()));
+                    ProjectedItem { value, component }
+                }
+            };
+            let component = Arc::clone(&projected.component);
+            items.entry(key).or_insert(projected);
+            component
+        })
+        .collect()
+}
+
+impl<T> dyn ReactiveValue<Vec<T>>
+where
+    T: 'static + Send + Sync + Clone + PartialEq,
+{
+    /// Maps each item of a list to a component, such as a row in a list UI, and keeps those
+    /// components up to date as the list changes.
+    ///
+    /// Components are matched to items by the key that `key_function` returns. When the list
+    /// changes, items whose key was already in the list keep their component, and the new value
+    /// of the item is set on the ReactiveValue that the component was created with. Components
+    /// are only created for new keys, and the components of removed keys are dropped (as soon as
+    /// nothing else holds on to them). Keys should be unique within the list: an item that
+    /// repeats the key of an earlier item gets a new component every time the list changes.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveValue, ReadonlyReactiveValue};
+    /// use std::sync::Arc;
+    ///
+    /// #[derive(Clone, PartialEq)]
+    /// struct Todo {
+    ///     id: u32,
+    ///     title: &'static str,
+    /// }
+    ///
+    /// struct TodoRow {
+    ///     todo: ReadonlyReactiveValue<Todo>,
+    /// }
+    ///
+    /// let todos = ReactiveValue::new(vec![
+    ///     Todo { id: 1, title: "Buy milk" },
+    ///     Todo { id: 2, title: "Walk dog" },
+    /// ]);
+    /// let rows = ReactiveValue::project(&todos, |todo| todo.id, |todo| TodoRow { todo });
+    /// let first_row = rows.get()[0].clone();
+    ///
+    /// todos.set(vec![
+    ///     Todo { id: 3, title: "Call mom" },
+    ///     Todo { id: 1, title: "Buy oat milk" },
+    /// ]);
+    ///
+    /// // The row for the first todo was reused, and now shows its new title.
+    /// assert!(Arc::ptr_eq(&rows.get()[1], &first_row));
+    /// assert_eq!(first_row.todo.get().title, "Buy oat milk");
+    /// assert_eq!(rows.get()[0].todo.get().title, "Call mom");
+    /// ```
+    pub fn project<K, C, KF, F>(
+        value: &dyn ReactiveValue<Vec<T>>,
+        key_function: KF,
+        create_component: F,
+    ) -> ReadonlyReactiveValue<Vec<Arc<C>>>
+    where
+        K: 'static + Send + Hash + Eq,
+        C: 'static + Send + Sync,
+        KF: Fn(&T) -> K,
+        KF: Send,
+        KF: Sync,
+        KF: 'static,
+        F: Fn(ReadonlyReactiveValue<T>) -> C,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let mut items = HashMap::new();
+        let initial_components =
+            project_items(&value.get(), &mut items, &key_function, &create_component);
+
+        let items = Mutex::new(items);
+        value
+            .as_stream()
+            .map(move |list| {
+                let mut items = match items.lock() {
+                    Ok(items) => items,
+                    Err(err) => panic!("Projection mutex poisoned: {}", err),
+                };
+                project_items(list, &mut items, &key_function, &create_component)
+            })
+            .to_reactive_value_with_default(initial_components)
+    }
+}
@@ -0,0 +1,109 @@
+use super::{MailboxError, Sink, Stream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// A lightweight actor. Messages sent to a Mailbox wait in a bounded queue and are handled one at
+/// a time, in the order they were sent, on a worker thread owned by the mailbox. The handler can
+/// therefore keep mutable state without any locking.
+///
+/// Each message is emitted from `get_stream` after the handler is done with it. Subscribers to
+/// that stream run on the worker thread too, so they see the same ordering guarantee.
+///
+/// Dropping the mailbox (or calling `shutdown`) stops it from accepting new messages, waits for
+/// the queued messages to be handled, and then completes the stream.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{Mailbox, ReactiveCache};
+///
+/// let mut total = 0;
+/// let mailbox = Mailbox::new(16, move |val: &i32| {
+///     total += val;
+///     println!("Running total: {}", total);
+/// });
+/// let handled = ReactiveCache::from_stream(mailbox.get_stream());
+///
+/// for val in 1..=5 {
+///     mailbox.send(val).unwrap();
+/// }
+/// mailbox.shutdown();
+/// assert_eq!(handled.get_cloned(), vec![1, 2, 3, 4, 5]);
+/// ```
+pub struct Mailbox<T> {
+    sender: Option<SyncSender<T>>,
+    worker: Option<JoinHandle<()>>,
+    stream: Stream<T>,
+}
+
+impl<T: Send + Sync + 'static> Mailbox<T> {
+    /// Starts a mailbox whose queue holds at most `capacity` messages, handling each message with
+    /// the given function.
+    pub fn new<F>(capacity: usize, mut handler: F) -> Mailbox<T>
+    where
+        F: FnMut(&T) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel::<T>(capacity);
+        let sink = Sink::new();
+        let stream = sink.get_stream();
+        let worker = thread::Builder::new()
+            .name("epoxy-mailbox".to_string())
+            .spawn(move || {
+                for message in receiver {
+                    handler(&message);
+                    sink.emit_rc(Arc::new(message));
+                }
+                sink.close();
+            })
+            .expect("Could not spawn the mailbox worker thread");
+
+        Mailbox {
+            sender: Some(sender),
+            worker: Some(worker),
+            stream,
+        }
+    }
+
+    /// Returns a stream that emits each message once the handler has processed it.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.stream.clone()
+    }
+
+    /// Queues a message, blocking while the queue is full.
+    pub fn send(&self, message: T) -> Result<(), MailboxError<T>> {
+        match &self.sender {
+            Some(sender) => sender
+                .send(message)
+                .map_err(|err| MailboxError::Closed(err.0)),
+            None => Err(MailboxError::Closed(message)),
+        }
+    }
+
+    /// Queues a message, or returns it immediately if the queue is full.
+    pub fn try_send(&self, message: T) -> Result<(), MailboxError<T>> {
+        match &self.sender {
+            Some(sender) => sender.try_send(message).map_err(|err| match err {
+                TrySendError::Full(message) => MailboxError::Full(message),
+                TrySendError::Disconnected(message) => MailboxError::Closed(message),
+            }),
+            None => Err(MailboxError::Closed(message)),
+        }
+    }
+
+    /// Stops accepting messages and blocks until every queued message has been handled.
+    pub fn shutdown(self) {
+        drop(self)
+    }
+}
+
+impl<T> Drop for Mailbox<T> {
+    fn drop(&mut self) {
+        // Closing the channel lets the worker drain the queue and exit.
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            if worker.thread().id() != thread::current().id() {
+                let _ = worker.join();
+            }
+        }
+    }
+}
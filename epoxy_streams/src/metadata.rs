@@ -0,0 +1,178 @@
+use super::{Sink, Stream, Subscription};
+use std::any::Any;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+type Meta = Arc<dyn Any + Send + Sync>;
+
+thread_local! {
+    static CURRENT_META: RefCell<Option<Meta>> = const { RefCell::new(None) };
+}
+
+/// Restores the metadata of the enclosing emission (if any) when dropped, even if a subscriber
+/// panics.
+struct MetaGuard {
+    previous: Option<Meta>,
+}
+
+impl Drop for MetaGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_META.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Runs the given function with `meta` attached to every emission it causes on this thread.
+pub(crate) fn with_meta<R, F>(meta: Meta, emit_fn: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let previous = CURRENT_META.with(|current| current.borrow_mut().replace(meta));
+    let _guard = MetaGuard { previous };
+    emit_fn()
+}
+
+/// Returns the metadata attached to the emission currently being delivered on this thread.
+pub(crate) fn current_meta() -> Option<Meta> {
+    CURRENT_META.with(|current| current.borrow().clone())
+}
+
+impl<T: 'static + Send + Sync> Sink<T> {
+    /// Same as `emit`, but attaches metadata describing where the value came from, such as the
+    /// user, thread or request that caused the change. Subscribers created with
+    /// `subscribe_with_meta` receive it alongside the value.
+    ///
+    /// The metadata follows the value through operators that emit synchronously, like `map` or
+    /// `filter`, and is also attached to anything the subscribers emit in response. It is not
+    /// carried across threads or timers, so values emitted by operators like `debounce` arrive
+    /// without metadata.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct Origin(&'static str);
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let audit_log = Arc::new(Mutex::new(vec![]));
+    /// let audit_log_write = audit_log.clone();
+    /// let _subscription = stream_host
+    ///     .get_stream()
+    ///     .map(|val| val * 10)
+    ///     .subscribe_with_meta(move |val, origin: Option<Arc<Origin>>| {
+    ///         let origin = origin.map(|origin| origin.0).unwrap_or("unknown");
+    ///         audit_log_write.lock().unwrap().push(format!("{} set by {}", val, origin));
+    ///     });
+    ///
+    /// stream_host.emit_with_meta(1, Origin("alice"));
+    /// stream_host.emit(2);
+    /// assert_eq!(
+    ///     *audit_log.lock().unwrap(),
+    ///     vec!["10 set by alice".to_string(), "20 set by unknown".to_string()]
+    /// );
+    /// ```
+    pub fn emit_with_meta<M>(&self, value: T, meta: M)
+    where
+        M: Any + Send + Sync,
+    {
+        self.emit_rc_with_meta(Arc::new(value), Arc::new(meta))
+    }
+
+    /// Same logic as `emit_with_meta`, but takes existing Arc pointers.
+    pub fn emit_rc_with_meta<M>(&self, value: Arc<T>, meta: Arc<M>)
+    where
+        M: Any + Send + Sync,
+    {
+        with_meta(meta, || self.emit_rc(value))
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Same as `subscribe`, but the listener also receives the metadata that was attached to the
+    /// value with `Sink::emit_with_meta`. The metadata is None if the value was emitted without
+    /// metadata, or with metadata of a different type than `M`.
+    pub fn subscribe_with_meta<M, F>(&self, listener: F) -> Subscription<T>
+    where
+        M: Any + Send + Sync,
+        F: Fn(Arc<T>, Option<Arc<M>>),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.subscribe(move |val| {
+            let meta = current_meta().and_then(|meta| meta.downcast::<M>().ok());
+            listener(val, meta)
+        })
+    }
+}
+
+/// Identifies the pipe that forwarded a value, so the pipe can recognize its own echoes.
+struct PipeOrigin(usize);
+
+static NEXT_PIPE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps two Sinks in sync. Created with `pipe_into_bidirectional`, and stops syncing when
+/// dropped.
+pub struct BidirectionalPipe<T> {
+    #[allow(dead_code)]
+    a_to_b: Subscription<T>,
+
+    #[allow(dead_code)]
+    b_to_a: Subscription<T>,
+}
+
+/// Forwards every value emitted by `sink_a` into `sink_b`, and every value emitted by `sink_b`
+/// into `sink_a`, without forwarding a value back to the Sink it came from. This is useful for
+/// mirroring state between two models, like a UI model and a network model, that each accept
+/// changes of their own.
+///
+/// Echoes are recognized using emission metadata (see `Sink::emit_with_meta`), so the values a
+/// pipe forwards carry the pipe's own metadata instead of the metadata they were emitted with.
+/// Several pipes can be chained, for example to sync three Sinks in a line, without values
+/// bouncing between them.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::ReactiveCache;
+///
+/// let ui_model: epoxy_streams::Sink<String> = epoxy_streams::Sink::new();
+/// let network_model: epoxy_streams::Sink<String> = epoxy_streams::Sink::new();
+/// let ui_values = ReactiveCache::from_stream(ui_model.get_stream());
+/// let network_values = ReactiveCache::from_stream(network_model.get_stream());
+///
+/// let _pipe = epoxy_streams::pipe_into_bidirectional(&ui_model, &network_model);
+/// ui_model.emit("typed locally".to_string());
+/// network_model.emit("changed remotely".to_string());
+///
+/// let expected = vec!["typed locally".to_string(), "changed remotely".to_string()];
+/// assert_eq!(ui_values.get_cloned(), expected);
+/// assert_eq!(network_values.get_cloned(), expected);
+/// ```
+pub fn pipe_into_bidirectional<T>(sink_a: &Sink<T>, sink_b: &Sink<T>) -> BidirectionalPipe<T>
+where
+    T: 'static + Send + Sync,
+{
+    let pipe_id = NEXT_PIPE_ID.fetch_add(1, Ordering::Relaxed);
+    BidirectionalPipe {
+        a_to_b: pipe_without_echoes(pipe_id, &sink_a.get_stream(), sink_b.get_stream()),
+        b_to_a: pipe_without_echoes(pipe_id, &sink_b.get_stream(), sink_a.get_stream()),
+    }
+}
+
+fn pipe_without_echoes<T>(pipe_id: usize, from: &Stream<T>, to: Stream<T>) -> Subscription<T>
+where
+    T: 'static + Send + Sync,
+{
+    from.subscribe_with_meta(move |val, origin: Option<Arc<PipeOrigin>>| {
+        if let Some(origin) = origin {
+            if origin.0 == pipe_id {
+                return;
+            }
+        }
+        with_meta(Arc::new(PipeOrigin(pipe_id)), || {
+            to.emit_intercepted_rc(val);
+        });
+    })
+}
@@ -0,0 +1,141 @@
+use super::{Stream, Subscription};
+use alloc::sync::Arc;
+
+/// One event in the life of a stream of `Result` values, as a plain value. Created with
+/// `Stream::materialize`, and turned back into a stream with `Stream::dematerialize`.
+///
+/// With the `serde` feature (enabled by `journal` and `ipc`), notifications can be serialized, so
+/// a stream's completion can be persisted and replayed along with its values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Notification<T, E> {
+    /// The stream emitted an `Ok` value.
+    Next(T),
+
+    /// The stream emitted an `Err` value.
+    Error(E),
+
+    /// The stream completed. This is always the last notification.
+    Complete,
+}
+
+struct MaterializeFields<T, E> {
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Result<T, E>>>,
+}
+
+struct DematerializeFields<T, E> {
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Notification<T, E>>>,
+}
+
+impl<T, E> Stream<Result<T, E>>
+where
+    T: 'static + Send + Sync + Clone,
+    E: 'static + Send + Sync + Clone,
+{
+    /// Returns a stream that emits a `Notification` for every value of this stream, followed by
+    /// `Notification::Complete` when this stream completes. The returned stream completes right
+    /// after that.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{Notification, ReactiveCache};
+    ///
+    /// let stream_host: epoxy_streams::Sink<Result<i32, String>> = epoxy_streams::Sink::new();
+    /// let notifications = ReactiveCache::from_stream(stream_host.get_stream().materialize());
+    ///
+    /// stream_host.emit(Ok(1));
+    /// stream_host.emit(Err("Oops".to_string()));
+    /// stream_host.close();
+    ///
+    /// assert_eq!(
+    ///     notifications.get_cloned(),
+    ///     vec![
+    ///         Notification::Next(1),
+    ///         Notification::Error("Oops".to_string()),
+    ///         Notification::Complete,
+    ///     ]
+    /// );
+    /// ```
+    pub fn materialize(&self) -> Stream<Notification<T, E>> {
+        let derived_stream =
+            self.derive_with_fields(MaterializeFields::<T, E> { subscription: None });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                if let Some(pointer) = weak_stream_ref.upgrade() {
+                    Stream { pointer }.emit_rc(Arc::new(match &*val {
+                        Ok(value) => Notification::Next(value.clone()),
+                        Err(err) => Notification::Error(err.clone()),
+                    }));
+                }
+            },
+            move || {
+                completion_stream_ref.emit_rc(Arc::new(Notification::Complete));
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut MaterializeFields<T, E>| {
+            fields.subscription = Some(subscription);
+        });
+        derived_stream
+    }
+}
+
+impl<T, E> Stream<Notification<T, E>>
+where
+    T: 'static + Send + Sync + Clone,
+    E: 'static + Send + Sync + Clone,
+{
+    /// The reverse of `materialize`. Returns a stream that emits `Next` notifications as `Ok`
+    /// values and `Error` notifications as `Err` values, and completes at the first `Complete`
+    /// notification (or when this stream completes).
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{Notification, ReactiveCache};
+    ///
+    /// let stream_host: epoxy_streams::Sink<Notification<i32, String>> =
+    ///     epoxy_streams::Sink::new();
+    /// let replayed = stream_host.get_stream().dematerialize();
+    /// let values = ReactiveCache::from_stream(replayed.clone());
+    ///
+    /// stream_host.emit(Notification::Next(1));
+    /// stream_host.emit(Notification::Error("Oops".to_string()));
+    /// stream_host.emit(Notification::Complete);
+    /// stream_host.emit(Notification::Next(2));
+    ///
+    /// assert_eq!(values.get_cloned(), vec![Ok(1), Err("Oops".to_string())]);
+    /// assert!(replayed.is_complete());
+    /// ```
+    pub fn dematerialize(&self) -> Stream<Result<T, E>> {
+        let derived_stream =
+            self.derive_with_fields(DematerializeFields::<T, E> { subscription: None });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                match &*val {
+                    Notification::Next(value) => stream.emit_rc(Arc::new(Ok(value.clone()))),
+                    Notification::Error(err) => stream.emit_rc(Arc::new(Err(err.clone()))),
+                    Notification::Complete => stream.complete(),
+                }
+            },
+            move || completion_stream_ref.complete(),
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut DematerializeFields<T, E>| {
+            fields.subscription = Some(subscription);
+        });
+        derived_stream
+    }
+}
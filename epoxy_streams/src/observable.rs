@@ -0,0 +1,85 @@
+#[cfg(feature = "std")]
+use super::{ReactiveValue, ReadonlyReactiveValue, WriteableReactiveValue};
+use super::{Sink, Stream, Subscription};
+use alloc::sync::Arc;
+
+/// Anything that can be observed for changes, so generic code can accept a Stream, a Sink, a
+/// ReactiveValue or a reactive collection without converting it first.
+///
+/// For reactive values the observed changes are the values set after subscribing, without the
+/// current one. Read the current value with `ReactiveValue::get` where it is needed.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{Observable, ReactiveCache, ReactiveValue, Sink};
+///
+/// fn labels<O: Observable<i32>>(source: &O) -> ReactiveCache<String> {
+///     ReactiveCache::from_stream(source.map(|val| format!("#{}", val)))
+/// }
+///
+/// let stream_host: Sink<i32> = Sink::new();
+/// let from_stream = labels(&stream_host.get_stream());
+/// stream_host.emit(1);
+///
+/// let value = ReactiveValue::new(0);
+/// let from_value = labels(&value);
+/// value.set(2);
+///
+/// assert_eq!(from_stream.get_cloned(), vec!["#1".to_string()]);
+/// assert_eq!(from_value.get_cloned(), vec!["#2".to_string()]);
+/// ```
+pub trait Observable<T: Send + Sync + 'static> {
+    /// Returns a stream that emits every change from now on.
+    fn changes(&self) -> Stream<T>;
+
+    /// Calls `listener` with every change until the returned Subscription is dropped.
+    fn subscribe_changes<F>(&self, listener: F) -> Subscription<T>
+    where
+        F: Fn(Arc<T>) + Send + Sync + 'static,
+    {
+        self.changes().subscribe(listener)
+    }
+
+    /// Returns a stream that runs every change through a mapping function.
+    fn map<U, F>(&self, map_function: F) -> Stream<U>
+    where
+        U: Send + Sync + 'static,
+        F: Fn(&T) -> U + Send + Sync + 'static,
+    {
+        self.changes().map(map_function)
+    }
+
+    /// Returns a stream of the changes that pass a test function.
+    fn filter<F>(&self, filter_function: F) -> Stream<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.changes().filter(filter_function)
+    }
+}
+
+impl<T: Send + Sync + 'static> Observable<T> for Stream<T> {
+    fn changes(&self) -> Stream<T> {
+        self.clone()
+    }
+}
+
+impl<T: Send + Sync + 'static> Observable<T> for Sink<T> {
+    fn changes(&self) -> Stream<T> {
+        self.get_stream()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Send + Sync + 'static> Observable<T> for WriteableReactiveValue<T> {
+    fn changes(&self) -> Stream<T> {
+        self.as_stream()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Send + Sync + 'static> Observable<T> for ReadonlyReactiveValue<T> {
+    fn changes(&self) -> Stream<T> {
+        self.as_stream()
+    }
+}
@@ -0,0 +1,246 @@
+use super::{Stream, Subscription};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+
+trait Stage: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn tear_down(&self);
+}
+
+impl<T: Send + Sync + 'static> Stage for Stream<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn tear_down(&self) {
+        self.complete();
+    }
+}
+
+impl<T: Send + Sync + 'static> Stage for Subscription<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    // Subscriptions end when they are dropped along with the pipeline.
+    fn tear_down(&self) {}
+}
+
+struct NamedStage {
+    name: Option<&'static str>,
+    stage: Box<dyn Stage>,
+}
+
+/// A chain of operators and subscribers built by the `pipeline!` macro or a `PipelineBuilder`.
+/// The pipeline owns every stage it created, and dropping it (or calling `teardown`) tears all of
+/// them down at once: every subscriber it added is unsubscribed, and every stream an operator
+/// created is completed, which also notifies anything else that subscribed to them. The source
+/// stream the pipeline started from is left alone.
+pub struct Pipeline<T> {
+    output: Stream<T>,
+    stages: Vec<NamedStage>,
+}
+
+impl<T> Pipeline<T> {
+    /// Returns the stream produced by the last operator in the pipeline, or the source stream if
+    /// there were no operators.
+    pub fn output(&self) -> Stream<T> {
+        self.output.clone()
+    }
+
+    /// Returns the stream produced by the operator that was named `name` with `as`, if its values
+    /// are of type `U`.
+    pub fn stream<U: 'static>(&self, name: &str) -> Option<Stream<U>> {
+        self.stages
+            .iter()
+            .filter(|stage| stage.name == Some(name))
+            .find_map(|stage| stage.stage.as_any().downcast_ref::<Stream<U>>())
+            .cloned()
+    }
+
+    /// Returns the number of operators and subscribers in the pipeline.
+    pub fn count_stages(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Tears down every stage of the pipeline. Same as dropping it.
+    pub fn teardown(self) {}
+}
+
+impl<T> Drop for Pipeline<T> {
+    fn drop(&mut self) {
+        for stage in &self.stages {
+            stage.stage.tear_down();
+        }
+    }
+}
+
+/// Builds a `Pipeline` one stage at a time. This is what the `pipeline!` macro expands to, and can
+/// be used directly where stages are decided at runtime.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{PipelineBuilder, ReactiveCache, Sink};
+///
+/// let stream_host: Sink<i32> = Sink::new();
+/// let pipeline = PipelineBuilder::new(stream_host.get_stream())
+///     .then(|stream| stream.map(|val| val + 1))
+///     .then(|stream| stream.filter(|val| val % 2 == 0))
+///     .build();
+/// let cache = ReactiveCache::from_stream(pipeline.output());
+///
+/// stream_host.emit_many(vec![1, 2, 3]);
+/// assert_eq!(cache.get_cloned(), vec![2, 4]);
+/// ```
+pub struct PipelineBuilder<T> {
+    current: Stream<T>,
+    stages: Vec<NamedStage>,
+}
+
+impl<T: Send + Sync + 'static> PipelineBuilder<T> {
+    /// Starts a pipeline from the given source stream.
+    pub fn new(source: Stream<T>) -> PipelineBuilder<T> {
+        PipelineBuilder {
+            current: source,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Adds a stage to the pipeline. If `stage` returns a stream, like an operator does, later
+    /// stages are applied to that stream. If it returns a Subscription, later stages are applied
+    /// to the same stream as this one, so several subscribers can be attached in a row.
+    pub fn then<S, F>(self, stage: F) -> PipelineBuilder<S::Output>
+    where
+        S: PipelineStep<T>,
+        F: FnOnce(&Stream<T>) -> S,
+    {
+        let step = stage(&self.current);
+        step.add_to(self, None)
+    }
+
+    /// Same as `then`, and makes the stage's stream available from `Pipeline::stream` under the
+    /// given name.
+    pub fn then_named<S, F>(self, name: &'static str, stage: F) -> PipelineBuilder<S::Output>
+    where
+        S: PipelineStep<T>,
+        F: FnOnce(&Stream<T>) -> S,
+    {
+        let step = stage(&self.current);
+        step.add_to(self, Some(name))
+    }
+
+    /// Finishes the pipeline.
+    pub fn build(self) -> Pipeline<T> {
+        Pipeline {
+            output: self.current,
+            stages: self.stages,
+        }
+    }
+}
+
+/// The result of a stage in a `PipelineBuilder`: either a new stream, or a Subscription to the
+/// current one.
+pub trait PipelineStep<T>: Sized {
+    /// The type of the values that later stages receive.
+    type Output;
+
+    /// Adds this stage to the builder.
+    fn add_to(
+        self,
+        builder: PipelineBuilder<T>,
+        name: Option<&'static str>,
+    ) -> PipelineBuilder<Self::Output>;
+}
+
+impl<T, U: Send + Sync + 'static> PipelineStep<T> for Stream<U> {
+    type Output = U;
+
+    fn add_to(self, builder: PipelineBuilder<T>, name: Option<&'static str>) -> PipelineBuilder<U> {
+        let mut stages = builder.stages;
+        stages.push(NamedStage {
+            name,
+            stage: Box::new(self.clone()),
+        });
+        PipelineBuilder {
+            current: self,
+            stages,
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> PipelineStep<T> for Subscription<T> {
+    type Output = T;
+
+    fn add_to(self, builder: PipelineBuilder<T>, name: Option<&'static str>) -> PipelineBuilder<T> {
+        let mut builder = builder;
+        builder.stages.push(NamedStage {
+            name,
+            stage: Box::new(self),
+        });
+        builder
+    }
+}
+
+/// Declares a chain of stream operators and subscribers in one block, and returns a `Pipeline`
+/// that owns all of them. The chain starts with a source stream, followed by any number of stages
+/// separated by `=>`. Each stage is a call to a Stream method, like `map(...)` or
+/// `subscribe(...)`. Methods that return a stream pass it on to the next stage, and can be named
+/// with `as name` to be looked up later with `Pipeline::stream`. Methods that return a
+/// Subscription, like `subscribe` or `pipe_into`, attach to the current stream without replacing
+/// it.
+///
+/// Dropping the pipeline tears down every stage at once. See `Pipeline`.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{pipeline, ReactiveCache, Sink};
+/// use std::sync::{Arc, Mutex};
+///
+/// let stream_host: Sink<i32> = Sink::new();
+/// let alerts = Arc::new(Mutex::new(vec![]));
+/// let alerts_write = alerts.clone();
+///
+/// let pipeline = pipeline!(
+///     stream_host.get_stream()
+///         => map(|celsius| celsius * 9 / 5 + 32) as fahrenheit
+///         => filter(|fahrenheit| *fahrenheit > 100)
+///         => subscribe(move |val| alerts_write.lock().unwrap().push(*val))
+/// );
+/// let readings = ReactiveCache::from_stream(pipeline.stream::<i32>("fahrenheit").unwrap());
+///
+/// stream_host.emit_many(vec![20, 40]);
+/// assert_eq!(readings.get_cloned(), vec![68, 104]);
+/// assert_eq!(*alerts.lock().unwrap(), vec![104]);
+///
+/// // Tearing down the pipeline completes its streams and removes its subscribers.
+/// pipeline.teardown();
+/// stream_host.emit(50);
+/// assert_eq!(readings.get_cloned(), vec![68, 104]);
+/// assert_eq!(*alerts.lock().unwrap(), vec![104]);
+/// ```
+#[macro_export]
+macro_rules! pipeline {
+    (@stages $builder:expr;) => {
+        $builder.build()
+    };
+    (@stages $builder:expr; $method:ident($($args:tt)*) as $name:ident $(=> $($stages:tt)+)?) => {
+        $crate::pipeline!(
+            @stages $builder.then_named(
+                stringify!($name),
+                |stream| stream.$method($($args)*),
+            );
+            $($($stages)+)?
+        )
+    };
+    (@stages $builder:expr; $method:ident($($args:tt)*) $(=> $($stages:tt)+)?) => {
+        $crate::pipeline!(
+            @stages $builder.then(|stream| stream.$method($($args)*));
+            $($($stages)+)?
+        )
+    };
+    // Stages are matched first, since a failed attempt to parse the source is a hard error.
+    ($source:expr $(=> $($stages:tt)+)?) => {
+        $crate::pipeline!(@stages $crate::PipelineBuilder::new($source); $($($stages)+)?)
+    };
+}
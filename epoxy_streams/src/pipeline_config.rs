@@ -0,0 +1,313 @@
+//! Pipelines built from a declarative JSON config instead of code, so that the filtering and
+//! sampling applied to a stream can be tuned at runtime, for example by operators adjusting how
+//! much telemetry is kept, without redeploying the application.
+//!
+//! A `PipelineConfig` lists the stages that values go through, in order. Each stage names one of
+//! the built-in operators below, along with its parameters:
+//! * `{"op": "filter", "name": "..."}` keeps the values that pass a registered filter function.
+//! * `{"op": "map", "name": "..."}` runs values through a registered map function.
+//! * `{"op": "sample", "every": 10}` keeps the first of every 10 values.
+//! * `{"op": "debounce", "millis": 100}` see `Stream::debounce`.
+//! * `{"op": "audit", "millis": 100}` see `Stream::audit`.
+//! * `{"op": "rate_limit", "per_second": 5.0, "burst": 10, "overflow": "Drop"}` see
+//!   `Stream::rate_limit`. `overflow` is optional, and defaults to `Drop`.
+//!
+//! Filter and map functions are registered by name with `PipelineFunctions`, since code can not be
+//! part of a config file.
+//!
+//! A `ConfiguredPipeline` builds the stages and can be reloaded with a new config at any time. It
+//! only rebuilds the stages from the first one that changed onwards, so the stages before it keep
+//! their state, like the position of a sample or the tokens in a rate limit. Its output stream
+//! stays the same across reloads, so subscribers do not notice. Values that pass through the
+//! pipeline at the same moment as a reload may be dropped.
+//!
+//! Requires the `pipeline_config` feature.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::pipeline_config::{ConfiguredPipeline, PipelineConfig, PipelineFunctions};
+//! use epoxy_streams::{ReactiveCache, Sink};
+//!
+//! let mut functions = PipelineFunctions::new();
+//! functions.register_filter("errors_only", |level: &u8| *level >= 3);
+//!
+//! let stream_host: Sink<u8> = Sink::new();
+//! let config = PipelineConfig::from_json(r#"{"stages": [{"op": "sample", "every": 2}]}"#);
+//! let pipeline =
+//!     ConfiguredPipeline::new(&stream_host.get_stream(), functions, config.unwrap()).unwrap();
+//! let cache = ReactiveCache::from_stream(pipeline.get_stream());
+//!
+//! stream_host.emit_many(vec![1, 2, 3, 4]);
+//! assert_eq!(cache.get_cloned(), vec![1, 3]);
+//!
+//! let config = PipelineConfig::from_json(
+//!     r#"{"stages": [{"op": "sample", "every": 2}, {"op": "filter", "name": "errors_only"}]}"#,
+//! );
+//! assert_eq!(pipeline.reload(config.unwrap()).unwrap(), 1);
+//! stream_host.emit_many(vec![5, 7, 1, 2]);
+//! assert_eq!(cache.get_cloned(), vec![1, 3, 5]);
+//! ```
+use super::{PipelineConfigError, RateLimitOverflow, Stream, Subscription};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+type FilterFunction<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+type MapFunction<T> = Arc<dyn Fn(&T) -> T + Send + Sync>;
+
+/// The stages of a configured pipeline, in the order values go through them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    pub stages: Vec<StageConfig>,
+}
+
+impl PipelineConfig {
+    /// Reads a config from JSON, like `{"stages": [{"op": "sample", "every": 10}]}`.
+    pub fn from_json(json: &str) -> Result<PipelineConfig, PipelineConfigError> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// One stage of a `PipelineConfig`. See the module documentation for the JSON form of each.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum StageConfig {
+    /// Keeps the values that pass the filter function registered under `name`.
+    Filter { name: String },
+
+    /// Runs values through the map function registered under `name`.
+    Map { name: String },
+
+    /// Keeps the first of every `every` values.
+    Sample { every: u64 },
+
+    /// Emits the latest value once the stream has been quiet for `millis`.
+    Debounce { millis: u64 },
+
+    /// Emits the latest value `millis` after each value that starts a window.
+    Audit { millis: u64 },
+
+    /// Limits values to `per_second`, allowing bursts of up to `burst` values.
+    RateLimit {
+        per_second: f64,
+        burst: u32,
+        #[serde(default = "default_overflow")]
+        overflow: RateLimitOverflow,
+    },
+}
+
+fn default_overflow() -> RateLimitOverflow {
+    RateLimitOverflow::Drop
+}
+
+/// The filter and map functions that a `PipelineConfig` can refer to by name.
+pub struct PipelineFunctions<T> {
+    filters: HashMap<String, FilterFunction<T>>,
+    maps: HashMap<String, MapFunction<T>>,
+}
+
+impl<T> PipelineFunctions<T> {
+    pub fn new() -> PipelineFunctions<T> {
+        PipelineFunctions {
+            filters: HashMap::new(),
+            maps: HashMap::new(),
+        }
+    }
+
+    /// Makes a filter function available to `filter` stages under the given name.
+    pub fn register_filter<F>(&mut self, name: &str, filter_function: F)
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.filters
+            .insert(name.to_string(), Arc::new(filter_function));
+    }
+
+    /// Makes a map function available to `map` stages under the given name.
+    pub fn register_map<F>(&mut self, name: &str, map_function: F)
+    where
+        F: Fn(&T) -> T + Send + Sync + 'static,
+    {
+        self.maps.insert(name.to_string(), Arc::new(map_function));
+    }
+
+    // Checks a stage before anything is built, so an invalid config leaves the pipeline as is.
+    fn validate(&self, stage: &StageConfig) -> Result<(), PipelineConfigError> {
+        match stage {
+            StageConfig::Filter { name } if !self.filters.contains_key(name) => {
+                Err(PipelineConfigError::UnknownFunction(name.clone()))
+            }
+            StageConfig::Map { name } if !self.maps.contains_key(name) => {
+                Err(PipelineConfigError::UnknownFunction(name.clone()))
+            }
+            StageConfig::Sample { every: 0 } => Err(PipelineConfigError::InvalidParameter(
+                "sample must keep every 1st value or more".to_string(),
+            )),
+            StageConfig::RateLimit { per_second, .. }
+                if !per_second.is_finite() || *per_second <= 0.0 =>
+            {
+                Err(PipelineConfigError::InvalidParameter(
+                    "rate_limit must allow more than 0 values per second".to_string(),
+                ))
+            }
+            StageConfig::RateLimit { burst: 0, .. } => Err(PipelineConfigError::InvalidParameter(
+                "rate_limit must allow bursts of at least 1 value".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T> Default for PipelineFunctions<T> {
+    fn default() -> Self {
+        PipelineFunctions::new()
+    }
+}
+
+impl<T: Send + Sync + 'static> PipelineFunctions<T> {
+    fn build(&self, input: &Stream<T>, stage: &StageConfig) -> Stream<T> {
+        match stage {
+            StageConfig::Filter { name } => {
+                let filter_function = self.filters[name].clone();
+                input.filter(move |val| filter_function(val))
+            }
+            StageConfig::Map { name } => {
+                let map_function = self.maps[name].clone();
+                input.map(move |val| map_function(val))
+            }
+            StageConfig::Sample { every } => {
+                let every = *every;
+                let count = AtomicU64::new(0);
+                input.filter(move |_| count.fetch_add(1, Ordering::Relaxed).is_multiple_of(every))
+            }
+            StageConfig::Debounce { millis } => input.debounce(Duration::from_millis(*millis)),
+            StageConfig::Audit { millis } => input.audit(Duration::from_millis(*millis)),
+            StageConfig::RateLimit {
+                per_second,
+                burst,
+                overflow,
+            } => input.rate_limit(*per_second, *burst, *overflow),
+        }
+    }
+}
+
+struct BuiltPipeline<T> {
+    config: PipelineConfig,
+
+    // The stream produced by each stage, in order.
+    stages: Vec<Stream<T>>,
+
+    // Forwards the values of the last stage to the output stream. Only empty during `reload`.
+    forwarding: Option<Subscription<T>>,
+}
+
+/// A pipeline built from a `PipelineConfig`, which can be rebuilt with `reload`. See the
+/// `pipeline_config` module.
+pub struct ConfiguredPipeline<T> {
+    source: Stream<T>,
+    functions: PipelineFunctions<T>,
+    output: Stream<T>,
+    built: Mutex<BuiltPipeline<T>>,
+}
+
+fn forward<T: Send + Sync + 'static>(from: &Stream<T>, to: &Stream<T>) -> Subscription<T> {
+    let value_output = to.clone();
+    let completion_output = to.clone();
+    from.subscribe_with_completion(
+        move |val| value_output.emit_rc(val),
+        move || completion_output.complete(),
+    )
+}
+
+impl<T: Send + Sync + 'static> ConfiguredPipeline<T> {
+    /// Builds a pipeline that runs the values of `source` through the stages in `config`.
+    pub fn new(
+        source: &Stream<T>,
+        functions: PipelineFunctions<T>,
+        config: PipelineConfig,
+    ) -> Result<ConfiguredPipeline<T>, PipelineConfigError> {
+        for stage in &config.stages {
+            functions.validate(stage)?;
+        }
+
+        let mut stages: Vec<Stream<T>> = vec![];
+        for stage in &config.stages {
+            let input = stages.last().unwrap_or(source);
+            stages.push(functions.build(input, stage));
+        }
+        let output = Stream::new();
+        let forwarding = forward(stages.last().unwrap_or(source), &output);
+
+        Ok(ConfiguredPipeline {
+            source: source.clone(),
+            functions,
+            output,
+            built: Mutex::new(BuiltPipeline {
+                config,
+                stages,
+                forwarding: Some(forwarding),
+            }),
+        })
+    }
+
+    fn lock_built(&self) -> MutexGuard<'_, BuiltPipeline<T>> {
+        match self.built.lock() {
+            Ok(built) => built,
+            Err(err) => panic!("ConfiguredPipeline mutex poisoned: {}", err),
+        }
+    }
+
+    /// Returns the stream of values that made it through the pipeline. The same stream is
+    /// returned before and after a reload.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.output.clone()
+    }
+
+    /// Returns the config the pipeline is currently built from.
+    pub fn config(&self) -> PipelineConfig {
+        self.lock_built().config.clone()
+    }
+
+    /// Rebuilds the pipeline from a new config, keeping the stages up to the first one that
+    /// changed. Returns the number of stages that were rebuilt, which is 0 if the config did not
+    /// change. If the config is invalid the pipeline is left as it was.
+    pub fn reload(&self, config: PipelineConfig) -> Result<usize, PipelineConfigError> {
+        for stage in &config.stages {
+            self.functions.validate(stage)?;
+        }
+
+        let mut built = self.lock_built();
+        let unchanged = built
+            .config
+            .stages
+            .iter()
+            .zip(&config.stages)
+            .take_while(|(old, new)| old == new)
+            .count();
+        if unchanged == built.stages.len() && unchanged == config.stages.len() {
+            return Ok(0);
+        }
+
+        // Disconnect the output before anything else, so that it is never forwarded to from both
+        // the old and the new last stage.
+        drop(built.forwarding.take());
+        built.stages.truncate(unchanged);
+        for stage in &config.stages[unchanged..] {
+            let input = built.stages.last().unwrap_or(&self.source);
+            let next = self.functions.build(input, stage);
+            built.stages.push(next);
+        }
+
+        let last = built.stages.last().unwrap_or(&self.source).clone();
+        built.forwarding = Some(forward(&last, &self.output));
+        built.config = config;
+        Ok(built.stages.len() - unchanged)
+    }
+
+    /// Same as `reload`, but reads the new config from JSON.
+    pub fn reload_json(&self, json: &str) -> Result<usize, PipelineConfigError> {
+        self.reload(PipelineConfig::from_json(json)?)
+    }
+}
@@ -0,0 +1,138 @@
+use super::sync::Mutex;
+use super::Sink;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Values that can be emptied and reused instead of being dropped, see `ArcPool`.
+pub trait Recycle {
+    /// Resets the value so it can be filled again, ideally keeping any memory it has allocated.
+    fn recycle(&mut self);
+}
+
+impl<T> Recycle for Vec<T> {
+    fn recycle(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Recycle for VecDeque<T> {
+    fn recycle(&mut self) {
+        self.clear();
+    }
+}
+
+impl Recycle for String {
+    fn recycle(&mut self) {
+        self.clear();
+    }
+}
+
+type Factory<T> = Box<dyn Fn() -> T + Send + Sync>;
+
+/// A pool of reusable values for streams that emit large buffers at high rates. Every value the
+/// pool hands out stays in the pool, and is reused once all subscribers have dropped their
+/// copies of the Arc. Once the pool is warm, emitting a value does not allocate at all.
+///
+/// The pool holds at most `max_size` values. If all of them are still in use, a new value is
+/// allocated and simply dropped after use, so a slow subscriber never blocks the producer.
+pub struct ArcPool<T> {
+    values: Mutex<Vec<Arc<T>>>,
+    max_size: usize,
+    factory: Factory<T>,
+}
+
+impl<T: Recycle> ArcPool<T> {
+    /// Creates an empty pool that creates new values with `factory`.
+    pub fn new<F>(max_size: usize, factory: F) -> ArcPool<T>
+    where
+        F: Fn() -> T,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        ArcPool {
+            values: Mutex::new(Vec::with_capacity(max_size)),
+            max_size,
+            factory: Box::new(factory),
+        }
+    }
+
+    /// Returns a value that has been recycled and then filled in by `fill`. The value is reused
+    /// from the pool if one is available, and allocated otherwise.
+    pub fn acquire<F>(&self, fill: F) -> Arc<T>
+    where
+        F: FnOnce(&mut T),
+    {
+        // The value is taken out of the pool while `fill` runs, so that `fill` can use the pool
+        // as well.
+        let (mut value, is_pooled) = {
+            let mut values = self.values.lock();
+            match values
+                .iter_mut()
+                .position(|value| Arc::get_mut(value).is_some())
+            {
+                Some(index) => (values.swap_remove(index), true),
+                None => (Arc::new((self.factory)()), values.len() < self.max_size),
+            }
+        };
+
+        let contents = Arc::get_mut(&mut value).unwrap();
+        contents.recycle();
+        fill(contents);
+
+        if is_pooled {
+            let mut values = self.values.lock();
+            if values.len() < self.max_size {
+                values.push(Arc::clone(&value));
+            }
+        }
+        value
+    }
+
+    /// Returns the number of values that are currently kept in the pool, whether or not they are
+    /// in use.
+    pub fn count_pooled(&self) -> usize {
+        self.values.lock().len()
+    }
+
+    /// Returns the number of pooled values that are not used by anyone and can be reused.
+    pub fn count_available(&self) -> usize {
+        self.values
+            .lock()
+            .iter_mut()
+            .filter_map(Arc::get_mut)
+            .count()
+    }
+}
+
+impl<T: Recycle + 'static> Sink<T> {
+    /// Takes a value from `pool`, fills it in with `fill`, and emits it. Subscribers that keep the
+    /// value around only delay its reuse.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ArcPool;
+    ///
+    /// let frames = ArcPool::new(4, || Vec::with_capacity(1024));
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let _subscription = stream_host.get_stream().subscribe(|frame| assert_eq!(frame.len(), 3));
+    ///
+    /// for _ in 0..100 {
+    ///     stream_host.emit_pooled(&frames, |frame| frame.extend_from_slice(&[1, 2, 3]));
+    /// }
+    ///
+    /// // Every frame was dropped by its subscriber before the next one was emitted, so a single
+    /// // buffer was enough.
+    /// assert_eq!(frames.count_pooled(), 1);
+    /// assert_eq!(frames.count_available(), 1);
+    /// ```
+    pub fn emit_pooled<F>(&self, pool: &ArcPool<T>, fill: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        self.emit_rc(pool.acquire(fill));
+    }
+}
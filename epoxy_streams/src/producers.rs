@@ -0,0 +1,160 @@
+use super::{DeliveryReport, PermitError, Sink, Stream, StreamClosed};
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A lightweight handle that can emit values into a Sink from anywhere, including other threads.
+/// Producers are cheap to clone, so each thread or component that needs write access to a stream
+/// can be handed its own copy while the Sink itself stays with its owner.
+///
+/// Unlike the Sink, a producer does not keep the stream alive. Once the Sink is dropped all of its
+/// producers start returning `Err(StreamClosed)`, which lets producer threads notice that nobody
+/// will ever receive their values again and shut down.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{ReactiveCache, Sink, StreamClosed};
+///
+/// let sink: Sink<i32> = Sink::new();
+/// let cache = ReactiveCache::from_stream(sink.get_stream());
+///
+/// let producer = sink.producer();
+/// let handle = std::thread::spawn(move || producer.emit(5));
+/// assert_eq!(handle.join().unwrap(), Ok(()));
+/// assert_eq!(cache.get_cloned(), vec![5]);
+///
+/// let producer = sink.producer();
+/// drop(sink);
+/// assert_eq!(producer.emit(6), Err(StreamClosed));
+/// ```
+pub struct SinkProducer<T> {
+    stream: Stream<T>,
+}
+
+impl<T> Clone for SinkProducer<T> {
+    fn clone(&self) -> Self {
+        SinkProducer {
+            stream: self.stream.clone(),
+        }
+    }
+}
+
+impl<T> SinkProducer<T> {
+    /// Emits a new value into the Sink this producer was created from, or returns an error if
+    /// that Sink has been dropped.
+    pub fn emit(&self, value: T) -> Result<(), StreamClosed> {
+        self.emit_rc(Arc::new(value))
+    }
+
+    /// Same logic as `emit`, but takes an existing Arc pointer.
+    pub fn emit_rc(&self, value: Arc<T>) -> Result<(), StreamClosed> {
+        if self.stream.emit_intercepted_rc(value).stream_alive {
+            Ok(())
+        } else {
+            Err(StreamClosed)
+        }
+    }
+
+    /// Same as `emit`, but reports how many subscribers received the value. If the Sink has been
+    /// dropped the report's `stream_alive` field will be false.
+    pub fn emit_counted(&self, value: T) -> DeliveryReport {
+        self.stream.emit_intercepted_rc(Arc::new(value))
+    }
+
+    /// Returns true if the Sink this producer was created from has not been closed or dropped yet.
+    pub fn is_alive(&self) -> bool {
+        self.stream.is_alive()
+    }
+}
+
+/// A revocable right to emit values into a Sink, minted with `Sink::mint_permit`. This is meant
+/// for plugin architectures, where components that are not fully trusted get temporary write
+/// access to a stream. Once the permit is revoked (or the Sink is dropped) every emit fails, even
+/// from clones of the permit that the component has passed around.
+///
+/// Revoking a permit does not wait for emits that are already in progress on other threads, so a
+/// value that is being emitted at the moment of revocation may still be delivered.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{PermitError, ReactiveCache, Sink};
+///
+/// let sink: Sink<i32> = Sink::new();
+/// let cache = ReactiveCache::from_stream(sink.get_stream());
+///
+/// let (permit, revoker) = sink.mint_permit();
+/// assert_eq!(permit.emit(1), Ok(()));
+///
+/// revoker.revoke();
+/// assert_eq!(permit.emit(2), Err(PermitError::Revoked));
+/// assert_eq!(cache.get_cloned(), vec![1]);
+/// ```
+pub struct EmitPermit<T> {
+    stream: Stream<T>,
+    revoked: Arc<AtomicBool>,
+}
+
+/// Revokes the `EmitPermit` it was minted with, along with all of that permit's clones.
+pub struct PermitRevoker {
+    revoked: Arc<AtomicBool>,
+}
+
+impl<T> Clone for EmitPermit<T> {
+    fn clone(&self) -> Self {
+        EmitPermit {
+            stream: self.stream.clone(),
+            revoked: Arc::clone(&self.revoked),
+        }
+    }
+}
+
+impl<T> EmitPermit<T> {
+    /// Emits a new value into the Sink this permit was minted from, or returns an error if the
+    /// permit has been revoked or the Sink has been dropped.
+    pub fn emit(&self, value: T) -> Result<(), PermitError> {
+        self.emit_rc(Arc::new(value))
+    }
+
+    /// Same logic as `emit`, but takes an existing Arc pointer.
+    pub fn emit_rc(&self, value: Arc<T>) -> Result<(), PermitError> {
+        if self.is_revoked() {
+            return Err(PermitError::Revoked);
+        }
+        if self.stream.emit_intercepted_rc(value).stream_alive {
+            Ok(())
+        } else {
+            Err(PermitError::StreamClosed)
+        }
+    }
+
+    /// Returns true once the permit has been revoked.
+    pub fn is_revoked(&self) -> bool {
+        self.revoked.load(Ordering::Acquire)
+    }
+}
+
+impl PermitRevoker {
+    /// Revokes the permit. Revoking a permit cannot be undone, so mint a new one instead.
+    pub fn revoke(&self) {
+        self.revoked.store(true, Ordering::Release);
+    }
+}
+
+impl<T> Sink<T> {
+    /// Creates a new producer handle for this Sink. See `SinkProducer` for details.
+    pub fn producer(&self) -> SinkProducer<T> {
+        SinkProducer {
+            stream: self.get_stream(),
+        }
+    }
+
+    /// Mints a new permit to emit into this Sink, along with the handle that revokes it. See
+    /// `EmitPermit` for details.
+    pub fn mint_permit(&self) -> (EmitPermit<T>, PermitRevoker) {
+        let revoked = Arc::new(AtomicBool::new(false));
+        let permit = EmitPermit {
+            stream: self.get_stream(),
+            revoked: Arc::clone(&revoked),
+        };
+        (permit, PermitRevoker { revoked })
+    }
+}
@@ -0,0 +1,219 @@
+use super::sync::is_lock_held;
+use super::{ReactiveValue, ReadonlyReactiveValue, WriteableReactiveValue};
+use std::cell::{Cell, RefCell};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Held for writing while changes propagate through the graph of reactive values, and for
+/// reading by `read_consistent`. It guards no data, so poisoning is ignored.
+///
+/// The lock is never waited for while the thread holds a stream lock, since propagation emits
+/// values and so takes stream locks while holding this one. Changes made from a stream listener
+/// propagate without it.
+static PROPAGATION_LOCK: RwLock<()> = RwLock::new(());
+
+type EndOfTurnCallback = Box<dyn FnOnce()>;
+
+thread_local! {
+    static PROPAGATION_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static END_OF_TURN_CALLBACKS: RefCell<Vec<EndOfTurnCallback>> = const { RefCell::new(vec![]) };
+}
+
+struct PropagationDepthGuard;
+
+impl PropagationDepthGuard {
+    /// Increments the propagation depth of the current thread, returning true if the thread was
+    /// not already propagating.
+    fn enter() -> (PropagationDepthGuard, bool) {
+        let is_outermost = PROPAGATION_DEPTH.with(|depth| {
+            let current = depth.get();
+            depth.set(current + 1);
+            current == 0
+        });
+        (PropagationDepthGuard, is_outermost)
+    }
+}
+
+impl Drop for PropagationDepthGuard {
+    fn drop(&mut self) {
+        PROPAGATION_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+pub(crate) fn is_propagating() -> bool {
+    PROPAGATION_DEPTH.with(|depth| depth.get() > 0)
+}
+
+/// Runs the given function while holding the propagation lock. Nested calls on the same thread
+/// (for example a subscriber that sets another ReactiveValue) re-use the outermost lock, and
+/// calls made while a stream lock is held do not take it (see `PROPAGATION_LOCK`).
+pub(crate) fn propagate<R, F>(propagate_fn: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let (result, is_outermost) = {
+        let (_depth_guard, is_outermost) = PropagationDepthGuard::enter();
+        let _lock_guard = if is_outermost && !is_lock_held() {
+            Some(
+                PROPAGATION_LOCK
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner),
+            )
+        } else {
+            None
+        };
+        (propagate_fn(), is_outermost)
+    };
+    if is_outermost {
+        run_end_of_turn_callbacks();
+    }
+    result
+}
+
+/// Runs the given function once the current propagation turn has finished and the propagation
+/// lock has been released. Returns false (without running the function) if the current thread
+/// is not propagating.
+pub(crate) fn after_propagation<F>(callback: F) -> bool
+where
+    F: FnOnce() + 'static,
+{
+    if !is_propagating() {
+        return false;
+    }
+    END_OF_TURN_CALLBACKS.with(|callbacks| callbacks.borrow_mut().push(Box::new(callback)));
+    true
+}
+
+fn run_end_of_turn_callbacks() {
+    // Callbacks may start new propagation turns that queue further callbacks.
+    loop {
+        let callbacks = END_OF_TURN_CALLBACKS.with(|callbacks| callbacks.take());
+        if callbacks.is_empty() {
+            return;
+        }
+        for callback in callbacks {
+            callback();
+        }
+    }
+}
+
+/// Groups several updates into a single transaction. A `read_consistent` call on another thread
+/// will either see the state from before the transaction started or after it ended, never a mix
+/// of both. Changes made by `ReactiveValue::set` are always transactional on their own, this
+/// function is only needed to update several values together.
+///
+/// Transactions started from inside a stream listener are not isolated from `read_consistent`,
+/// since waiting for other threads' transactions there could deadlock with a thread that is
+/// emitting into the same stream.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::ReactiveValue;
+///
+/// let first = ReactiveValue::new(1);
+/// let second = ReactiveValue::new(1);
+///
+/// epoxy_streams::transaction(|| {
+///     first.set(2);
+///     second.set(2);
+/// });
+/// assert_eq!(epoxy_streams::read_consistent((&first, &second)), (2.into(), 2.into()));
+/// ```
+pub fn transaction<R, F>(transaction_fn: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    propagate(transaction_fn)
+}
+
+/// A reactive value, or tuple of reactive values, that can be read by `read_consistent`.
+pub trait ConsistentRead {
+    type Output;
+
+    /// Reads the current value(s). Only called by `read_consistent`, which holds the lock.
+    fn read_unlocked(&self) -> Self::Output;
+}
+
+/// Reads several ReactiveValues at once, guaranteeing that the result does not contain a torn
+/// state where some values reflect an update (or `transaction`) and some do not. Values are
+/// passed as a tuple of references and returned as a tuple of Arcs.
+///
+/// Called from inside a stream listener, the values are read without waiting for other threads'
+/// updates to finish, for the same reason as `transaction`.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::ReactiveValue;
+///
+/// let width = ReactiveValue::new(2);
+/// let height = ReactiveValue::new(3);
+/// let area = ReactiveValue::map(&width, |width| width * 3);
+///
+/// let (width, height, area) = epoxy_streams::read_consistent((&width, &height, &area));
+/// assert_eq!((*width, *height, *area), (2, 3, 6));
+/// ```
+pub fn read_consistent<R: ConsistentRead>(values: R) -> R::Output {
+    if is_propagating() || is_lock_held() {
+        // The current thread either already holds the lock for writing, or could deadlock by
+        // waiting for it (see `PROPAGATION_LOCK`).
+        return values.read_unlocked();
+    }
+    let _lock_guard = PROPAGATION_LOCK
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+    values.read_unlocked()
+}
+
+impl<T> ConsistentRead for WriteableReactiveValue<T> {
+    type Output = Arc<T>;
+
+    fn read_unlocked(&self) -> Arc<T> {
+        self.get()
+    }
+}
+
+impl<T: Send + Sync + 'static> ConsistentRead for ReadonlyReactiveValue<T> {
+    type Output = Arc<T>;
+
+    fn read_unlocked(&self) -> Arc<T> {
+        self.get()
+    }
+}
+
+impl<T> ConsistentRead for dyn ReactiveValue<T> {
+    type Output = Arc<T>;
+
+    fn read_unlocked(&self) -> Arc<T> {
+        self.get()
+    }
+}
+
+impl<R: ConsistentRead + ?Sized> ConsistentRead for &R {
+    type Output = R::Output;
+
+    fn read_unlocked(&self) -> R::Output {
+        (**self).read_unlocked()
+    }
+}
+
+macro_rules! consistent_read_for_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: ConsistentRead),+> ConsistentRead for ($($name,)+) {
+            type Output = ($($name::Output,)+);
+
+            #[allow(non_snake_case)]
+            fn read_unlocked(&self) -> Self::Output {
+                let ($($name,)+) = self;
+                ($($name.read_unlocked(),)+)
+            }
+        }
+    };
+}
+
+consistent_read_for_tuple!(A);
+consistent_read_for_tuple!(A, B);
+consistent_read_for_tuple!(A, B, C);
+consistent_read_for_tuple!(A, B, C, D);
+consistent_read_for_tuple!(A, B, C, D, E);
+consistent_read_for_tuple!(A, B, C, D, E, F);
+consistent_read_for_tuple!(A, B, C, D, E, F, G);
+consistent_read_for_tuple!(A, B, C, D, E, F, G, H);
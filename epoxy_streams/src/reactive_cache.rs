@@ -85,13 +85,13 @@ impl<T: Send + Sync + 'static> ReactiveCache<T> {
 
         ReactiveCache {
             cache: value_arc,
-            subscription: subscription,
+            subscription,
         }
     }
 
     /// Returns a VecDeque containing recent values emitted by the stream, ordered such that
     /// the newest values are at the back of the queue.
-    pub fn get(&self) -> RwLockReadGuard<VecDeque<Arc<T>>> {
+    pub fn get(&self) -> RwLockReadGuard<'_, VecDeque<Arc<T>>> {
         self.cache.read().unwrap()
     }
 
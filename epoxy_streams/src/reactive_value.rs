@@ -1,12 +1,51 @@
-use super::{Stream, Sink, Subscription};
+use super::computed_stats::{ComputedStats, ComputedStatsRecorder};
+use super::derivation::{check_immediate_write, check_write};
+use super::propagation::propagate;
+use super::{SetError, Stream, Sink, Subscription, ValuePoisoned};
 use std::default::Default;
-use std::sync::{Arc, RwLock};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, RwLock, RwLockReadGuard, Weak};
 
 /// Trait that applies to both readonly and writeable reactive values.
 pub trait ReactiveValue<T> {
     /// Returns the current value of the ReactiveValue.
     fn get(&self) -> Arc<T>;
 
+    /// Returns a guard that gives borrowed access to the current value without cloning its Arc.
+    /// The ReactiveValue cannot change while the guard is held, so calling `set` on the same
+    /// thread before dropping the guard will deadlock. Keep guards short-lived.
+    ///
+    /// The default implementation holds on to the value returned by `get` instead, which does not
+    /// stop the ReactiveValue from changing.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let value = ReactiveValue::new(vec![1, 2, 3]);
+    /// assert_eq!(value.read().len(), 3);
+    /// ```
+    fn read(&self) -> ReactiveValueReadGuard<'_, T> {
+        ReactiveValueReadGuard {
+            guard: ReadGuard::Owned(self.get()),
+        }
+    }
+
+    /// Same as `get`, but returns an error instead of panicking if the value's lock has been
+    /// poisoned by a thread that panicked during an update. The default implementation always
+    /// returns the value from `get`.
+    fn try_get(&self) -> Result<Arc<T>, ValuePoisoned> {
+        Ok(self.get())
+    }
+
+    /// Returns the current value of the ReactiveValue without registering it as a dependency.
+    /// Outside of a `computed!` expression this is identical to `get`, but inside one it allows
+    /// reading a value without recomputing whenever that value changes.
+    fn get_untracked(&self) -> Arc<T> {
+        self.get()
+    }
+
     /// Returns a Stream that represents the changing value over time.
     /// Use this function to subscribe to changes in the ReactiveValue.ReadonlyReactiveValue
     ///
@@ -33,6 +72,43 @@ pub trait ReactiveValue<T> {
     fn as_stream(&self) -> Stream<T>;
 }
 
+/// Borrowed access to the current value of a ReactiveValue, returned by `ReactiveValue::read`.
+pub struct ReactiveValueReadGuard<'a, T> {
+    guard: ReadGuard<'a, T>,
+}
+
+enum ReadGuard<'a, T> {
+    Locked(RwLockReadGuard<'a, Arc<T>>),
+    Owned(Arc<T>),
+}
+
+impl<'a, T> Deref for ReactiveValueReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match &self.guard {
+            ReadGuard::Locked(guard) => guard,
+            ReadGuard::Owned(value) => value,
+        }
+    }
+}
+
+fn read_value<T>(value: &RwLock<Arc<T>>) -> ReactiveValueReadGuard<'_, T> {
+    match value.read() {
+        Ok(guard) => ReactiveValueReadGuard {
+            guard: ReadGuard::Locked(guard),
+        },
+        Err(err) => panic!("ReactiveValue mutex poisoned: {}", err),
+    }
+}
+
+fn try_get_value<T>(value: &RwLock<Arc<T>>) -> Result<Arc<T>, ValuePoisoned> {
+    match value.read() {
+        Ok(val) => Ok(Arc::clone(&val)),
+        Err(_) => Err(ValuePoisoned),
+    }
+}
+
 // IMPLEMENTATIONS
 
 struct ReadonlyReactiveValueImpl<T> {
@@ -40,6 +116,9 @@ struct ReadonlyReactiveValueImpl<T> {
 
     #[allow(dead_code)]
     subscription: Subscription<T>,
+
+    /// Only set for values created by `computed!`.
+    stats_recorder: OnceLock<ComputedStatsRecorder>,
 }
 
 impl<T> ReactiveValue<T> for ReadonlyReactiveValueImpl<T> {
@@ -53,11 +132,29 @@ impl<T> ReactiveValue<T> for ReadonlyReactiveValueImpl<T> {
             Err(err) => panic!("ReactiveValue mutex poisoned: {}", err),
         }
     }
+
+    fn read(&self) -> ReactiveValueReadGuard<'_, T> {
+        read_value(&self.value)
+    }
+
+    fn try_get(&self) -> Result<Arc<T>, ValuePoisoned> {
+        try_get_value(&self.value)
+    }
 }
 
 struct WriteableReactiveValueImpl<T> {
     value: Box<RwLock<Arc<T>>>,
     host: Sink<T>,
+    equality_check: Option<fn(&T, &T) -> bool>,
+
+    /// Only changed while holding the value's write lock, so no write can slip past `freeze`.
+    frozen: AtomicBool,
+    frozen_host: OnceLock<Sink<bool>>,
+}
+
+// Writing a frozen value is a bug in the program, but not one worth crashing a release build over.
+fn debug_frozen_write() {
+    debug_assert!(false, "{}", SetError::Frozen);
 }
 
 impl<T> ReactiveValue<T> for WriteableReactiveValueImpl<T> {
@@ -71,6 +168,14 @@ impl<T> ReactiveValue<T> for WriteableReactiveValueImpl<T> {
             Err(err) => panic!("ReactiveValue mutex poisoned: {}", err),
         }
     }
+
+    fn read(&self) -> ReactiveValueReadGuard<'_, T> {
+        read_value(&self.value)
+    }
+
+    fn try_get(&self) -> Result<Arc<T>, ValuePoisoned> {
+        try_get_value(&self.value)
+    }
 }
 
 /// Holds the latest value emitted by a stream.
@@ -115,6 +220,76 @@ impl<T: Send + Sync + 'static> ReactiveValue<T> for ReadonlyReactiveValue<T> {
     fn get(&self) -> Arc<T> {
         self.pointer.get()
     }
+
+    fn read(&self) -> ReactiveValueReadGuard<'_, T> {
+        self.pointer.read()
+    }
+
+    fn try_get(&self) -> Result<Arc<T>, ValuePoisoned> {
+        self.pointer.try_get()
+    }
+}
+
+impl<T: Send + Sync + 'static> ReadonlyReactiveValue<T> {
+    /// Returns how often a value created by `computed!` has been recomputed, how long that took,
+    /// and which values it depends on. This helps find expensive computed values in large graphs.
+    /// Returns None for values that were not created by `computed!`.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ComputedStatsRecorder, ReactiveValue};
+    ///
+    /// let points = ReactiveValue::new(4);
+    /// let recorder = ComputedStatsRecorder::new(vec![("points", points.as_stream().id())]);
+    /// let score = points
+    ///     .as_stream()
+    ///     .map({
+    ///         let recorder = recorder.clone();
+    ///         move |points| recorder.record(|| points * 10)
+    ///     })
+    ///     .to_reactive_value_with_default(recorder.record(|| 40))
+    ///     .with_stats_recorder(recorder);
+    ///
+    /// points.set(5);
+    /// points.set(6);
+    /// let stats = score.stats().unwrap();
+    /// assert_eq!(stats.recompute_count, 2);
+    /// assert_eq!(stats.dependencies[0].name, "points");
+    /// ```
+    pub fn stats(&self) -> Option<ComputedStats> {
+        self.pointer
+            .stats_recorder
+            .get()
+            .and_then(|recorder| recorder.stats())
+    }
+
+    /// Attaches the recorder whose statistics `stats` returns. `computed!` calls this for every
+    /// value it creates. Does nothing if the value already has a recorder.
+    pub fn with_stats_recorder(self, recorder: ComputedStatsRecorder) -> ReadonlyReactiveValue<T> {
+        let _ = self.pointer.stats_recorder.set(recorder);
+        self
+    }
+}
+
+/// A ReadonlyReactiveValue that does not keep the value (or its subscription) alive.
+pub(crate) struct WeakReadonlyReactiveValue<T> {
+    pointer: Weak<ReadonlyReactiveValueImpl<T>>,
+}
+
+impl<T> WeakReadonlyReactiveValue<T> {
+    pub(crate) fn upgrade(&self) -> Option<ReadonlyReactiveValue<T>> {
+        self.pointer
+            .upgrade()
+            .map(|pointer| ReadonlyReactiveValue { pointer })
+    }
+}
+
+impl<T> ReadonlyReactiveValue<T> {
+    pub(crate) fn downgrade(&self) -> WeakReadonlyReactiveValue<T> {
+        WeakReadonlyReactiveValue {
+            pointer: Arc::downgrade(&self.pointer),
+        }
+    }
 }
 
 impl<T: Send + Sync + 'static> Clone for ReadonlyReactiveValue<T> {
@@ -149,6 +324,14 @@ impl<T> ReactiveValue<T> for WriteableReactiveValue<T> {
     fn get(&self) -> Arc<T> {
         self.pointer.get()
     }
+
+    fn read(&self) -> ReactiveValueReadGuard<'_, T> {
+        self.pointer.read()
+    }
+
+    fn try_get(&self) -> Result<Arc<T>, ValuePoisoned> {
+        self.pointer.try_get()
+    }
 }
 
 impl<T> Clone for WriteableReactiveValue<T> {
@@ -169,17 +352,221 @@ impl<T: 'static + Send + Sync> WriteableReactiveValue<T> {
     }
 
     /// Sets the value of the ReactiveValue, using a mutex to ensure thread safety.
+    ///
+    /// Panics with a `ReentrantWriteError` if called while a `computed!` expression is being
+    /// evaluated, unless `config::set_reentrant_write_policy` allows deferred writes. Setting a
+    /// frozen value panics in debug builds, and is ignored in release builds.
     pub fn set_rc(&self, value: Arc<T>) {
+        match self.try_set_rc(value) {
+            Ok(()) => {}
+            Err(SetError::Frozen) => debug_frozen_write(),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Same as `set`, but returns an error instead of panicking: `SetError::Reentrant` if called
+    /// while a `computed!` expression is being evaluated, and `SetError::Frozen` if the value has
+    /// been frozen. If the reentrant write policy defers writes made during an evaluation, this
+    /// returns Ok and the write happens once the evaluation has finished, unless the value has
+    /// been frozen by then.
+    pub fn try_set(&self, value: T) -> Result<(), SetError> {
+        self.try_set_rc(Arc::new(value))
+    }
+
+    /// Same as `set_rc`, but returns an error instead of panicking. See `try_set`.
+    pub fn try_set_rc(&self, value: Arc<T>) -> Result<(), SetError> {
+        if self.pointer.frozen.load(Ordering::SeqCst) {
+            return Err(SetError::Frozen);
+        }
+        let deferred_self = self.clone();
+        let deferred_value = value.clone();
+        let write_now = check_write(move || {
+            if deferred_self.write_rc(deferred_value).is_err() {
+                debug_frozen_write();
+            }
+        })?;
+        if write_now {
+            self.write_rc(value)?;
+        }
+        Ok(())
+    }
+
+    fn write_rc(&self, value: Arc<T>) -> Result<(), SetError> {
+        propagate(|| {
+            {
+                let mut val_mut = self.pointer.value.write().unwrap();
+                if self.pointer.frozen.load(Ordering::SeqCst) {
+                    return Err(SetError::Frozen);
+                }
+                if let Some(is_equal) = self.pointer.equality_check {
+                    if is_equal(&*val_mut, &*value) {
+                        return Ok(());
+                    }
+                }
+                *val_mut = value.clone();
+            }
+            self.pointer.host.emit_rc(value);
+            Ok(())
+        })
+    }
+
+    /// Sets the value to `new_value` only if the current value equals `expected`, and returns
+    /// whether it did. The comparison and the write happen under the same lock, so no other
+    /// thread can change the value in between. This makes optimistic concurrency possible when
+    /// several threads write the same ReactiveValue: read it, compute a new value, and retry if
+    /// `compare_and_set` returns false.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let version = ReactiveValue::new(1);
+    /// assert!(version.compare_and_set(&1, 2));
+    /// assert!(!version.compare_and_set(&1, 3));
+    /// assert_eq!(*version.get(), 2);
+    /// ```
+    pub fn compare_and_set(&self, expected: &T, new_value: T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.compare_update(|current| {
+            if *current == *expected {
+                Some(new_value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Computes a new value from the current one while holding the lock, so no other thread can
+    /// change the value in between. Returning None leaves the value unchanged. Returns whether
+    /// the value was updated.
+    ///
+    /// The result of the update is needed right away, so unlike `set` this always panics with a
+    /// `ReentrantWriteError` when called while a `computed!` expression is being evaluated. Like
+    /// `set`, updating a frozen value panics in debug builds, and returns false without calling
+    /// `update_function` in release builds.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let stock = ReactiveValue::new(1);
+    /// let take_one = |count: &i32| if *count > 0 { Some(count - 1) } else { None };
+    ///
+    /// assert!(stock.compare_update(take_one));
+    /// assert!(!stock.compare_update(take_one));
+    /// assert_eq!(*stock.get(), 0);
+    /// ```
+    pub fn compare_update<F>(&self, update_function: F) -> bool
+    where
+        F: FnOnce(&T) -> Option<T>,
+    {
+        if let Err(err) = check_immediate_write() {
+            panic!("{}", err);
+        }
+        propagate(|| {
+            let value = {
+                let mut val_mut = self.pointer.value.write().unwrap();
+                if self.pointer.frozen.load(Ordering::SeqCst) {
+                    drop(val_mut);
+                    debug_frozen_write();
+                    return false;
+                }
+                let value = match update_function(&*val_mut) {
+                    Some(value) => Arc::new(value),
+                    None => return false,
+                };
+                if let Some(is_equal) = self.pointer.equality_check {
+                    if is_equal(&*val_mut, &*value) {
+                        return true;
+                    }
+                }
+                *val_mut = value.clone();
+                value
+            };
+            self.pointer.host.emit_rc(value);
+            true
+        })
+    }
+
+    /// Makes the value permanent, while subscribers and readonly copies keep working. This suits
+    /// configuration that is assembled during startup and must not change afterwards. Any later
+    /// attempt to set the value fails: `try_set` returns `SetError::Frozen`, while `set` panics
+    /// in debug builds and leaves the value unchanged in release builds. Freezing a value more
+    /// than once has no effect.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveValue, SetError};
+    ///
+    /// let max_connections = ReactiveValue::new(10);
+    /// let is_frozen = max_connections.is_frozen();
+    /// max_connections.set(20);
+    /// assert_eq!(*is_frozen.get(), false);
+    ///
+    /// max_connections.freeze();
+    /// assert_eq!(*is_frozen.get(), true);
+    /// assert_eq!(*max_connections.get(), 20);
+    ///
+    /// assert_eq!(max_connections.try_set(30), Err(SetError::Frozen));
+    /// assert_eq!(*max_connections.get(), 20);
+    /// ```
+    pub fn freeze(&self) {
         {
-            let mut val_mut = self.pointer.value.write().unwrap();
-            *val_mut = value.clone();
+            let _val = self.pointer.value.write().unwrap();
+            if self.pointer.frozen.swap(true, Ordering::SeqCst) {
+                return;
+            }
+        }
+        if let Some(frozen_host) = self.pointer.frozen_host.get() {
+            frozen_host.emit(true);
         }
-        self.pointer.host.emit_rc(value)
+    }
+
+    /// Returns a ReadonlyReactiveValue that becomes true once this value has been frozen.
+    pub fn is_frozen(&self) -> ReadonlyReactiveValue<bool> {
+        // Holding the lock keeps `freeze` from emitting before the returned value has subscribed.
+        let _val = self.pointer.value.read().unwrap();
+        let is_frozen = self.pointer.frozen.load(Ordering::SeqCst);
+        self.pointer
+            .frozen_host
+            .get_or_init(Sink::new)
+            .get_stream()
+            .to_reactive_value_with_default(is_frozen)
     }
 
     /// Returns a ReadonlyReactiveValue whose value matches this one.
     /// This is helpful when exposing ReactiveValues to public APIs, so that
     /// the consumer cannot alter the state of your component.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveValue, ReadonlyReactiveValue, WriteableReactiveValue};
+    ///
+    /// struct Player {
+    ///     health: WriteableReactiveValue<u32>,
+    /// }
+    ///
+    /// impl Player {
+    ///     fn health(&self) -> ReadonlyReactiveValue<u32> {
+    ///         self.health.as_readonly()
+    ///     }
+    /// }
+    ///
+    /// let player = Player { health: ReactiveValue::new(100) };
+    /// let health = player.health();
+    /// player.health.set(80);
+    /// assert_eq!(*health.get(), 80);
+    /// ```
+    ///
+    /// The readonly value has no way to change the original.
+    /// ```compile_fail
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let health = ReactiveValue::new(100);
+    /// health.as_readonly().set(0);
+    /// ```
     pub fn as_readonly(&self) -> ReadonlyReactiveValue<T> {
         self.as_stream()
             .to_reactive_value_with_default_rc(self.get())
@@ -205,15 +592,60 @@ impl<T: 'static + Send + Sync> dyn ReactiveValue<T> {
     /// assert_eq!(*writeable_value.get(), 50);
     /// ```
     pub fn new(initial_value: T) -> WriteableReactiveValue<T> {
-        ReactiveValue::new_rc(Arc::new(initial_value))
+        <dyn ReactiveValue<T>>::new_rc(Arc::new(initial_value))
     }
 
     /// See docs for `new`
     pub fn new_rc(initial_value: Arc<T>) -> WriteableReactiveValue<T> {
+        <dyn ReactiveValue<T>>::new_with_equality_check(initial_value, None)
+    }
+
+    /// Creates a new writeable reactive value that ignores any call to `set` whose new value is
+    /// equal to the current one, so dependents are only notified of actual changes. This is
+    /// usually the desired behavior for UI state, and can save a lot of recomputation in large
+    /// graphs of computed values.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let writeable_value = ReactiveValue::new_distinct(5);
+    /// let change_count = writeable_value.as_stream().count_values().to_reactive_value();
+    ///
+    /// writeable_value.set(5);
+    /// assert_eq!(*change_count.get(), 0);
+    ///
+    /// writeable_value.set(50);
+    /// writeable_value.set(50);
+    /// assert_eq!(*writeable_value.get(), 50);
+    /// assert_eq!(*change_count.get(), 1);
+    /// ```
+    pub fn new_distinct(initial_value: T) -> WriteableReactiveValue<T>
+    where
+        T: PartialEq,
+    {
+        <dyn ReactiveValue<T>>::new_distinct_rc(Arc::new(initial_value))
+    }
+
+    /// See docs for `new_distinct`
+    pub fn new_distinct_rc(initial_value: Arc<T>) -> WriteableReactiveValue<T>
+    where
+        T: PartialEq,
+    {
+        <dyn ReactiveValue<T>>::new_with_equality_check(initial_value, Some(<T as PartialEq>::eq))
+    }
+
+    fn new_with_equality_check(
+        initial_value: Arc<T>,
+        equality_check: Option<fn(&T, &T) -> bool>,
+    ) -> WriteableReactiveValue<T> {
         WriteableReactiveValue {
             pointer: Arc::new(WriteableReactiveValueImpl {
                 value: Box::new(RwLock::new(initial_value)),
                 host: Sink::new(),
+                equality_check,
+                frozen: AtomicBool::new(false),
+                frozen_host: OnceLock::new(),
             }),
         }
     }
@@ -222,11 +654,11 @@ impl<T: 'static + Send + Sync> dyn ReactiveValue<T> {
     where
         T: Default,
     {
-        ReactiveValue::from_stream_with_default(stream, Default::default())
+        <dyn ReactiveValue<T>>::from_stream_with_default(stream, Default::default())
     }
 
     pub fn from_stream_with_default(stream: Stream<T>, default: T) -> ReadonlyReactiveValue<T> {
-        ReactiveValue::from_stream_with_default_rc(stream, Arc::new(default))
+        <dyn ReactiveValue<T>>::from_stream_with_default_rc(stream, Arc::new(default))
     }
 
     pub fn from_stream_with_default_rc(
@@ -244,7 +676,8 @@ impl<T: 'static + Send + Sync> dyn ReactiveValue<T> {
         ReadonlyReactiveValue {
             pointer: Arc::new(ReadonlyReactiveValueImpl {
                 value: value_arc,
-                subscription: subscription,
+                subscription,
+                stats_recorder: OnceLock::new(),
             }),
         }
     }
@@ -274,16 +707,16 @@ impl<T: 'static + Send + Sync> Stream<T> {
     where
         T: Default,
     {
-        ReactiveValue::from_stream(self)
+        <dyn ReactiveValue<T>>::from_stream(self)
     }
 
     /// See `to_reactive_value`.
     pub fn to_reactive_value_with_default(self, default: T) -> ReadonlyReactiveValue<T> {
-        ReactiveValue::from_stream_with_default(self, default)
+        <dyn ReactiveValue<T>>::from_stream_with_default(self, default)
     }
 
     /// See `to_reactive_value`.
     pub fn to_reactive_value_with_default_rc(self, default: Arc<T>) -> ReadonlyReactiveValue<T> {
-        ReactiveValue::from_stream_with_default_rc(self, default)
+        <dyn ReactiveValue<T>>::from_stream_with_default_rc(self, default)
     }
 }
@@ -1,5 +1,7 @@
-use super::{ReactiveValue, ReadonlyReactiveValue};
-use std::sync::Arc;
+use super::config::worker_scheduler;
+use super::{ReactiveValue, ReadonlyReactiveValue, Stream, WriteableReactiveValue};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 impl<T: 'static + Send + Sync> dyn ReactiveValue<T> {
     /// Returns a ReactiveValue that only changes when the content of the original ReactiveValue
@@ -121,4 +123,290 @@ impl<T: 'static + Send + Sync> dyn ReactiveValue<T> {
             .map(map_function)
             .to_reactive_value_with_default(default)
     }
+
+    /// Returns a ReactiveValue that contains a copy of the content of the original ReactiveValue
+    /// whenever it passes a test (specified by `filter_function`), and `None` whenever it does
+    /// not.
+    ///
+    /// Unlike `sanitize`, the output always reflects the current state of the original value.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let original = ReactiveValue::new(1_i32);
+    /// let only_even = ReactiveValue::filter(&original, |val| val % 2 == 0);
+    /// assert_eq!(*only_even.get(), None);
+    ///
+    /// original.set(2);
+    /// assert_eq!(*only_even.get(), Some(2));
+    ///
+    /// original.set(3);
+    /// assert_eq!(*only_even.get(), None);
+    /// ```
+    pub fn filter<F>(
+        value: &dyn ReactiveValue<T>,
+        filter_function: F,
+    ) -> ReadonlyReactiveValue<Option<T>>
+    where
+        T: Clone,
+        F: Fn(&T) -> bool,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let filter_value = move |val: &T| {
+            if filter_function(val) {
+                Some(val.clone())
+            } else {
+                None
+            }
+        };
+        let default = filter_value(&value.get());
+        value
+            .as_stream()
+            .map(filter_value)
+            .to_reactive_value_with_default(default)
+    }
+
+    /// Returns a ReactiveValue that follows the original ReactiveValue, but only updates once the
+    /// original has stopped changing for the given quiet period. Useful for expensive work that
+    /// depends on rapidly changing state, such as search-as-you-type.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    /// use std::time::Duration;
+    ///
+    /// let original = ReactiveValue::new(1_i32);
+    /// let debounced = ReactiveValue::debounce(&original, Duration::from_millis(50));
+    /// assert_eq!(*debounced.get(), 1);
+    ///
+    /// original.set(2);
+    /// original.set(3);
+    /// assert_eq!(*debounced.get(), 1);
+    ///
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(*debounced.get(), 3);
+    /// ```
+    #[cfg(feature = "timers")]
+    pub fn debounce(
+        value: &dyn ReactiveValue<T>,
+        quiet_period: Duration,
+    ) -> ReadonlyReactiveValue<T> {
+        value
+            .as_stream()
+            .debounce(quiet_period)
+            .to_reactive_value_with_default_rc(value.get())
+    }
+
+    /// Returns a ReactiveValue that follows a data source which cannot notify anyone when it
+    /// changes, such as a file, a hardware register or an HTTP endpoint. `poll_function` is
+    /// called once immediately and then every `interval`, on the default scheduler if the
+    /// application has set one and otherwise on a worker thread of epoxy's own, so that a slow
+    /// poll never holds up the timers of time-based operators. The ReactiveValue only changes
+    /// when the polled result differs from the current value.
+    /// Polling stops once the ReactiveValue (and every clone of it) has been dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let sensor = Arc::new(AtomicUsize::new(20));
+    /// let sensor_read = sensor.clone();
+    /// let temperature = ReactiveValue::from_poll(Duration::from_millis(10), move || {
+    ///     sensor_read.load(Ordering::SeqCst)
+    /// });
+    /// assert_eq!(*temperature.get(), 20);
+    ///
+    /// sensor.store(25, Ordering::SeqCst);
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(*temperature.get(), 25);
+    /// ```
+    pub fn from_poll<F>(interval: Duration, poll_function: F) -> ReadonlyReactiveValue<T>
+    where
+        T: PartialEq,
+        F: Fn() -> T,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let value = <dyn ReactiveValue<T>>::new_distinct(poll_function());
+        let readonly_value = value.as_readonly();
+        schedule_poll(value, Arc::new(poll_function), interval);
+        readonly_value
+    }
+}
+
+fn schedule_poll<T, F>(value: WriteableReactiveValue<T>, poll_function: Arc<F>, interval: Duration)
+where
+    T: 'static + Send + Sync,
+    F: Fn() -> T + Send + Sync + 'static,
+{
+    worker_scheduler().schedule_after(
+        interval,
+        Box::new(move || {
+            // Every readonly copy of the value subscribes to its stream, so no subscribers means
+            // that nobody can read the polled value anymore.
+            if value.as_stream().count_subscribers() == 0 {
+                return;
+            }
+            value.set(poll_function());
+            schedule_poll(value, poll_function, interval);
+        }),
+    );
+}
+
+impl<T, E> dyn ReactiveValue<Result<T, E>>
+where
+    T: 'static + Send + Sync,
+    E: 'static + Send + Sync + Clone,
+{
+    /// Returns a ReactiveValue that runs the Ok content of the original ReactiveValue through a
+    /// mapping function, and passes errors through unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let input: epoxy_streams::WriteableReactiveValue<Result<i32, String>> =
+    ///     ReactiveValue::new(Ok(2));
+    /// let doubled = ReactiveValue::map_ok(&input, |value| value * 2);
+    /// assert_eq!(*doubled.get(), Ok(4));
+    ///
+    /// input.set(Err("Not a number".to_string()));
+    /// assert_eq!(*doubled.get(), Err("Not a number".to_string()));
+    /// ```
+    pub fn map_ok<U, F>(
+        value: &dyn ReactiveValue<Result<T, E>>,
+        map_function: F,
+    ) -> ReadonlyReactiveValue<Result<U, E>>
+    where
+        U: 'static + Send + Sync,
+        F: Fn(&T) -> U,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        <dyn ReactiveValue<Result<T, E>>>::and_then(value, move |ok| Ok(map_function(ok)))
+    }
+
+    /// Returns a ReactiveValue that runs the Ok content of the original ReactiveValue through a
+    /// fallible function, and passes errors through unchanged. This chains fallible computations
+    /// without unwrapping anything.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let input: epoxy_streams::WriteableReactiveValue<Result<i32, String>> =
+    ///     ReactiveValue::new(Ok(2));
+    /// let reciprocal = ReactiveValue::and_then(&input, |value| match value {
+    ///     0 => Err("Division by zero".to_string()),
+    ///     value => Ok(1.0 / *value as f32),
+    /// });
+    /// assert_eq!(*reciprocal.get(), Ok(0.5));
+    ///
+    /// input.set(Ok(0));
+    /// assert_eq!(*reciprocal.get(), Err("Division by zero".to_string()));
+    /// ```
+    pub fn and_then<U, F>(
+        value: &dyn ReactiveValue<Result<T, E>>,
+        then_function: F,
+    ) -> ReadonlyReactiveValue<Result<U, E>>
+    where
+        U: 'static + Send + Sync,
+        F: Fn(&T) -> Result<U, E>,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let apply = move |result: &Result<T, E>| match result {
+            Ok(ok) => then_function(ok),
+            Err(err) => Err(err.clone()),
+        };
+        let default = apply(&value.get());
+        value
+            .as_stream()
+            .map(apply)
+            .to_reactive_value_with_default(default)
+    }
+
+    /// Returns a ReactiveValue that contains the Ok content of the original ReactiveValue, and
+    /// keeps its last Ok content while the original ReactiveValue contains an error. It contains
+    /// `default_value` until the original ReactiveValue is Ok for the first time.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let input: epoxy_streams::WriteableReactiveValue<Result<i32, String>> =
+    ///     ReactiveValue::new(Err("Loading".to_string()));
+    /// let last_good = ReactiveValue::last_ok_or(&input, 0);
+    /// assert_eq!(*last_good.get(), 0);
+    ///
+    /// input.set(Ok(5));
+    /// input.set(Err("Connection lost".to_string()));
+    /// assert_eq!(*last_good.get(), 5);
+    /// ```
+    pub fn last_ok_or(
+        value: &dyn ReactiveValue<Result<T, E>>,
+        default_value: T,
+    ) -> ReadonlyReactiveValue<T>
+    where
+        T: Clone,
+    {
+        let default = match &*value.get() {
+            Ok(ok) => ok.clone(),
+            Err(_) => default_value,
+        };
+        value
+            .as_stream()
+            .map(|result| result.as_ref().ok().cloned())
+            .some_values()
+            .to_reactive_value_with_default(default)
+    }
+
+    /// Returns a Stream that only emits when the error state of the ReactiveValue changes:
+    /// `Some(error)` when it starts containing an error (or a different error than before), and
+    /// `None` when it recovers. Changes from one Ok value to another are not emitted.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, ReactiveValue};
+    ///
+    /// let input: epoxy_streams::WriteableReactiveValue<Result<i32, String>> =
+    ///     ReactiveValue::new(Ok(1));
+    /// let transitions = ReactiveCache::from_stream(ReactiveValue::error_transitions(&input));
+    ///
+    /// input.set(Ok(2));
+    /// input.set(Err("Offline".to_string()));
+    /// input.set(Err("Offline".to_string()));
+    /// input.set(Ok(3));
+    /// assert_eq!(transitions.get_cloned(), vec![Some("Offline".to_string()), None]);
+    /// ```
+    pub fn error_transitions(value: &dyn ReactiveValue<Result<T, E>>) -> Stream<Option<E>>
+    where
+        E: PartialEq,
+    {
+        let last_error = Mutex::new((*value.get()).as_ref().err().cloned());
+        value
+            .as_stream()
+            .map(move |result| {
+                let error = result.as_ref().err().cloned();
+                let mut last_error = match last_error.lock() {
+                    Ok(last_error) => last_error,
+                    Err(err) => panic!("Error transition mutex poisoned: {}", err),
+                };
+                if *last_error == error {
+                    return None;
+                }
+                *last_error = error.clone();
+                Some(error)
+            })
+            .some_values()
+    }
 }
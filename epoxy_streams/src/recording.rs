@@ -0,0 +1,484 @@
+//! Captures the values of a live stream to files for offline analysis. `record_csv` writes CSV
+//! files (with the `csv` feature) and `record_parquet` writes Parquet files (with the `parquet`
+//! feature). Every row starts with a `timestamp` column holding the wall-clock time at which the
+//! value was emitted, as reported by the application's clock (see `config::set_clock`).
+//!
+//! Long recordings can be split across several files with a `RotationPolicy`. The first file is
+//! written to the given path, and later files get a number before the extension, so recording to
+//! `data.csv` produces `data.csv`, `data.1.csv`, `data.2.csv` and so on.
+//!
+//! Files are written on the thread that emits each value. A file is only complete once the
+//! recording has been finished, which happens when the stream completes, when `finish` is
+//! called, or when the recording is dropped. The first file is created right away, so it is left
+//! empty if the stream never emits.
+use super::config;
+use super::{Stream, Subscription};
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "parquet")]
+pub use arrow_array;
+#[cfg(feature = "parquet")]
+pub use arrow_schema;
+
+/// Decides when a recording moves on to a new file. A new file is started before writing a value
+/// that would exceed any of the limits. With the default policy everything goes into one file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// The most rows (not counting headers) to write to a single file.
+    pub max_rows: Option<u64>,
+
+    /// The longest a single file is written to, measured from when it was created.
+    pub max_age: Option<Duration>,
+}
+
+/// A file format that values can be recorded in.
+trait RecordingFormat<T>: Send {
+    fn write(&mut self, timestamp: SystemTime, value: &Arc<T>) -> io::Result<()>;
+
+    /// Writes out anything that is still buffered and closes the file.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+type FormatFactory<T> = Box<dyn Fn(File) -> Box<dyn RecordingFormat<T>> + Send>;
+
+struct OpenFile<T> {
+    format: Box<dyn RecordingFormat<T>>,
+    rows: u64,
+    opened_at: SystemTime,
+}
+
+struct RecorderState<T> {
+    path: PathBuf,
+    rotation: RotationPolicy,
+    create_format: FormatFactory<T>,
+    current: Option<OpenFile<T>>,
+    files: Vec<PathBuf>,
+    errors: Vec<io::Error>,
+    is_finished: bool,
+}
+
+impl<T> RecorderState<T> {
+    fn record(&mut self, value: &Arc<T>) -> io::Result<()> {
+        let timestamp = config::clock().wall_time();
+        if self.current.is_none() || self.should_rotate(timestamp) {
+            self.finish_file()?;
+            self.open_file(timestamp)?;
+        }
+        let current = self.current.as_mut().unwrap();
+        current.rows += 1;
+        current.format.write(timestamp, value)
+    }
+
+    fn open_file(&mut self, now: SystemTime) -> io::Result<()> {
+        let path = numbered_path(&self.path, self.files.len());
+        let file = File::create(&path)?;
+        self.files.push(path);
+        self.current = Some(OpenFile {
+            format: (self.create_format)(file),
+            rows: 0,
+            opened_at: now,
+        });
+        Ok(())
+    }
+
+    fn should_rotate(&self, now: SystemTime) -> bool {
+        let current = match &self.current {
+            Some(current) => current,
+            None => return false,
+        };
+        let is_full = matches!(self.rotation.max_rows, Some(max_rows) if current.rows >= max_rows);
+        let is_old = match self.rotation.max_age {
+            Some(max_age) => now
+                .duration_since(current.opened_at)
+                .is_ok_and(|age| age >= max_age),
+            None => false,
+        };
+        is_full || is_old
+    }
+
+    fn finish_file(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some(current) => current.format.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the path of the file with the given index, see the module documentation.
+fn numbered_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, index),
+    };
+    path.with_file_name(name)
+}
+
+fn lock_state<T>(state: &Mutex<RecorderState<T>>) -> MutexGuard<'_, RecorderState<T>> {
+    match state.lock() {
+        Ok(state) => state,
+        Err(err) => panic!("Recording mutex poisoned: {}", err),
+    }
+}
+
+/// Keeps recording the values of a stream to files until it is finished or dropped. See
+/// `record_csv` and `record_parquet`.
+pub struct Recording<T> {
+    state: Arc<Mutex<RecorderState<T>>>,
+    subscription: Option<Subscription<T>>,
+}
+
+impl<T> Recording<T> {
+    /// Returns any errors that occurred while writing since the last call. Values that could not
+    /// be written are skipped, and recording continues with the next value.
+    pub fn take_errors(&self) -> Vec<io::Error> {
+        mem::take(&mut lock_state(&self.state).errors)
+    }
+
+    /// Returns the paths of every file that has been written to so far, in order.
+    pub fn files(&self) -> Vec<PathBuf> {
+        lock_state(&self.state).files.clone()
+    }
+
+    /// Stops recording and closes the current file, returning the paths of every file that was
+    /// written to.
+    pub fn finish(mut self) -> io::Result<Vec<PathBuf>> {
+        self.subscription = None;
+        let mut state = lock_state(&self.state);
+        state.is_finished = true;
+        state.finish_file()?;
+        Ok(state.files.clone())
+    }
+}
+
+impl<T> Drop for Recording<T> {
+    fn drop(&mut self) {
+        self.subscription = None;
+        let mut state = lock_state(&self.state);
+        state.is_finished = true;
+        if let Err(err) = state.finish_file() {
+            state.errors.push(err);
+        }
+    }
+}
+
+fn record<T: Send + Sync + 'static>(
+    stream: &Stream<T>,
+    path: PathBuf,
+    rotation: RotationPolicy,
+    create_format: FormatFactory<T>,
+) -> io::Result<Recording<T>> {
+    let mut state = RecorderState {
+        path,
+        rotation,
+        create_format,
+        current: None,
+        files: vec![],
+        errors: vec![],
+        is_finished: false,
+    };
+    // The first file is created right away, so that a path that cannot be written to is reported
+    // here rather than on the first value.
+    state.open_file(config::clock().wall_time())?;
+    let state = Arc::new(Mutex::new(state));
+    let value_state = state.clone();
+    let completion_state = state.clone();
+    let subscription = stream.subscribe_with_completion(
+        move |val| {
+            let mut state = lock_state(&value_state);
+            if state.is_finished {
+                return;
+            }
+            if let Err(err) = state.record(&val) {
+                state.errors.push(err);
+            }
+        },
+        move || {
+            let mut state = lock_state(&completion_state);
+            state.is_finished = true;
+            if let Err(err) = state.finish_file() {
+                state.errors.push(err);
+            }
+        },
+    );
+
+    Ok(Recording {
+        state,
+        subscription: Some(subscription),
+    })
+}
+
+#[cfg(feature = "csv")]
+struct CsvFormat {
+    writer: csv::Writer<File>,
+    has_header: bool,
+}
+
+#[cfg(feature = "csv")]
+impl<T: serde::Serialize> RecordingFormat<T> for CsvFormat {
+    fn write(&mut self, timestamp: SystemTime, value: &Arc<T>) -> io::Result<()> {
+        if !self.has_header {
+            let mut header = csv_header(&**value)?;
+            header.insert(0, "timestamp".to_string());
+            self.writer.write_record(&header)?;
+            self.has_header = true;
+        }
+        let seconds = timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        self.writer.write_field(seconds.to_string())?;
+        self.writer.serialize(&**value)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Returns the column names for a value. The csv crate only knows them for structs, so other
+/// values get generic names.
+#[cfg(feature = "csv")]
+fn csv_header<T: serde::Serialize>(value: &T) -> io::Result<Vec<String>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.serialize(value)?;
+    let data = writer.into_inner().map_err(|err| err.into_error())?;
+    let records = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(&data[..])
+        .into_records()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The writer only adds a header row for structs.
+    if records.len() == 2 {
+        return Ok(records[0].iter().map(|name| name.to_string()).collect());
+    }
+    Ok(match records.first().map_or(0, |record| record.len()) {
+        1 => vec!["value".to_string()],
+        count => (0..count).map(|index| format!("value_{}", index)).collect(),
+    })
+}
+
+/// Records every value of the stream to CSV files, one row per value. Structs get one column per
+/// field, named after the field. Other values are written to columns named `value` (or
+/// `value_0`, `value_1` and so on for tuples). Nested structs and collections are not supported
+/// by the CSV format, and are reported through `Recording::take_errors`.
+///
+/// Requires the `csv` feature.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::recording::{self, RotationPolicy};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Reading {
+///     sensor: &'static str,
+///     celsius: f64,
+/// }
+///
+/// let dir = std::env::temp_dir().join(format!("epoxy-recording-doc-{}", std::process::id()));
+/// std::fs::create_dir_all(&dir).unwrap();
+///
+/// let stream_host: epoxy_streams::Sink<Reading> = epoxy_streams::Sink::new();
+/// let rotation = RotationPolicy {
+///     max_rows: Some(2),
+///     ..Default::default()
+/// };
+/// let path = dir.join("readings.csv");
+/// let recording = recording::record_csv(&stream_host.get_stream(), &path, rotation).unwrap();
+///
+/// stream_host.emit(Reading { sensor: "attic", celsius: 31.5 });
+/// stream_host.emit(Reading { sensor: "cellar", celsius: 12.0 });
+/// stream_host.emit(Reading { sensor: "attic", celsius: 32.0 });
+/// let files = recording.finish().unwrap();
+/// assert_eq!(files, vec![path, dir.join("readings.1.csv")]);
+///
+/// let first = std::fs::read_to_string(&files[0]).unwrap();
+/// let lines: Vec<&str> = first.lines().collect();
+/// assert_eq!(lines[0], "timestamp,sensor,celsius");
+/// assert!(lines[2].ends_with(",cellar,12.0"));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[cfg(feature = "csv")]
+pub fn record_csv<T, P>(
+    stream: &Stream<T>,
+    path: P,
+    rotation: RotationPolicy,
+) -> io::Result<Recording<T>>
+where
+    T: serde::Serialize + Send + Sync + 'static,
+    P: AsRef<Path>,
+{
+    record(
+        stream,
+        path.as_ref().to_path_buf(),
+        rotation,
+        Box::new(|file| {
+            Box::new(CsvFormat {
+                writer: csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(file),
+                has_header: false,
+            })
+        }),
+    )
+}
+
+/// Converts buffered values into the columns of a Parquet file, see `record_parquet`.
+#[cfg(feature = "parquet")]
+type ToRecordBatch<T> =
+    dyn Fn(&[Arc<T>]) -> Result<arrow_array::RecordBatch, arrow_schema::ArrowError> + Send + Sync;
+
+// Values are converted and written in batches, since Parquet stores data by column.
+#[cfg(feature = "parquet")]
+const PARQUET_BATCH_SIZE: usize = 1024;
+
+#[cfg(feature = "parquet")]
+struct ParquetFormat<T> {
+    file: Option<File>,
+    writer: Option<parquet::arrow::ArrowWriter<File>>,
+    timestamps: Vec<i64>,
+    values: Vec<Arc<T>>,
+    to_batch: Arc<ToRecordBatch<T>>,
+}
+
+#[cfg(feature = "parquet")]
+impl<T> ParquetFormat<T> {
+    fn write_buffered(&mut self) -> io::Result<()> {
+        use arrow_array::{ArrayRef, RecordBatch, TimestampMicrosecondArray};
+        use arrow_schema::{DataType, Field, Schema, TimeUnit};
+
+        if self.values.is_empty() {
+            return Ok(());
+        }
+        let values = (self.to_batch)(&self.values).map_err(io::Error::other)?;
+        let timestamps: ArrayRef = Arc::new(TimestampMicrosecondArray::from(mem::take(
+            &mut self.timestamps,
+        )));
+        self.values.clear();
+
+        let mut fields = vec![Arc::new(Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            false,
+        ))];
+        fields.extend(values.schema().fields().iter().cloned());
+        let mut columns = vec![timestamps];
+        columns.extend(values.columns().iter().cloned());
+        let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .map_err(io::Error::other)?;
+
+        if self.writer.is_none() {
+            let file = self.file.take().unwrap();
+            let writer = parquet::arrow::ArrowWriter::try_new(file, batch.schema(), None)
+                .map_err(io::Error::other)?;
+            self.writer = Some(writer);
+        }
+        self.writer
+            .as_mut()
+            .unwrap()
+            .write(&batch)
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl<T: Send + Sync> RecordingFormat<T> for ParquetFormat<T> {
+    fn write(&mut self, timestamp: SystemTime, value: &Arc<T>) -> io::Result<()> {
+        let micros = timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        self.timestamps.push(micros as i64);
+        self.values.push(value.clone());
+        if self.values.len() >= PARQUET_BATCH_SIZE {
+            self.write_buffered()?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.write_buffered()?;
+        match self.writer.take() {
+            Some(writer) => writer.close().map(|_| ()).map_err(io::Error::other),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Records every value of the stream to Parquet files. Parquet files are strongly typed, so
+/// `to_batch` converts a batch of values into an Arrow `RecordBatch` with one column per field.
+/// A `timestamp` column is added in front of those columns. Every batch must have the same
+/// schema. The `arrow_array` and `arrow_schema` crates are re-exported from this module, so the
+/// conversion can be written without depending on a matching version of them.
+///
+/// Requires the `parquet` feature.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::recording::arrow_array::{ArrayRef, Float64Array, RecordBatch};
+/// use epoxy_streams::recording::{self, RotationPolicy};
+/// use std::sync::Arc;
+///
+/// let name = format!("epoxy-parquet-doc-{}.parquet", std::process::id());
+/// let path = std::env::temp_dir().join(name);
+/// let stream_host: epoxy_streams::Sink<f64> = epoxy_streams::Sink::new();
+/// let recording = recording::record_parquet(
+///     &stream_host.get_stream(),
+///     &path,
+///     RotationPolicy::default(),
+///     |values| {
+///         let prices = Float64Array::from_iter_values(values.iter().map(|price| **price));
+///         let prices: ArrayRef = Arc::new(prices);
+///         RecordBatch::try_from_iter([("price", prices)])
+///     },
+/// )
+/// .unwrap();
+///
+/// stream_host.emit(101.5);
+/// stream_host.emit(102.25);
+/// assert_eq!(recording.finish().unwrap(), vec![path.clone()]);
+/// assert!(std::fs::metadata(&path).unwrap().len() > 0);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[cfg(feature = "parquet")]
+pub fn record_parquet<T, P, F>(
+    stream: &Stream<T>,
+    path: P,
+    rotation: RotationPolicy,
+    to_batch: F,
+) -> io::Result<Recording<T>>
+where
+    T: Send + Sync + 'static,
+    P: AsRef<Path>,
+    F: Fn(&[Arc<T>]) -> Result<arrow_array::RecordBatch, arrow_schema::ArrowError>,
+    F: Send,
+    F: Sync,
+    F: 'static,
+{
+    let to_batch: Arc<ToRecordBatch<T>> = Arc::new(to_batch);
+    record(
+        stream,
+        path.as_ref().to_path_buf(),
+        rotation,
+        Box::new(move |file| {
+            Box::new(ParquetFormat {
+                file: Some(file),
+                writer: None,
+                timestamps: vec![],
+                values: vec![],
+                to_batch: to_batch.clone(),
+            })
+        }),
+    )
+}
@@ -0,0 +1,268 @@
+use super::config::default_scheduler;
+use super::{RequestError, Sink, Stream, Subscription};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// A request as seen by the Responder. The `id` correlates it with its response, and must be
+/// passed to `Responder::respond`.
+pub struct Request<Req> {
+    pub id: u64,
+    pub body: Arc<Req>,
+}
+
+struct Response<Resp> {
+    id: u64,
+    body: Arc<Resp>,
+}
+
+struct PendingState<Resp> {
+    result: Option<Result<Arc<Resp>, RequestError>>,
+    waker: Option<Waker>,
+}
+
+struct PendingSlot<Resp> {
+    state: Mutex<PendingState<Resp>>,
+    is_ready: Condvar,
+}
+
+impl<Resp> PendingSlot<Resp> {
+    fn new() -> PendingSlot<Resp> {
+        PendingSlot {
+            state: Mutex::new(PendingState {
+                result: None,
+                waker: None,
+            }),
+            is_ready: Condvar::new(),
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, PendingState<Resp>> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(err) => panic!("Request mutex poisoned: {}", err),
+        }
+    }
+
+    fn resolve(&self, result: Result<Arc<Resp>, RequestError>) {
+        let waker = {
+            let mut state = self.lock_state();
+            state.result = Some(result);
+            state.waker.take()
+        };
+        self.is_ready.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+type PendingMap<Resp> = Mutex<HashMap<u64, Arc<PendingSlot<Resp>>>>;
+
+fn take_pending<Resp>(pending: &PendingMap<Resp>, id: u64) -> Option<Arc<PendingSlot<Resp>>> {
+    match pending.lock() {
+        Ok(mut pending) => pending.remove(&id),
+        Err(err) => panic!("Request mutex poisoned: {}", err),
+    }
+}
+
+/// The eventual response to a request made with `Requester::request`. Block on it with `wait`, or
+/// `.await` it from async code.
+pub struct PendingResponse<Resp> {
+    slot: Arc<PendingSlot<Resp>>,
+}
+
+impl<Resp> PendingResponse<Resp> {
+    /// Blocks the current thread until the response arrives or the request fails.
+    pub fn wait(self) -> Result<Arc<Resp>, RequestError> {
+        let mut state = self.slot.lock_state();
+        loop {
+            if let Some(result) = state.result.take() {
+                return result;
+            }
+            state = match self.slot.is_ready.wait(state) {
+                Ok(state) => state,
+                Err(err) => panic!("Request mutex poisoned: {}", err),
+            };
+        }
+    }
+}
+
+impl<Resp> Future for PendingResponse<Resp> {
+    type Output = Result<Arc<Resp>, RequestError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.lock_state();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct RequesterImpl<Req, Resp> {
+    next_id: AtomicU64,
+    timeout: Duration,
+    requests: Sink<Request<Req>>,
+    pending: Arc<PendingMap<Resp>>,
+
+    #[allow(dead_code)]
+    subscription: Subscription<Response<Resp>>,
+}
+
+/// Sends requests to a Responder and hands back their responses. Created with `request_channel`.
+///
+/// Handles are cheap to clone, and all clones share the same Responder.
+pub struct Requester<Req, Resp> {
+    pointer: Arc<RequesterImpl<Req, Resp>>,
+}
+
+impl<Req, Resp> Clone for Requester<Req, Resp> {
+    fn clone(&self) -> Self {
+        Requester {
+            pointer: Arc::clone(&self.pointer),
+        }
+    }
+}
+
+impl<Req, Resp> Requester<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + Sync + 'static,
+{
+    /// Sends a request and returns a handle to its response. The request fails immediately with
+    /// `RequestError::NoResponder` if nothing is subscribed to the Responder's stream, or with
+    /// `RequestError::TimedOut` if the response does not arrive within the channel's timeout.
+    pub fn request(&self, body: Req) -> PendingResponse<Resp> {
+        let pointer = &self.pointer;
+        let id = pointer.next_id.fetch_add(1, Ordering::Relaxed);
+        let slot = Arc::new(PendingSlot::new());
+        match pointer.pending.lock() {
+            Ok(mut pending) => pending.insert(id, slot.clone()),
+            Err(err) => panic!("Request mutex poisoned: {}", err),
+        };
+
+        let weak_pending = Arc::downgrade(&pointer.pending);
+        default_scheduler().schedule_after(
+            pointer.timeout,
+            Box::new(move || {
+                if let Some(pending) = weak_pending.upgrade() {
+                    if let Some(slot) = take_pending(&pending, id) {
+                        slot.resolve(Err(RequestError::TimedOut));
+                    }
+                }
+            }),
+        );
+
+        let report = pointer.requests.emit_counted(Request {
+            id,
+            body: Arc::new(body),
+        });
+        if report.subscribers_reached == 0 {
+            if let Some(slot) = take_pending(&pointer.pending, id) {
+                slot.resolve(Err(RequestError::NoResponder));
+            }
+        }
+        PendingResponse { slot }
+    }
+}
+
+/// Receives the requests sent by a Requester and answers them. Created with `request_channel`.
+pub struct Responder<Req, Resp> {
+    requests: Stream<Request<Req>>,
+    responses: Arc<Sink<Response<Resp>>>,
+}
+
+impl<Req, Resp> Responder<Req, Resp>
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + Sync + 'static,
+{
+    /// Returns the stream of incoming requests.
+    pub fn get_stream(&self) -> Stream<Request<Req>> {
+        self.requests.clone()
+    }
+
+    /// Answers the request with the given id. Responses to requests that have already timed out
+    /// are ignored.
+    pub fn respond(&self, request_id: u64, response: Resp) {
+        self.responses.emit(Response {
+            id: request_id,
+            body: Arc::new(response),
+        });
+    }
+
+    /// Answers every incoming request by calling the handler, until the returned subscription is
+    /// dropped.
+    pub fn serve<F>(&self, handler: F) -> Subscription<Request<Req>>
+    where
+        F: Fn(&Req) -> Resp + Send + Sync + 'static,
+    {
+        let responses = self.responses.clone();
+        self.requests.subscribe(move |request| {
+            responses.emit(Response {
+                id: request.id,
+                body: Arc::new(handler(&request.body)),
+            });
+        })
+    }
+}
+
+/// Creates a connected Requester and Responder. Requests that are not answered within `timeout`
+/// fail with `RequestError::TimedOut`.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{request_channel, RequestError};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let (requester, responder) = request_channel(Duration::from_millis(50));
+/// assert_eq!(requester.request(21).wait(), Err(RequestError::NoResponder));
+///
+/// let serving = responder.serve(|val: &i32| val * 2);
+/// assert_eq!(requester.request(21).wait(), Ok(Arc::new(42)));
+/// drop(serving);
+///
+/// // A responder that never answers.
+/// let _ignoring = responder.get_stream().subscribe(|_| {});
+/// assert_eq!(requester.request(21).wait(), Err(RequestError::TimedOut));
+/// ```
+pub fn request_channel<Req, Resp>(timeout: Duration) -> (Requester<Req, Resp>, Responder<Req, Resp>)
+where
+    Req: Send + Sync + 'static,
+    Resp: Send + Sync + 'static,
+{
+    let requests = Sink::new();
+    let responses: Arc<Sink<Response<Resp>>> = Arc::new(Sink::new());
+    let pending: Arc<PendingMap<Resp>> = Arc::new(Mutex::new(HashMap::new()));
+
+    let subscription_pending = pending.clone();
+    let subscription = responses.get_stream().subscribe(move |response| {
+        if let Some(slot) = take_pending(&subscription_pending, response.id) {
+            slot.resolve(Ok(response.body.clone()));
+        }
+    });
+
+    let responder = Responder {
+        requests: requests.get_stream(),
+        responses,
+    };
+    let requester = Requester {
+        pointer: Arc::new(RequesterImpl {
+            next_id: AtomicU64::new(0),
+            timeout,
+            requests,
+            pending,
+            subscription,
+        }),
+    };
+    (requester, responder)
+}
@@ -0,0 +1,405 @@
+use super::{ReactiveValue, ReadonlyReactiveValue, Stream, Subscription, WriteableReactiveValue};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long `Stream::retry_with_backoff` waits between attempts, and how many attempts it makes
+/// before giving up.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::exponential(Duration::from_millis(100), Duration::from_secs(10))
+///     .with_max_attempts(5);
+/// assert_eq!(policy.delay_for_attempt(1), Duration::from_millis(100));
+/// assert_eq!(policy.delay_for_attempt(3), Duration::from_millis(400));
+/// assert_eq!(policy.delay_for_attempt(20), Duration::from_secs(10));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    multiplier: u32,
+    jitter: bool,
+    max_attempts: u32,
+}
+
+impl RetryPolicy {
+    /// Waits the same amount of time before every attempt. Retries 3 times by default.
+    pub fn fixed(delay: Duration) -> RetryPolicy {
+        RetryPolicy {
+            initial_delay: delay,
+            max_delay: delay,
+            multiplier: 1,
+            jitter: false,
+            max_attempts: 3,
+        }
+    }
+
+    /// Doubles the delay after every failed attempt, up to `max_delay`. Retries 3 times by
+    /// default.
+    pub fn exponential(initial_delay: Duration, max_delay: Duration) -> RetryPolicy {
+        RetryPolicy {
+            initial_delay,
+            max_delay,
+            multiplier: 2,
+            jitter: false,
+            max_attempts: 3,
+        }
+    }
+
+    /// Sets the number of times the source will be recreated before the error is passed along.
+    pub fn with_max_attempts(self, max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            ..self
+        }
+    }
+
+    /// Picks a random delay between zero and the delay the policy would otherwise use, so that
+    /// many clients retrying at once do not all hit the server at the same moment.
+    pub fn with_jitter(self) -> RetryPolicy {
+        RetryPolicy {
+            jitter: true,
+            ..self
+        }
+    }
+
+    /// The maximum number of times the source will be recreated.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the delay before the given attempt (starting at 1), ignoring jitter.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let mut delay = self.initial_delay;
+        for _ in 1..attempt {
+            if delay >= self.max_delay {
+                break;
+            }
+            delay = delay.saturating_mul(self.multiplier);
+        }
+        delay.min(self.max_delay)
+    }
+
+    fn next_delay(&self, attempt: u32) -> Duration {
+        let delay = self.delay_for_attempt(attempt);
+        if !self.jitter {
+            return delay;
+        }
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(attempt);
+        let fraction = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+        delay.mul_f64(fraction)
+    }
+}
+
+struct RetryFields<T, E> {
+    generation: u64,
+    attempts: u32,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Result<T, E>>>,
+}
+
+/// The state of a circuit breaker created with `Stream::circuit_breaker`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Values flow through the breaker normally.
+    Closed,
+
+    /// Too many errors occurred. All values are dropped until the cooldown has elapsed.
+    Open,
+
+    /// The cooldown has elapsed. The next value decides whether the breaker closes again (if it
+    /// is Ok) or re-opens (if it is an error).
+    HalfOpen,
+}
+
+/// A stream guarded by a circuit breaker, along with the current state of that breaker.
+pub struct CircuitBreaker<T, E> {
+    stream: Stream<Result<T, E>>,
+    state: ReadonlyReactiveValue<BreakerState>,
+}
+
+impl<T: 'static + Send + Sync, E: 'static + Send + Sync> CircuitBreaker<T, E> {
+    /// Returns the stream of values that made it through the breaker.
+    pub fn get_stream(&self) -> Stream<Result<T, E>> {
+        self.stream.clone()
+    }
+
+    /// Returns the state of the breaker, which changes whenever the breaker opens or closes.
+    pub fn state(&self) -> ReadonlyReactiveValue<BreakerState> {
+        self.state.clone()
+    }
+}
+
+struct CircuitBreakerFields<T, E> {
+    state: BreakerState,
+    consecutive_errors: u32,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Result<T, E>>>,
+}
+
+impl<T, E> Stream<Result<T, E>>
+where
+    T: 'static + Send + Sync,
+    E: 'static + Send + Sync,
+{
+    /// Returns a stream of the values emitted by the stream that `factory` creates. When that
+    /// stream emits an error it is discarded and, after waiting for the delay specified by the
+    /// policy, `factory` is called again to create a replacement. Once the policy runs out of
+    /// attempts the error is emitted and the returned stream completes. Attempts are counted
+    /// since the last successful value.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, RetryPolicy, Sink, Stream};
+    /// use std::sync::{Arc, Mutex};
+    /// use std::time::Duration;
+    ///
+    /// let connections: Arc<Mutex<Vec<Sink<Result<i32, String>>>>> = Default::default();
+    /// let connections_write = connections.clone();
+    ///
+    /// let policy = RetryPolicy::fixed(Duration::from_millis(10));
+    /// let stream = Stream::retry_with_backoff(policy, move || {
+    ///     let sink = Sink::new();
+    ///     let stream = sink.get_stream();
+    ///     connections_write.lock().unwrap().push(sink);
+    ///     stream
+    /// });
+    /// let cache = ReactiveCache::from_stream(stream);
+    ///
+    /// connections.lock().unwrap()[0].emit(Ok(1));
+    /// connections.lock().unwrap()[0].emit(Err("Connection reset".to_string()));
+    /// std::thread::sleep(Duration::from_millis(200));
+    ///
+    /// assert_eq!(connections.lock().unwrap().len(), 2);
+    /// connections.lock().unwrap()[1].emit(Ok(2));
+    /// assert_eq!(cache.get_cloned(), vec![Ok(1), Ok(2)]);
+    /// ```
+    pub fn retry_with_backoff<F>(policy: RetryPolicy, factory: F) -> Stream<Result<T, E>>
+    where
+        F: Fn() -> Stream<Result<T, E>>,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let retrying_stream = Stream::new_with_fields(RetryFields::<T, E> {
+            generation: 0,
+            attempts: 0,
+            subscription: None,
+        });
+        connect_retry_source(&retrying_stream, Arc::new(factory), policy);
+        retrying_stream
+    }
+
+    /// Guards the stream with a circuit breaker. Values pass through normally until
+    /// `error_threshold` errors are emitted in a row, at which point the breaker opens and drops
+    /// every value until `cooldown` has elapsed. After that the breaker lets a single probe value
+    /// through: an Ok value closes the breaker again, while another error re-opens it for another
+    /// cooldown period.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{BreakerState, ReactiveCache, ReactiveValue, Sink};
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: Sink<Result<i32, &'static str>> = Sink::new();
+    /// let breaker = stream_host
+    ///     .get_stream()
+    ///     .circuit_breaker(2, Duration::from_millis(50));
+    /// let cache = ReactiveCache::from_stream(breaker.get_stream());
+    ///
+    /// stream_host.emit(Err("timeout"));
+    /// stream_host.emit(Err("timeout"));
+    /// assert_eq!(*breaker.state().get(), BreakerState::Open);
+    ///
+    /// stream_host.emit(Ok(1)); // Dropped, the breaker is open.
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(*breaker.state().get(), BreakerState::HalfOpen);
+    ///
+    /// stream_host.emit(Ok(2));
+    /// assert_eq!(*breaker.state().get(), BreakerState::Closed);
+    /// assert_eq!(cache.get_cloned(), vec![Err("timeout"), Err("timeout"), Ok(2)]);
+    /// ```
+    pub fn circuit_breaker(
+        &self,
+        error_threshold: u32,
+        cooldown: Duration,
+    ) -> CircuitBreaker<T, E> {
+        let state: WriteableReactiveValue<BreakerState> =
+            <dyn ReactiveValue<BreakerState>>::new_distinct(BreakerState::Closed);
+        let derived_stream = self.derive_with_fields(CircuitBreakerFields::<T, E> {
+            state: BreakerState::Closed,
+            consecutive_errors: 0,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+        let scheduler = derived_stream.scheduler();
+        let state_write = state.clone();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+
+                let mut should_forward = false;
+                let mut new_state = None;
+                stream_ref.mutate_extra_fields(|fields: &mut CircuitBreakerFields<T, E>| {
+                    match (fields.state, val.is_ok()) {
+                        (BreakerState::Open, _) => return,
+                        (_, true) => {
+                            fields.consecutive_errors = 0;
+                            fields.state = BreakerState::Closed;
+                        }
+                        (BreakerState::HalfOpen, false) => fields.state = BreakerState::Open,
+                        (BreakerState::Closed, false) => {
+                            fields.consecutive_errors += 1;
+                            if fields.consecutive_errors >= error_threshold {
+                                fields.state = BreakerState::Open;
+                            }
+                        }
+                    }
+                    should_forward = true;
+                    new_state = Some(fields.state);
+                });
+                if !should_forward {
+                    return;
+                }
+
+                if new_state == Some(BreakerState::Open) {
+                    let weak_timer_ref = Arc::downgrade(&stream_ref.pointer);
+                    let state_write = state_write.clone();
+                    scheduler.schedule_after(
+                        cooldown,
+                        Box::new(move || {
+                            let stream_ref = match weak_timer_ref.upgrade() {
+                                Some(pointer) => Stream { pointer },
+                                None => return,
+                            };
+                            stream_ref.mutate_extra_fields(
+                                |fields: &mut CircuitBreakerFields<T, E>| {
+                                    fields.state = BreakerState::HalfOpen;
+                                    fields.consecutive_errors = 0;
+                                },
+                            );
+                            state_write.set(BreakerState::HalfOpen);
+                        }),
+                    );
+                }
+                if let Some(new_state) = new_state {
+                    state_write.set(new_state);
+                }
+                stream_ref.emit_rc(val);
+            },
+            move || completion_stream_ref.complete(),
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut CircuitBreakerFields<T, E>| {
+            fields.subscription = Some(subscription);
+        });
+
+        CircuitBreaker {
+            stream: derived_stream,
+            state: state.as_readonly(),
+        }
+    }
+}
+
+fn connect_retry_source<T, E, F>(
+    retrying_stream: &Stream<Result<T, E>>,
+    factory: Arc<F>,
+    policy: RetryPolicy,
+) where
+    T: 'static + Send + Sync,
+    E: 'static + Send + Sync,
+    F: Fn() -> Stream<Result<T, E>> + Send + Sync + 'static,
+{
+    let mut generation = 0;
+    retrying_stream.mutate_extra_fields(|fields: &mut RetryFields<T, E>| {
+        fields.generation += 1;
+        generation = fields.generation;
+    });
+
+    let source = factory();
+    let weak_stream_ref = Arc::downgrade(&retrying_stream.pointer);
+    let weak_completion_ref = Arc::downgrade(&retrying_stream.pointer);
+    let scheduler = retrying_stream.scheduler();
+
+    let subscription = source.subscribe_with_completion(
+        move |val| {
+            let stream_ref = match weak_stream_ref.upgrade() {
+                Some(pointer) => Stream { pointer },
+                None => return,
+            };
+
+            let mut is_current = false;
+            let mut retry_delay = None;
+            let mut gave_up = false;
+            stream_ref.mutate_extra_fields(|fields: &mut RetryFields<T, E>| {
+                if fields.generation != generation {
+                    return;
+                }
+                is_current = true;
+                match *val {
+                    Ok(_) => fields.attempts = 0,
+                    Err(_) if fields.attempts < policy.max_attempts => {
+                        fields.attempts += 1;
+                        // Ignore anything else the failed source emits while we wait.
+                        fields.generation += 1;
+                        retry_delay = Some(policy.next_delay(fields.attempts));
+                    }
+                    Err(_) => gave_up = true,
+                }
+            });
+            if !is_current {
+                return;
+            }
+
+            if let Some(delay) = retry_delay {
+                let weak_retry_ref = Arc::downgrade(&stream_ref.pointer);
+                let factory = factory.clone();
+                scheduler.schedule_after(
+                    delay,
+                    Box::new(move || {
+                        if let Some(pointer) = weak_retry_ref.upgrade() {
+                            connect_retry_source(&Stream { pointer }, factory, policy);
+                        }
+                    }),
+                );
+                return;
+            }
+
+            stream_ref.emit_rc(val);
+            if gave_up {
+                stream_ref.complete();
+            }
+        },
+        move || {
+            if let Some(pointer) = weak_completion_ref.upgrade() {
+                let stream_ref = Stream { pointer };
+                let is_current = stream_ref.read_extra_fields(|fields: &RetryFields<T, E>| {
+                    fields.generation == generation
+                });
+                if is_current {
+                    stream_ref.complete();
+                }
+            }
+        },
+    );
+
+    // The previous source is unsubscribed outside of the stream lock.
+    let mut previous_subscription = Some(subscription);
+    retrying_stream.mutate_extra_fields(|fields: &mut RetryFields<T, E>| {
+        std::mem::swap(&mut fields.subscription, &mut previous_subscription);
+    });
+    drop(previous_subscription);
+}
@@ -0,0 +1,209 @@
+use super::sync::Mutex;
+use super::{Stream, Subscription};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+/// A payload emitted by a `CountedBroadcast` that is still alive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetainedPayload {
+    /// The position of the payload in the stream, starting at 0.
+    pub sequence: u64,
+
+    /// The number of Arc clones of the payload that are still alive.
+    pub references: usize,
+}
+
+/// A snapshot of the payloads that subscribers of a `CountedBroadcast` are holding on to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// Every payload that is still alive, oldest first.
+    pub payloads: Vec<RetainedPayload>,
+}
+
+impl RetentionReport {
+    /// Returns the number of payloads that are still alive.
+    pub fn retained_payloads(&self) -> usize {
+        self.payloads.len()
+    }
+
+    /// Returns the total number of Arc clones across all payloads that are still alive.
+    pub fn total_references(&self) -> usize {
+        self.payloads.iter().map(|payload| payload.references).sum()
+    }
+}
+
+type WatermarkCallback = Arc<dyn Fn(&RetentionReport) + Send + Sync>;
+
+struct Watermark {
+    max_retained: usize,
+    callback: WatermarkCallback,
+    is_exceeded: bool,
+}
+
+struct RetentionState<T> {
+    payloads: Vec<(u64, Weak<T>)>,
+    next_sequence: u64,
+    watermarks: Vec<Watermark>,
+}
+
+impl<T> RetentionState<T> {
+    fn report(&mut self) -> RetentionReport {
+        self.payloads
+            .retain(|(_, payload)| payload.strong_count() > 0);
+        RetentionReport {
+            payloads: self
+                .payloads
+                .iter()
+                .map(|(sequence, payload)| RetainedPayload {
+                    sequence: *sequence,
+                    references: payload.strong_count(),
+                })
+                .collect(),
+        }
+    }
+}
+
+struct CountedBroadcastFields<T> {
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+/// A stream whose payloads are tracked after they have been emitted, see
+/// `Stream::broadcast_counted`.
+pub struct CountedBroadcast<T> {
+    stream: Stream<T>,
+    state: Arc<Mutex<RetentionState<T>>>,
+}
+
+impl<T: 'static> CountedBroadcast<T> {
+    /// Returns the stream to subscribe to. Values are the same Arcs that the source emitted.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.stream.clone()
+    }
+
+    /// Returns the payloads that are currently alive, and how many references each one has.
+    pub fn retention(&self) -> RetentionReport {
+        self.state.lock().report()
+    }
+
+    /// Calls `callback` whenever the number of payloads that are still alive rises above
+    /// `max_retained`. It is not called again until the number has dropped back down to the
+    /// threshold. Retention is checked right before each new value is emitted.
+    pub fn on_retention_exceeds<F>(&self, max_retained: usize, callback: F)
+    where
+        F: Fn(&RetentionReport),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.state.lock().watermarks.push(Watermark {
+            max_retained,
+            callback: Arc::new(callback),
+            is_exceeded: false,
+        });
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Passes every value through without copying it, while keeping track of how long
+    /// subscribers hold on to each payload. This helps find subscribers that keep giant buffers
+    /// alive long after they were emitted. Tracking uses weak references, so it never extends the
+    /// life of a payload.
+    ///
+    /// A payload's references are only counted once its own emission has finished, so the
+    /// references held by the emitting call stack are not included.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let stream_host: epoxy_streams::Sink<Vec<u8>> = epoxy_streams::Sink::new();
+    /// let broadcast = stream_host.get_stream().broadcast_counted();
+    ///
+    /// // This subscriber forgets to let go of its buffers.
+    /// let hoard = Arc::new(Mutex::new(vec![]));
+    /// let hoard_ref = hoard.clone();
+    /// let _subscription = broadcast
+    ///     .get_stream()
+    ///     .subscribe(move |buffer| hoard_ref.lock().unwrap().push(buffer));
+    ///
+    /// let alerts = Arc::new(Mutex::new(vec![]));
+    /// let alerts_ref = alerts.clone();
+    /// broadcast.on_retention_exceeds(2, move |report| {
+    ///     alerts_ref.lock().unwrap().push(report.retained_payloads());
+    /// });
+    ///
+    /// for _ in 0..4 {
+    ///     stream_host.emit(vec![0; 1024]);
+    /// }
+    /// assert_eq!(*alerts.lock().unwrap(), vec![3]);
+    /// assert_eq!(broadcast.retention().retained_payloads(), 4);
+    ///
+    /// hoard.lock().unwrap().clear();
+    /// assert_eq!(broadcast.retention().retained_payloads(), 0);
+    /// ```
+    pub fn broadcast_counted(&self) -> CountedBroadcast<T> {
+        let state = Arc::new(Mutex::new(RetentionState {
+            payloads: Vec::new(),
+            next_sequence: 0,
+            watermarks: Vec::new(),
+        }));
+        let derived_stream =
+            self.derive_with_fields(CountedBroadcastFields::<T> { subscription: None });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+
+        let subscription_state = state.clone();
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                check_watermarks(&subscription_state);
+
+                {
+                    let mut state = subscription_state.lock();
+                    let sequence = state.next_sequence;
+                    state.next_sequence += 1;
+                    state.payloads.push((sequence, Arc::downgrade(&val)));
+                }
+                stream_ref.emit_rc(val);
+            },
+            move || completion_stream_ref.complete(),
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut CountedBroadcastFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        CountedBroadcast {
+            stream: derived_stream,
+            state,
+        }
+    }
+}
+
+fn check_watermarks<T>(state: &Mutex<RetentionState<T>>) {
+    // Callbacks run after the lock is released, so that they can inspect the broadcast.
+    let (report, triggered) = {
+        let mut state = state.lock();
+        if state.watermarks.is_empty() {
+            return;
+        }
+        let report = state.report();
+        let retained = report.retained_payloads();
+        let mut triggered = Vec::new();
+        for watermark in state.watermarks.iter_mut() {
+            let is_exceeded = retained > watermark.max_retained;
+            if is_exceeded && !watermark.is_exceeded {
+                triggered.push(watermark.callback.clone());
+            }
+            watermark.is_exceeded = is_exceeded;
+        }
+        (report, triggered)
+    };
+    for callback in triggered {
+        callback(&report);
+    }
+}
@@ -0,0 +1,182 @@
+use super::streams::StreamImpl;
+use super::sync;
+use super::{Stream, Subscription};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+type WeakRoute<V> = Weak<sync::Mutex<StreamImpl<V>>>;
+
+struct RouterState<K, V> {
+    routes: Mutex<Routes<K, V>>,
+    subscription: Mutex<Option<Subscription<(K, V)>>>,
+}
+
+struct Routes<K, V> {
+    streams: HashMap<K, WeakRoute<V>>,
+    is_complete: bool,
+}
+
+struct RouteFields<K, V> {
+    #[allow(dead_code)]
+    router: Arc<RouterState<K, V>>,
+}
+
+/// Splits a stream of keyed values into one stream per key. Created with
+/// `Stream::route_by_key`.
+///
+/// Each value is looked up by its key and delivered only to that key's stream, so routing to
+/// hundreds of handlers costs one map lookup per value instead of one `filter` call per handler.
+/// Streams for a key are created the first time they are requested, and are removed from the
+/// router once nothing holds on to them anymore. Values whose key has no stream are discarded.
+///
+/// The router stays connected to its source for as long as the handle, or any of the streams it
+/// returned, are alive.
+pub struct RouterHandle<K, V> {
+    state: Arc<RouterState<K, V>>,
+}
+
+impl<K, V> Clone for RouterHandle<K, V> {
+    fn clone(&self) -> Self {
+        RouterHandle {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<K, V> RouterState<K, V> {
+    fn lock_routes(&self) -> MutexGuard<'_, Routes<K, V>> {
+        match self.routes.lock() {
+            Ok(routes) => routes,
+            Err(err) => panic!("Router mutex poisoned: {}", err),
+        }
+    }
+}
+
+impl<K, V> RouterHandle<K, V>
+where
+    K: 'static + Send + Sync + Clone + Eq + Hash,
+    V: 'static + Send + Sync + Clone,
+{
+    /// Returns the stream of values routed to the given key. Every call with the same key returns
+    /// the same stream, as long as it is still alive. If the source stream has completed the
+    /// returned stream is already complete.
+    pub fn route(&self, key: K) -> Stream<V> {
+        let mut routes = self.state.lock_routes();
+        if let Some(pointer) = routes.streams.get(&key).and_then(Weak::upgrade) {
+            return Stream { pointer };
+        }
+
+        let stream = Stream::new_with_fields(RouteFields {
+            router: Arc::clone(&self.state),
+        });
+        if routes.is_complete {
+            stream.complete();
+        } else {
+            routes.streams.retain(|_, route| route.strong_count() > 0);
+            routes.streams.insert(key, Arc::downgrade(&stream.pointer));
+        }
+        stream
+    }
+
+    /// Returns the number of keys that currently have a live stream.
+    pub fn count_routes(&self) -> usize {
+        self.state
+            .lock_routes()
+            .streams
+            .values()
+            .filter(|route| route.strong_count() > 0)
+            .count()
+    }
+}
+
+impl<K, V> Stream<(K, V)>
+where
+    K: 'static + Send + Sync + Clone + Eq + Hash,
+    V: 'static + Send + Sync + Clone,
+{
+    /// Returns a router that delivers each value of this stream to the stream for its key. See
+    /// `RouterHandle` for details.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<(u16, String)> = epoxy_streams::Sink::new();
+    /// let router = stream_host.get_stream().route_by_key();
+    ///
+    /// let pings = ReactiveCache::from_stream(router.route(1));
+    /// let chats = ReactiveCache::from_stream(router.route(2));
+    ///
+    /// stream_host.emit((1, "ping".to_string()));
+    /// stream_host.emit((2, "hello".to_string()));
+    /// stream_host.emit((3, "unhandled".to_string()));
+    /// stream_host.emit((1, "ping again".to_string()));
+    ///
+    /// assert_eq!(pings.get_cloned(), vec!["ping".to_string(), "ping again".to_string()]);
+    /// assert_eq!(chats.get_cloned(), vec!["hello".to_string()]);
+    ///
+    /// drop(chats);
+    /// assert_eq!(router.count_routes(), 1);
+    /// ```
+    pub fn route_by_key(&self) -> RouterHandle<K, V> {
+        let state = Arc::new(RouterState {
+            routes: Mutex::new(Routes {
+                streams: HashMap::new(),
+                is_complete: false,
+            }),
+            subscription: Mutex::new(None),
+        });
+
+        // The subscription only holds weak references, since the router itself owns it.
+        let weak_state = Arc::downgrade(&state);
+        let weak_completion_state = Arc::downgrade(&state);
+        let subscription = self.subscribe_with_completion(
+            move |value| {
+                let state = match weak_state.upgrade() {
+                    Some(state) => state,
+                    None => return,
+                };
+                let route = {
+                    let mut routes = state.lock_routes();
+                    match routes.streams.get(&value.0).map(Weak::upgrade) {
+                        Some(Some(pointer)) => Some(Stream { pointer }),
+                        Some(None) => {
+                            routes.streams.remove(&value.0);
+                            None
+                        }
+                        None => None,
+                    }
+                };
+                if let Some(route) = route {
+                    route.emit_rc(Arc::new(value.1.clone()));
+                }
+            },
+            move || {
+                let state = match weak_completion_state.upgrade() {
+                    Some(state) => state,
+                    None => return,
+                };
+                let routes: Vec<Stream<V>> = {
+                    let mut routes = state.lock_routes();
+                    routes.is_complete = true;
+                    routes
+                        .streams
+                        .drain()
+                        .filter_map(|(_, route)| route.upgrade())
+                        .map(|pointer| Stream { pointer })
+                        .collect()
+                };
+                for route in routes {
+                    route.complete();
+                }
+            },
+        );
+
+        match state.subscription.lock() {
+            Ok(mut state_subscription) => *state_subscription = Some(subscription),
+            Err(err) => panic!("Router mutex poisoned: {}", err),
+        }
+        RouterHandle { state }
+    }
+}
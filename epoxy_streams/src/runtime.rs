@@ -0,0 +1,86 @@
+//! Control over when epoxy's deferred work runs. In manual mode every scheduled task, including
+//! timers, waits until the application explicitly runs it, and time only passes when the
+//! application says so. This makes whole applications deterministic under test, and lets game
+//! loops decide exactly when (and for how much simulated time) streams get to run.
+//!
+//! Values emitted on a Sink still reach their subscribers immediately, since that never depends
+//! on a scheduler. Only work that operators defer (debounces, delays, rate limits, timeouts,
+//! coalescing, etc) is affected.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::{runtime, ReactiveCache};
+//! use std::time::Duration;
+//!
+//! let runtime = runtime::manual();
+//! let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+//! let debounced = stream_host.get_stream().debounce(Duration::from_millis(100));
+//! let cache = ReactiveCache::from_stream(debounced);
+//!
+//! stream_host.emit(1);
+//! stream_host.emit(2);
+//! runtime.advance(Duration::from_millis(99));
+//! assert_eq!(cache.get_cloned(), Vec::<i32>::new());
+//!
+//! runtime.advance(Duration::from_millis(1));
+//! assert_eq!(cache.get_cloned(), vec![2]);
+//! assert_eq!(runtime.count_pending(), 0);
+//! ```
+use super::config;
+use super::scheduler::{ManualScheduler, Scheduler};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Installs a `ManualScheduler` as the default scheduler, and returns a handle for driving it.
+/// Streams that override their scheduler with `Stream::with_scheduler` are not affected. The
+/// default scheduler is reset (see `config::reset_default_scheduler`) when the handle is dropped.
+pub fn manual() -> ManualRuntime {
+    let scheduler = Arc::new(ManualScheduler::new());
+    config::set_default_scheduler(scheduler.clone());
+    ManualRuntime { scheduler }
+}
+
+/// Drives the scheduler installed by `manual`.
+pub struct ManualRuntime {
+    scheduler: Arc<ManualScheduler>,
+}
+
+impl ManualRuntime {
+    /// Runs every task that is due at the current virtual time, including tasks scheduled while
+    /// doing so, and returns how many ran.
+    pub fn run_until_idle(&self) -> usize {
+        self.scheduler.run_until_idle()
+    }
+
+    /// Moves virtual time forward, running timers as they become due. Returns how many tasks
+    /// ran.
+    pub fn advance(&self, duration: Duration) -> usize {
+        self.scheduler.advance(duration)
+    }
+
+    /// Returns the current virtual time.
+    pub fn now(&self) -> Instant {
+        self.scheduler.now()
+    }
+
+    /// Returns the number of tasks that have not run yet, including ones that are not due.
+    pub fn count_pending(&self) -> usize {
+        self.scheduler.count_pending()
+    }
+
+    /// Returns the underlying scheduler, for example to pass it to `Stream::with_scheduler`.
+    pub fn scheduler(&self) -> Arc<ManualScheduler> {
+        self.scheduler.clone()
+    }
+}
+
+impl Drop for ManualRuntime {
+    fn drop(&mut self) {
+        // Leave the default alone if the application has installed a different one since.
+        let default_scheduler = config::default_scheduler();
+        if Arc::as_ptr(&default_scheduler) as *const () == Arc::as_ptr(&self.scheduler) as *const ()
+        {
+            config::reset_default_scheduler();
+        }
+    }
+}
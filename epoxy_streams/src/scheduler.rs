@@ -0,0 +1,837 @@
+//! Schedulers decide where (and when) deferred work runs. Time-based operators like `debounce`
+//! hand their timers to a scheduler, so applications can control which threads epoxy uses.
+//!
+//! By default all scheduled work runs on a single background thread owned by epoxy. Use
+//! `epoxy_streams::config::set_default_scheduler` to change this for the whole application, or
+//! `Stream::with_scheduler` to override it for one particular pipeline.
+use super::config;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A unit of work that can be run by a Scheduler.
+pub type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// Runs deferred and delayed work on behalf of streams.
+pub trait Scheduler: Send + Sync {
+    /// Runs a task as soon as possible, but not on the current call stack.
+    fn schedule(&self, task: Task);
+
+    /// Runs a task once the given delay has elapsed.
+    fn schedule_after(&self, delay: Duration, task: Task);
+
+    /// Returns the current time as seen by this scheduler. Operators that measure time (such as
+    /// `rate_limit`) use this instead of `Instant::now`, so that schedulers with a virtual clock
+    /// (see `ManualScheduler`) control them completely. By default this is the time of the
+    /// application's clock, see `config::set_clock`.
+    fn now(&self) -> Instant {
+        config::clock().now()
+    }
+}
+
+struct TimerEntry {
+    deadline: Instant,
+    order: u64,
+    task: Task,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline && self.order == other.order
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    // Reversed so that the BinaryHeap (a max-heap) pops the earliest deadline first. Entries
+    // with equal deadlines run in the order they were scheduled.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .deadline
+            .cmp(&self.deadline)
+            .then_with(|| other.order.cmp(&self.order))
+    }
+}
+
+struct TimerQueue {
+    entries: BinaryHeap<TimerEntry>,
+    next_order: u64,
+    is_shut_down: bool,
+}
+
+// How long the scheduler thread sleeps at most before checking the clock again. Bounds how late a
+// timer fires when the application's clock jumps forward, like a `TestClock` being advanced.
+const CLOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct TimerThreadState {
+    queue: Mutex<TimerQueue>,
+    wakeup: Condvar,
+}
+
+impl TimerThreadState {
+    fn lock_queue(&self) -> MutexGuard<'_, TimerQueue> {
+        match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(err) => panic!("Scheduler mutex poisoned: {}", err),
+        }
+    }
+
+    fn schedule_at(&self, deadline: Instant, task: Task) {
+        let mut queue = self.lock_queue();
+        let order = queue.next_order;
+        queue.next_order += 1;
+        queue.entries.push(TimerEntry {
+            deadline,
+            order,
+            task,
+        });
+        self.wakeup.notify_one();
+    }
+
+    fn run(&self) {
+        let mut queue = self.lock_queue();
+        while !queue.is_shut_down {
+            let now = config::clock().now();
+            let next_deadline = queue.entries.peek().map(|entry| entry.deadline);
+            queue = match next_deadline {
+                Some(deadline) if deadline <= now => {
+                    let entry = queue.entries.pop().unwrap();
+                    drop(queue);
+                    // A panicking task (like a subscriber of a debounced stream) must not take
+                    // down the thread, and with it every other timer that shares it.
+                    let _ = catch_unwind(AssertUnwindSafe(entry.task));
+                    self.lock_queue()
+                }
+                Some(deadline) => match self
+                    .wakeup
+                    .wait_timeout(queue, (deadline - now).min(CLOCK_POLL_INTERVAL))
+                {
+                    Ok((queue, _)) => queue,
+                    Err(err) => panic!("Scheduler mutex poisoned: {}", err),
+                },
+                None => match self.wakeup.wait(queue) {
+                    Ok(queue) => queue,
+                    Err(err) => panic!("Scheduler mutex poisoned: {}", err),
+                },
+            };
+        }
+    }
+}
+
+/// Runs all tasks sequentially on a single background thread that it owns. This is the default
+/// scheduler. The thread shuts down when the scheduler is dropped, abandoning any pending tasks.
+///
+/// Delays are measured with the application's clock (see `config::set_clock`), the same one that
+/// `Scheduler::now` reports. A task that panics is abandoned, and the thread carries on with the
+/// next one.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::scheduler::{DedicatedThreadScheduler, Scheduler};
+/// use std::sync::mpsc::channel;
+/// use std::time::Duration;
+///
+/// let scheduler = DedicatedThreadScheduler::new();
+/// let (sender, receiver) = channel();
+/// scheduler.schedule(Box::new(|| panic!("Abandoned, without stopping the thread")));
+/// scheduler.schedule_after(Duration::from_millis(10), Box::new(move || {
+///     sender.send(std::thread::current().name().map(String::from)).unwrap();
+/// }));
+/// assert_eq!(receiver.recv().unwrap(), Some("epoxy-scheduler".to_string()));
+/// ```
+pub struct DedicatedThreadScheduler {
+    state: Arc<TimerThreadState>,
+}
+
+impl DedicatedThreadScheduler {
+    /// Spawns a new background thread for this scheduler.
+    pub fn new() -> DedicatedThreadScheduler {
+        DedicatedThreadScheduler::spawn("epoxy-scheduler")
+    }
+
+    fn spawn(thread_name: &str) -> DedicatedThreadScheduler {
+        let state = Arc::new(TimerThreadState {
+            queue: Mutex::new(TimerQueue {
+                entries: BinaryHeap::new(),
+                next_order: 0,
+                is_shut_down: false,
+            }),
+            wakeup: Condvar::new(),
+        });
+        let thread_state = state.clone();
+        thread::Builder::new()
+            .name(thread_name.to_string())
+            .spawn(move || thread_state.run())
+            .expect("Could not spawn the epoxy scheduler thread");
+        DedicatedThreadScheduler { state }
+    }
+}
+
+impl Default for DedicatedThreadScheduler {
+    fn default() -> DedicatedThreadScheduler {
+        DedicatedThreadScheduler::new()
+    }
+}
+
+impl Scheduler for DedicatedThreadScheduler {
+    fn schedule(&self, task: Task) {
+        self.state.schedule_at(self.now(), task)
+    }
+
+    fn schedule_after(&self, delay: Duration, task: Task) {
+        self.state.schedule_at(self.now() + delay, task)
+    }
+}
+
+impl Drop for DedicatedThreadScheduler {
+    fn drop(&mut self) {
+        self.state.lock_queue().is_shut_down = true;
+        self.state.wakeup.notify_one();
+    }
+}
+
+/// Runs tasks only when `tick` is called, on the thread that calls it. This lets applications
+/// that already have a main loop (such as games) run epoxy's deferred work at a well-defined
+/// point of each frame. Delays are measured with the application's clock (see
+/// `config::set_clock`), and a delayed task only runs on the first tick after its delay has
+/// elapsed. A task that panics is abandoned, and the tick carries on with the next one.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::scheduler::{Scheduler, TickScheduler};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let scheduler = TickScheduler::new();
+/// let runs = Arc::new(AtomicUsize::new(0));
+/// let runs_ref = runs.clone();
+/// scheduler.schedule(Box::new(|| panic!("Abandoned, without stopping the tick")));
+/// scheduler.schedule(Box::new(move || {
+///     runs_ref.fetch_add(1, Ordering::SeqCst);
+/// }));
+/// scheduler.schedule_after(Duration::from_secs(60), Box::new(|| {}));
+/// assert_eq!(runs.load(Ordering::SeqCst), 0);
+///
+/// assert_eq!(scheduler.tick(), 2);
+/// assert_eq!(runs.load(Ordering::SeqCst), 1);
+/// assert_eq!(scheduler.count_pending(), 1);
+/// ```
+pub struct TickScheduler {
+    queue: Mutex<TimerQueue>,
+}
+
+impl TickScheduler {
+    pub fn new() -> TickScheduler {
+        TickScheduler {
+            queue: Mutex::new(TimerQueue {
+                entries: BinaryHeap::new(),
+                next_order: 0,
+                is_shut_down: false,
+            }),
+        }
+    }
+
+    fn lock_queue(&self) -> MutexGuard<'_, TimerQueue> {
+        match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(err) => panic!("Scheduler mutex poisoned: {}", err),
+        }
+    }
+
+    fn schedule_at(&self, deadline: Instant, task: Task) {
+        let mut queue = self.lock_queue();
+        let order = queue.next_order;
+        queue.next_order += 1;
+        queue.entries.push(TimerEntry {
+            deadline,
+            order,
+            task,
+        });
+    }
+
+    /// Runs every task that is due, and returns how many tasks ran. Tasks scheduled by those
+    /// tasks wait for the next tick, so a task that keeps rescheduling itself can not stall the
+    /// caller.
+    pub fn tick(&self) -> usize {
+        let now = self.now();
+        let mut due = vec![];
+        {
+            let mut queue = self.lock_queue();
+            while queue
+                .entries
+                .peek()
+                .is_some_and(|entry| entry.deadline <= now)
+            {
+                due.push(queue.entries.pop().unwrap());
+            }
+        }
+        let count = due.len();
+        for entry in due {
+            // Same as on a `DedicatedThreadScheduler`, a panicking task must not take the rest of
+            // the tick down with it.
+            let _ = catch_unwind(AssertUnwindSafe(entry.task));
+        }
+        count
+    }
+
+    /// Returns the number of tasks that have not run yet, including ones that are not due.
+    pub fn count_pending(&self) -> usize {
+        self.lock_queue().entries.len()
+    }
+}
+
+impl Default for TickScheduler {
+    fn default() -> TickScheduler {
+        TickScheduler::new()
+    }
+}
+
+impl Scheduler for TickScheduler {
+    fn schedule(&self, task: Task) {
+        self.schedule_at(self.now(), task)
+    }
+
+    fn schedule_after(&self, delay: Duration, task: Task) {
+        self.schedule_at(self.now() + delay, task)
+    }
+}
+
+/// Runs tasks only when asked to, against a virtual clock that only moves when `advance` is
+/// called. Nothing it schedules depends on real time or on other threads, which makes streams
+/// that use it completely deterministic. Usually created through `runtime::manual`.
+pub struct ManualScheduler {
+    queue: Mutex<TimerQueue>,
+    start: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl ManualScheduler {
+    /// Creates a scheduler whose virtual clock starts at the current time.
+    pub fn new() -> ManualScheduler {
+        ManualScheduler {
+            queue: Mutex::new(TimerQueue {
+                entries: BinaryHeap::new(),
+                next_order: 0,
+                is_shut_down: false,
+            }),
+            start: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    fn lock_queue(&self) -> MutexGuard<'_, TimerQueue> {
+        match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(err) => panic!("Scheduler mutex poisoned: {}", err),
+        }
+    }
+
+    fn lock_elapsed(&self) -> MutexGuard<'_, Duration> {
+        match self.elapsed.lock() {
+            Ok(elapsed) => elapsed,
+            Err(err) => panic!("Scheduler clock mutex poisoned: {}", err),
+        }
+    }
+
+    fn schedule_at(&self, deadline: Instant, task: Task) {
+        let mut queue = self.lock_queue();
+        let order = queue.next_order;
+        queue.next_order += 1;
+        queue.entries.push(TimerEntry {
+            deadline,
+            order,
+            task,
+        });
+    }
+
+    /// Runs the earliest task that is due at or before `deadline`, if there is one, after moving
+    /// the clock forward to its deadline.
+    fn run_next_until(&self, deadline: Instant) -> bool {
+        let entry = {
+            let mut queue = self.lock_queue();
+            match queue.entries.peek() {
+                Some(entry) if entry.deadline <= deadline => queue.entries.pop().unwrap(),
+                _ => return false,
+            }
+        };
+        {
+            let mut elapsed = self.lock_elapsed();
+            *elapsed = (*elapsed).max(entry.deadline - self.start);
+        }
+        (entry.task)();
+        true
+    }
+
+    /// Runs tasks until none are due at the current virtual time, and returns how many ran. This
+    /// includes tasks that were scheduled by the tasks it ran, so a task that keeps rescheduling
+    /// itself without a delay makes this loop forever.
+    pub fn run_until_idle(&self) -> usize {
+        let now = self.now();
+        let mut count = 0;
+        while self.run_next_until(now) {
+            count += 1;
+        }
+        count
+    }
+
+    /// Moves the virtual clock forward by `duration`, running every task that becomes due along
+    /// the way in deadline order. Each task sees the clock at its own deadline. Returns how many
+    /// tasks ran.
+    pub fn advance(&self, duration: Duration) -> usize {
+        let target = self.now() + duration;
+        let mut count = 0;
+        while self.run_next_until(target) {
+            count += 1;
+        }
+        *self.lock_elapsed() = target - self.start;
+        count
+    }
+
+    /// Returns the number of tasks that have not run yet, including ones that are not due.
+    pub fn count_pending(&self) -> usize {
+        self.lock_queue().entries.len()
+    }
+}
+
+impl Default for ManualScheduler {
+    fn default() -> ManualScheduler {
+        ManualScheduler::new()
+    }
+}
+
+impl Scheduler for ManualScheduler {
+    fn schedule(&self, task: Task) {
+        self.schedule_at(self.now(), task)
+    }
+
+    fn schedule_after(&self, delay: Duration, task: Task) {
+        self.schedule_at(self.now() + delay, task)
+    }
+
+    fn now(&self) -> Instant {
+        self.start + *self.lock_elapsed()
+    }
+}
+
+/// Limits used by `FairScheduler`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FairnessPolicy {
+    /// The most tasks one lane may run before the scheduler yields and moves on to other lanes.
+    pub max_batch_size: usize,
+
+    /// A lane whose oldest task has waited longer than this is considered starved, and runs
+    /// next regardless of its place in the rotation.
+    pub starvation_threshold: Duration,
+}
+
+impl Default for FairnessPolicy {
+    fn default() -> FairnessPolicy {
+        FairnessPolicy {
+            max_batch_size: 16,
+            starvation_threshold: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Counters collected by a `FairScheduler`, see `FairScheduler::stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FairSchedulerStats {
+    /// The number of tasks that have run.
+    pub tasks_run: u64,
+
+    /// The number of batches that ended because they reached `max_batch_size` while the lane
+    /// still had work left.
+    pub yields: u64,
+
+    /// The number of times a starved lane was moved to the front of the rotation.
+    pub starvation_events: u64,
+
+    /// The longest time any task has waited in a lane before it ran.
+    pub longest_wait: Duration,
+}
+
+struct FairLane {
+    tasks: VecDeque<(Instant, Task)>,
+    is_running: bool,
+}
+
+struct FairQueues {
+    lanes: HashMap<usize, FairLane>,
+    // Lanes that have work and are not running, in rotation order.
+    ready: VecDeque<usize>,
+    next_lane_id: usize,
+    stats: FairSchedulerStats,
+}
+
+// The batch a drain task is working through. Dropping it finishes the batch, so that a panicking
+// task can not leave its lane marked as running forever.
+struct RunningBatch {
+    state: Arc<FairState>,
+    lane_id: usize,
+    tasks: VecDeque<(Instant, Task)>,
+}
+
+impl Drop for RunningBatch {
+    fn drop(&mut self) {
+        let unrun = std::mem::take(&mut self.tasks);
+        self.state.finish_batch(self.lane_id, unrun);
+    }
+}
+
+struct FairState {
+    inner: Arc<dyn Scheduler>,
+    policy: FairnessPolicy,
+    queues: Mutex<FairQueues>,
+}
+
+impl FairState {
+    fn lock_queues(&self) -> MutexGuard<'_, FairQueues> {
+        match self.queues.lock() {
+            Ok(queues) => queues,
+            Err(err) => panic!("Scheduler mutex poisoned: {}", err),
+        }
+    }
+
+    fn push(self: &Arc<Self>, lane_id: usize, task: Task) {
+        let became_ready = {
+            let mut queues = self.lock_queues();
+            let lane = queues.lanes.entry(lane_id).or_insert_with(|| FairLane {
+                tasks: VecDeque::new(),
+                is_running: false,
+            });
+            lane.tasks.push_back((self.inner.now(), task));
+            let became_ready = lane.tasks.len() == 1 && !lane.is_running;
+            if became_ready {
+                queues.ready.push_back(lane_id);
+            }
+            became_ready
+        };
+
+        // Every entry in the ready queue is matched by exactly one drain task on the inner
+        // scheduler, and every drain task runs a single batch. Between batches the inner
+        // scheduler is free to run other work.
+        if became_ready {
+            self.schedule_drain();
+        }
+    }
+
+    fn schedule_drain(self: &Arc<Self>) {
+        let state = self.clone();
+        self.inner
+            .schedule(Box::new(move || state.drain_one_batch()));
+    }
+
+    fn drain_one_batch(self: &Arc<Self>) {
+        let (lane_id, batch) = {
+            let mut queues = self.lock_queues();
+            let now = self.inner.now();
+            let starved = queues
+                .ready
+                .iter()
+                .enumerate()
+                .filter_map(|(index, lane_id)| {
+                    let queued_at = queues.lanes[lane_id].tasks.front()?.0;
+                    Some((index, queued_at))
+                })
+                .filter(|(_, queued_at)| now - *queued_at > self.policy.starvation_threshold)
+                .min_by_key(|(_, queued_at)| *queued_at)
+                .map(|(index, _)| index);
+            let lane_id = match starved {
+                Some(0) => queues.ready.pop_front(),
+                Some(index) => {
+                    queues.stats.starvation_events += 1;
+                    queues.ready.remove(index)
+                }
+                None => queues.ready.pop_front(),
+            };
+            let lane_id = match lane_id {
+                Some(lane_id) => lane_id,
+                None => return,
+            };
+
+            let max_batch_size = self.policy.max_batch_size.max(1);
+            let lane = queues.lanes.get_mut(&lane_id).unwrap();
+            lane.is_running = true;
+            let batch_size = lane.tasks.len().min(max_batch_size);
+            let batch: Vec<_> = lane.tasks.drain(..batch_size).collect();
+            let has_more = !lane.tasks.is_empty();
+
+            let stats = &mut queues.stats;
+            stats.tasks_run += batch.len() as u64;
+            if has_more {
+                stats.yields += 1;
+            }
+            for (queued_at, _) in &batch {
+                stats.longest_wait = stats.longest_wait.max(now - *queued_at);
+            }
+            (lane_id, batch)
+        };
+
+        let mut batch = RunningBatch {
+            state: self.clone(),
+            lane_id,
+            tasks: batch.into(),
+        };
+        while let Some((_, task)) = batch.tasks.pop_front() {
+            task();
+        }
+    }
+
+    // Called once a batch is over, including when one of its tasks panicked. Tasks of the batch
+    // that did not get to run go back to the front of their lane.
+    fn finish_batch(self: &Arc<Self>, lane_id: usize, unrun: VecDeque<(Instant, Task)>) {
+        let became_ready = {
+            let mut queues = self.lock_queues();
+            queues.stats.tasks_run -= unrun.len() as u64;
+            let lane = queues.lanes.get_mut(&lane_id).unwrap();
+            lane.is_running = false;
+            for entry in unrun.into_iter().rev() {
+                lane.tasks.push_front(entry);
+            }
+            if lane.tasks.is_empty() {
+                queues.lanes.remove(&lane_id);
+                false
+            } else {
+                queues.ready.push_back(lane_id);
+                true
+            }
+        };
+        if became_ready {
+            self.schedule_drain();
+        }
+    }
+}
+
+/// Shares another scheduler fairly between several streams. Each stream gets its own lane (see
+/// `FairScheduler::lane`), and lanes take turns: a lane runs at most `max_batch_size` tasks before
+/// the scheduler yields to the next lane, so one chatty stream can not starve the others. A lane
+/// that has waited longer than the `starvation_threshold` skips ahead in the rotation.
+///
+/// Tasks within one lane run in order and never at the same time, even when the inner scheduler
+/// is a thread pool. A task that panics ends its batch and the panic carries on into the inner
+/// scheduler (a `DedicatedThreadScheduler` abandons the task and keeps going). The lane's other
+/// tasks are not lost, and run in later batches as usual.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::scheduler::{FairScheduler, FairnessPolicy, Scheduler, TickScheduler};
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+///
+/// let ticker = Arc::new(TickScheduler::new());
+/// let scheduler = FairScheduler::new(
+///     ticker.clone(),
+///     FairnessPolicy {
+///         max_batch_size: 2,
+///         starvation_threshold: Duration::from_secs(1),
+///     },
+/// );
+/// let chatty = scheduler.lane();
+/// let quiet = scheduler.lane();
+///
+/// let log = Arc::new(Mutex::new(vec![]));
+/// for i in 1..=5 {
+///     let log = log.clone();
+///     chatty.schedule(Box::new(move || log.lock().unwrap().push(format!("chatty {}", i))));
+/// }
+/// let quiet_log = log.clone();
+/// quiet.schedule(Box::new(move || quiet_log.lock().unwrap().push("quiet".to_string())));
+///
+/// while ticker.count_pending() > 0 {
+///     ticker.tick();
+/// }
+/// assert_eq!(
+///     *log.lock().unwrap(),
+///     vec!["chatty 1", "chatty 2", "quiet", "chatty 3", "chatty 4", "chatty 5"]
+/// );
+/// assert_eq!(scheduler.stats().yields, 2);
+/// ```
+pub struct FairScheduler {
+    state: Arc<FairState>,
+    default_lane: usize,
+}
+
+/// One lane of a `FairScheduler`, usually passed to `Stream::with_scheduler`.
+pub struct FairSchedulerLane {
+    state: Arc<FairState>,
+    id: usize,
+}
+
+impl FairScheduler {
+    /// Creates a scheduler that runs its tasks on `inner`, following the given policy.
+    pub fn new(inner: Arc<dyn Scheduler>, policy: FairnessPolicy) -> FairScheduler {
+        let state = Arc::new(FairState {
+            inner,
+            policy,
+            queues: Mutex::new(FairQueues {
+                lanes: HashMap::new(),
+                ready: VecDeque::new(),
+                next_lane_id: 1,
+                stats: FairSchedulerStats::default(),
+            }),
+        });
+        FairScheduler {
+            state,
+            default_lane: 0,
+        }
+    }
+
+    /// Creates a new lane. Tasks scheduled through the scheduler itself share a default lane.
+    pub fn lane(&self) -> Arc<FairSchedulerLane> {
+        let id = {
+            let mut queues = self.state.lock_queues();
+            let id = queues.next_lane_id;
+            queues.next_lane_id += 1;
+            id
+        };
+        Arc::new(FairSchedulerLane {
+            state: self.state.clone(),
+            id,
+        })
+    }
+
+    /// Returns the counters collected so far.
+    pub fn stats(&self) -> FairSchedulerStats {
+        self.state.lock_queues().stats
+    }
+}
+
+impl Scheduler for FairScheduler {
+    fn schedule(&self, task: Task) {
+        self.state.push(self.default_lane, task);
+    }
+
+    fn schedule_after(&self, delay: Duration, task: Task) {
+        let state = self.state.clone();
+        let lane_id = self.default_lane;
+        self.state
+            .inner
+            .schedule_after(delay, Box::new(move || state.push(lane_id, task)));
+    }
+
+    fn now(&self) -> Instant {
+        self.state.inner.now()
+    }
+}
+
+impl Scheduler for FairSchedulerLane {
+    fn schedule(&self, task: Task) {
+        self.state.push(self.id, task);
+    }
+
+    fn schedule_after(&self, delay: Duration, task: Task) {
+        let state = self.state.clone();
+        let lane_id = self.id;
+        self.state
+            .inner
+            .schedule_after(delay, Box::new(move || state.push(lane_id, task)));
+    }
+
+    fn now(&self) -> Instant {
+        self.state.inner.now()
+    }
+}
+
+/// Returns the scheduler thread shared by everything that has not been configured otherwise.
+pub(crate) fn shared_thread_scheduler() -> Arc<dyn Scheduler> {
+    static SHARED_SCHEDULER: OnceLock<Arc<dyn Scheduler>> = OnceLock::new();
+    SHARED_SCHEDULER
+        .get_or_init(|| Arc::new(DedicatedThreadScheduler::new()))
+        .clone()
+}
+
+/// Returns the thread that runs user code that may take a while, like the listeners of
+/// `DeliveryMode::Latest` subscriptions, when nothing has been configured otherwise. Keeping that
+/// code off the shared scheduler thread means that it cannot hold up the timers of other streams.
+pub(crate) fn shared_worker_scheduler() -> Arc<dyn Scheduler> {
+    static SHARED_WORKER: OnceLock<Arc<dyn Scheduler>> = OnceLock::new();
+    SHARED_WORKER
+        .get_or_init(|| Arc::new(DedicatedThreadScheduler::spawn("epoxy-worker")))
+        .clone()
+}
+
+/// Runs tasks on a rayon thread pool. Delays are measured on epoxy's shared scheduler thread,
+/// which then hands the task over to the pool.
+#[cfg(feature = "rayon")]
+pub struct RayonScheduler {
+    pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+#[cfg(feature = "rayon")]
+impl RayonScheduler {
+    /// Creates a scheduler that runs tasks on rayon's global thread pool.
+    pub fn global() -> RayonScheduler {
+        RayonScheduler { pool: None }
+    }
+
+    /// Creates a scheduler that runs tasks on a specific rayon thread pool.
+    pub fn with_pool(pool: Arc<rayon::ThreadPool>) -> RayonScheduler {
+        RayonScheduler { pool: Some(pool) }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Scheduler for RayonScheduler {
+    fn schedule(&self, task: Task) {
+        match &self.pool {
+            Some(pool) => pool.spawn(task),
+            None => rayon::spawn(task),
+        }
+    }
+
+    fn schedule_after(&self, delay: Duration, task: Task) {
+        let pool = self.pool.clone();
+        shared_thread_scheduler().schedule_after(
+            delay,
+            Box::new(move || match pool {
+                Some(pool) => pool.spawn(task),
+                None => rayon::spawn(task),
+            }),
+        )
+    }
+}
+
+/// Runs tasks on a tokio runtime. The runtime must have its time driver enabled.
+#[cfg(feature = "tokio")]
+pub struct TokioScheduler {
+    handle: tokio::runtime::Handle,
+}
+
+#[cfg(feature = "tokio")]
+impl TokioScheduler {
+    /// Creates a scheduler that spawns tasks onto the runtime behind the given handle.
+    pub fn new(handle: tokio::runtime::Handle) -> TokioScheduler {
+        TokioScheduler { handle }
+    }
+
+    /// Creates a scheduler for the runtime the current thread is running in. Panics if called
+    /// outside of a tokio runtime.
+    pub fn current() -> TokioScheduler {
+        TokioScheduler::new(tokio::runtime::Handle::current())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Scheduler for TokioScheduler {
+    fn schedule(&self, task: Task) {
+        self.handle.spawn(async move { task() });
+    }
+
+    fn schedule_after(&self, delay: Duration, task: Task) {
+        self.handle.spawn(async move {
+            tokio::time::sleep(delay).await;
+            task()
+        });
+    }
+}
@@ -0,0 +1,198 @@
+//! Scopes tie the lifetime of subscriptions to a component, instead of to the Subscription
+//! objects themselves. Every subscription created while a scope is running (including the ones
+//! that operators like `map` and computed values create internally) belongs to that scope, and
+//! stays active until the scope is disposed, even if its Subscription object is dropped right
+//! away. This saves component frameworks from threading a collection of subscriptions through
+//! every constructor.
+//!
+//! Scopes created inside another scope become its children, and are disposed along with it. Like
+//! any other scope, a child scope is also disposed when it is dropped.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::scope::scope;
+//!
+//! let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+//! let stream = stream_host.get_stream();
+//!
+//! let mut child = None;
+//! let component = scope(|_| {
+//!     stream.map(|val| val * 2).subscribe(|_| {});
+//!     child = Some(scope(|_| {
+//!         stream.subscribe(|_| {});
+//!     }));
+//! });
+//! assert_eq!(stream.count_subscribers(), 2);
+//!
+//! drop(component);
+//! assert_eq!(stream.count_subscribers(), 0);
+//! assert!(child.unwrap().is_disposed());
+//! ```
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+type Teardown = Box<dyn FnOnce() + Send>;
+
+struct ScopeState {
+    is_disposed: bool,
+    teardowns: Vec<Teardown>,
+    children: Vec<Weak<ScopeImpl>>,
+}
+
+struct ScopeImpl {
+    state: Mutex<ScopeState>,
+}
+
+thread_local! {
+    static CURRENT_SCOPE: RefCell<Option<Arc<ScopeImpl>>> = const { RefCell::new(None) };
+}
+
+/// Restores the enclosing scope (if any) when dropped, even if the scope function panics.
+struct CurrentScopeGuard {
+    previous: Option<Arc<ScopeImpl>>,
+}
+
+impl Drop for CurrentScopeGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_SCOPE.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Owns the subscriptions created while it was running. See the module documentation.
+///
+/// Disposing the scope (or dropping it) removes all of them, and disposes its child scopes.
+pub struct Scope {
+    pointer: Arc<ScopeImpl>,
+}
+
+impl ScopeImpl {
+    fn lock_state(&self) -> MutexGuard<'_, ScopeState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(err) => panic!("Scope mutex poisoned: {}", err),
+        }
+    }
+
+    fn add_teardown(&self, teardown: Teardown) {
+        let mut state = self.lock_state();
+        if state.is_disposed {
+            drop(state);
+            teardown();
+        } else {
+            state.teardowns.push(teardown);
+        }
+    }
+
+    fn dispose(&self) {
+        let (teardowns, children) = {
+            let mut state = self.lock_state();
+            if state.is_disposed {
+                return;
+            }
+            state.is_disposed = true;
+            (
+                std::mem::take(&mut state.teardowns),
+                std::mem::take(&mut state.children),
+            )
+        };
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.dispose();
+        }
+        for teardown in teardowns {
+            teardown();
+        }
+    }
+}
+
+impl Scope {
+    /// Runs the given function inside this scope, so that the subscriptions it creates belong to
+    /// the scope. This is useful for event handlers and other callbacks that create subscriptions
+    /// after the scope was first set up. If the scope has already been disposed, those
+    /// subscriptions are removed as soon as they are created.
+    pub fn run<R, F>(&self, scope_fn: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let previous =
+            CURRENT_SCOPE.with(|current| current.borrow_mut().replace(Arc::clone(&self.pointer)));
+        let _guard = CurrentScopeGuard { previous };
+        scope_fn()
+    }
+
+    /// Runs the given function when the scope is disposed, or right away if it already has been.
+    pub fn on_dispose<F>(&self, teardown: F)
+    where
+        F: FnOnce(),
+        F: Send,
+        F: 'static,
+    {
+        self.pointer.add_teardown(Box::new(teardown))
+    }
+
+    /// Removes every subscription that belongs to the scope, and disposes its child scopes.
+    pub fn dispose(&self) {
+        self.pointer.dispose()
+    }
+
+    /// Returns true once the scope, or any of its ancestors, has been disposed.
+    pub fn is_disposed(&self) -> bool {
+        self.pointer.lock_state().is_disposed
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        self.dispose()
+    }
+}
+
+/// Creates a scope and runs the given function inside it. If another scope is running, the new
+/// scope becomes its child. See the module documentation.
+pub fn scope<F>(scope_fn: F) -> Scope
+where
+    F: FnOnce(&Scope),
+{
+    let scope = Scope {
+        pointer: Arc::new(ScopeImpl {
+            state: Mutex::new(ScopeState {
+                is_disposed: false,
+                teardowns: vec![],
+                children: vec![],
+            }),
+        }),
+    };
+
+    let parent = CURRENT_SCOPE.with(|current| current.borrow().clone());
+    if let Some(parent) = parent {
+        let mut parent_state = parent.lock_state();
+        if parent_state.is_disposed {
+            drop(parent_state);
+            scope.dispose();
+        } else {
+            parent_state
+                .children
+                .retain(|child| child.strong_count() > 0);
+            parent_state.children.push(Arc::downgrade(&scope.pointer));
+        }
+    }
+
+    scope.run(|| scope_fn(&scope));
+    scope
+}
+
+/// Hands a subscription's teardown to the scope running on this thread, if there is one.
+/// Returns false if no scope is running, in which case the subscription is not adopted.
+pub(crate) fn adopt_subscription<F>(teardown: F) -> bool
+where
+    F: FnOnce(),
+    F: Send,
+    F: 'static,
+{
+    let current = match CURRENT_SCOPE.with(|current| current.borrow().clone()) {
+        Some(current) => current,
+        None => return false,
+    };
+    current.add_teardown(Box::new(teardown));
+    true
+}
@@ -0,0 +1,121 @@
+use super::reactive_value::WeakReadonlyReactiveValue;
+use super::ReadonlyReactiveValue;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type SelectorFactory<A, T> = Box<dyn Fn(&A) -> ReadonlyReactiveValue<T> + Send + Sync>;
+
+struct SelectorImpl<A, T> {
+    factory: SelectorFactory<A, T>,
+    cache: Mutex<HashMap<A, WeakReadonlyReactiveValue<T>>>,
+}
+
+/// A function from plain arguments to derived ReactiveValues that caches one ReactiveValue per
+/// distinct argument, see `selector`.
+pub struct Selector<A, T> {
+    pointer: Arc<SelectorImpl<A, T>>,
+}
+
+impl<A, T> Clone for Selector<A, T> {
+    fn clone(&self) -> Self {
+        Selector {
+            pointer: Arc::clone(&self.pointer),
+        }
+    }
+}
+
+/// Creates a parameterized derived value, like `score_for(player_id)`. The factory function
+/// builds a ReactiveValue (usually with `computed!` or `ReactiveValue::map`) for one argument.
+/// Asking the Selector for the same argument again returns the same ReactiveValue instead of
+/// building a new one, for as long as something is still holding on to it. Entries that nobody
+/// holds on to anymore are evicted, so the cache does not grow with every argument ever used.
+///
+/// Use a tuple as the argument type for selectors that take several arguments.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::ReactiveValue;
+///
+/// let scores = ReactiveValue::new(vec![10, 20]);
+/// let score_for = epoxy_streams::selector({
+///     let scores = scores.clone();
+///     move |player: &usize| {
+///         let player = *player;
+///         ReactiveValue::map(&scores, move |scores| scores[player])
+///     }
+/// });
+///
+/// let first_player = score_for.get(0);
+/// let first_player_again = score_for.get(0);
+/// let second_player = score_for.get(1);
+/// assert_eq!(score_for.count_cached(), 2);
+///
+/// scores.set(vec![15, 20]);
+/// assert_eq!(*first_player_again.get(), 15);
+///
+/// drop(first_player);
+/// drop(first_player_again);
+/// assert_eq!(score_for.count_cached(), 1);
+/// # drop(second_player);
+/// ```
+pub fn selector<A, T, F>(factory: F) -> Selector<A, T>
+where
+    A: Hash + Eq + Clone,
+    T: Send + Sync + 'static,
+    F: Fn(&A) -> ReadonlyReactiveValue<T>,
+    F: Send,
+    F: Sync,
+    F: 'static,
+{
+    Selector {
+        pointer: Arc::new(SelectorImpl {
+            factory: Box::new(factory),
+            cache: Mutex::new(HashMap::new()),
+        }),
+    }
+}
+
+impl<A, T> Selector<A, T>
+where
+    A: Hash + Eq + Clone,
+    T: Send + Sync + 'static,
+{
+    /// Returns the ReactiveValue for the given argument, creating it if it is not cached.
+    pub fn get(&self, argument: A) -> ReadonlyReactiveValue<T> {
+        if let Some(value) = self
+            .lock_cache()
+            .get(&argument)
+            .and_then(|weak| weak.upgrade())
+        {
+            return value;
+        }
+
+        // The factory runs without the lock held, since it may use other selectors (or this one).
+        let value = (self.pointer.factory)(&argument);
+
+        let mut cache = self.lock_cache();
+        cache.retain(|_, weak| weak.upgrade().is_some());
+        if let Some(existing) = cache.get(&argument).and_then(|weak| weak.upgrade()) {
+            // Another thread created the same entry in the meantime.
+            return existing;
+        }
+        cache.insert(argument, value.downgrade());
+        value
+    }
+
+    /// Returns the number of arguments whose ReactiveValue is still in use.
+    pub fn count_cached(&self) -> usize {
+        self.lock_cache()
+            .values()
+            .filter(|weak| weak.upgrade().is_some())
+            .count()
+    }
+
+    fn lock_cache(&self) -> MutexGuard<'_, HashMap<A, WeakReadonlyReactiveValue<T>>> {
+        match self.pointer.cache.lock() {
+            Ok(cache) => cache,
+            Err(err) => panic!("Selector mutex poisoned: {}", err),
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use super::{OrderViolation, Sink, Stream, StreamClosed, Subscription};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A value tagged with the position at which it was emitted from its Sink. See
+/// `Sink::emit_sequenced`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Sequenced<T> {
+    pub sequence: u64,
+    pub value: Arc<T>,
+}
+
+impl<T> Clone for Sequenced<T> {
+    fn clone(&self) -> Self {
+        Sequenced {
+            sequence: self.sequence,
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T> Sink<Sequenced<T>> {
+    /// Emits a value tagged with the next sequence number of this Sink, returning that number.
+    /// Numbers start at 0 and are assigned in exactly the order in which values are delivered to
+    /// subscribers, even when several threads emit at once. Downstream operators that deliver
+    /// values asynchronously may still reorder them, which `reorder_window` can undo.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Sequenced, Sink};
+    ///
+    /// let stream_host: Sink<Sequenced<&'static str>> = Sink::new();
+    /// let cache = ReactiveCache::from_stream(stream_host.get_stream().map(|val| val.sequence));
+    ///
+    /// assert_eq!(stream_host.emit_sequenced("a"), Ok(0));
+    /// assert_eq!(stream_host.emit_sequenced("b"), Ok(1));
+    /// assert_eq!(cache.get_cloned(), vec![0, 1]);
+    /// ```
+    pub fn emit_sequenced(&self, value: T) -> Result<u64, StreamClosed> {
+        let value = Arc::new(value);
+        self.get_stream()
+            .emit_sequenced_with(move |sequence| Arc::new(Sequenced { sequence, value }))
+    }
+}
+
+struct ReorderFields<T> {
+    next_expected: u64,
+    pending: BTreeMap<u64, Arc<Sequenced<T>>>,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Sequenced<T>>>,
+}
+
+impl<T> ReorderFields<T> {
+    fn take_ready(&mut self, window_size: usize) -> Vec<Arc<Sequenced<T>>> {
+        let mut ready = vec![];
+        loop {
+            while let Some(value) = self.pending.remove(&self.next_expected) {
+                self.next_expected += 1;
+                ready.push(value);
+            }
+            // Give up on missing values once too many later ones are waiting for them.
+            match self.pending.keys().next() {
+                Some(&first_pending) if self.pending.len() > window_size => {
+                    self.next_expected = first_pending
+                }
+                _ => return ready,
+            }
+        }
+    }
+}
+
+struct OrderCheckFields<T> {
+    next_expected: u64,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<Sequenced<T>>>,
+}
+
+impl<T: 'static + Send + Sync> Stream<Sequenced<T>> {
+    /// Returns a stream that restores the original order of sequenced values. Values that
+    /// arrive early are held back until the values before them arrive, with up to `window_size`
+    /// values held at once. When the window overflows the missing values are assumed lost and
+    /// skipped. Values that arrive after they were skipped are dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, Sequenced, Sink};
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: Sink<Sequenced<&'static str>> = Sink::new();
+    /// let ordered = stream_host.get_stream().reorder_window(2);
+    /// let cache = ReactiveCache::from_stream(ordered.map(|val| *val.value));
+    ///
+    /// let emit = |sequence, value| stream_host.emit(Sequenced { sequence, value: Arc::new(value) });
+    /// emit(1, "b");
+    /// emit(0, "a");
+    /// assert_eq!(cache.get_cloned(), vec!["a", "b"]);
+    ///
+    /// emit(3, "d");
+    /// emit(4, "e");
+    /// emit(5, "f"); // The window overflows, so "c" is skipped.
+    /// emit(2, "c");
+    /// assert_eq!(cache.get_cloned(), vec!["a", "b", "d", "e", "f"]);
+    /// ```
+    pub fn reorder_window(&self, window_size: usize) -> Stream<Sequenced<T>> {
+        let derived_stream = self.derive_with_fields(ReorderFields::<T> {
+            next_expected: 0,
+            pending: BTreeMap::new(),
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                let mut ready = vec![];
+                stream_ref.mutate_extra_fields(|fields: &mut ReorderFields<T>| {
+                    if val.sequence >= fields.next_expected {
+                        fields.pending.insert(val.sequence, val);
+                        ready = fields.take_ready(window_size);
+                    }
+                });
+                for value in ready {
+                    stream_ref.emit_rc(value);
+                }
+            },
+            move || {
+                let mut remaining = vec![];
+                completion_stream_ref.mutate_extra_fields(|fields: &mut ReorderFields<T>| {
+                    remaining = fields.take_ready(0);
+                });
+                for value in remaining {
+                    completion_stream_ref.emit_rc(value);
+                }
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut ReorderFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Returns a stream that checks that sequenced values arrive in order. Values are passed
+    /// through as `Ok`, and an `Err` is emitted whenever a sequence number is skipped (before the
+    /// value that skipped it) or arrives late (instead of the late value).
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{OrderViolation, ReactiveCache, Sequenced, Sink};
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: Sink<Sequenced<i32>> = Sink::new();
+    /// let checked = stream_host.get_stream().assert_ordered();
+    /// let cache = ReactiveCache::from_stream(checked.map(|val| val.clone().map(|v| v.sequence)));
+    ///
+    /// let emit = |sequence| stream_host.emit(Sequenced { sequence, value: Arc::new(0) });
+    /// emit(0);
+    /// emit(2);
+    /// emit(1);
+    /// assert_eq!(
+    ///     cache.get_cloned(),
+    ///     vec![
+    ///         Ok(0),
+    ///         Err(OrderViolation::Gap { expected: 1, received: 2 }),
+    ///         Ok(2),
+    ///         Err(OrderViolation::OutOfOrder { expected: 3, received: 1 }),
+    ///     ]
+    /// );
+    /// ```
+    pub fn assert_ordered(&self) -> Stream<Result<Sequenced<T>, OrderViolation>> {
+        let derived_stream = self.derive_with_fields(OrderCheckFields::<T> {
+            next_expected: 0,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                let mut expected = 0;
+                stream_ref.mutate_extra_fields(|fields: &mut OrderCheckFields<T>| {
+                    expected = fields.next_expected;
+                    fields.next_expected = fields.next_expected.max(val.sequence + 1);
+                });
+
+                let received = val.sequence;
+                if received < expected {
+                    stream_ref.emit_rc(Arc::new(Err(OrderViolation::OutOfOrder {
+                        expected,
+                        received,
+                    })));
+                    return;
+                }
+                if received > expected {
+                    stream_ref.emit_rc(Arc::new(Err(OrderViolation::Gap { expected, received })));
+                }
+                stream_ref.emit_rc(Arc::new(Ok((*val).clone())));
+            },
+            move || completion_stream_ref.complete(),
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut OrderCheckFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
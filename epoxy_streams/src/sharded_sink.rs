@@ -0,0 +1,206 @@
+use super::config::default_scheduler;
+use super::{Sink, Stream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, TryLockError, Weak};
+use std::time::Duration;
+
+/// How long a partially filled shard waits before it is delivered anyway.
+const DELIVERY_DELAY: Duration = Duration::from_millis(1);
+
+static NEXT_THREAD_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Assigned round-robin so that threads spread evenly across shards.
+    static THREAD_INDEX: usize = NEXT_THREAD_INDEX.fetch_add(1, Ordering::Relaxed);
+}
+
+// Aligned so that producers on different shards do not contend for the same cache line.
+#[repr(align(128))]
+struct Shard<T> {
+    buffer: Mutex<Vec<Arc<T>>>,
+
+    // Held while a batch is taken from the buffer and emitted, so that batches from the same
+    // shard are always emitted in order.
+    delivery: Mutex<()>,
+
+    is_delivery_scheduled: AtomicBool,
+}
+
+struct ShardedSinkImpl<T> {
+    shards: Vec<Shard<T>>,
+    batch_size: usize,
+    sink: Sink<T>,
+}
+
+impl<T> ShardedSinkImpl<T> {
+    fn take_batch(&self, shard: &Shard<T>, min_size: usize) -> Option<Vec<Arc<T>>> {
+        match shard.buffer.lock() {
+            Ok(mut buffer) if !buffer.is_empty() && buffer.len() >= min_size => Some(
+                std::mem::replace(&mut *buffer, Vec::with_capacity(self.batch_size)),
+            ),
+            Ok(_) => None,
+            Err(err) => panic!("Shard mutex poisoned: {}", err),
+        }
+    }
+
+    /// Delivers every full batch in the shard, unless another thread is already delivering it.
+    /// That thread will pick up any full batches once it is done.
+    fn deliver_full_batches(&self, shard: &Shard<T>) {
+        let _delivery_guard = match shard.delivery.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => return,
+            Err(TryLockError::Poisoned(err)) => panic!("Shard mutex poisoned: {}", err),
+        };
+        while let Some(batch) = self.take_batch(shard, self.batch_size) {
+            self.sink.get_stream().emit_batch_rc(batch);
+        }
+    }
+
+    fn deliver_all(&self, shard: &Shard<T>) {
+        let _delivery_guard = match shard.delivery.lock() {
+            Ok(guard) => guard,
+            Err(err) => panic!("Shard mutex poisoned: {}", err),
+        };
+        if let Some(batch) = self.take_batch(shard, 0) {
+            self.sink.get_stream().emit_batch_rc(batch);
+        }
+    }
+
+    fn flush(&self) {
+        for shard in &self.shards {
+            self.deliver_all(shard);
+        }
+    }
+}
+
+impl<T> Drop for ShardedSinkImpl<T> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// A Sink designed for many producer threads emitting at once. Each thread writes into its own
+/// shard, and shards are delivered to the consumer-facing stream in batches, so producers rarely
+/// contend for the same lock.
+///
+/// The tradeoff is ordering and latency. Values emitted by a single thread always arrive in order,
+/// but values from different threads may be reordered by up to `batch_size` values per shard.
+/// A shard is delivered once it holds `batch_size` values, when `flush` is called, or by the
+/// default scheduler about a millisecond after its first value was emitted, whichever happens
+/// first.
+///
+/// Handles are cheap to clone, so each producer thread can be given its own.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{ReactiveCache, ShardedSink};
+///
+/// let sink: ShardedSink<i32> = ShardedSink::new(4);
+/// let cache = ReactiveCache::from_stream(sink.get_stream());
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|thread| {
+///         let sink = sink.clone();
+///         std::thread::spawn(move || {
+///             for val in 0..100 {
+///                 sink.emit(thread * 100 + val);
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// sink.flush();
+///
+/// let mut values: Vec<i32> = cache.get_cloned().into_iter().collect();
+/// values.sort();
+/// assert_eq!(values, (0..400).collect::<Vec<_>>());
+/// ```
+pub struct ShardedSink<T> {
+    pointer: Arc<ShardedSinkImpl<T>>,
+}
+
+impl<T> Clone for ShardedSink<T> {
+    fn clone(&self) -> Self {
+        ShardedSink {
+            pointer: Arc::clone(&self.pointer),
+        }
+    }
+}
+
+impl<T: 'static + Send + Sync> ShardedSink<T> {
+    /// Creates a sink with the given number of shards, delivering values in batches of 64.
+    pub fn new(n_shards: usize) -> ShardedSink<T> {
+        ShardedSink::with_batch_size(n_shards, 64)
+    }
+
+    /// Creates a sink with the given number of shards and batch size.
+    pub fn with_batch_size(n_shards: usize, batch_size: usize) -> ShardedSink<T> {
+        assert!(n_shards > 0, "A ShardedSink needs at least one shard");
+        ShardedSink {
+            pointer: Arc::new(ShardedSinkImpl {
+                shards: (0..n_shards)
+                    .map(|_| Shard {
+                        buffer: Mutex::new(vec![]),
+                        delivery: Mutex::new(()),
+                        is_delivery_scheduled: AtomicBool::new(false),
+                    })
+                    .collect(),
+                batch_size: batch_size.max(1),
+                sink: Sink::new(),
+            }),
+        }
+    }
+
+    /// Returns the stream that all shards are delivered to.
+    pub fn get_stream(&self) -> Stream<T> {
+        self.pointer.sink.get_stream()
+    }
+
+    /// Emits a value into the current thread's shard.
+    pub fn emit(&self, value: T) {
+        self.emit_rc(Arc::new(value))
+    }
+
+    /// Same logic as `emit`, but takes an existing Arc pointer.
+    pub fn emit_rc(&self, value: Arc<T>) {
+        let shard_index = THREAD_INDEX.with(|index| *index) % self.pointer.shards.len();
+        let shard = &self.pointer.shards[shard_index];
+
+        let is_full = match shard.buffer.lock() {
+            Ok(mut buffer) => {
+                buffer.push(value);
+                buffer.len() >= self.pointer.batch_size
+            }
+            Err(err) => panic!("Shard mutex poisoned: {}", err),
+        };
+
+        if is_full {
+            self.pointer.deliver_full_batches(shard);
+        } else if !shard.is_delivery_scheduled.load(Ordering::Acquire)
+            && !shard.is_delivery_scheduled.swap(true, Ordering::AcqRel)
+        {
+            self.schedule_delivery(shard_index);
+        }
+    }
+
+    /// Immediately delivers all values that are waiting in any shard.
+    pub fn flush(&self) {
+        self.pointer.flush()
+    }
+
+    fn schedule_delivery(&self, shard_index: usize) {
+        let weak_pointer: Weak<ShardedSinkImpl<T>> = Arc::downgrade(&self.pointer);
+        default_scheduler().schedule_after(
+            DELIVERY_DELAY,
+            Box::new(move || {
+                if let Some(pointer) = weak_pointer.upgrade() {
+                    let shard = &pointer.shards[shard_index];
+                    shard.is_delivery_scheduled.store(false, Ordering::Release);
+                    pointer.deliver_all(shard);
+                }
+            }),
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use smallvec::SmallVec;
+
+/// Identifies a value in a SlotMap. Keys are never reused: a slot that is freed and then filled
+/// again gets a new generation, so stale keys do not remove the new value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SlotKey {
+    index: u32,
+    generation: u32,
+}
+
+impl SlotKey {
+    /// A key that never refers to a value in any map.
+    pub(crate) const NULL: SlotKey = SlotKey {
+        index: u32::MAX,
+        generation: 0,
+    };
+}
+
+struct Slot {
+    generation: u32,
+    dense_index: Option<u32>,
+}
+
+/// A map that hands out its own keys. Values are stored contiguously, so iterating over them is
+/// as fast as iterating over a Vec, while inserts and removals are O(1). This makes it a good fit
+/// for subscriber lists, which are iterated on every emit but change rarely. Removing a value
+/// moves the last value into its place, so iteration order is not insertion order.
+///
+/// Up to `N` values are stored inline, without any heap allocation. The map only moves its
+/// contents to the heap once it grows beyond that.
+pub(crate) struct SlotMap<V, const N: usize> {
+    values: SmallVec<[V; N]>,
+    value_slots: SmallVec<[u32; N]>,
+    slots: SmallVec<[Slot; N]>,
+    free_slots: SmallVec<[u32; N]>,
+}
+
+impl<V, const N: usize> SlotMap<V, N> {
+    pub(crate) fn new() -> SlotMap<V, N> {
+        SlotMap {
+            values: SmallVec::new(),
+            value_slots: SmallVec::new(),
+            slots: SmallVec::new(),
+            free_slots: SmallVec::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: V) -> SlotKey {
+        let dense_index = Some(self.values.len() as u32);
+        let index = match self.free_slots.pop() {
+            Some(index) => {
+                self.slots[index as usize].dense_index = dense_index;
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    dense_index,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        self.values.push(value);
+        self.value_slots.push(index);
+        SlotKey {
+            index,
+            generation: self.slots[index as usize].generation,
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: SlotKey) -> Option<V> {
+        let slot = match self.slots.get_mut(key.index as usize) {
+            Some(slot) if slot.generation == key.generation => slot,
+            _ => return None,
+        };
+        let dense_index = slot.dense_index.take()? as usize;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(key.index);
+
+        let value = self.values.swap_remove(dense_index);
+        self.value_slots.swap_remove(dense_index);
+        if let Some(&moved_slot) = self.value_slots.get(dense_index) {
+            self.slots[moved_slot as usize].dense_index = Some(dense_index as u32);
+        }
+        Some(value)
+    }
+
+    pub(crate) fn values(&self) -> core::slice::Iter<'_, V> {
+        self.values.iter()
+    }
+
+    /// Removes every value, leaving all existing keys invalid.
+    pub(crate) fn drain(&mut self) -> SmallVec<[V; N]> {
+        for index in self.value_slots.drain(..) {
+            let slot = &mut self.slots[index as usize];
+            slot.dense_index = None;
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free_slots.push(index);
+        }
+        core::mem::take(&mut self.values)
+    }
+}
@@ -0,0 +1,270 @@
+use super::scheduler::Scheduler;
+use super::{CancellationToken, Stream, Subscription};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+
+/// Controls how `Stream::spawn_for_each` runs its tasks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpawnOptions {
+    /// The most tasks that may run at the same time. Values that arrive while this many tasks
+    /// are running wait in a queue. `None` means there is no limit.
+    pub max_concurrency: Option<usize>,
+
+    /// When true, a new value cancels the tasks of every earlier value and replaces any values
+    /// that are still queued, so only the latest value is worked on.
+    pub cancel_superseded: bool,
+}
+
+type SpawnedTask<T> = Arc<dyn Fn(Arc<T>, CancellationToken) + Send + Sync>;
+
+struct TaskGroupState<T> {
+    running: HashMap<u64, CancellationToken>,
+    queue: VecDeque<Arc<T>>,
+    next_task_id: u64,
+    is_source_complete: bool,
+    is_cancelled: bool,
+    subscription: Option<Subscription<T>>,
+}
+
+struct TaskGroupShared<T> {
+    state: Mutex<TaskGroupState<T>>,
+    finished: Condvar,
+    token: CancellationToken,
+    scheduler: Arc<dyn Scheduler>,
+    options: SpawnOptions,
+    task: SpawnedTask<T>,
+}
+
+impl<T> TaskGroupState<T> {
+    fn is_finished(&self) -> bool {
+        self.running.is_empty()
+            && self.queue.is_empty()
+            && (self.is_cancelled || self.is_source_complete)
+    }
+}
+
+impl<T: Send + Sync + 'static> TaskGroupShared<T> {
+    fn lock_state(&self) -> MutexGuard<'_, TaskGroupState<T>> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(err) => panic!("Task group mutex poisoned: {}", err),
+        }
+    }
+
+    fn on_value(self: &Arc<Self>, value: Arc<T>) {
+        let mut state = self.lock_state();
+        if state.is_cancelled {
+            return;
+        }
+        if self.options.cancel_superseded {
+            for token in state.running.values() {
+                token.cancel();
+            }
+            state.queue.clear();
+        }
+        state.queue.push_back(value);
+        self.start_queued(state);
+    }
+
+    /// Starts as many queued values as the concurrency limit allows.
+    fn start_queued(self: &Arc<Self>, mut state: MutexGuard<'_, TaskGroupState<T>>) {
+        let max_concurrency = self.options.max_concurrency.unwrap_or(usize::MAX).max(1);
+        let mut started = vec![];
+        while state.running.len() < max_concurrency {
+            let value = match state.queue.pop_front() {
+                Some(value) => value,
+                None => break,
+            };
+            let id = state.next_task_id;
+            state.next_task_id += 1;
+            let token = self.token.child_token();
+            state.running.insert(id, token.clone());
+            started.push((id, value, token));
+        }
+        drop(state);
+
+        for (id, value, token) in started {
+            let shared = self.clone();
+            self.scheduler.schedule(Box::new(move || {
+                // Marks the task as finished even if it panics, so that `join` does not hang.
+                let _guard = FinishedTaskGuard {
+                    shared: shared.clone(),
+                    id,
+                };
+                (shared.task)(value, token);
+            }));
+        }
+    }
+
+    fn on_task_finished(self: &Arc<Self>, id: u64) {
+        let mut state = self.lock_state();
+        state.running.remove(&id);
+        if state.is_finished() {
+            self.finished.notify_all();
+        } else {
+            self.start_queued(state);
+        }
+    }
+
+    fn on_source_complete(&self) {
+        let mut state = self.lock_state();
+        state.is_source_complete = true;
+        if state.is_finished() {
+            self.finished.notify_all();
+        }
+    }
+}
+
+struct FinishedTaskGuard<T: Send + Sync + 'static> {
+    shared: Arc<TaskGroupShared<T>>,
+    id: u64,
+}
+
+impl<T: Send + Sync + 'static> Drop for FinishedTaskGuard<T> {
+    fn drop(&mut self) {
+        self.shared.on_task_finished(self.id);
+    }
+}
+
+/// The tasks spawned by `Stream::spawn_for_each`. Dropping the group cancels it, so tasks never
+/// outlive the code that started them.
+pub struct TaskGroup<T: Send + Sync + 'static> {
+    shared: Arc<TaskGroupShared<T>>,
+}
+
+impl<T: Send + Sync + 'static> TaskGroup<T> {
+    /// Stops spawning tasks, discards queued values and cancels the tokens of running tasks.
+    /// Tasks stop cooperatively, by checking their token.
+    pub fn cancel(&self) {
+        let subscription = {
+            let mut state = self.shared.lock_state();
+            state.is_cancelled = true;
+            state.queue.clear();
+            if state.is_finished() {
+                self.shared.finished.notify_all();
+            }
+            state.subscription.take()
+        };
+        drop(subscription);
+        self.shared.token.cancel();
+    }
+
+    /// Blocks until the source stream has completed (or the group has been cancelled) and every
+    /// task has finished. This must not be called from a thread that the scheduler needs in order
+    /// to run the tasks, such as the only thread of a `DedicatedThreadScheduler`.
+    pub fn join(&self) {
+        let mut state = self.shared.lock_state();
+        while !state.is_finished() {
+            state = match self.shared.finished.wait(state) {
+                Ok(state) => state,
+                Err(err) => panic!("Task group mutex poisoned: {}", err),
+            };
+        }
+    }
+
+    /// Returns the number of tasks that are running (or waiting for the scheduler to run them).
+    pub fn count_running(&self) -> usize {
+        self.shared.lock_state().running.len()
+    }
+
+    /// Returns the number of values that are waiting for a free task slot.
+    pub fn count_queued(&self) -> usize {
+        self.shared.lock_state().queue.len()
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for TaskGroup<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl<T: Send + Sync + 'static> Stream<T> {
+    /// Runs `task` on the given scheduler for every value of the stream, instead of doing the
+    /// work inside a subscriber or on a hand-spawned thread. Each task receives a
+    /// `CancellationToken` that is cancelled when the task is superseded (see `SpawnOptions`) or
+    /// when the returned `TaskGroup` is cancelled or dropped. Long-running tasks should check it
+    /// regularly.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::scheduler::{ManualScheduler, Scheduler};
+    /// use epoxy_streams::SpawnOptions;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let scheduler = Arc::new(ManualScheduler::new());
+    /// let stream_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+    /// let searched = Arc::new(Mutex::new(vec![]));
+    /// let searched_ref = searched.clone();
+    /// let group = stream_host.get_stream().spawn_for_each(
+    ///     scheduler.clone(),
+    ///     SpawnOptions {
+    ///         max_concurrency: Some(1),
+    ///         cancel_superseded: true,
+    ///     },
+    ///     move |query, token| {
+    ///         if !token.is_cancelled() {
+    ///             searched_ref.lock().unwrap().push(*query);
+    ///         }
+    ///     },
+    /// );
+    ///
+    /// // The first query starts right away, the second one is replaced by the third.
+    /// stream_host.emit("e");
+    /// stream_host.emit("ep");
+    /// stream_host.emit("epo");
+    /// assert_eq!(group.count_running(), 1);
+    /// assert_eq!(group.count_queued(), 1);
+    ///
+    /// stream_host.close();
+    /// scheduler.run_until_idle();
+    /// group.join();
+    /// assert_eq!(*searched.lock().unwrap(), vec!["epo"]);
+    /// ```
+    pub fn spawn_for_each<F>(
+        &self,
+        scheduler: Arc<dyn Scheduler>,
+        options: SpawnOptions,
+        task: F,
+    ) -> TaskGroup<T>
+    where
+        F: Fn(Arc<T>, CancellationToken),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let shared = Arc::new(TaskGroupShared {
+            state: Mutex::new(TaskGroupState {
+                running: HashMap::new(),
+                queue: VecDeque::new(),
+                next_task_id: 0,
+                is_source_complete: false,
+                is_cancelled: false,
+                subscription: None,
+            }),
+            finished: Condvar::new(),
+            token: CancellationToken::new(),
+            scheduler,
+            options,
+            task: Arc::new(task),
+        });
+
+        let weak_value_ref: Weak<TaskGroupShared<T>> = Arc::downgrade(&shared);
+        let weak_completion_ref = weak_value_ref.clone();
+        let subscription = self.subscribe_with_completion(
+            move |value| {
+                if let Some(shared) = weak_value_ref.upgrade() {
+                    shared.on_value(value);
+                }
+            },
+            move || {
+                if let Some(shared) = weak_completion_ref.upgrade() {
+                    shared.on_source_complete();
+                }
+            },
+        );
+        shared.lock_state().subscription = Some(subscription);
+
+        TaskGroup { shared }
+    }
+}
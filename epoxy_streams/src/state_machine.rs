@@ -0,0 +1,138 @@
+use super::{ReactiveValue, ReadonlyReactiveValue, Sink, Stream, Subscription};
+use std::sync::Arc;
+
+/// Reported by a StateMachine when an event has no transition out of the current state. The
+/// machine stays in `state`.
+pub struct InvalidTransition<S, E> {
+    pub state: Arc<S>,
+    pub event: Arc<E>,
+}
+
+/// A finite state machine driven by a stream of events. Created with `Stream::scan_machine` or
+/// `Stream::scan_machine_with_table`.
+pub struct StateMachine<S, E> {
+    state: ReadonlyReactiveValue<S>,
+    invalid_transitions: Stream<InvalidTransition<S, E>>,
+
+    #[allow(dead_code)]
+    subscription: Subscription<E>,
+}
+
+impl<S: 'static + Send + Sync, E: 'static + Send + Sync> StateMachine<S, E> {
+    /// Returns the current state of the machine, which changes with every valid transition.
+    pub fn state(&self) -> ReadonlyReactiveValue<S> {
+        self.state.clone()
+    }
+
+    /// Returns a stream of the events that were rejected because they had no transition out of
+    /// the state the machine was in.
+    pub fn get_invalid_transitions(&self) -> Stream<InvalidTransition<S, E>> {
+        self.invalid_transitions.clone()
+    }
+}
+
+impl<E: 'static + Send + Sync> Stream<E> {
+    /// Runs a state machine over the events in this stream. For each event, `transition` is
+    /// called with the current state and returns the next state, or None if the event is not
+    /// allowed in the current state. Rejected events are reported on the machine's
+    /// `get_invalid_transitions` stream. The machine stops once it is dropped.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, ReactiveValue, Sink};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Door { Open, Closed, Locked }
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Action { Open, Close, Lock, Unlock }
+    ///
+    /// let actions: Sink<Action> = Sink::new();
+    /// let door = actions.get_stream().scan_machine(Door::Closed, |door, action| {
+    ///     match (door, action) {
+    ///         (Door::Closed, Action::Open) => Some(Door::Open),
+    ///         (Door::Open, Action::Close) => Some(Door::Closed),
+    ///         (Door::Closed, Action::Lock) => Some(Door::Locked),
+    ///         (Door::Locked, Action::Unlock) => Some(Door::Closed),
+    ///         _ => None,
+    ///     }
+    /// });
+    /// let rejected = ReactiveCache::from_stream(
+    ///     door.get_invalid_transitions().map(|invalid| format!("{:?}", invalid.event)),
+    /// );
+    ///
+    /// actions.emit(Action::Lock);
+    /// assert_eq!(*door.state().get(), Door::Locked);
+    ///
+    /// actions.emit(Action::Open);
+    /// assert_eq!(*door.state().get(), Door::Locked);
+    /// assert_eq!(rejected.get_cloned(), vec!["Open".to_string()]);
+    /// ```
+    pub fn scan_machine<S, F>(&self, initial_state: S, transition: F) -> StateMachine<S, E>
+    where
+        S: 'static + Send + Sync,
+        F: Fn(&S, &E) -> Option<S>,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let state = <dyn ReactiveValue<S>>::new(initial_state);
+        let invalid_transitions = Sink::new();
+        let readonly_state = state.as_readonly();
+        let invalid_transitions_stream = invalid_transitions.get_stream();
+
+        // Emits on a single stream never overlap, so reading and then setting the state here
+        // cannot race with another event.
+        let subscription = self.subscribe(move |event| {
+            let current_state = state.get();
+            match transition(&current_state, &event) {
+                Some(next_state) => state.set(next_state),
+                None => invalid_transitions.emit(InvalidTransition {
+                    state: current_state,
+                    event,
+                }),
+            }
+        });
+
+        StateMachine {
+            state: readonly_state,
+            invalid_transitions: invalid_transitions_stream,
+            subscription,
+        }
+    }
+
+    /// Same as `scan_machine`, but transitions are listed in a table of
+    /// `(from_state, event, to_state)` entries instead of being computed by a function.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveValue, Sink};
+    ///
+    /// let events: Sink<&'static str> = Sink::new();
+    /// let light = events.get_stream().scan_machine_with_table("red", vec![
+    ///     ("red", "next", "green"),
+    ///     ("green", "next", "yellow"),
+    ///     ("yellow", "next", "red"),
+    /// ]);
+    ///
+    /// events.emit("next");
+    /// events.emit("next");
+    /// assert_eq!(*light.state().get(), "yellow");
+    /// ```
+    pub fn scan_machine_with_table<S>(
+        &self,
+        initial_state: S,
+        table: Vec<(S, E, S)>,
+    ) -> StateMachine<S, E>
+    where
+        S: 'static + Send + Sync + Clone + PartialEq,
+        E: PartialEq,
+    {
+        self.scan_machine(initial_state, move |state, event| {
+            table
+                .iter()
+                .find(|(from, on, _)| from == state && on == event)
+                .map(|(_, _, to)| to.clone())
+        })
+    }
+}
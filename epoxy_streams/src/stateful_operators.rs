@@ -1,5 +1,7 @@
 use super::{Stream, Subscription};
-use std::sync::Arc;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 
 pub struct StatefulDerivedStreamFields<T, StateType> {
     state: StateType,
@@ -52,28 +54,41 @@ impl<T: 'static> Stream<T> {
         U: Send,
         U: Sync,
     {
-        let derived_stream = Stream::new_with_fields::<StatefulDerivedStreamFields<T, Arc<U>>>(
+        let derived_stream = self.derive_with_fields::<U, StatefulDerivedStreamFields<T, Arc<U>>>(
             StatefulDerivedStreamFields {
                 state: Arc::new(initial_value),
                 subscription: None,
             },
         );
+        #[cfg(feature = "checkpoint")]
+        derived_stream.set_state_hooks(
+            |fields: &StatefulDerivedStreamFields<T, Arc<U>>| vec![fields.state.clone()],
+            |fields: &mut StatefulDerivedStreamFields<T, Arc<U>>, values| {
+                if let Some(state) = values.into_iter().last() {
+                    fields.state = state;
+                }
+            },
+        );
         let subscription_stream_ref = derived_stream.clone();
+        let completion_stream_ref = derived_stream.clone();
 
-        let subscription = self.subscribe(move |val| {
-            let new_state = subscription_stream_ref.read_extra_fields(
-                |fields: &StatefulDerivedStreamFields<T, Arc<U>>| {
-                    Arc::new(scan_fn(&fields.state, val))
-                },
-            );
-
-            subscription_stream_ref.mutate_extra_fields(
-                |fields: &mut StatefulDerivedStreamFields<T, Arc<U>>| {
-                    fields.state = Arc::clone(&new_state)
-                },
-            );
-            subscription_stream_ref.emit_rc(new_state);
-        });
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let new_state = subscription_stream_ref.read_extra_fields(
+                    |fields: &StatefulDerivedStreamFields<T, Arc<U>>| {
+                        Arc::new(scan_fn(&fields.state, val))
+                    },
+                );
+
+                subscription_stream_ref.mutate_extra_fields(
+                    |fields: &mut StatefulDerivedStreamFields<T, Arc<U>>| {
+                        fields.state = Arc::clone(&new_state)
+                    },
+                );
+                subscription_stream_ref.emit_rc(new_state);
+            },
+            move || completion_stream_ref.complete(),
+        );
 
         derived_stream.mutate_extra_fields(
             move |fields: &mut StatefulDerivedStreamFields<T, Arc<U>>| {
@@ -146,11 +161,11 @@ impl<T: 'static> Stream<T> {
         self.scan(
             move |acc, val| {
                 let mut extended = vec![];
-                if acc.len() > 0 && acc.len() < max_buffer_size {
+                if !acc.is_empty() && acc.len() < max_buffer_size {
                     extended.push(acc[0].clone());
                 }
-                for i in 1..acc.len() {
-                    extended.push(acc[i].clone());
+                for item in acc.iter().skip(1) {
+                    extended.push(item.clone());
                 }
                 extended.push((*val).clone());
                 extended
@@ -164,7 +179,7 @@ impl<T: 'static> Stream<T> {
     /// (1, 2, 3). Note that this does _not_ dedup the entire stream, it just prevents
     /// the same value from being emitted twice in a row. So (1, 1, 2, 1, 3) would
     /// turn into (1, 2, 1, 3), not (1, 2, 3).
-    /// 
+    ///
     /// # Examples
     /// ```
     /// use std::sync::{Arc, Mutex};
@@ -183,42 +198,140 @@ impl<T: 'static> Stream<T> {
     /// stream_host.emit(3);
     /// assert_eq!(cache.get_cloned(), vec![2, 3]);
     /// ```
-    pub fn distinct_until_changed(&self) -> Stream<T> where 
+    pub fn distinct_until_changed(&self) -> Stream<T>
+    where
         T: Send,
         T: Sync,
-        T: Eq
+        T: Eq,
     {
-        let derived_stream = Stream::new_with_fields::<StatefulDerivedStreamFields<T, Option<Arc<T>>>>(
-            StatefulDerivedStreamFields {
+        let derived_stream =
+            self.derive_with_fields(StatefulDerivedStreamFields::<T, Option<Arc<T>>> {
                 state: None,
                 subscription: None,
-            },
-        );
+            });
         let subscription_stream_ref = derived_stream.clone();
+        let completion_stream_ref = derived_stream.clone();
 
-        let subscription = self.subscribe(move |val| {
-            let is_duplicate = subscription_stream_ref.read_extra_fields(
-                |fields: &StatefulDerivedStreamFields<T, Option<Arc<T>>>| {
-                   if let Some(last_val) = &fields.state {
-                       last_val == &val
-                   } else {
-                       false
-                   }
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let is_duplicate = subscription_stream_ref.read_extra_fields(
+                    |fields: &StatefulDerivedStreamFields<T, Option<Arc<T>>>| {
+                        if let Some(last_val) = &fields.state {
+                            last_val == &val
+                        } else {
+                            false
+                        }
+                    },
+                );
+
+                if !is_duplicate {
+                    subscription_stream_ref.mutate_extra_fields(
+                        |fields: &mut StatefulDerivedStreamFields<T, Option<Arc<T>>>| {
+                            fields.state = Some(val.clone());
+                        },
+                    );
+                    subscription_stream_ref.emit_rc(val);
                 }
-            );
+            },
+            move || completion_stream_ref.complete(),
+        );
+
+        derived_stream.mutate_extra_fields(
+            move |fields: &mut StatefulDerivedStreamFields<T, Option<Arc<T>>>| {
+                fields.subscription = Some(subscription);
+            },
+        );
+
+        derived_stream
+    }
+
+    /// Collects values into chunks that are separated by boundary values, as decided by
+    /// `is_boundary`. This frames a stream of bytes, characters or lines into records. Where the
+    /// boundary value itself ends up is decided by `boundary` (see `ChunkBoundary`). When the
+    /// stream completes, any partial chunk is emitted before the derived stream completes.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ChunkBoundary, ReactiveCache};
+    ///
+    /// let stream_host: epoxy_streams::Sink<u8> = epoxy_streams::Sink::new();
+    /// let lines = stream_host
+    ///     .get_stream()
+    ///     .chunk_by(|byte| *byte == b'\n', ChunkBoundary::Excluded);
+    /// let cache = ReactiveCache::from_stream(lines);
+    ///
+    /// for byte in b"ab\n\ncd" {
+    ///     stream_host.emit(*byte);
+    /// }
+    /// assert_eq!(cache.get_cloned(), vec![b"ab".to_vec(), vec![]]);
+    ///
+    /// stream_host.close();
+    /// assert_eq!(cache.get_cloned(), vec![b"ab".to_vec(), vec![], b"cd".to_vec()]);
+    /// ```
+    pub fn chunk_by<F>(&self, is_boundary: F, boundary: ChunkBoundary) -> Stream<Vec<T>>
+    where
+        T: Clone,
+        T: Send,
+        T: Sync,
+        F: Fn(&T) -> bool,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let derived_stream = self.derive_with_fields(StatefulDerivedStreamFields::<T, Vec<T>> {
+            state: vec![],
+            subscription: None,
+        });
+        let subscription_stream_ref = derived_stream.clone();
+        let completion_stream_ref = derived_stream.clone();
 
-            if !is_duplicate {
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let mut chunk = None;
                 subscription_stream_ref.mutate_extra_fields(
-                    |fields: &mut StatefulDerivedStreamFields<T, Option<Arc<T>>>| {
-                        fields.state = Some(val.clone());
-                    }
+                    |fields: &mut StatefulDerivedStreamFields<T, Vec<T>>| {
+                        if !is_boundary(&val) {
+                            fields.state.push((*val).clone());
+                            return;
+                        }
+                        chunk = match boundary {
+                            ChunkBoundary::Excluded => Some(core::mem::take(&mut fields.state)),
+                            ChunkBoundary::EndsChunk => {
+                                fields.state.push((*val).clone());
+                                Some(core::mem::take(&mut fields.state))
+                            }
+                            ChunkBoundary::StartsChunk => {
+                                let previous =
+                                    core::mem::replace(&mut fields.state, vec![(*val).clone()]);
+                                if previous.is_empty() {
+                                    None
+                                } else {
+                                    Some(previous)
+                                }
+                            }
+                        };
+                    },
                 );
-                subscription_stream_ref.emit_rc(val);
-            }
-        });
+                if let Some(chunk) = chunk {
+                    subscription_stream_ref.emit_rc(Arc::new(chunk));
+                }
+            },
+            move || {
+                let mut chunk = vec![];
+                completion_stream_ref.mutate_extra_fields(
+                    |fields: &mut StatefulDerivedStreamFields<T, Vec<T>>| {
+                        chunk = core::mem::take(&mut fields.state);
+                    },
+                );
+                if !chunk.is_empty() {
+                    completion_stream_ref.emit_rc(Arc::new(chunk));
+                }
+                completion_stream_ref.complete();
+            },
+        );
 
         derived_stream.mutate_extra_fields(
-            move |fields: &mut StatefulDerivedStreamFields<T, Option<Arc<T>>>| {
+            move |fields: &mut StatefulDerivedStreamFields<T, Vec<T>>| {
                 fields.subscription = Some(subscription);
             },
         );
@@ -226,3 +339,16 @@ impl<T: 'static> Stream<T> {
         derived_stream
     }
 }
+
+/// Decides what `Stream::chunk_by` does with the values that separate chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkBoundary {
+    /// Boundary values are dropped, like the newlines between lines of text.
+    Excluded,
+
+    /// Boundary values are the last value of the chunk they end, like a message terminator.
+    EndsChunk,
+
+    /// Boundary values are the first value of the chunk they start, like a record header.
+    StartsChunk,
+}
@@ -1,5 +1,5 @@
-use super::{Stream, Subscription};
-use std::sync::Arc;
+use super::{Sink, Stream, Subscription};
+use alloc::sync::Arc;
 
 pub struct DerivedStreamFields<T> {
     #[allow(dead_code)]
@@ -16,13 +16,14 @@ impl<T: 'static + Send + Sync> Stream<T> {
         U: 'static,
     {
         let derived_stream =
-            Stream::new_with_fields::<DerivedStreamFields<T>>(DerivedStreamFields {
-                subscription: None,
-            });
+            self.derive_with_fields(DerivedStreamFields::<T> { subscription: None });
         let subscription_stream_ref = derived_stream.clone();
+        let completion_stream_ref = derived_stream.clone();
 
-        let subscription =
-            self.subscribe(move |val| subscription_re_emit(&subscription_stream_ref, val));
+        let subscription = self.subscribe_with_completion(
+            move |val| subscription_re_emit(&subscription_stream_ref, val),
+            move || completion_stream_ref.complete(),
+        );
 
         derived_stream.mutate_extra_fields(move |fields: &mut DerivedStreamFields<T>| {
             fields.subscription = Some(subscription);
@@ -155,6 +156,41 @@ impl<T: 'static + Send + Sync> Stream<T> {
         })
     }
 
+    /// Same as `map`, but the mapping function receives an owned clone of each value rather than
+    /// a reference. Convenient for small Clone types where the function consumes its input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let stream_host: epoxy_streams::Sink<String> = epoxy_streams::Sink::new();
+    /// let stream = stream_host.get_stream();
+    ///
+    /// let shouted = stream
+    ///     .map_values(|mut val: String| {
+    ///         val.push('!');
+    ///         val
+    ///     })
+    ///     .to_reactive_value();
+    ///
+    /// stream_host.emit("hello".to_string());
+    /// assert_eq!(*shouted.get(), "hello!");
+    /// ```
+    pub fn map_values<U, F>(&self, map_function: F) -> Stream<U>
+    where
+        T: Clone,
+        U: 'static,
+        F: Fn(T) -> U,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.create_derived_stream(move |host, val| {
+            host.emit_rc(Arc::new(map_function((*val).clone())));
+        })
+    }
+
     /// Returns a stream that can emit multiple values for each value from the original stream.
     ///
     /// # Examples
@@ -190,12 +226,8 @@ impl<T: 'static + Send + Sync> Stream<T> {
         F: 'static,
     {
         self.create_derived_stream(move |host, val| {
-            let mut iter = iter_map_function(&*val).into_iter();
-            loop {
-                match iter.next() {
-                    Some(x) => host.emit_rc(Arc::new(x)),
-                    None => break,
-                }
+            for x in iter_map_function(&*val) {
+                host.emit_rc(Arc::new(x));
             }
         })
     }
@@ -215,4 +247,172 @@ impl<T: 'static + Send + Sync> Stream<T> {
             host.emit_rc(val.clone());
         })
     }
+
+    /// Returns a stream whose values are the Arc pointers of the original stream, so subscribers
+    /// receive `Arc<Arc<T>>`. No values are copied. This is mostly useful when handing a stream
+    /// to code that expects its items to already be reference-counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let nested = stream_host.get_stream().nest_arc().to_reactive_value();
+    ///
+    /// let value = Arc::new(5);
+    /// stream_host.emit_rc(value.clone());
+    /// assert!(Arc::ptr_eq(&*nested.get(), &value));
+    /// ```
+    pub fn nest_arc(&self) -> Stream<Arc<T>> {
+        self.create_derived_stream(move |host, val| {
+            host.emit_rc(Arc::new(val));
+        })
+    }
+}
+
+impl<T: 'static + Send + Sync> Stream<Arc<T>> {
+    /// Unwraps the outer Arc of a `Stream<Arc<T>>`, so that subscribers receive `Arc<T>` instead
+    /// of `Arc<Arc<T>>`. The inner pointer is passed along as-is, so no values are copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Arc<i32>> = epoxy_streams::Sink::new();
+    /// let flattened = stream_host.get_stream().flatten_arc().to_reactive_value();
+    ///
+    /// let value = Arc::new(5);
+    /// stream_host.emit(value.clone());
+    /// assert_eq!(*flattened.get(), 5);
+    /// assert!(Arc::ptr_eq(&flattened.get(), &value));
+    /// ```
+    pub fn flatten_arc(&self) -> Stream<T> {
+        self.create_derived_stream(move |host, val| {
+            host.emit_rc(Arc::clone(&*val));
+        })
+    }
+}
+
+impl<T: 'static + Send + Sync> Sink<Arc<T>> {
+    /// Returns the stream of this Sink with the outer Arc unwrapped (see `Stream::flatten_arc`).
+    /// Hand this out instead of `get_stream` when a `Sink<Arc<T>>` can not be avoided, so that
+    /// subscribers never see `Arc<Arc<T>>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Arc<String>> = epoxy_streams::Sink::new();
+    /// let names: epoxy_streams::Stream<String> = stream_host.get_flattened_stream();
+    /// let latest = names.to_reactive_value();
+    ///
+    /// stream_host.emit(Arc::new("Ada".to_string()));
+    /// assert_eq!(*latest.get(), "Ada".to_string());
+    /// ```
+    #[must_use]
+    pub fn get_flattened_stream(&self) -> Stream<T> {
+        self.get_stream().flatten_arc()
+    }
+}
+
+impl<T, E> Stream<Result<T, E>>
+where
+    T: Send,
+    T: Sync,
+    T: 'static,
+    E: Send,
+    E: Sync,
+    E: 'static,
+{
+    /// Returns a stream that emits the unwrapped content of every `Ok` value of the original
+    /// stream, skipping any errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Result<i32, String>> = epoxy_streams::Sink::new();
+    /// let values = ReactiveCache::from_stream(stream_host.get_stream().ok_values());
+    ///
+    /// stream_host.emit(Ok(1));
+    /// stream_host.emit(Err("Oops".to_string()));
+    /// stream_host.emit(Ok(2));
+    /// assert_eq!(values.get_cloned(), vec![1, 2]);
+    /// ```
+    pub fn ok_values(&self) -> Stream<T>
+    where
+        T: Clone,
+    {
+        self.create_derived_stream(move |host, val| {
+            if let Ok(ok_value) = &*val {
+                host.emit_rc(Arc::new(ok_value.clone()));
+            }
+        })
+    }
+
+    /// Returns a stream that emits the unwrapped content of every `Err` value of the original
+    /// stream, skipping any successful values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Result<i32, String>> = epoxy_streams::Sink::new();
+    /// let errors = ReactiveCache::from_stream(stream_host.get_stream().err_values());
+    ///
+    /// stream_host.emit(Ok(1));
+    /// stream_host.emit(Err("Oops".to_string()));
+    /// stream_host.emit(Ok(2));
+    /// assert_eq!(errors.get_cloned(), vec!["Oops".to_string()]);
+    /// ```
+    pub fn err_values(&self) -> Stream<E>
+    where
+        E: Clone,
+    {
+        self.create_derived_stream(move |host, val| {
+            if let Err(err_value) = &*val {
+                host.emit_rc(Arc::new(err_value.clone()));
+            }
+        })
+    }
+}
+
+impl<T> Stream<Option<T>>
+where
+    T: Clone,
+    T: Send,
+    T: Sync,
+    T: 'static,
+{
+    /// Returns a stream that emits the unwrapped content of every `Some` value of the original
+    /// stream, skipping any `None` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<Option<i32>> = epoxy_streams::Sink::new();
+    /// let values = ReactiveCache::from_stream(stream_host.get_stream().some_values());
+    ///
+    /// stream_host.emit(Some(1));
+    /// stream_host.emit(None);
+    /// stream_host.emit(Some(2));
+    /// assert_eq!(values.get_cloned(), vec![1, 2]);
+    /// ```
+    pub fn some_values(&self) -> Stream<T> {
+        self.create_derived_stream(move |host, val| {
+            if let Some(some_value) = &*val {
+                host.emit_rc(Arc::new(some_value.clone()));
+            }
+        })
+    }
 }
@@ -0,0 +1,175 @@
+use super::{Stream, Subscription};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+struct StatisticFields<T, S> {
+    state: S,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+/// The values currently in a sliding window, along with their running mean and sum of squared
+/// differences from the mean (Welford's algorithm), so both can be updated in constant time.
+struct SlidingWindow {
+    values: VecDeque<f64>,
+    size: usize,
+    mean: f64,
+    squared_differences: f64,
+}
+
+impl SlidingWindow {
+    fn new(size: usize) -> SlidingWindow {
+        let size = size.max(1);
+        SlidingWindow {
+            values: VecDeque::with_capacity(size),
+            size,
+            mean: 0.0,
+            squared_differences: 0.0,
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        if self.values.len() == self.size {
+            let removed = self.values.pop_front().unwrap();
+            let count = self.values.len() as f64;
+            if count == 0.0 {
+                self.mean = 0.0;
+                self.squared_differences = 0.0;
+            } else {
+                let delta = removed - self.mean;
+                self.mean -= delta / count;
+                self.squared_differences -= delta * (removed - self.mean);
+            }
+        }
+
+        self.values.push_back(value);
+        let delta = value - self.mean;
+        self.mean += delta / self.values.len() as f64;
+        self.squared_differences += delta * (value - self.mean);
+        // Rounding errors can push this slightly below zero when every value is the same.
+        self.squared_differences = self.squared_differences.max(0.0);
+    }
+
+    fn standard_deviation(&self) -> f64 {
+        (self.squared_differences / self.values.len() as f64).sqrt()
+    }
+}
+
+impl<T> Stream<T>
+where
+    T: Copy + Into<f64> + Send + Sync + 'static,
+{
+    /// Returns a stream that emits the average of the last `window_size` values each time the
+    /// original stream emits. Until `window_size` values have arrived, the average covers every
+    /// value so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let cache = ReactiveCache::from_stream(stream_host.get_stream().moving_average(2));
+    ///
+    /// stream_host.emit(2);
+    /// stream_host.emit(4);
+    /// stream_host.emit(10);
+    /// assert_eq!(cache.get_cloned(), vec![2.0, 3.0, 7.0]);
+    /// ```
+    pub fn moving_average(&self, window_size: usize) -> Stream<f64> {
+        self.statistic(SlidingWindow::new(window_size), |window, value| {
+            window.push(value);
+            window.mean
+        })
+    }
+
+    /// Returns a stream that emits the exponential moving average of the original stream, where
+    /// each new value has a weight of `alpha` (between 0 and 1) and the previous average a weight
+    /// of `1 - alpha`. The first value is emitted as is. Higher values of `alpha` follow changes
+    /// more closely, lower values smooth out more noise.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<f32> = epoxy_streams::Sink::new();
+    /// let cache = ReactiveCache::from_stream(stream_host.get_stream().ema(0.5));
+    ///
+    /// stream_host.emit(10.0);
+    /// stream_host.emit(20.0);
+    /// stream_host.emit(20.0);
+    /// assert_eq!(cache.get_cloned(), vec![10.0, 15.0, 17.5]);
+    /// ```
+    pub fn ema(&self, alpha: f64) -> Stream<f64> {
+        self.statistic(None, move |average: &mut Option<f64>, value| {
+            let next = match *average {
+                Some(average) => alpha * value + (1.0 - alpha) * average,
+                None => value,
+            };
+            *average = Some(next);
+            next
+        })
+    }
+
+    /// Returns a stream that emits the (population) standard deviation of the last `window_size`
+    /// values each time the original stream emits. Until `window_size` values have arrived, it
+    /// covers every value so far.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<u8> = epoxy_streams::Sink::new();
+    /// let cache = ReactiveCache::from_stream(stream_host.get_stream().windowed_stddev(2));
+    ///
+    /// stream_host.emit(5);
+    /// stream_host.emit(7);
+    /// stream_host.emit(7);
+    /// assert_eq!(cache.get_cloned(), vec![0.0, 1.0, 0.0]);
+    /// ```
+    pub fn windowed_stddev(&self, window_size: usize) -> Stream<f64> {
+        self.statistic(SlidingWindow::new(window_size), |window, value| {
+            window.push(value);
+            window.standard_deviation()
+        })
+    }
+
+    /// Feeds every value into `state` using `step`, and emits the number it returns.
+    fn statistic<S, F>(&self, state: S, step: F) -> Stream<f64>
+    where
+        S: Send + Sync + 'static,
+        F: Fn(&mut S, f64) -> f64 + Send + Sync + 'static,
+    {
+        let derived_stream = self.derive_with_fields(StatisticFields::<T, S> {
+            state,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                let mut result = 0.0;
+                stream_ref.mutate_extra_fields(|fields: &mut StatisticFields<T, S>| {
+                    result = step(&mut fields.state, (*val).into());
+                });
+                stream_ref.emit_rc(Arc::new(result));
+            },
+            move || {
+                if let Some(pointer) = weak_completion_ref.upgrade() {
+                    Stream { pointer }.complete();
+                }
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut StatisticFields<T, S>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
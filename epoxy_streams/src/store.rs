@@ -0,0 +1,278 @@
+//! A Redux-style store. All application state lives in a single value that only changes when an
+//! action is dispatched, by running that action through a pure reducer function. Components read
+//! the parts of the state they care about with `select`, which returns ReactiveValues that only
+//! change when the selected part does.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::store::ReduxStore;
+//! use epoxy_streams::ReactiveValue;
+//! use std::sync::{Arc, Mutex};
+//!
+//! #[derive(Clone, Default)]
+//! struct Todos {
+//!     items: Vec<String>,
+//!     filter: String,
+//! }
+//!
+//! enum Action {
+//!     Add(String),
+//!     SetFilter(String),
+//! }
+//!
+//! let store = ReduxStore::new(Todos::default(), |state: &Todos, action: &Action| {
+//!     let mut state = state.clone();
+//!     match action {
+//!         Action::Add(item) => state.items.push(item.clone()),
+//!         Action::SetFilter(filter) => state.filter = filter.clone(),
+//!     }
+//!     state
+//! });
+//!
+//! let log = Arc::new(Mutex::new(vec![]));
+//! let log_write = log.clone();
+//! store.add_middleware(move |_store, action, next| {
+//!     if let Action::Add(item) = &action {
+//!         log_write.lock().unwrap().push(item.clone());
+//!     }
+//!     next(action);
+//! });
+//!
+//! let item_count = store.select(|state| state.items.len());
+//! store.dispatch(Action::Add("Buy milk".to_string()));
+//! store.dispatch(Action::SetFilter("milk".to_string()));
+//!
+//! assert_eq!(*item_count.get(), 1);
+//! assert_eq!(store.get_state().filter, "milk");
+//! assert_eq!(*log.lock().unwrap(), vec!["Buy milk".to_string()]);
+//! ```
+use super::{ReactiveValue, ReadonlyReactiveValue, Sink, Stream, WriteableReactiveValue};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A middleware function. It receives the store, the dispatched action and a `next` function
+/// that passes the action on to the next middleware (and eventually the reducer). Middleware can
+/// inspect or replace the action, dispatch other actions, or swallow the action by not calling
+/// `next` at all.
+pub type Middleware<S, A> = dyn Fn(&ReduxStore<S, A>, A, &dyn Fn(A)) + Send + Sync;
+
+type Reducer<S, A> = dyn Fn(&S, &A) -> S + Send + Sync;
+
+struct DispatchQueue<A> {
+    actions: VecDeque<A>,
+    is_reducing: bool,
+}
+
+struct StoreImpl<S, A> {
+    state: WriteableReactiveValue<S>,
+    reducer: Box<Reducer<S, A>>,
+    middleware: Mutex<Vec<Arc<Middleware<S, A>>>>,
+    queue: Mutex<DispatchQueue<A>>,
+    actions: Sink<A>,
+}
+
+/// A single source of truth for application state. See the module documentation.
+///
+/// Handles are cheap to clone, and every clone refers to the same store.
+pub struct ReduxStore<S, A> {
+    pointer: Arc<StoreImpl<S, A>>,
+}
+
+impl<S, A> Clone for ReduxStore<S, A> {
+    fn clone(&self) -> Self {
+        ReduxStore {
+            pointer: Arc::clone(&self.pointer),
+        }
+    }
+}
+
+impl<S, A> ReduxStore<S, A>
+where
+    S: Send + Sync + 'static,
+    A: Send + Sync + 'static,
+{
+    /// Creates a store with the given initial state. The reducer computes the next state from the
+    /// current state and an action, and should not have side effects. Side effects belong in
+    /// middleware or thunks.
+    pub fn new<R>(initial_state: S, reducer: R) -> ReduxStore<S, A>
+    where
+        R: Fn(&S, &A) -> S,
+        R: Send,
+        R: Sync,
+        R: 'static,
+    {
+        ReduxStore {
+            pointer: Arc::new(StoreImpl {
+                state: <dyn ReactiveValue<S>>::new(initial_state),
+                reducer: Box::new(reducer),
+                middleware: Mutex::new(vec![]),
+                queue: Mutex::new(DispatchQueue {
+                    actions: VecDeque::new(),
+                    is_reducing: false,
+                }),
+                actions: Sink::new(),
+            }),
+        }
+    }
+
+    /// Returns the current state.
+    pub fn get_state(&self) -> Arc<S> {
+        self.pointer.state.get()
+    }
+
+    /// Returns the state as a ReactiveValue, which changes after every action.
+    pub fn state(&self) -> ReadonlyReactiveValue<S> {
+        self.pointer.state.as_readonly()
+    }
+
+    /// Returns a stream of every action that reached the reducer, emitted after the state has
+    /// been updated.
+    pub fn get_actions(&self) -> Stream<A> {
+        self.pointer.actions.get_stream()
+    }
+
+    /// Adds a middleware function to the end of the chain. Middleware runs in the order it was
+    /// added, so the first middleware sees every action before anything else does.
+    pub fn add_middleware<F>(&self, middleware: F)
+    where
+        F: Fn(&ReduxStore<S, A>, A, &dyn Fn(A)),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.lock_middleware().push(Arc::new(middleware));
+    }
+
+    /// Sends an action through the middleware chain and then the reducer.
+    ///
+    /// Actions are reduced one at a time. An action dispatched while another one is being
+    /// reduced (for example from a subscriber to the state) is queued, and is reduced as soon as
+    /// the current action finishes, before `dispatch` returns on the thread that is reducing.
+    pub fn dispatch(&self, action: A) {
+        let middleware = self.lock_middleware().clone();
+        self.run_middleware(&middleware, action);
+    }
+
+    /// Runs a function with access to the store. This is useful for logic that needs to read the
+    /// state before deciding what to dispatch, or that dispatches later from another thread.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::store::ReduxStore;
+    ///
+    /// let store = ReduxStore::new(0, |count: &i32, increment: &i32| count + increment);
+    /// store.dispatch_thunk(|store| {
+    ///     if *store.get_state() < 10 {
+    ///         store.dispatch(10);
+    ///     }
+    /// });
+    /// assert_eq!(*store.get_state(), 10);
+    /// ```
+    pub fn dispatch_thunk<F>(&self, thunk: F)
+    where
+        F: FnOnce(&ReduxStore<S, A>),
+    {
+        thunk(self)
+    }
+
+    /// Returns a ReactiveValue computed from the state. It only changes when the selected value
+    /// changes, so subscribers are not notified about unrelated updates to the state.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::store::ReduxStore;
+    /// use epoxy_streams::ReactiveValue;
+    ///
+    /// let store = ReduxStore::new((0, 0), |state: &(i32, i32), action: &(i32, i32)| {
+    ///     (state.0 + action.0, state.1 + action.1)
+    /// });
+    /// let first = store.select(|state| state.0);
+    /// let first_changes = first.as_stream().count_values().to_reactive_value();
+    ///
+    /// store.dispatch((0, 1));
+    /// store.dispatch((1, 0));
+    /// assert_eq!(*first.get(), 1);
+    /// assert_eq!(*first_changes.get(), 1);
+    /// ```
+    pub fn select<U, F>(&self, selector: F) -> ReadonlyReactiveValue<U>
+    where
+        U: 'static,
+        U: Send,
+        U: Sync,
+        U: Clone,
+        U: PartialEq,
+        F: Fn(&S) -> U,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let initial_value = selector(&self.get_state());
+        let last_value = Mutex::new(initial_value.clone());
+        self.pointer
+            .state
+            .as_stream()
+            .map(selector)
+            .filter(move |val| {
+                let mut last_value = match last_value.lock() {
+                    Ok(last_value) => last_value,
+                    Err(err) => panic!("Store mutex poisoned: {}", err),
+                };
+                if *last_value == *val {
+                    false
+                } else {
+                    *last_value = val.clone();
+                    true
+                }
+            })
+            .to_reactive_value_with_default(initial_value)
+    }
+
+    fn lock_middleware(&self) -> MutexGuard<'_, Vec<Arc<Middleware<S, A>>>> {
+        match self.pointer.middleware.lock() {
+            Ok(middleware) => middleware,
+            Err(err) => panic!("Store mutex poisoned: {}", err),
+        }
+    }
+
+    fn lock_queue(&self) -> MutexGuard<'_, DispatchQueue<A>> {
+        match self.pointer.queue.lock() {
+            Ok(queue) => queue,
+            Err(err) => panic!("Store mutex poisoned: {}", err),
+        }
+    }
+
+    fn run_middleware(&self, middleware: &[Arc<Middleware<S, A>>], action: A) {
+        match middleware.split_first() {
+            Some((first, rest)) => first(self, action, &|action| self.run_middleware(rest, action)),
+            None => self.reduce(action),
+        }
+    }
+
+    fn reduce(&self, action: A) {
+        {
+            let mut queue = self.lock_queue();
+            queue.actions.push_back(action);
+            if queue.is_reducing {
+                return;
+            }
+            queue.is_reducing = true;
+        }
+
+        loop {
+            let action = {
+                let mut queue = self.lock_queue();
+                match queue.actions.pop_front() {
+                    Some(action) => action,
+                    None => {
+                        queue.is_reducing = false;
+                        return;
+                    }
+                }
+            };
+            let pointer = &self.pointer;
+            let next_state = (pointer.reducer)(&pointer.state.get(), &action);
+            pointer.state.set(next_state);
+            pointer.actions.emit(action);
+        }
+    }
+}
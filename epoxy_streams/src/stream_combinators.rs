@@ -1,12 +1,24 @@
-use super::{Stream, Subscription};
-use std::sync::Arc;
+#[cfg(feature = "std")]
+use super::streams::StreamImpl;
+#[cfg(feature = "std")]
+use super::sync::Mutex;
+use super::{IntoStream, Stream, Subscription};
+#[cfg(feature = "std")]
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use alloc::sync::Weak;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct CombinedStreamFields<T> {
     #[allow(dead_code)]
     subscriptions: Vec<Subscription<T>>,
 }
 
-/// Combines all values emitted from a list of same-typed streams into one single stream.
+/// Combines all values emitted from a list of same-typed streams into one single stream. The list
+/// can hold anything that implements `IntoStream`.
 ///
 /// # Examples
 /// ```
@@ -33,22 +45,213 @@ pub struct CombinedStreamFields<T> {
 /// assert_eq!(*merged_value.get(), 17);
 /// assert_eq!(*emit_count.get(), 4);
 /// ```
-pub fn merge<T: 'static>(streams: Vec<Stream<T>>) -> Stream<T> {
+pub fn merge<T: 'static, S: IntoStream<T>>(streams: Vec<S>) -> Stream<T> {
     let merged_stream = Stream::new_with_fields::<CombinedStreamFields<T>>(CombinedStreamFields {
         subscriptions: vec![],
     });
 
+    let remaining_streams = Arc::new(AtomicUsize::new(streams.len()));
     let subscriptions: Vec<Subscription<T>> = streams
         .into_iter()
         .map(|stream| {
+            let stream = stream.into_stream();
             let weak_stream_ref = Arc::downgrade(&merged_stream.pointer);
-            stream.subscribe(move |value| match weak_stream_ref.upgrade() {
-                Some(stream_ref) => match stream_ref.lock() {
-                    Ok(stream_impl) => stream_impl.emit_rc(value),
-                    Err(err) => panic!("Stream mutex poisoned: {}", err),
+            let weak_completion_ref = Arc::downgrade(&merged_stream.pointer);
+            let remaining_streams = remaining_streams.clone();
+            stream.subscribe_with_completion(
+                move |value| {
+                    if let Some(stream_ref) = weak_stream_ref.upgrade() {
+                        stream_ref.lock().emit_rc(value)
+                    }
                 },
-                None => (),
-            })
+                move || {
+                    // The merged stream completes once all of its inputs have completed.
+                    if remaining_streams.fetch_sub(1, Ordering::SeqCst) == 1 {
+                        if let Some(pointer) = weak_completion_ref.upgrade() {
+                            Stream { pointer }.complete();
+                        }
+                    }
+                },
+            )
+        })
+        .collect();
+
+    merged_stream.mutate_extra_fields(move |extra_fields: &mut CombinedStreamFields<T>| {
+        extra_fields.subscriptions = subscriptions;
+    });
+
+    merged_stream
+}
+
+#[cfg(feature = "std")]
+struct PriorityQueues<T> {
+    high: VecDeque<Arc<T>>,
+    low: VecDeque<Arc<T>>,
+    conflate_low: bool,
+    is_scheduled: bool,
+    remaining_streams: usize,
+}
+
+/// Combines two streams into one, giving the values of `high` precedence over the values of
+/// `low`. Values are delivered on the scheduler of `high`, and whenever both inputs have values
+/// waiting to be delivered, every `high` value goes first. This keeps an interactive application
+/// responsive to user input while background updates are streaming in.
+///
+/// # Examples
+/// ```
+/// # use epoxy_streams::scheduler::{Scheduler, Task};
+/// # use std::sync::{Arc, Mutex};
+/// # use std::time::Duration;
+/// # #[derive(Default)]
+/// # struct ManualScheduler(Mutex<Vec<Task>>);
+/// # impl Scheduler for ManualScheduler {
+/// #     fn schedule(&self, task: Task) { self.0.lock().unwrap().push(task) }
+/// #     fn schedule_after(&self, _delay: Duration, task: Task) { self.schedule(task) }
+/// # }
+/// # impl ManualScheduler {
+/// #     fn run_pending(&self) {
+/// #         let tasks: Vec<Task> = self.0.lock().unwrap().drain(..).collect();
+/// #         tasks.into_iter().for_each(|task| task());
+/// #     }
+/// # }
+/// use epoxy_streams::ReactiveCache;
+///
+/// // Only runs scheduled work when asked to, which keeps this example deterministic.
+/// let scheduler = Arc::new(ManualScheduler::default());
+/// let input_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+/// let background_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+/// let merged = epoxy_streams::merge_with_priority(
+///     &input_host.get_stream().with_scheduler(scheduler.clone()),
+///     &background_host.get_stream(),
+/// );
+/// let cache = ReactiveCache::from_stream(merged);
+///
+/// background_host.emit("sync 1");
+/// background_host.emit("sync 2");
+/// input_host.emit("click");
+/// scheduler.run_pending();
+///
+/// assert_eq!(cache.get_cloned(), vec!["click", "sync 1", "sync 2"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn merge_with_priority<T: 'static + Send + Sync>(
+    high: &Stream<T>,
+    low: &Stream<T>,
+) -> Stream<T> {
+    merge_with_priority_impl(high, low, false)
+}
+
+/// Same as `merge_with_priority`, except that `low` values waiting to be delivered are
+/// conflated: only the latest one is delivered, after all of the waiting `high` values.
+///
+/// # Examples
+/// ```
+/// # use epoxy_streams::scheduler::{Scheduler, Task};
+/// # use std::sync::{Arc, Mutex};
+/// # use std::time::Duration;
+/// # #[derive(Default)]
+/// # struct ManualScheduler(Mutex<Vec<Task>>);
+/// # impl Scheduler for ManualScheduler {
+/// #     fn schedule(&self, task: Task) { self.0.lock().unwrap().push(task) }
+/// #     fn schedule_after(&self, _delay: Duration, task: Task) { self.schedule(task) }
+/// # }
+/// # impl ManualScheduler {
+/// #     fn run_pending(&self) {
+/// #         let tasks: Vec<Task> = self.0.lock().unwrap().drain(..).collect();
+/// #         tasks.into_iter().for_each(|task| task());
+/// #     }
+/// # }
+/// use epoxy_streams::ReactiveCache;
+///
+/// // Only runs scheduled work when asked to, which keeps this example deterministic.
+/// let scheduler = Arc::new(ManualScheduler::default());
+/// let input_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+/// let background_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+/// let merged = epoxy_streams::merge_with_priority_conflated(
+///     &input_host.get_stream().with_scheduler(scheduler.clone()),
+///     &background_host.get_stream(),
+/// );
+/// let cache = ReactiveCache::from_stream(merged);
+///
+/// background_host.emit("sync 1");
+/// background_host.emit("sync 2");
+/// input_host.emit("click");
+/// scheduler.run_pending();
+///
+/// assert_eq!(cache.get_cloned(), vec!["click", "sync 2"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn merge_with_priority_conflated<T: 'static + Send + Sync>(
+    high: &Stream<T>,
+    low: &Stream<T>,
+) -> Stream<T> {
+    merge_with_priority_impl(high, low, true)
+}
+
+#[cfg(feature = "std")]
+fn merge_with_priority_impl<T: 'static + Send + Sync>(
+    high: &Stream<T>,
+    low: &Stream<T>,
+    conflate_low: bool,
+) -> Stream<T> {
+    let merged_stream =
+        high.derive_with_fields::<T, CombinedStreamFields<T>>(CombinedStreamFields {
+            subscriptions: vec![],
+        });
+    let queues = Arc::new(Mutex::new(PriorityQueues {
+        high: VecDeque::new(),
+        low: VecDeque::new(),
+        conflate_low,
+        is_scheduled: false,
+        remaining_streams: 2,
+    }));
+
+    let subscriptions = [(high, true), (low, false)]
+        .iter()
+        .map(|(stream, is_high)| {
+            let is_high = *is_high;
+            let weak_stream_ref = Arc::downgrade(&merged_stream.pointer);
+            let weak_completion_ref = Arc::downgrade(&merged_stream.pointer);
+            let queues = queues.clone();
+            let completion_queues = queues.clone();
+            let scheduler = merged_stream.scheduler();
+            stream.subscribe_with_completion(
+                move |value| {
+                    let should_schedule = {
+                        let mut queues = queues.lock();
+                        if is_high {
+                            queues.high.push_back(value);
+                        } else {
+                            if queues.conflate_low {
+                                queues.low.clear();
+                            }
+                            queues.low.push_back(value);
+                        }
+                        let should_schedule = !queues.is_scheduled;
+                        queues.is_scheduled = true;
+                        should_schedule
+                    };
+                    if should_schedule {
+                        let weak_stream_ref = weak_stream_ref.clone();
+                        let queues = queues.clone();
+                        scheduler.schedule(Box::new(move || {
+                            drain_priority_queues(&queues, &weak_stream_ref)
+                        }));
+                    }
+                },
+                move || {
+                    let should_complete = {
+                        let mut queues = completion_queues.lock();
+                        queues.remaining_streams -= 1;
+                        queues.remaining_streams == 0 && !queues.is_scheduled
+                    };
+                    if should_complete {
+                        if let Some(pointer) = weak_completion_ref.upgrade() {
+                            Stream { pointer }.complete();
+                        }
+                    }
+                },
+            )
         })
         .collect();
 
@@ -56,5 +259,219 @@ pub fn merge<T: 'static>(streams: Vec<Stream<T>>) -> Stream<T> {
         extra_fields.subscriptions = subscriptions;
     });
 
-    return merged_stream;
+    merged_stream
+}
+
+#[cfg(feature = "std")]
+fn drain_priority_queues<T: 'static + Send + Sync>(
+    queues: &Mutex<PriorityQueues<T>>,
+    weak_stream_ref: &Weak<Mutex<StreamImpl<T>>>,
+) {
+    loop {
+        // Values that arrive while this loop is running are picked up by it, and high priority
+        // values still jump ahead of any low priority values that are waiting.
+        let (next, should_complete) = {
+            let mut queues = queues.lock();
+            let next = match queues.high.pop_front() {
+                Some(value) => Some(value),
+                None => queues.low.pop_front(),
+            };
+            let should_complete = next.is_none() && queues.remaining_streams == 0;
+            if next.is_none() {
+                queues.is_scheduled = false;
+            }
+            (next, should_complete)
+        };
+        let stream = match weak_stream_ref.upgrade() {
+            Some(pointer) => Stream { pointer },
+            None => return,
+        };
+        match next {
+            Some(value) => stream.emit_rc(value),
+            None => {
+                if should_complete {
+                    stream.complete();
+                }
+                return;
+            }
+        }
+    }
+}
+
+struct SampleFields<T, U> {
+    latest: Option<Arc<T>>,
+
+    #[allow(dead_code)]
+    source_subscription: Option<Subscription<T>>,
+
+    #[allow(dead_code)]
+    trigger_subscription: Option<Subscription<U>>,
+}
+
+struct ConcatFields<T> {
+    next: Option<Stream<T>>,
+
+    #[allow(dead_code)]
+    source_subscription: Option<Subscription<T>>,
+
+    #[allow(dead_code)]
+    next_subscription: Option<Subscription<T>>,
+}
+
+/// Forwards every value from `stream` into `derived_stream`, completing it once `stream`
+/// completes.
+pub(crate) fn forward_into<T: 'static + Send + Sync>(
+    stream: &Stream<T>,
+    derived_stream: &Stream<T>,
+) -> Subscription<T> {
+    let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+    let completion_stream_ref = derived_stream.clone();
+    stream.subscribe_with_completion(
+        move |val| {
+            if let Some(pointer) = weak_stream_ref.upgrade() {
+                Stream { pointer }.emit_rc(val);
+            }
+        },
+        move || completion_stream_ref.complete(),
+    )
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Returns a stream that emits the values of this stream until it completes, and then the
+    /// values of `next`. Streams do not buffer, so anything `next` emits before this stream
+    /// completes is not included. This is the natural way to express multi-phase pipelines, like
+    /// replaying cached data and then switching to live updates. `next` can be anything that
+    /// implements `IntoStream`.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let cached_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let live_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let concatenated = cached_host.get_stream().concat(live_host.get_stream());
+    /// let cache = ReactiveCache::from_stream(concatenated);
+    ///
+    /// cached_host.emit(1);
+    /// live_host.emit(100);
+    /// cached_host.emit(2);
+    /// cached_host.close();
+    /// live_host.emit(3);
+    ///
+    /// assert_eq!(cache.get_cloned(), vec![1, 2, 3]);
+    /// ```
+    pub fn concat<S: IntoStream<T>>(&self, next: S) -> Stream<T> {
+        let derived_stream = self.derive_with_fields(ConcatFields::<T> {
+            next: Some(next.into_stream()),
+            source_subscription: None,
+            next_subscription: None,
+        });
+
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                if let Some(pointer) = weak_stream_ref.upgrade() {
+                    Stream { pointer }.emit_rc(val);
+                }
+            },
+            move || connect_concat_next(&completion_stream_ref),
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut ConcatFields<T>| {
+            fields.source_subscription = Some(subscription);
+        });
+
+        // A stream that has already completed never calls its completion listeners again.
+        if self.is_complete() {
+            connect_concat_next(&derived_stream);
+        }
+
+        derived_stream
+    }
+
+    /// Returns a stream that emits the latest value from this stream each time the `trigger`
+    /// stream emits. Nothing is emitted if this stream has not emitted since the last time it was
+    /// sampled. This is useful for syncing updates to some external clock, such as sampling
+    /// application state once per rendered frame.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let frame_host: epoxy_streams::Sink<()> = epoxy_streams::Sink::new();
+    /// let sampled = stream_host.get_stream().sample_on(&frame_host.get_stream());
+    /// let cache = ReactiveCache::from_stream(sampled);
+    ///
+    /// stream_host.emit(1);
+    /// stream_host.emit(2);
+    /// frame_host.emit(());
+    /// frame_host.emit(());
+    /// stream_host.emit(3);
+    /// frame_host.emit(());
+    ///
+    /// assert_eq!(cache.get_cloned(), vec![2, 3]);
+    /// ```
+    pub fn sample_on<U>(&self, trigger: &Stream<U>) -> Stream<T>
+    where
+        U: 'static + Send + Sync,
+    {
+        let derived_stream = self.derive_with_fields(SampleFields::<T, U> {
+            latest: None,
+            source_subscription: None,
+            trigger_subscription: None,
+        });
+        let weak_source_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_trigger_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+
+        let source_subscription = self.subscribe_with_completion(
+            move |val| {
+                if let Some(pointer) = weak_source_ref.upgrade() {
+                    Stream { pointer }.mutate_extra_fields(|fields: &mut SampleFields<T, U>| {
+                        fields.latest = Some(val);
+                    });
+                }
+            },
+            move || completion_stream_ref.complete(),
+        );
+
+        let trigger_subscription = trigger.subscribe(move |_| {
+            let stream_ref = match weak_trigger_ref.upgrade() {
+                Some(pointer) => Stream { pointer },
+                None => return,
+            };
+            let mut latest = None;
+            stream_ref.mutate_extra_fields(|fields: &mut SampleFields<T, U>| {
+                latest = fields.latest.take();
+            });
+            if let Some(value) = latest {
+                stream_ref.emit_rc(value);
+            }
+        });
+
+        derived_stream.mutate_extra_fields(move |fields: &mut SampleFields<T, U>| {
+            fields.source_subscription = Some(source_subscription);
+            fields.trigger_subscription = Some(trigger_subscription);
+        });
+
+        derived_stream
+    }
+}
+
+fn connect_concat_next<T: 'static + Send + Sync>(derived_stream: &Stream<T>) {
+    let mut next = None;
+    derived_stream.mutate_extra_fields(|fields: &mut ConcatFields<T>| {
+        next = fields.next.take();
+    });
+    if let Some(next) = next {
+        let subscription = forward_into(&next, derived_stream);
+        if next.is_complete() {
+            derived_stream.complete();
+        }
+        derived_stream.mutate_extra_fields(move |fields: &mut ConcatFields<T>| {
+            fields.next_subscription = Some(subscription);
+        });
+    }
 }
@@ -1,11 +1,51 @@
-use std::any::Any;
-use std::collections::BTreeMap;
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "checkpoint")]
+use super::checkpoint::StateHooks;
+#[cfg(feature = "std")]
+use super::config::{default_scheduler, worker_scheduler};
+use super::dead_letters::{DeadLetterReason, DeadLetterTarget};
+#[cfg(feature = "std")]
+use super::scheduler::Scheduler;
+#[cfg(feature = "std")]
+use super::scope;
+use super::slot_map::{SlotKey, SlotMap};
+use super::sync::Mutex;
+use super::StreamClosed;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+type Listener<T> = Box<dyn Fn(Arc<T>) + Send + Sync>;
+type CompletionListener = Box<dyn FnOnce() + Send>;
+type Factory<T> = dyn Fn() -> Stream<T> + Send + Sync;
+type Replay<T> = dyn Fn(&StreamImpl<T>) -> Vec<Arc<T>> + Send + Sync;
+type Interceptor<T> = dyn Fn(Arc<T>, EmitNext<T>) + Send + Sync;
+type InterceptorChain<T> = Vec<Arc<Interceptor<T>>>;
+
+// Most streams only have one or two subscribers, so those are stored inline in the stream.
+const INLINE_SUBSCRIBERS: usize = 2;
+
+struct Subscriber<T> {
+    on_emit: Option<Listener<T>>,
+    on_complete: Option<CompletionListener>,
+}
 
 pub(crate) struct StreamImpl<T> {
-    highest_id: u16,
+    next_sequence: u64,
     is_alive: bool,
-    on_emit: BTreeMap<u16, Box<dyn Fn(Arc<T>) + Send + Sync>>,
+    is_complete: bool,
+    subscribers: SlotMap<Subscriber<T>, INLINE_SUBSCRIBERS>,
+    listener_count: usize,
+    #[cfg(feature = "std")]
+    scheduler: Option<Arc<dyn Scheduler>>,
+    interceptors: Option<Arc<InterceptorChain<T>>>,
+    factory: Option<Arc<Factory<T>>>,
+    replay: Option<Arc<Replay<T>>>,
+    batch_ends: Option<Sink<usize>>,
+    pub(crate) dead_letters: Option<Arc<dyn DeadLetterTarget<T>>>,
+    #[cfg(feature = "checkpoint")]
+    pub(crate) state_hooks: Option<Arc<StateHooks<T>>>,
     pub(crate) extra_fields: Option<Box<dyn Any + Send + Sync + 'static>>,
 }
 
@@ -67,8 +107,12 @@ pub struct Stream<T> {
 /// assert_eq!(stream.count_subscribers(), 0);
 /// ```
 pub struct Subscription<T> {
-    id: u16,
+    id: SlotKey,
     pub(crate) stream: Stream<T>,
+
+    /// Subscriptions created inside a `scope` are removed when the scope is disposed, rather than
+    /// when they are dropped.
+    is_scoped: bool,
 }
 
 /// A Sink is an object used to create a Stream. If you have ever visited a kitchen or bathroom
@@ -99,6 +143,54 @@ pub struct Sink<T> {
     stream: Stream<T>,
 }
 
+/// Describes the outcome of a call to `Sink::emit_counted`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeliveryReport {
+    /// The number of subscribers that received the value. Derived streams (ones created with a
+    /// pipe operation like `map` or `filter`) count as a single subscriber each.
+    pub subscribers_reached: usize,
+
+    /// False if the stream is no longer accepting values, in which case nobody received it.
+    pub stream_alive: bool,
+}
+
+/// Passes a value on to the next interceptor registered on a Sink, or to the Sink's stream once
+/// every interceptor has run. See `Sink::add_interceptor`.
+pub struct EmitNext<T> {
+    stream: Stream<T>,
+    chain: Arc<InterceptorChain<T>>,
+    index: usize,
+    subscribers_reached: Arc<AtomicUsize>,
+}
+
+impl<T> EmitNext<T> {
+    /// Hands a value to the rest of the chain.
+    pub fn emit(self, value: T) {
+        self.emit_rc(Arc::new(value))
+    }
+
+    /// Same logic as `emit`, but takes an existing Arc pointer.
+    pub fn emit_rc(self, value: Arc<T>) {
+        match self.chain.get(self.index) {
+            Some(interceptor) => {
+                let interceptor = interceptor.clone();
+                interceptor(
+                    value,
+                    EmitNext {
+                        index: self.index + 1,
+                        ..self
+                    },
+                )
+            }
+            None => {
+                let report = self.stream.emit_rc_counted(value);
+                self.subscribers_reached
+                    .store(report.subscribers_reached, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
 impl<T> Clone for Stream<T> {
     fn clone(&self) -> Self {
         Stream {
@@ -108,22 +200,36 @@ impl<T> Clone for Stream<T> {
 }
 
 impl<T> StreamImpl<T> {
-    fn subscribe<F>(&mut self, listener: F) -> u16
-    where
-        F: Fn(Arc<T>),
-        F: Send,
-        F: Sync,
-        F: 'static,
-    {
-        let new_subscription_id = self.highest_id;
-        self.highest_id += 1;
-        self.on_emit.insert(new_subscription_id, Box::new(listener));
-        new_subscription_id
+    fn add_subscriber(
+        &mut self,
+        on_emit: Option<Listener<T>>,
+        on_complete: Option<CompletionListener>,
+    ) -> SlotKey {
+        if on_emit.is_some() {
+            self.listener_count += 1;
+        }
+        self.subscribers.insert(Subscriber {
+            on_emit,
+            on_complete,
+        })
+    }
+
+    fn remove_subscriber(&mut self, key: SlotKey) {
+        if let Some(subscriber) = self.subscribers.remove(key) {
+            if subscriber.on_emit.is_some() {
+                self.listener_count -= 1;
+            }
+        }
     }
 
     pub(crate) fn emit_rc(&self, value: Arc<T>) {
-        for (_id, call) in &self.on_emit {
-            call(value.clone())
+        if !self.is_alive {
+            return;
+        }
+        for subscriber in self.subscribers.values() {
+            if let Some(call) = &subscriber.on_emit {
+                call(value.clone())
+            }
         }
     }
 }
@@ -134,22 +240,218 @@ impl<T> Stream<T> {
     /// it lives as long as the returned Subscription object, which means that in most cases if the
     /// given function needs to capture any scope from its environment it will need to be used with
     /// Rust's `move` annotation.
+    ///
+    /// Subscribing to a stream that has already completed succeeds, but the listener will never
+    /// be called. Use `try_subscribe` to detect this, or `subscribe_with_completion` to be told.
     pub fn subscribe<F>(&self, listener: F) -> Subscription<T>
     where
+        T: 'static,
         F: Fn(Arc<T>),
         F: Send,
         F: Sync,
         F: 'static,
     {
-        let mut stream_mut = match self.pointer.lock() {
-            Ok(mut_ref) => mut_ref,
-            Err(err) => panic!("Stream mutex poisoned: {}", err),
-        };
+        self.add_subscription(Some(Box::new(listener)), None)
+    }
 
-        Subscription {
-            id: stream_mut.subscribe(listener),
-            stream: self.clone(),
+    /// Same as `subscribe`, but fails if the stream has already completed (because its Sink was
+    /// closed or dropped), rather than returning a subscription that will never be called.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::StreamClosed;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let stream = stream_host.get_stream();
+    /// assert!(stream.try_subscribe(|_| {}).is_ok());
+    ///
+    /// drop(stream_host);
+    /// assert_eq!(stream.try_subscribe(|_| {}).err(), Some(StreamClosed));
+    /// ```
+    pub fn try_subscribe<F>(&self, listener: F) -> Result<Subscription<T>, StreamClosed>
+    where
+        T: 'static,
+        F: Fn(Arc<T>),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.try_add_subscription(Some(Box::new(listener)), None)
+            .map_err(|_| StreamClosed)
+    }
+
+    /// Same as `subscribe`, but also runs `on_complete` once the stream completes, which happens
+    /// when its Sink is closed (see `Sink::close`) or dropped. Streams derived from a completed
+    /// stream complete as well, after emitting any values they were still holding on to. If the
+    /// stream has already completed, `on_complete` runs right away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let stream = stream_host.get_stream();
+    ///
+    /// let is_complete = Arc::new(AtomicBool::new(false));
+    /// let is_complete_write = is_complete.clone();
+    /// let _subscription = stream.map(|val| val * 2).subscribe_with_completion(
+    ///     |_| {},
+    ///     move || is_complete_write.store(true, Ordering::SeqCst),
+    /// );
+    ///
+    /// stream_host.emit(1);
+    /// assert!(!is_complete.load(Ordering::SeqCst));
+    ///
+    /// stream_host.close();
+    /// assert!(is_complete.load(Ordering::SeqCst));
+    /// ```
+    pub fn subscribe_with_completion<F, C>(&self, listener: F, on_complete: C) -> Subscription<T>
+    where
+        T: 'static,
+        F: Fn(Arc<T>),
+        F: Send,
+        F: Sync,
+        F: 'static,
+        C: FnOnce(),
+        C: Send,
+        C: 'static,
+    {
+        self.add_subscription(Some(Box::new(listener)), Some(Box::new(on_complete)))
+    }
+
+    /// Runs the given function once the stream completes, or right away if it already has. Unlike
+    /// the other subscribe functions this does not count towards `count_subscribers`, since it
+    /// does not listen to any values.
+    pub fn on_complete<C>(&self, on_complete: C) -> Subscription<T>
+    where
+        T: 'static,
+        C: FnOnce(),
+        C: Send,
+        C: 'static,
+    {
+        self.add_subscription(None, Some(Box::new(on_complete)))
+    }
+
+    /// Returns a stream that calls `factory` every time something subscribes to it, and connects
+    /// that subscriber to the stream the factory returns. This makes side effects that start a
+    /// source, like opening a file or making a request, happen once per subscription instead of
+    /// once up front. Operators like `map` subscribe once, so all of their subscribers share a
+    /// single call to the factory.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{Sink, Stream};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let connections: Arc<Mutex<Vec<Sink<i32>>>> = Default::default();
+    /// let connections_write = connections.clone();
+    /// let deferred = Stream::defer(move || {
+    ///     let sink = Sink::new();
+    ///     let stream = sink.get_stream();
+    ///     connections_write.lock().unwrap().push(sink);
+    ///     stream
+    /// });
+    /// assert_eq!(connections.lock().unwrap().len(), 0);
+    ///
+    /// let first = Arc::new(Mutex::new(vec![]));
+    /// let first_write = first.clone();
+    /// let _first = deferred.subscribe(move |val| first_write.lock().unwrap().push(*val));
+    /// let _second = deferred.subscribe(|_| {});
+    /// assert_eq!(connections.lock().unwrap().len(), 2);
+    ///
+    /// connections.lock().unwrap()[0].emit(1);
+    /// connections.lock().unwrap()[1].emit(2);
+    /// assert_eq!(*first.lock().unwrap(), vec![1]);
+    /// ```
+    pub fn defer<F>(factory: F) -> Stream<T>
+    where
+        F: Fn() -> Stream<T>,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let stream = Stream::new();
+        stream.pointer.lock().factory = Some(Arc::new(factory));
+        stream
+    }
+
+    /// Returns a stream that emits the number of values in each batch emitted with
+    /// `Sink::emit_many`, right after the whole batch has been delivered. Subscribers can use this
+    /// to flush work they have been accumulating, like writing rows to a database once per batch
+    /// instead of once per value. Only the stream of the Sink reports its batches, streams derived
+    /// from it do not. See `Sink::emit_many` for an example.
+    pub fn batch_ends(&self) -> Stream<usize> {
+        let mut stream_impl = self.pointer.lock();
+        if stream_impl.is_complete {
+            // The Sink is dropped right away, so the returned stream has completed as well.
+            return Sink::new().get_stream();
         }
+        stream_impl
+            .batch_ends
+            .get_or_insert_with(Sink::new)
+            .get_stream()
+    }
+
+    /// Returns true once the stream has completed. A completed stream will never emit again.
+    pub fn is_complete(&self) -> bool {
+        self.pointer.lock().is_complete
+    }
+
+    /// Returns true while the stream can still emit values, meaning its Sink has not been closed
+    /// or dropped. Derived streams stay alive until they have emitted any values they were still
+    /// holding on to.
+    ///
+    /// # Examples
+    /// ```
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let stream = stream_host.get_stream();
+    /// let subscription = stream.map(|val| val * 2).subscribe(|_| {});
+    /// assert!(stream.is_alive());
+    /// assert!(subscription.is_source_alive());
+    ///
+    /// drop(stream_host);
+    /// assert!(!stream.is_alive());
+    /// assert!(!subscription.is_source_alive());
+    /// ```
+    pub fn is_alive(&self) -> bool {
+        self.pointer.lock().is_alive
+    }
+
+    /// Same as `subscribe`, but the listener receives an owned clone of each value instead of an
+    /// Arc. This reads more naturally for small Clone types like numbers or enums, where
+    /// dereferencing the Arc in every listener is just noise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let stream = stream_host.get_stream();
+    ///
+    /// let total = Arc::new(Mutex::new(0_i32));
+    /// let total_write = total.clone();
+    ///
+    /// let subscription = stream.subscribe_values(move |val: i32| {
+    ///     *total_write.lock().unwrap() += val;
+    /// });
+    ///
+    /// stream_host.emit(1);
+    /// stream_host.emit(10);
+    /// assert_eq!(*total.lock().unwrap(), 11);
+    /// ```
+    pub fn subscribe_values<F>(&self, listener: F) -> Subscription<T>
+    where
+        T: 'static,
+        T: Clone,
+        F: Fn(T),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        self.subscribe(move |val| listener((*val).clone()))
     }
 
     /// Usually subscriptions are removed by simply letting the Subscription object fall out of
@@ -197,19 +499,160 @@ impl<T> Stream<T> {
     /// Returns the total number of subscribers listening to this stream, includes any derived
     /// streams (ones created with a pipe operation like `map` or `filter`).
     pub fn count_subscribers(&self) -> usize {
-        let stream = match self.pointer.lock() {
-            Ok(stream_impl) => stream_impl,
-            Err(err) => panic!("Stream mutex poisoned: {}", err),
-        };
-        stream.on_emit.len()
+        let stream = self.pointer.lock();
+        stream.listener_count
+    }
+
+    /// Returns a number that identifies this stream. Clones of a stream share the same id, and no
+    /// two streams that exist at the same time have the same id.
+    ///
+    /// # Examples
+    /// ```
+    /// let sink: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let stream = sink.get_stream();
+    /// assert_eq!(stream.id(), stream.clone().id());
+    /// assert_ne!(stream.id(), stream.map(|value| *value).id());
+    /// ```
+    pub fn id(&self) -> usize {
+        Arc::as_ptr(&self.pointer) as *const () as usize
+    }
+
+    /// Returns a stream that matches this one, except that time-based operators derived from it
+    /// run on the given scheduler instead of the default one (see `config::set_default_scheduler`).
+    /// Streams derived from the returned stream inherit the scheduler.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::scheduler::DedicatedThreadScheduler;
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let debounced = stream_host
+    ///     .get_stream()
+    ///     .with_scheduler(Arc::new(DedicatedThreadScheduler::new()))
+    ///     .map(|val| val * 2)
+    ///     .debounce(Duration::from_millis(10));
+    /// let cache = ReactiveCache::from_stream(debounced);
+    ///
+    /// stream_host.emit(4);
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert_eq!(cache.get_cloned(), vec![8]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_scheduler(&self, scheduler: Arc<dyn Scheduler>) -> Stream<T>
+    where
+        T: Send,
+        T: Sync,
+        T: 'static,
+    {
+        let derived_stream = self.map_rc(|val| val);
+        derived_stream.pointer.lock().scheduler = Some(scheduler);
+        derived_stream
+    }
+
+    /// Returns the scheduler that time-based operators on this stream should use.
+    #[cfg(feature = "std")]
+    pub(crate) fn scheduler(&self) -> Arc<dyn Scheduler> {
+        self.scheduler_override().unwrap_or_else(default_scheduler)
+    }
+
+    /// Returns the scheduler that slow listeners on this stream should run on, which is never the
+    /// thread that runs the timers of time-based operators unless the application asked for it.
+    #[cfg(feature = "std")]
+    pub(crate) fn worker_scheduler(&self) -> Arc<dyn Scheduler> {
+        self.scheduler_override().unwrap_or_else(worker_scheduler)
     }
 
-    fn unsubscribe_by_id(&self, subscription_id: u16) {
-        let mut stream_mut = match self.pointer.lock() {
-            Ok(mut_ref) => mut_ref,
-            Err(err) => panic!("Stream mutex poisoned: {}", err),
+    #[cfg(feature = "std")]
+    fn scheduler_override(&self) -> Option<Arc<dyn Scheduler>> {
+        self.pointer.lock().scheduler.clone()
+    }
+
+    fn add_subscription(
+        &self,
+        on_emit: Option<Listener<T>>,
+        on_complete: Option<CompletionListener>,
+    ) -> Subscription<T>
+    where
+        T: 'static,
+    {
+        match self.try_add_subscription(on_emit, on_complete) {
+            Ok(subscription) => subscription,
+            Err(on_complete) => {
+                if let Some(on_complete) = on_complete {
+                    on_complete();
+                }
+                Subscription {
+                    id: SlotKey::NULL,
+                    stream: self.clone(),
+                    is_scoped: false,
+                }
+            }
+        }
+    }
+
+    /// Adds a subscriber, unless the stream has already completed, in which case the completion
+    /// listener is handed back so the caller can decide what to do with it.
+    fn try_add_subscription(
+        &self,
+        on_emit: Option<Listener<T>>,
+        on_complete: Option<CompletionListener>,
+    ) -> Result<Subscription<T>, Option<CompletionListener>>
+    where
+        T: 'static,
+    {
+        let factory = {
+            let mut stream_mut = self.pointer.lock();
+            match &stream_mut.factory {
+                Some(factory) => factory.clone(),
+                None => {
+                    if let (Some(replay), Some(on_emit)) = (&stream_mut.replay, &on_emit) {
+                        for value in replay(&stream_mut) {
+                            on_emit(value);
+                        }
+                    }
+                    if stream_mut.is_complete {
+                        return Err(on_complete);
+                    }
+                    let id = stream_mut.add_subscriber(on_emit, on_complete);
+                    drop(stream_mut);
+                    return Ok(Subscription {
+                        id,
+                        stream: self.clone(),
+                        is_scoped: self.adopt_into_scope(id),
+                    });
+                }
+            }
         };
-        stream_mut.on_emit.remove(&subscription_id);
+        // Called without holding the lock, since the factory is free to create and subscribe to
+        // other streams.
+        factory().try_add_subscription(on_emit, on_complete)
+    }
+
+    /// Hands the subscription over to the scope running on this thread, if there is one.
+    #[cfg(feature = "std")]
+    fn adopt_into_scope(&self, subscription_id: SlotKey) -> bool
+    where
+        T: 'static,
+    {
+        let weak_pointer = Arc::downgrade(&self.pointer);
+        scope::adopt_subscription(move || {
+            if let Some(pointer) = weak_pointer.upgrade() {
+                pointer.lock().remove_subscriber(subscription_id);
+            }
+        })
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn adopt_into_scope(&self, _subscription_id: SlotKey) -> bool {
+        false
+    }
+
+    fn unsubscribe_by_id(&self, subscription_id: SlotKey) {
+        let mut stream_mut = self.pointer.lock();
+        stream_mut.remove_subscriber(subscription_id);
     }
 
     // PRIVATE FUNCTIONS
@@ -217,9 +660,20 @@ impl<T> Stream<T> {
     pub(crate) fn new() -> Stream<T> {
         Stream {
             pointer: Arc::new(Mutex::new(StreamImpl {
-                highest_id: 0_u16,
+                next_sequence: 0,
                 is_alive: true,
-                on_emit: BTreeMap::new(),
+                is_complete: false,
+                subscribers: SlotMap::new(),
+                listener_count: 0,
+                #[cfg(feature = "std")]
+                scheduler: None,
+                interceptors: None,
+                factory: None,
+                replay: None,
+                batch_ends: None,
+                dead_letters: None,
+                #[cfg(feature = "checkpoint")]
+                state_hooks: None,
                 extra_fields: None,
             })),
         }
@@ -233,18 +687,183 @@ impl<T> Stream<T> {
     {
         Stream {
             pointer: Arc::new(Mutex::new(StreamImpl {
-                highest_id: 0_u16,
+                next_sequence: 0,
                 is_alive: true,
-                on_emit: BTreeMap::new(),
+                is_complete: false,
+                subscribers: SlotMap::new(),
+                listener_count: 0,
+                #[cfg(feature = "std")]
+                scheduler: None,
+                interceptors: None,
+                factory: None,
+                replay: None,
+                batch_ends: None,
+                dead_letters: None,
+                #[cfg(feature = "checkpoint")]
+                state_hooks: None,
                 extra_fields: Some(Box::new(fields)),
             })),
         }
     }
 
+    /// Marks the stream as complete, drops all of its value listeners and then notifies its
+    /// completion listeners. Does nothing if the stream has already completed.
+    pub(crate) fn complete(&self) {
+        let (completion_listeners, batch_ends) = {
+            let mut stream_impl = self.pointer.lock();
+            if stream_impl.is_complete {
+                return;
+            }
+            stream_impl.is_complete = true;
+            stream_impl.is_alive = false;
+            stream_impl.listener_count = 0;
+            (
+                stream_impl.subscribers.drain(),
+                stream_impl.batch_ends.take(),
+            )
+        };
+        for subscriber in completion_listeners {
+            if let Some(on_complete) = subscriber.on_complete {
+                on_complete();
+            }
+        }
+        // Dropping the Sink completes the stream of batch ends as well.
+        drop(batch_ends);
+    }
+
+    /// Creates a new stream whose values will be derived from this one. The new stream inherits
+    /// this stream's scheduler override, if any.
+    pub(crate) fn derive_with_fields<U, FieldsType>(&self, fields: FieldsType) -> Stream<U>
+    where
+        FieldsType: 'static,
+        FieldsType: Send,
+        FieldsType: Sync,
+    {
+        let derived_stream = Stream::new_with_fields(fields);
+        #[cfg(feature = "std")]
+        if let Some(scheduler) = self.scheduler_override() {
+            derived_stream.pointer.lock().scheduler = Some(scheduler);
+        }
+        derived_stream
+    }
+
     pub(crate) fn emit_rc(&self, value: Arc<T>) {
-        match self.pointer.lock() {
-            Ok(stream_impl) => stream_impl.emit_rc(value),
-            Err(err) => panic!("Stream mutex poisoned: {}", err),
+        self.pointer.lock().emit_rc(value)
+    }
+
+    /// Emits several values while only taking the stream lock once.
+    #[cfg(feature = "std")]
+    pub(crate) fn emit_batch_rc(&self, values: Vec<Arc<T>>) {
+        let stream_impl = self.pointer.lock();
+        for value in values {
+            stream_impl.emit_rc(value);
+        }
+    }
+
+    /// Builds a value from the stream's next sequence number and emits it. Both happen under the
+    /// stream lock, so sequence numbers always match the order in which values were emitted.
+    pub(crate) fn emit_sequenced_with<F>(&self, build_value: F) -> Result<u64, StreamClosed>
+    where
+        F: FnOnce(u64) -> Arc<T>,
+    {
+        let mut stream_impl = self.pointer.lock();
+        if !stream_impl.is_alive {
+            return Err(StreamClosed);
+        }
+        let sequence = stream_impl.next_sequence;
+        stream_impl.next_sequence += 1;
+        stream_impl.emit_rc(build_value(sequence));
+        Ok(sequence)
+    }
+
+    /// Makes every new subscriber receive the values returned by `replay` before any other value.
+    /// Both happen under the stream lock, so nothing is emitted in between. Subscribers that
+    /// arrive after the stream has completed still receive the replayed values, followed by the
+    /// completion.
+    pub(crate) fn set_replay<F>(&self, replay: F)
+    where
+        F: Fn(&StreamImpl<T>) -> Vec<Arc<T>> + Send + Sync + 'static,
+    {
+        self.pointer.lock().replay = Some(Arc::new(replay));
+    }
+
+    /// Updates the extra fields and emits the value returned by `cb`, if any, under a single lock.
+    /// This keeps the fields that a replay reads consistent with the values emitted so far.
+    pub(crate) fn mutate_extra_fields_and_emit<ExtraFieldsType, FnType>(&self, cb: FnType)
+    where
+        ExtraFieldsType: 'static,
+        ExtraFieldsType: Send,
+        ExtraFieldsType: Sync,
+        FnType: FnOnce(&mut ExtraFieldsType) -> Option<Arc<T>>,
+    {
+        let mut stream_impl = self.pointer.lock();
+        let value = match stream_impl
+            .extra_fields
+            .as_mut()
+            .and_then(|fields| fields.downcast_mut::<ExtraFieldsType>())
+        {
+            Some(fields) => cb(fields),
+            None => panic!("Invalid type for derived stream field."),
+        };
+        if let Some(value) = value {
+            stream_impl.emit_rc(value);
+        }
+    }
+
+    /// Emits a value through the interceptors registered on the stream's Sink. Values that an
+    /// interceptor drops or holds on to count as not having reached any subscribers.
+    pub(crate) fn emit_intercepted_rc(&self, value: Arc<T>) -> DeliveryReport {
+        let chain = {
+            let stream_impl = self.pointer.lock();
+            if !stream_impl.is_alive {
+                drop(stream_impl);
+                self.report_dead_letter(value, DeadLetterReason::SinkClosed);
+                return DeliveryReport {
+                    subscribers_reached: 0,
+                    stream_alive: false,
+                };
+            }
+            match &stream_impl.interceptors {
+                Some(chain) => chain.clone(),
+                None => {
+                    stream_impl.emit_rc(value);
+                    return DeliveryReport {
+                        subscribers_reached: stream_impl.listener_count,
+                        stream_alive: true,
+                    };
+                }
+            }
+        };
+
+        let subscribers_reached = Arc::new(AtomicUsize::new(0));
+        EmitNext {
+            stream: self.clone(),
+            chain,
+            index: 0,
+            subscribers_reached: subscribers_reached.clone(),
+        }
+        .emit_rc(value);
+        DeliveryReport {
+            subscribers_reached: subscribers_reached.load(Ordering::Relaxed),
+            stream_alive: true,
+        }
+    }
+
+    pub(crate) fn emit_rc_counted(&self, value: Arc<T>) -> DeliveryReport {
+        let stream_impl = self.pointer.lock();
+        if !stream_impl.is_alive {
+            // An interceptor held on to the value until after the Sink was closed.
+            drop(stream_impl);
+            self.report_dead_letter(value, DeadLetterReason::SinkClosed);
+            return DeliveryReport {
+                subscribers_reached: 0,
+                stream_alive: false,
+            };
+        }
+        stream_impl.emit_rc(value);
+        DeliveryReport {
+            subscribers_reached: stream_impl.listener_count,
+            stream_alive: true,
         }
     }
 
@@ -256,17 +875,13 @@ impl<T> Stream<T> {
         RetType: 'static,
         FnType: FnOnce(&ExtraFieldsType) -> RetType,
     {
-        match self.pointer.lock() {
-            Ok(stream_impl) => {
-                if let Some(extra_field_box) = &stream_impl.extra_fields {
-                    if let Some(fields) = extra_field_box.downcast_ref::<ExtraFieldsType>() {
-                        return cb(&*fields);
-                    }
-                }
-                panic!("Invalid type for derived stream field.");
-            },
-            Err(err) => panic!("Stream mutex poisoned: {}", err),
+        let stream_impl = self.pointer.lock();
+        if let Some(extra_field_box) = &stream_impl.extra_fields {
+            if let Some(fields) = extra_field_box.downcast_ref::<ExtraFieldsType>() {
+                return cb(fields);
+            }
         }
+        panic!("Invalid type for derived stream field.");
     }
 
     pub(crate) fn mutate_extra_fields<ExtraFieldsType, FnType>(&self, cb: FnType)
@@ -276,17 +891,13 @@ impl<T> Stream<T> {
         ExtraFieldsType: Sync,
         FnType: FnOnce(&mut ExtraFieldsType),
     {
-        match self.pointer.lock() {
-            Ok(mut stream_impl) => {
-                if let Some(extra_field_box) = &mut stream_impl.extra_fields {
-                    if let Some(fields) = extra_field_box.downcast_mut::<ExtraFieldsType>() {
-                        return cb(&mut *fields);
-                    }
-                }
-                panic!("Invalid type for derived stream field.");
-            },
-            Err(err) => panic!("Stream mutex poisoned: {}", err),
+        let mut stream_impl = self.pointer.lock();
+        if let Some(extra_field_box) = &mut stream_impl.extra_fields {
+            if let Some(fields) = extra_field_box.downcast_mut::<ExtraFieldsType>() {
+                return cb(&mut *fields);
+            }
         }
+        panic!("Invalid type for derived stream field.");
     }
 }
 
@@ -305,30 +916,210 @@ impl<T> Sink<T> {
     }
 
     /// Emits a new value from this Sink, which will broadcast out to any Subscriber to the stream
-    /// returned by the `get_stream` function.
+    /// returned by the `get_stream` function. Values emitted after the Sink has been closed are
+    /// discarded.
     pub fn emit(&self, value: T) {
         self.emit_rc(Arc::new(value))
     }
 
+    /// Same as `emit`, but returns a report describing how many subscribers received the value.
+    /// Producers can use this to skip expensive work while nobody is listening.
+    ///
+    /// # Examples
+    /// ```
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let stream = stream_host.get_stream();
+    /// assert_eq!(stream_host.emit_counted(1).subscribers_reached, 0);
+    ///
+    /// let _subscription = stream.subscribe(|_| {});
+    /// let report = stream_host.emit_counted(2);
+    /// assert_eq!(report.subscribers_reached, 1);
+    /// assert!(report.stream_alive);
+    /// ```
+    pub fn emit_counted(&self, value: T) -> DeliveryReport {
+        self.stream.emit_intercepted_rc(Arc::new(value))
+    }
+
     /// Same logic as `emit`, but takes an existing Arc pointer (Epoxy streams use Arc pointers
     /// internally, so this saves a Copy).
+    ///
+    /// Note that values you already hold as `Arc<T>` should be emitted from a `Sink<T>` using this
+    /// function, not from a `Sink<Arc<T>>`. The latter wraps every value in a second Arc, so
+    /// subscribers receive `Arc<Arc<T>>`. If you are stuck with such a Sink, hand out its
+    /// `get_flattened_stream` rather than `get_stream`, and for such a stream, `flatten_arc` will
+    /// unwrap the outer layer without copying the underlying value.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveValue;
+    /// use std::sync::Arc;
+    ///
+    /// let config = Arc::new("config".to_string());
+    /// let stream_host: epoxy_streams::Sink<String> = epoxy_streams::Sink::new();
+    /// let latest = stream_host.get_stream().to_reactive_value();
+    ///
+    /// stream_host.emit_rc(config.clone());
+    /// assert!(Arc::ptr_eq(&latest.get(), &config));
+    /// ```
     pub fn emit_rc(&self, value: Arc<T>) {
-        self.stream.emit_rc(value)
+        self.stream.emit_intercepted_rc(value);
+    }
+
+    /// Emits several values in a row. The stream is only locked once for the whole batch, which
+    /// saves a lot of overhead when emitting many values at once, like the records parsed from a
+    /// file. Once every value has been delivered, the size of the batch is emitted from the
+    /// stream's `batch_ends`. Interceptors still see each value on its own. The values are taken
+    /// from the iterator while the stream is locked, so it must not emit to this Sink itself.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let stream = stream_host.get_stream();
+    /// let values = ReactiveCache::from_stream(stream.clone());
+    /// let batch_ends = ReactiveCache::from_stream(stream.batch_ends());
+    ///
+    /// stream_host.emit_many(1..=3);
+    /// stream_host.emit(4);
+    /// stream_host.emit_many(vec![5, 6]);
+    ///
+    /// assert_eq!(values.get_cloned(), vec![1, 2, 3, 4, 5, 6]);
+    /// assert_eq!(batch_ends.get_cloned(), vec![3, 2]);
+    /// ```
+    pub fn emit_many<I: IntoIterator<Item = T>>(&self, values: I) {
+        self.emit_many_rc(values.into_iter().map(Arc::new))
+    }
+
+    /// Same logic as `emit_many`, but takes existing Arc pointers.
+    pub fn emit_many_rc<I: IntoIterator<Item = Arc<T>>>(&self, values: I) {
+        let stream_impl = self.stream.pointer.lock();
+        if !stream_impl.is_alive {
+            drop(stream_impl);
+            for value in values {
+                self.stream
+                    .report_dead_letter(value, DeadLetterReason::SinkClosed);
+            }
+            return;
+        }
+        let batch_ends = stream_impl.batch_ends.as_ref().map(Sink::get_stream);
+        let mut count = 0;
+        if stream_impl.interceptors.is_some() {
+            drop(stream_impl);
+            for value in values {
+                self.stream.emit_intercepted_rc(value);
+                count += 1;
+            }
+        } else {
+            for value in values {
+                stream_impl.emit_rc(value);
+                count += 1;
+            }
+            drop(stream_impl);
+        }
+
+        match batch_ends {
+            Some(batch_ends) if count > 0 => batch_ends.emit_rc(Arc::new(count)),
+            _ => {}
+        }
+    }
+
+    /// Registers a function that sees every value emitted by this Sink (or its producers) before
+    /// it reaches the stream. The interceptor receives the value and an `EmitNext` handle, and
+    /// decides what happens next: pass the value on, pass on a different value, or drop it by not
+    /// calling `EmitNext` at all. The handle can also be moved to another thread or a timer to
+    /// delay the value. Interceptors run in the order they were added.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let cache = ReactiveCache::from_stream(stream_host.get_stream());
+    ///
+    /// // Rejects negative values.
+    /// stream_host.add_interceptor(|val, next| {
+    ///     if *val >= 0 {
+    ///         next.emit_rc(val);
+    ///     }
+    /// });
+    /// // Caps values at 100.
+    /// stream_host.add_interceptor(|val, next| next.emit((*val).min(100)));
+    ///
+    /// stream_host.emit(5);
+    /// stream_host.emit(-5);
+    /// stream_host.emit(500);
+    /// assert_eq!(cache.get_cloned(), vec![5, 100]);
+    /// ```
+    pub fn add_interceptor<F>(&self, interceptor: F)
+    where
+        F: Fn(Arc<T>, EmitNext<T>),
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let mut stream_impl = self.stream.pointer.lock();
+        let mut chain = match stream_impl.interceptors.take() {
+            Some(chain) => (*chain).clone(),
+            None => Vec::new(),
+        };
+        chain.push(Arc::new(interceptor));
+        stream_impl.interceptors = Some(Arc::new(chain));
+    }
+}
+
+impl<T> Sink<T> {
+    /// Closes the Sink, completing its stream. Any further values emitted by the Sink (or by its
+    /// producers, which will return an error) are rejected. Derived streams that are still holding
+    /// on to values, like a `debounce` that is waiting for its quiet period, emit those values
+    /// before completing themselves, so nothing that was emitted before `close` is lost.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let debounced = stream_host.get_stream().debounce(Duration::from_secs(60));
+    /// let cache = ReactiveCache::from_stream(debounced.clone());
+    ///
+    /// stream_host.emit(1);
+    /// stream_host.close();
+    /// stream_host.emit(2);
+    ///
+    /// assert_eq!(cache.get_cloned(), vec![1]);
+    /// assert!(debounced.is_complete());
+    /// ```
+    pub fn close(&self) {
+        self.stream.complete()
+    }
+}
+
+impl<T> Default for Sink<T> {
+    fn default() -> Sink<T> {
+        Sink::new()
     }
 }
 
 impl<T> Drop for Sink<T> {
+    /// Dropping a Sink closes it, see `Sink::close`.
     fn drop(&mut self) {
-        let mut stream_mut = match self.stream.pointer.lock() {
-            Ok(mut_ref) => mut_ref,
-            Err(err) => panic!("Stream mutex poisoned: {}", err),
-        };
-        stream_mut.is_alive = false;
+        self.stream.complete()
+    }
+}
+
+impl<T> Subscription<T> {
+    /// Returns true while the subscribed stream can still emit values, see `Stream::is_alive`.
+    /// Once this returns false the listener will never be called again.
+    pub fn is_source_alive(&self) -> bool {
+        self.stream.is_alive()
     }
 }
 
 impl<T> Drop for Subscription<T> {
     fn drop(&mut self) {
-        self.stream.unsubscribe_by_id(self.id)
+        if !self.is_scoped {
+            self.stream.unsubscribe_by_id(self.id)
+        }
     }
 }
@@ -0,0 +1,142 @@
+use super::sync::Mutex;
+use super::{Sink, Stream, Subscription};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// An event on `SubscriberGroup::membership_changes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MembershipChange<K> {
+    Joined(K),
+    Left(K),
+}
+
+type Members<K, T> = Arc<Mutex<BTreeMap<K, Arc<Sink<T>>>>>;
+
+/// Forwards the values of a stream to a changing set of members, such as the clients connected to
+/// a server. Each member gets a stream of its own, which completes when the member leaves, so
+/// per-connection code only has to deal with that one stream.
+///
+/// # Examples
+/// ```
+/// use epoxy_streams::{MembershipChange, ReactiveCache, SubscriberGroup};
+///
+/// let stream_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+/// let group = SubscriberGroup::new(&stream_host.get_stream());
+/// let changes = ReactiveCache::from_stream(group.membership_changes());
+///
+/// let alice = ReactiveCache::from_stream(group.join("alice"));
+/// stream_host.emit("hello");
+/// let bob = ReactiveCache::from_stream(group.join("bob"));
+/// stream_host.emit("welcome bob");
+/// group.leave(&"alice");
+/// stream_host.emit("bye alice");
+///
+/// assert_eq!(alice.get_cloned(), vec!["hello", "welcome bob"]);
+/// assert_eq!(bob.get_cloned(), vec!["welcome bob", "bye alice"]);
+/// assert_eq!(
+///     changes.get_cloned(),
+///     vec![
+///         MembershipChange::Joined("alice"),
+///         MembershipChange::Joined("bob"),
+///         MembershipChange::Left("alice"),
+///     ]
+/// );
+/// ```
+pub struct SubscriberGroup<K, T> {
+    members: Members<K, T>,
+    changes: Arc<Sink<MembershipChange<K>>>,
+
+    #[allow(dead_code)]
+    subscription: Subscription<T>,
+}
+
+impl<K, T> SubscriberGroup<K, T>
+where
+    K: Ord + Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+{
+    /// Creates an empty group that forwards the values of `source`. When `source` completes,
+    /// every member leaves the group.
+    pub fn new(source: &Stream<T>) -> SubscriberGroup<K, T> {
+        let members: Members<K, T> = Arc::new(Mutex::new(BTreeMap::new()));
+        let changes = Arc::new(Sink::new());
+
+        let value_members = members.clone();
+        let completion_members = members.clone();
+        let completion_changes = changes.clone();
+        let subscription = source.subscribe_with_completion(
+            move |val| {
+                // Emitting without holding the lock lets members leave from within a subscriber.
+                let sinks: Vec<_> = value_members.lock().values().cloned().collect();
+                for sink in sinks {
+                    sink.emit_rc(val.clone());
+                }
+            },
+            move || {
+                let removed = core::mem::take(&mut *completion_members.lock());
+                for (key, sink) in removed {
+                    sink.close();
+                    completion_changes.emit(MembershipChange::Left(key));
+                }
+            },
+        );
+
+        SubscriberGroup {
+            members,
+            changes,
+            subscription,
+        }
+    }
+
+    /// Adds a member to the group, and returns the stream of values it receives from now on. If
+    /// the member has already joined, its existing stream is returned.
+    pub fn join(&self, key: K) -> Stream<T> {
+        let stream = {
+            let mut members = self.members.lock();
+            if let Some(sink) = members.get(&key) {
+                return sink.get_stream();
+            }
+            let sink = Sink::new();
+            let stream = sink.get_stream();
+            members.insert(key.clone(), Arc::new(sink));
+            stream
+        };
+        self.changes.emit(MembershipChange::Joined(key));
+        stream
+    }
+
+    /// Removes a member from the group, completing its stream. Returns false if it was not a
+    /// member.
+    pub fn leave(&self, key: &K) -> bool {
+        let removed = self.members.lock().remove(key);
+        match removed {
+            Some(sink) => {
+                sink.close();
+                self.changes.emit(MembershipChange::Left(key.clone()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the given key is a member of the group.
+    pub fn is_member(&self, key: &K) -> bool {
+        self.members.lock().contains_key(key)
+    }
+
+    /// Returns the keys of every current member, in order.
+    pub fn members(&self) -> Vec<K> {
+        self.members.lock().keys().cloned().collect()
+    }
+
+    /// Returns the number of current members.
+    pub fn count_members(&self) -> usize {
+        self.members.lock().len()
+    }
+
+    /// Returns a stream that emits whenever a member joins or leaves the group.
+    pub fn membership_changes(&self) -> Stream<MembershipChange<K>> {
+        self.changes.get_stream()
+    }
+}
@@ -0,0 +1,101 @@
+//! The lock that guards the state of every stream. By default this is the standard library Mutex.
+//! The `parking_lot` feature swaps it for parking_lot's Mutex, which is smaller and faster under
+//! contention. Without the `std` feature a spin lock is used, so that the core of the crate can
+//! run in `no_std` environments that only provide an allocator.
+//!
+//! User code does run under stream locks: listeners are called while the stream is locked, and so
+//! are the operator closures passed to `mutate_extra_fields`, so a panic can poison a standard
+//! library Mutex. The lock is recovered rather than propagating the poison, for two reasons. The
+//! stream's own bookkeeping (its subscribers, sequence numbers and whether it is alive) is never
+//! halfway through an update while user code runs, because emitting only reads it. And an
+//! operator whose closure panics is left with whatever state it had written so far, exactly as
+//! if it held no lock at all, which is also what the parking_lot and spin backends (neither of
+//! which poisons) do.
+
+#[cfg(feature = "parking_lot")]
+mod backend {
+    pub(crate) type RawMutex<T> = parking_lot::Mutex<T>;
+    pub(crate) type MutexGuard<'a, T> = parking_lot::MutexGuard<'a, T>;
+
+    pub(crate) fn lock<T>(mutex: &RawMutex<T>) -> MutexGuard<'_, T> {
+        mutex.lock()
+    }
+}
+
+#[cfg(all(feature = "std", not(feature = "parking_lot")))]
+mod backend {
+    pub(crate) type RawMutex<T> = std::sync::Mutex<T>;
+    pub(crate) type MutexGuard<'a, T> = std::sync::MutexGuard<'a, T>;
+
+    pub(crate) fn lock<T>(mutex: &RawMutex<T>) -> MutexGuard<'_, T> {
+        mutex
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod backend {
+    pub(crate) type RawMutex<T> = spin::Mutex<T>;
+    pub(crate) type MutexGuard<'a, T> = spin::MutexGuard<'a, T>;
+
+    pub(crate) fn lock<T>(mutex: &RawMutex<T>) -> MutexGuard<'_, T> {
+        mutex.lock()
+    }
+}
+
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "std")]
+thread_local! {
+    static HELD_LOCKS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Returns true if the current thread holds the lock of any stream, which is the case while a
+/// listener runs. Anything that blocks on a lock of its own from here (like the propagation lock
+/// of `ReactiveValue::set`) could deadlock with a thread that holds that lock and is waiting for
+/// the stream.
+#[cfg(feature = "std")]
+pub(crate) fn is_lock_held() -> bool {
+    HELD_LOCKS.with(|held| held.get() > 0)
+}
+
+pub(crate) struct Mutex<T>(backend::RawMutex<T>);
+
+impl<T> Mutex<T> {
+    pub(crate) fn new(value: T) -> Mutex<T> {
+        Mutex(backend::RawMutex::new(value))
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        let guard = backend::lock(&self.0);
+        #[cfg(feature = "std")]
+        HELD_LOCKS.with(|held| held.set(held.get() + 1));
+        MutexGuard { guard }
+    }
+}
+
+pub(crate) struct MutexGuard<'a, T> {
+    guard: backend::MutexGuard<'a, T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        HELD_LOCKS.with(|held| held.set(held.get() - 1));
+    }
+}
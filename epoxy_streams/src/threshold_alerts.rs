@@ -0,0 +1,273 @@
+use super::{Stream, Subscription};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// Configures `Stream::threshold_alerts`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ThresholdConfig {
+    /// Alert when the value rises above this threshold.
+    pub above: Option<f64>,
+
+    /// Alert when the value falls below this threshold.
+    pub below: Option<f64>,
+
+    /// How far the value has to move back past a threshold before the alert ends. This keeps a
+    /// value that hovers around a threshold from raising a flood of alerts.
+    pub hysteresis: f64,
+
+    /// How long the value has to stay past a threshold before the alert starts, to filter out
+    /// short spikes.
+    pub min_duration: Duration,
+}
+
+/// Identifies one of the thresholds of a `ThresholdConfig`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ThresholdBound {
+    Above,
+    Below,
+}
+
+/// An event emitted by `Stream::threshold_alerts`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThresholdAlert {
+    /// The value crossed the threshold and stayed past it for the minimum duration. `value` is
+    /// the latest value at that point.
+    Enter { bound: ThresholdBound, value: f64 },
+
+    /// The value moved back past the threshold by at least the hysteresis.
+    Exit { bound: ThresholdBound, value: f64 },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Clear,
+    // Past the threshold, but not for long enough yet.
+    Pending,
+    Alerting,
+}
+
+struct BoundTracker {
+    bound: ThresholdBound,
+    threshold: f64,
+    phase: Phase,
+
+    // Incremented whenever a pending alert is cancelled, so its timer can tell it is stale.
+    generation: u64,
+}
+
+impl BoundTracker {
+    fn is_past(&self, value: f64) -> bool {
+        match self.bound {
+            ThresholdBound::Above => value > self.threshold,
+            ThresholdBound::Below => value < self.threshold,
+        }
+    }
+
+    fn has_recovered(&self, value: f64, hysteresis: f64) -> bool {
+        match self.bound {
+            ThresholdBound::Above => value <= self.threshold - hysteresis,
+            ThresholdBound::Below => value >= self.threshold + hysteresis,
+        }
+    }
+}
+
+struct ThresholdFields<T> {
+    trackers: Vec<BoundTracker>,
+    latest: f64,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+impl<T> Stream<T>
+where
+    T: Copy + Into<f64> + Send + Sync + 'static,
+{
+    /// Returns a stream of alerts for when the value crosses the thresholds in `config`. An
+    /// `Enter` alert is emitted once the value has been past a threshold for
+    /// `config.min_duration` (timed on the stream's scheduler), and an `Exit` alert once it has
+    /// moved back by at least `config.hysteresis`. Each threshold is tracked separately.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, ThresholdAlert, ThresholdBound, ThresholdConfig};
+    ///
+    /// let stream_host: epoxy_streams::Sink<f32> = epoxy_streams::Sink::new();
+    /// let alerts = stream_host.get_stream().threshold_alerts(ThresholdConfig {
+    ///     above: Some(90.0),
+    ///     hysteresis: 5.0,
+    ///     ..Default::default()
+    /// });
+    /// let cache = ReactiveCache::from_stream(alerts);
+    ///
+    /// for temperature in [85.0, 91.0, 89.0, 92.0, 84.0] {
+    ///     stream_host.emit(temperature);
+    /// }
+    /// assert_eq!(
+    ///     cache.get_cloned(),
+    ///     vec![
+    ///         ThresholdAlert::Enter {
+    ///             bound: ThresholdBound::Above,
+    ///             value: 91.0
+    ///         },
+    ///         ThresholdAlert::Exit {
+    ///             bound: ThresholdBound::Above,
+    ///             value: 84.0
+    ///         },
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// Short spikes can be ignored by setting a minimum duration.
+    /// ```
+    /// use epoxy_streams::{runtime, ReactiveCache, ThresholdConfig};
+    /// use std::time::Duration;
+    ///
+    /// let runtime = runtime::manual();
+    /// let stream_host: epoxy_streams::Sink<u32> = epoxy_streams::Sink::new();
+    /// let alerts = stream_host.get_stream().threshold_alerts(ThresholdConfig {
+    ///     below: Some(10.0),
+    ///     min_duration: Duration::from_secs(5),
+    ///     ..Default::default()
+    /// });
+    /// let cache = ReactiveCache::from_stream(alerts);
+    ///
+    /// stream_host.emit(5);
+    /// runtime.advance(Duration::from_secs(1));
+    /// stream_host.emit(20);
+    /// runtime.advance(Duration::from_secs(10));
+    /// assert!(cache.get_cloned().is_empty());
+    ///
+    /// stream_host.emit(5);
+    /// runtime.advance(Duration::from_secs(5));
+    /// assert_eq!(cache.get_cloned().len(), 1);
+    /// ```
+    pub fn threshold_alerts(&self, config: ThresholdConfig) -> Stream<ThresholdAlert> {
+        let mut trackers = vec![];
+        if let Some(threshold) = config.above {
+            trackers.push(BoundTracker {
+                bound: ThresholdBound::Above,
+                threshold,
+                phase: Phase::Clear,
+                generation: 0,
+            });
+        }
+        if let Some(threshold) = config.below {
+            trackers.push(BoundTracker {
+                bound: ThresholdBound::Below,
+                threshold,
+                phase: Phase::Clear,
+                generation: 0,
+            });
+        }
+
+        let derived_stream = self.derive_with_fields(ThresholdFields::<T> {
+            trackers,
+            latest: 0.0,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+        let scheduler = derived_stream.scheduler();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                let value: f64 = (*val).into();
+
+                let mut alerts = vec![];
+                let mut timers = vec![];
+                stream_ref.mutate_extra_fields(|fields: &mut ThresholdFields<T>| {
+                    fields.latest = value;
+                    for (index, tracker) in fields.trackers.iter_mut().enumerate() {
+                        match tracker.phase {
+                            Phase::Clear if tracker.is_past(value) => {
+                                if config.min_duration.is_zero() {
+                                    tracker.phase = Phase::Alerting;
+                                    alerts.push(ThresholdAlert::Enter {
+                                        bound: tracker.bound,
+                                        value,
+                                    });
+                                } else {
+                                    tracker.phase = Phase::Pending;
+                                    timers.push((index, tracker.generation));
+                                }
+                            }
+                            Phase::Pending if !tracker.is_past(value) => {
+                                tracker.phase = Phase::Clear;
+                                tracker.generation += 1;
+                            }
+                            Phase::Alerting if tracker.has_recovered(value, config.hysteresis) => {
+                                tracker.phase = Phase::Clear;
+                                alerts.push(ThresholdAlert::Exit {
+                                    bound: tracker.bound,
+                                    value,
+                                });
+                            }
+                            _ => {}
+                        }
+                    }
+                });
+
+                for alert in alerts {
+                    stream_ref.emit_rc(Arc::new(alert));
+                }
+                for (index, generation) in timers {
+                    let timer_stream_ref: Weak<_> = Arc::downgrade(&stream_ref.pointer);
+                    scheduler.schedule_after(
+                        config.min_duration,
+                        Box::new(move || {
+                            if let Some(pointer) = timer_stream_ref.upgrade() {
+                                enter_pending_alert::<T>(&Stream { pointer }, index, generation);
+                            }
+                        }),
+                    );
+                }
+            },
+            move || {
+                if let Some(pointer) = weak_completion_ref.upgrade() {
+                    let stream_ref = Stream { pointer };
+                    stream_ref.mutate_extra_fields(|fields: &mut ThresholdFields<T>| {
+                        for tracker in fields.trackers.iter_mut() {
+                            tracker.generation += 1;
+                        }
+                    });
+                    stream_ref.complete();
+                }
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut ThresholdFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+}
+
+/// Starts the alert of a threshold whose minimum duration has passed, unless the value has moved
+/// back since.
+fn enter_pending_alert<T: Send + Sync + 'static>(
+    stream: &Stream<ThresholdAlert>,
+    index: usize,
+    generation: u64,
+) {
+    let mut alert = None;
+    stream.mutate_extra_fields(|fields: &mut ThresholdFields<T>| {
+        let latest = fields.latest;
+        let tracker = &mut fields.trackers[index];
+        if tracker.phase == Phase::Pending && tracker.generation == generation {
+            tracker.phase = Phase::Alerting;
+            alert = Some(ThresholdAlert::Enter {
+                bound: tracker.bound,
+                value: latest,
+            });
+        }
+    });
+    if let Some(alert) = alert {
+        stream.emit_rc(Arc::new(alert));
+    }
+}
@@ -0,0 +1,1011 @@
+use super::dead_letters::DeadLetterReason;
+use super::propagation::after_propagation;
+use super::scheduler::Scheduler;
+use super::streams::StreamImpl;
+use super::sync::Mutex;
+use super::{Stream, Subscription};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// What `Stream::rate_limit` does with values that arrive when no tokens are available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RateLimitOverflow {
+    /// Discard the value.
+    Drop,
+
+    /// Queue the value and emit it as soon as a token becomes available.
+    Delay,
+}
+
+struct DebounceFields<T> {
+    generation: u64,
+    latest: Option<Arc<T>>,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+struct KeyedDebounceFields<T, K> {
+    generation: u64,
+    pending: HashMap<K, (u64, Arc<T>)>,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+struct SessionFields<T> {
+    generation: u64,
+    session: Vec<Arc<T>>,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+// Ends the session of a `session_window` once `gap` has passed, unless a newer value (or a
+// restore) has bumped the generation since.
+fn schedule_session_end<T: 'static + Send + Sync>(
+    scheduler: &Arc<dyn Scheduler>,
+    gap: Duration,
+    stream: Weak<Mutex<StreamImpl<Vec<Arc<T>>>>>,
+    generation: u64,
+) {
+    scheduler.schedule_after(
+        gap,
+        Box::new(move || {
+            let stream_ref = match stream.upgrade() {
+                Some(pointer) => Stream { pointer },
+                None => return,
+            };
+            let mut session = vec![];
+            stream_ref.mutate_extra_fields(|fields: &mut SessionFields<T>| {
+                if fields.generation == generation {
+                    session = std::mem::take(&mut fields.session);
+                }
+            });
+            if !session.is_empty() {
+                stream_ref.emit_rc(Arc::new(session));
+            }
+        }),
+    );
+}
+
+impl<T: 'static + Send + Sync> Stream<T> {
+    /// Returns a stream that waits until the original stream has been quiet for the given
+    /// duration before emitting the latest value. A value that is followed by another value
+    /// within the quiet period is never emitted.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let debounced = stream_host.get_stream().debounce(Duration::from_millis(50));
+    /// let cache = ReactiveCache::from_stream(debounced);
+    ///
+    /// stream_host.emit(1);
+    /// stream_host.emit(2);
+    /// stream_host.emit(3);
+    /// assert_eq!(cache.get_cloned(), Vec::<i32>::new());
+    ///
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(cache.get_cloned(), vec![3]);
+    /// ```
+    pub fn debounce(&self, quiet_period: Duration) -> Stream<T> {
+        let derived_stream = self.derive_with_fields(DebounceFields::<T> {
+            generation: 0,
+            latest: None,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+        let scheduler = derived_stream.scheduler();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+
+                let mut generation = 0;
+                stream_ref.mutate_extra_fields(|fields: &mut DebounceFields<T>| {
+                    fields.generation += 1;
+                    fields.latest = Some(val);
+                    generation = fields.generation;
+                });
+
+                let timer_stream_ref: Weak<_> = Arc::downgrade(&stream_ref.pointer);
+                scheduler.schedule_after(
+                    quiet_period,
+                    Box::new(move || {
+                        let stream_ref = match timer_stream_ref.upgrade() {
+                            Some(pointer) => Stream { pointer },
+                            None => return,
+                        };
+                        let mut latest = None;
+                        stream_ref.mutate_extra_fields(|fields: &mut DebounceFields<T>| {
+                            if fields.generation == generation {
+                                latest = fields.latest.take();
+                            }
+                        });
+                        if let Some(value) = latest {
+                            stream_ref.emit_rc(value);
+                        }
+                    }),
+                );
+            },
+            move || {
+                // Emit the pending value right away rather than waiting for the quiet period.
+                let mut latest = None;
+                completion_stream_ref.mutate_extra_fields(|fields: &mut DebounceFields<T>| {
+                    fields.generation += 1;
+                    latest = fields.latest.take();
+                });
+                if let Some(value) = latest {
+                    completion_stream_ref.emit_rc(value);
+                }
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut DebounceFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Same as `debounce`, but values are grouped by the key that `key_function` returns and
+    /// every group gets its own quiet period. A value only suppresses earlier values with the
+    /// same key, so for example keystrokes in one text field never delay the updates for
+    /// another.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: epoxy_streams::Sink<(&str, &str)> = epoxy_streams::Sink::new();
+    /// let debounced = stream_host
+    ///     .get_stream()
+    ///     .debounce_by_key(|(field, _)| *field, Duration::from_millis(50));
+    /// let cache = ReactiveCache::from_stream(debounced);
+    ///
+    /// stream_host.emit(("name", "K"));
+    /// stream_host.emit(("email", "k@"));
+    /// stream_host.emit(("name", "Ke"));
+    ///
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// let mut values: Vec<_> = cache.get_cloned().into_iter().collect();
+    /// values.sort();
+    /// assert_eq!(values, vec![("email", "k@"), ("name", "Ke")]);
+    /// ```
+    pub fn debounce_by_key<K, F>(&self, key_function: F, quiet_period: Duration) -> Stream<T>
+    where
+        K: 'static + Send + Sync + Clone + Eq + Hash,
+        F: Fn(&T) -> K,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        let derived_stream = self.derive_with_fields(KeyedDebounceFields::<T, K> {
+            generation: 0,
+            pending: HashMap::new(),
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+        let scheduler = derived_stream.scheduler();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+
+                let key = key_function(&val);
+                let mut generation = 0;
+                stream_ref.mutate_extra_fields(|fields: &mut KeyedDebounceFields<T, K>| {
+                    fields.generation += 1;
+                    generation = fields.generation;
+                    fields.pending.insert(key.clone(), (generation, val));
+                });
+
+                let timer_stream_ref: Weak<_> = Arc::downgrade(&stream_ref.pointer);
+                scheduler.schedule_after(
+                    quiet_period,
+                    Box::new(move || {
+                        let stream_ref = match timer_stream_ref.upgrade() {
+                            Some(pointer) => Stream { pointer },
+                            None => return,
+                        };
+                        let mut latest = None;
+                        stream_ref.mutate_extra_fields(|fields: &mut KeyedDebounceFields<T, K>| {
+                            if let Some((pending_generation, _)) = fields.pending.get(&key) {
+                                if *pending_generation == generation {
+                                    latest = fields.pending.remove(&key);
+                                }
+                            }
+                        });
+                        if let Some((_, value)) = latest {
+                            stream_ref.emit_rc(value);
+                        }
+                    }),
+                );
+            },
+            move || {
+                // Emit every pending value right away, in the order they arrived.
+                let mut pending = vec![];
+                completion_stream_ref.mutate_extra_fields(
+                    |fields: &mut KeyedDebounceFields<T, K>| {
+                        pending = fields.pending.drain().map(|(_, entry)| entry).collect();
+                    },
+                );
+                pending.sort_by_key(|(generation, _)| *generation);
+                for (_, value) in pending {
+                    completion_stream_ref.emit_rc(value);
+                }
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut KeyedDebounceFields<T, K>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Returns a stream that groups values into sessions, where a session ends once the original
+    /// stream has been quiet for `gap`. Each session is emitted as a whole when it ends, and a
+    /// session that is still open when the original stream completes is emitted right away.
+    /// Unlike fixed windows, a session can be any length, which makes this a natural fit for
+    /// bursts of activity like a user's clicks or a device's reconnect attempts. The gap is
+    /// measured by the stream's scheduler.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::scheduler::ManualScheduler;
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let scheduler = Arc::new(ManualScheduler::new());
+    /// let stream_host: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+    /// let sessions = stream_host
+    ///     .get_stream()
+    ///     .with_scheduler(scheduler.clone())
+    ///     .session_window(Duration::from_secs(60))
+    ///     .map(|session| session.iter().map(|click| **click).collect::<Vec<&str>>());
+    /// let cache = ReactiveCache::from_stream(sessions);
+    ///
+    /// stream_host.emit("home");
+    /// scheduler.advance(Duration::from_secs(30));
+    /// stream_host.emit("search");
+    /// scheduler.advance(Duration::from_secs(30));
+    /// stream_host.emit("checkout");
+    /// assert!(cache.get_cloned().is_empty());
+    ///
+    /// scheduler.advance(Duration::from_secs(60));
+    /// stream_host.emit("home");
+    /// stream_host.close();
+    /// assert_eq!(
+    ///     cache.get_cloned(),
+    ///     vec![vec!["home", "search", "checkout"], vec!["home"]]
+    /// );
+    /// ```
+    pub fn session_window(&self, gap: Duration) -> Stream<Vec<Arc<T>>> {
+        let derived_stream = self.derive_with_fields(SessionFields::<T> {
+            generation: 0,
+            session: Vec::new(),
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+        let scheduler = derived_stream.scheduler();
+
+        // A restored session ends after `gap` like any other, even if no new value arrives.
+        #[cfg(feature = "checkpoint")]
+        {
+            let restore_scheduler = scheduler.clone();
+            let restore_stream_ref = weak_stream_ref.clone();
+            derived_stream.set_state_hooks(
+                |fields: &SessionFields<T>| {
+                    if fields.session.is_empty() {
+                        vec![]
+                    } else {
+                        vec![Arc::new(fields.session.clone())]
+                    }
+                },
+                move |fields: &mut SessionFields<T>, values| {
+                    fields.generation += 1;
+                    fields.session = values
+                        .iter()
+                        .flat_map(|session| session.iter().cloned())
+                        .collect();
+                    if !fields.session.is_empty() {
+                        schedule_session_end::<T>(
+                            &restore_scheduler,
+                            gap,
+                            restore_stream_ref.clone(),
+                            fields.generation,
+                        );
+                    }
+                },
+            );
+        }
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+
+                let mut generation = 0;
+                stream_ref.mutate_extra_fields(|fields: &mut SessionFields<T>| {
+                    fields.generation += 1;
+                    fields.session.push(val);
+                    generation = fields.generation;
+                });
+
+                schedule_session_end::<T>(
+                    &scheduler,
+                    gap,
+                    Arc::downgrade(&stream_ref.pointer),
+                    generation,
+                );
+            },
+            move || {
+                let mut session = vec![];
+                completion_stream_ref.mutate_extra_fields(|fields: &mut SessionFields<T>| {
+                    fields.generation += 1;
+                    session = std::mem::take(&mut fields.session);
+                });
+                if !session.is_empty() {
+                    completion_stream_ref.emit_rc(Arc::new(session));
+                }
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut SessionFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Returns a stream that, whenever the original stream emits, waits for the given duration
+    /// and then emits the most recent value seen during that window. Unlike `debounce`, a steady
+    /// flow of values still produces one emission per window instead of waiting for a gap.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::ReactiveCache;
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let audited = stream_host.get_stream().audit(Duration::from_millis(50));
+    /// let cache = ReactiveCache::from_stream(audited);
+    ///
+    /// stream_host.emit(1);
+    /// stream_host.emit(2);
+    /// assert_eq!(cache.get_cloned(), Vec::<i32>::new());
+    ///
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(cache.get_cloned(), vec![2]);
+    /// ```
+    pub fn audit(&self, window: Duration) -> Stream<T> {
+        let derived_stream = self.derive_with_fields(AuditFields::<T> {
+            latest: None,
+            is_window_open: false,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+        let scheduler = derived_stream.scheduler();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+
+                let mut should_open_window = false;
+                stream_ref.mutate_extra_fields(|fields: &mut AuditFields<T>| {
+                    fields.latest = Some(val);
+                    should_open_window = !fields.is_window_open;
+                    fields.is_window_open = true;
+                });
+                if !should_open_window {
+                    return;
+                }
+
+                let timer_stream_ref: Weak<_> = Arc::downgrade(&stream_ref.pointer);
+                scheduler.schedule_after(
+                    window,
+                    Box::new(move || {
+                        let stream_ref = match timer_stream_ref.upgrade() {
+                            Some(pointer) => Stream { pointer },
+                            None => return,
+                        };
+                        let mut latest = None;
+                        stream_ref.mutate_extra_fields(|fields: &mut AuditFields<T>| {
+                            fields.is_window_open = false;
+                            latest = fields.latest.take();
+                        });
+                        if let Some(value) = latest {
+                            stream_ref.emit_rc(value);
+                        }
+                    }),
+                );
+            },
+            move || {
+                let mut latest = None;
+                completion_stream_ref.mutate_extra_fields(|fields: &mut AuditFields<T>| {
+                    latest = fields.latest.take();
+                });
+                if let Some(value) = latest {
+                    completion_stream_ref.emit_rc(value);
+                }
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut AuditFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Returns a stream that coalesces all of the values emitted during a single propagation turn
+    /// into one emission of the latest value. A propagation turn covers a `ReactiveValue::set`
+    /// call or a `transaction` and everything that updates as a result. Values emitted outside
+    /// of a propagation turn are coalesced until the stream's scheduler runs its next task.
+    ///
+    /// This is useful for UI bindings that depend on many values but should only re-render once
+    /// per update.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, ReactiveValue};
+    ///
+    /// let first_name = ReactiveValue::new("Ada".to_string());
+    /// let last_name = ReactiveValue::new("Byron".to_string());
+    /// let renders = epoxy_streams::merge(vec![first_name.as_stream(), last_name.as_stream()])
+    ///     .coalesce_microtask();
+    /// let cache = ReactiveCache::from_stream(renders);
+    ///
+    /// epoxy_streams::transaction(|| {
+    ///     first_name.set("Grace".to_string());
+    ///     last_name.set("Hopper".to_string());
+    /// });
+    /// assert_eq!(cache.get_cloned(), vec!["Hopper".to_string()]);
+    /// ```
+    pub fn coalesce_microtask(&self) -> Stream<T> {
+        let derived_stream = self.derive_with_fields(CoalesceFields::<T> {
+            latest: None,
+            is_flush_pending: false,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let completion_stream_ref = derived_stream.clone();
+        let scheduler = derived_stream.scheduler();
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+
+                let mut should_schedule_flush = false;
+                stream_ref.mutate_extra_fields(|fields: &mut CoalesceFields<T>| {
+                    fields.latest = Some(val);
+                    should_schedule_flush = !fields.is_flush_pending;
+                    fields.is_flush_pending = true;
+                });
+                if !should_schedule_flush {
+                    return;
+                }
+
+                let flush_stream_ref = Arc::downgrade(&stream_ref.pointer);
+                let flush = move || {
+                    if let Some(pointer) = flush_stream_ref.upgrade() {
+                        flush_coalesced(&Stream { pointer });
+                    }
+                };
+                if !after_propagation(flush.clone()) {
+                    scheduler.schedule(Box::new(flush));
+                }
+            },
+            move || {
+                flush_coalesced(&completion_stream_ref);
+                completion_stream_ref.complete();
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut CoalesceFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Returns a stream that limits the rate of values using a token bucket. The bucket holds up
+    /// to `burst` tokens and refills at `tokens_per_sec`, and every emitted value uses one token.
+    /// Unlike a throttle this lets short bursts through untouched while still enforcing a precise
+    /// sustained rate. Values that arrive when the bucket is empty are handled according to
+    /// `overflow`. Delayed values are still emitted if the original stream completes.
+    ///
+    /// Panics if `tokens_per_sec` is not a positive, finite number, or if `burst` is 0, since the
+    /// stream could never emit anything.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{RateLimitOverflow, ReactiveCache};
+    /// use std::time::Duration;
+    ///
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let limited = stream_host
+    ///     .get_stream()
+    ///     .rate_limit(20.0, 2, RateLimitOverflow::Delay);
+    /// let cache = ReactiveCache::from_stream(limited);
+    ///
+    /// stream_host.emit(1);
+    /// stream_host.emit(2);
+    /// stream_host.emit(3);
+    /// assert_eq!(cache.get_cloned(), vec![1, 2]);
+    ///
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(cache.get_cloned(), vec![1, 2, 3]);
+    /// ```
+    pub fn rate_limit(
+        &self,
+        tokens_per_sec: f64,
+        burst: u32,
+        overflow: RateLimitOverflow,
+    ) -> Stream<T> {
+        assert!(
+            tokens_per_sec.is_finite() && tokens_per_sec > 0.0,
+            "rate_limit needs a positive, finite number of tokens per second"
+        );
+        assert!(burst > 0, "rate_limit needs a burst of at least 1");
+        let scheduler = self.scheduler();
+        let derived_stream = self.derive_with_fields(RateLimitFields::<T> {
+            bucket: TokenBucket {
+                tokens: f64::from(burst),
+                capacity: f64::from(burst),
+                tokens_per_sec,
+                last_refill: scheduler.now(),
+            },
+            queue: VecDeque::new(),
+            is_drain_scheduled: false,
+            is_source_complete: false,
+            subscription: None,
+        });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+
+        let subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+
+                let mut should_emit = false;
+                let mut is_dropped = false;
+                let mut drain_delay = None;
+                stream_ref.mutate_extra_fields(|fields: &mut RateLimitFields<T>| {
+                    if fields.queue.is_empty() && fields.bucket.try_take(scheduler.now()) {
+                        should_emit = true;
+                    } else if overflow == RateLimitOverflow::Delay {
+                        fields.queue.push_back(val.clone());
+                        if !fields.is_drain_scheduled {
+                            fields.is_drain_scheduled = true;
+                            drain_delay = Some(fields.bucket.time_until_token());
+                        }
+                    } else {
+                        is_dropped = true;
+                    }
+                });
+
+                if should_emit {
+                    stream_ref.emit_rc(val);
+                } else if is_dropped {
+                    stream_ref.report_dead_letter(val, DeadLetterReason::RateLimited);
+                }
+                if let Some(delay) = drain_delay {
+                    schedule_rate_limit_drain(&stream_ref, delay);
+                }
+            },
+            move || {
+                if let Some(pointer) = weak_completion_ref.upgrade() {
+                    let stream_ref = Stream { pointer };
+                    let mut is_drained = false;
+                    stream_ref.mutate_extra_fields(|fields: &mut RateLimitFields<T>| {
+                        fields.is_source_complete = true;
+                        is_drained = fields.queue.is_empty();
+                    });
+                    if is_drained {
+                        stream_ref.complete();
+                    }
+                }
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut RateLimitFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Returns a stream that pairs up values from this stream and `other` that arrived within
+    /// `window` of each other, in either order. Each value is paired with every value from the
+    /// other stream that falls inside the window, so a value can appear in several pairs. This is
+    /// useful for correlating events from separate sources, like two sensors observing the same
+    /// thing or a request log and an error log. Arrival times come from the stream's scheduler,
+    /// and the returned stream completes once both streams have completed.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{runtime, ReactiveCache};
+    /// use std::time::Duration;
+    ///
+    /// let runtime = runtime::manual();
+    /// let doors: epoxy_streams::Sink<&str> = epoxy_streams::Sink::new();
+    /// let cameras: epoxy_streams::Sink<u32> = epoxy_streams::Sink::new();
+    /// let sightings = doors
+    ///     .get_stream()
+    ///     .join_within(&cameras.get_stream(), Duration::from_secs(2))
+    ///     .map(|(door, frame)| (**door, **frame));
+    /// let cache = ReactiveCache::from_stream(sightings);
+    ///
+    /// doors.emit("front");
+    /// runtime.advance(Duration::from_secs(1));
+    /// cameras.emit(1);
+    /// runtime.advance(Duration::from_secs(5));
+    /// cameras.emit(2);
+    /// doors.emit("back");
+    ///
+    /// assert_eq!(cache.get_cloned(), vec![("front", 1), ("back", 2)]);
+    /// ```
+    pub fn join_within<U>(&self, other: &Stream<U>, window: Duration) -> Stream<(Arc<T>, Arc<U>)>
+    where
+        U: 'static + Send + Sync,
+    {
+        let scheduler = self.scheduler();
+        let derived_stream = self.derive_with_fields(JoinWithinFields::<T, U> {
+            left: VecDeque::new(),
+            right: VecDeque::new(),
+            completed_sources: 0,
+            left_subscription: None,
+            right_subscription: None,
+        });
+
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let left_scheduler = scheduler.clone();
+        let left_subscription = self.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                let now = left_scheduler.now();
+                let mut pairs = vec![];
+                stream_ref.mutate_extra_fields(|fields: &mut JoinWithinFields<T, U>| {
+                    fields.evict_expired(now, window);
+                    for (_, other_val) in fields.right.iter() {
+                        pairs.push((val.clone(), other_val.clone()));
+                    }
+                    fields.left.push_back((now, val));
+                });
+                for pair in pairs {
+                    stream_ref.emit_rc(Arc::new(pair));
+                }
+            },
+            complete_join_source::<T, U>(Arc::downgrade(&derived_stream.pointer)),
+        );
+
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let right_subscription = other.subscribe_with_completion(
+            move |val| {
+                let stream_ref = match weak_stream_ref.upgrade() {
+                    Some(pointer) => Stream { pointer },
+                    None => return,
+                };
+                let now = scheduler.now();
+                let mut pairs = vec![];
+                stream_ref.mutate_extra_fields(|fields: &mut JoinWithinFields<T, U>| {
+                    fields.evict_expired(now, window);
+                    for (_, other_val) in fields.left.iter() {
+                        pairs.push((other_val.clone(), val.clone()));
+                    }
+                    fields.right.push_back((now, val));
+                });
+                for pair in pairs {
+                    stream_ref.emit_rc(Arc::new(pair));
+                }
+            },
+            complete_join_source::<T, U>(Arc::downgrade(&derived_stream.pointer)),
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut JoinWithinFields<T, U>| {
+            fields.left_subscription = Some(left_subscription);
+            fields.right_subscription = Some(right_subscription);
+        });
+
+        derived_stream
+    }
+
+    /// Subscribes to the stream with a listener that receives values in batches rather than one at
+    /// a time. A batch is delivered once it holds `max_batch` values, or `max_delay` after its
+    /// first value arrived, whichever comes first, and any values still waiting are delivered when
+    /// the stream completes. This amortizes the per-call overhead of listeners like database
+    /// writers and network senders. The delay is measured by the stream's scheduler, and batches
+    /// are always delivered in order.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::scheduler::ManualScheduler;
+    /// use std::sync::{Arc, Mutex};
+    /// use std::time::Duration;
+    ///
+    /// let scheduler = Arc::new(ManualScheduler::new());
+    /// let stream_host: epoxy_streams::Sink<i32> = epoxy_streams::Sink::new();
+    /// let batches = Arc::new(Mutex::new(vec![]));
+    /// let batches_write = batches.clone();
+    /// let _subscription = stream_host
+    ///     .get_stream()
+    ///     .with_scheduler(scheduler.clone())
+    ///     .subscribe_batched(3, Duration::from_millis(100), move |batch| {
+    ///         let batch = batch.iter().map(|val| **val).collect::<Vec<i32>>();
+    ///         batches_write.lock().unwrap().push(batch);
+    ///     });
+    ///
+    /// stream_host.emit_many(1..=4);
+    /// assert_eq!(*batches.lock().unwrap(), vec![vec![1, 2, 3]]);
+    ///
+    /// scheduler.advance(Duration::from_millis(100));
+    /// stream_host.emit(5);
+    /// stream_host.close();
+    /// assert_eq!(*batches.lock().unwrap(), vec![vec![1, 2, 3], vec![4], vec![5]]);
+    /// ```
+    pub fn subscribe_batched<F>(
+        &self,
+        max_batch: usize,
+        max_delay: Duration,
+        listener: F,
+    ) -> Subscription<T>
+    where
+        F: Fn(Vec<Arc<T>>) + Send + Sync + 'static,
+    {
+        let batcher = Arc::new(Batcher {
+            state: Mutex::new(BatchState {
+                pending: Vec::new(),
+                generation: 0,
+            }),
+            max_batch: max_batch.max(1),
+            listener,
+        });
+        let completion_batcher = batcher.clone();
+        let scheduler = self.scheduler();
+
+        self.subscribe_with_completion(
+            move |val| {
+                let mut state = batcher.state.lock();
+                state.pending.push(val);
+                if state.pending.len() >= batcher.max_batch {
+                    batcher.flush(&mut state);
+                } else if state.pending.len() == 1 {
+                    let generation = state.generation;
+                    let timer_batcher = Arc::downgrade(&batcher);
+                    scheduler.schedule_after(
+                        max_delay,
+                        Box::new(move || {
+                            if let Some(batcher) = timer_batcher.upgrade() {
+                                let mut state = batcher.state.lock();
+                                if state.generation == generation {
+                                    batcher.flush(&mut state);
+                                }
+                            }
+                        }),
+                    );
+                }
+            },
+            move || {
+                let mut state = completion_batcher.state.lock();
+                completion_batcher.flush(&mut state);
+            },
+        )
+    }
+}
+
+struct BatchState<T> {
+    pending: Vec<Arc<T>>,
+
+    // Incremented by every flush, so a timer can tell whether its batch is still pending.
+    generation: u64,
+}
+
+struct Batcher<T, F> {
+    state: Mutex<BatchState<T>>,
+    max_batch: usize,
+    listener: F,
+}
+
+impl<T, F: Fn(Vec<Arc<T>>)> Batcher<T, F> {
+    // The listener runs while the state is locked, which keeps batches in order when a timer and
+    // a full batch flush at the same time.
+    fn flush(&self, state: &mut BatchState<T>) {
+        state.generation += 1;
+        if !state.pending.is_empty() {
+            (self.listener)(std::mem::take(&mut state.pending));
+        }
+    }
+}
+
+struct CoalesceFields<T> {
+    latest: Option<Arc<T>>,
+    is_flush_pending: bool,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+fn flush_coalesced<T: 'static + Send + Sync>(stream: &Stream<T>) {
+    let mut latest = None;
+    stream.mutate_extra_fields(|fields: &mut CoalesceFields<T>| {
+        fields.is_flush_pending = false;
+        latest = fields.latest.take();
+    });
+    if let Some(value) = latest {
+        stream.emit_rc(value);
+    }
+}
+
+struct AuditFields<T> {
+    latest: Option<Arc<T>>,
+    is_window_open: bool,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    capacity: f64,
+    tokens_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.tokens_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn time_until_token(&self) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / self.tokens_per_sec).max(0.0))
+    }
+}
+
+struct RateLimitFields<T> {
+    bucket: TokenBucket,
+    queue: VecDeque<Arc<T>>,
+    is_drain_scheduled: bool,
+    is_source_complete: bool,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+fn schedule_rate_limit_drain<T: 'static + Send + Sync>(stream: &Stream<T>, delay: Duration) {
+    let weak_stream_ref = Arc::downgrade(&stream.pointer);
+    let scheduler = stream.scheduler();
+    scheduler.clone().schedule_after(
+        delay,
+        Box::new(move || {
+            let stream_ref = match weak_stream_ref.upgrade() {
+                Some(pointer) => Stream { pointer },
+                None => return,
+            };
+
+            let now = scheduler.now();
+            let mut ready = vec![];
+            let mut next_delay = None;
+            let mut is_finished = false;
+            stream_ref.mutate_extra_fields(|fields: &mut RateLimitFields<T>| {
+                while !fields.queue.is_empty() && fields.bucket.try_take(now) {
+                    ready.push(fields.queue.pop_front().unwrap());
+                }
+                if fields.queue.is_empty() {
+                    fields.is_drain_scheduled = false;
+                    is_finished = fields.is_source_complete;
+                } else {
+                    next_delay = Some(fields.bucket.time_until_token());
+                }
+            });
+
+            for value in ready {
+                stream_ref.emit_rc(value);
+            }
+            if let Some(delay) = next_delay {
+                schedule_rate_limit_drain(&stream_ref, delay);
+            }
+            if is_finished {
+                stream_ref.complete();
+            }
+        }),
+    );
+}
+
+type WeakJoinedStream<T, U> = Weak<Mutex<StreamImpl<(Arc<T>, Arc<U>)>>>;
+
+struct JoinWithinFields<T, U> {
+    // Values that arrived within the window, with their arrival times, oldest first.
+    left: VecDeque<(Instant, Arc<T>)>,
+    right: VecDeque<(Instant, Arc<U>)>,
+    completed_sources: u8,
+
+    #[allow(dead_code)]
+    left_subscription: Option<Subscription<T>>,
+    #[allow(dead_code)]
+    right_subscription: Option<Subscription<U>>,
+}
+
+impl<T, U> JoinWithinFields<T, U> {
+    fn evict_expired(&mut self, now: Instant, window: Duration) {
+        while matches!(self.left.front(), Some((time, _)) if now.duration_since(*time) > window) {
+            self.left.pop_front();
+        }
+        while matches!(self.right.front(), Some((time, _)) if now.duration_since(*time) > window) {
+            self.right.pop_front();
+        }
+    }
+}
+
+/// Returns a completion listener for one side of a `join_within`, which completes the joined
+/// stream once both sides have completed.
+fn complete_join_source<T, U>(
+    weak_stream_ref: WeakJoinedStream<T, U>,
+) -> impl FnOnce() + Send + 'static
+where
+    T: 'static + Send + Sync,
+    U: 'static + Send + Sync,
+{
+    move || {
+        if let Some(pointer) = weak_stream_ref.upgrade() {
+            let stream_ref = Stream { pointer };
+            let mut is_finished = false;
+            stream_ref.mutate_extra_fields(|fields: &mut JoinWithinFields<T, U>| {
+                fields.completed_sources += 1;
+                is_finished = fields.completed_sources == 2;
+            });
+            if is_finished {
+                stream_ref.complete();
+            }
+        }
+    }
+}
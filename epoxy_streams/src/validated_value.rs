@@ -0,0 +1,121 @@
+use super::{
+    ReactiveValue, ReactiveValueReadGuard, ReadonlyReactiveValue, Sink, Stream, ValuePoisoned,
+    WriteableReactiveValue,
+};
+use std::sync::Arc;
+
+type Validator<T, E> = dyn Fn(&T) -> Result<(), E> + Send + Sync;
+
+/// A writeable ReactiveValue that checks every new value before accepting it. Created with
+/// `WriteableReactiveValue::with_validator`.
+///
+/// Rejected values leave the current value unchanged. The error is returned from `set`, and is
+/// also emitted on the `validation_errors` stream so that UI code can display it without
+/// having access to every call site.
+pub struct ValidatedReactiveValue<T, E> {
+    value: WriteableReactiveValue<T>,
+    validator: Arc<Validator<T, E>>,
+    errors: Arc<Sink<E>>,
+}
+
+impl<T, E> Clone for ValidatedReactiveValue<T, E> {
+    fn clone(&self) -> Self {
+        ValidatedReactiveValue {
+            value: self.value.clone(),
+            validator: Arc::clone(&self.validator),
+            errors: Arc::clone(&self.errors),
+        }
+    }
+}
+
+impl<T: 'static + Send + Sync, E> ReactiveValue<T> for ValidatedReactiveValue<T, E> {
+    fn as_stream(&self) -> Stream<T> {
+        self.value.as_stream()
+    }
+
+    fn get(&self) -> Arc<T> {
+        self.value.get()
+    }
+
+    fn read(&self) -> ReactiveValueReadGuard<'_, T> {
+        self.value.read()
+    }
+
+    fn try_get(&self) -> Result<Arc<T>, ValuePoisoned> {
+        self.value.try_get()
+    }
+}
+
+impl<T, E> ValidatedReactiveValue<T, E>
+where
+    T: 'static + Send + Sync,
+    E: 'static + Send + Sync + Clone,
+{
+    /// Sets the value if it passes validation. Otherwise the value is left unchanged, and the
+    /// error is returned and emitted on the `validation_errors` stream.
+    pub fn set(&self, value: T) -> Result<(), E> {
+        self.set_rc(Arc::new(value))
+    }
+
+    /// Same logic as `set`, but takes an existing Arc pointer.
+    pub fn set_rc(&self, value: Arc<T>) -> Result<(), E> {
+        match (self.validator)(&value) {
+            Ok(()) => {
+                self.value.set_rc(value);
+                Ok(())
+            }
+            Err(err) => {
+                self.errors.emit(err.clone());
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns a stream of the errors produced by rejected calls to `set`.
+    pub fn validation_errors(&self) -> Stream<E> {
+        self.errors.get_stream()
+    }
+
+    /// Returns a ReadonlyReactiveValue whose value matches this one.
+    pub fn as_readonly(&self) -> ReadonlyReactiveValue<T> {
+        self.value.as_readonly()
+    }
+}
+
+impl<T: 'static + Send + Sync> WriteableReactiveValue<T> {
+    /// Adds a validator to the ReactiveValue. Every value passed to `set` on the returned
+    /// ValidatedReactiveValue is checked first, and rejected if the validator returns an error.
+    /// The initial value is not validated.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, ReactiveValue};
+    ///
+    /// let age = ReactiveValue::new(30).with_validator(|age| {
+    ///     if *age <= 150 {
+    ///         Ok(())
+    ///     } else {
+    ///         Err(format!("{} is not a valid age", age))
+    ///     }
+    /// });
+    /// let errors = ReactiveCache::from_stream(age.validation_errors());
+    ///
+    /// assert_eq!(age.set(31), Ok(()));
+    /// assert!(age.set(300).is_err());
+    /// assert_eq!(*age.get(), 31);
+    /// assert_eq!(errors.get_cloned(), vec!["300 is not a valid age".to_string()]);
+    /// ```
+    pub fn with_validator<E, F>(self, validator: F) -> ValidatedReactiveValue<T, E>
+    where
+        F: Fn(&T) -> Result<(), E>,
+        F: Send,
+        F: Sync,
+        F: 'static,
+    {
+        ValidatedReactiveValue {
+            value: self,
+            validator: Arc::new(validator),
+            errors: Arc::new(Sink::new()),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use super::{ReactiveValue, Stream, Subscription};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// A value tagged with the time at which it was recorded. See `ReactiveValue::history`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Timestamped<T> {
+    pub timestamp: Instant,
+    pub value: Arc<T>,
+}
+
+impl<T> Clone for Timestamped<T> {
+    fn clone(&self) -> Self {
+        Timestamped {
+            timestamp: self.timestamp,
+            value: self.value.clone(),
+        }
+    }
+}
+
+struct ValueHistoryFields<T> {
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+/// The recorded changes of a ReactiveValue, see `ReactiveValue::history`.
+pub struct ValueHistory<T> {
+    stream: Stream<Timestamped<T>>,
+    recent: Arc<Mutex<VecDeque<Timestamped<T>>>>,
+}
+
+impl<T> ValueHistory<T> {
+    /// Returns a stream that emits every change of the value along with its timestamp.
+    pub fn get_stream(&self) -> Stream<Timestamped<T>> {
+        self.stream.clone()
+    }
+
+    /// Returns up to `count` of the most recently recorded values, oldest first. The value the
+    /// ReactiveValue had when the history was created is recorded as well.
+    pub fn recent(&self, count: usize) -> Vec<Timestamped<T>> {
+        let recent = lock_recent(&self.recent);
+        recent
+            .iter()
+            .skip(recent.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+fn lock_recent<T>(
+    recent: &Mutex<VecDeque<Timestamped<T>>>,
+) -> MutexGuard<'_, VecDeque<Timestamped<T>>> {
+    match recent.lock() {
+        Ok(recent) => recent,
+        Err(err) => panic!("Value history mutex poisoned: {}", err),
+    }
+}
+
+impl<T: 'static + Send + Sync> dyn ReactiveValue<T> {
+    /// Records every change of a ReactiveValue along with the time it happened, for example to
+    /// chart a value over time. Timestamps come from the scheduler of the value's stream, so they
+    /// follow virtual time when using `runtime::manual`. The `capacity` most recent values are
+    /// kept in memory and can be read with `ValueHistory::recent`.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{runtime, ReactiveValue};
+    /// use std::time::Duration;
+    ///
+    /// let runtime = runtime::manual();
+    /// let temperature = ReactiveValue::new(20);
+    /// let history = ReactiveValue::history(&temperature, 2);
+    ///
+    /// runtime.advance(Duration::from_secs(1));
+    /// temperature.set(21);
+    /// runtime.advance(Duration::from_secs(1));
+    /// temperature.set(23);
+    ///
+    /// let recent = history.recent(10);
+    /// assert_eq!(recent.len(), 2);
+    /// assert_eq!(*recent[0].value, 21);
+    /// assert_eq!(*recent[1].value, 23);
+    /// assert_eq!(recent[1].timestamp - recent[0].timestamp, Duration::from_secs(1));
+    /// ```
+    pub fn history(value: &dyn ReactiveValue<T>, capacity: usize) -> ValueHistory<T> {
+        let source = value.as_stream();
+        let scheduler = source.scheduler();
+        let recent = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        record(
+            &recent,
+            capacity,
+            Timestamped {
+                timestamp: scheduler.now(),
+                value: value.get(),
+            },
+        );
+
+        let derived_stream =
+            source.derive_with_fields(ValueHistoryFields::<T> { subscription: None });
+        let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+        let weak_completion_ref = Arc::downgrade(&derived_stream.pointer);
+        let recent_ref = recent.clone();
+        let subscription = source.subscribe_with_completion(
+            move |val| {
+                let entry = Timestamped {
+                    timestamp: scheduler.now(),
+                    value: val,
+                };
+                record(&recent_ref, capacity, entry.clone());
+                if let Some(pointer) = weak_stream_ref.upgrade() {
+                    Stream { pointer }.emit_rc(Arc::new(entry));
+                }
+            },
+            move || {
+                if let Some(pointer) = weak_completion_ref.upgrade() {
+                    Stream { pointer }.complete();
+                }
+            },
+        );
+
+        derived_stream.mutate_extra_fields(move |fields: &mut ValueHistoryFields<T>| {
+            fields.subscription = Some(subscription);
+        });
+
+        ValueHistory {
+            stream: derived_stream,
+            recent,
+        }
+    }
+}
+
+fn record<T>(recent: &Mutex<VecDeque<Timestamped<T>>>, capacity: usize, entry: Timestamped<T>) {
+    if capacity == 0 {
+        return;
+    }
+    let mut recent = lock_recent(recent);
+    if recent.len() == capacity {
+        recent.pop_front();
+    }
+    recent.push_back(entry);
+}
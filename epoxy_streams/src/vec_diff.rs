@@ -0,0 +1,196 @@
+use super::{ReactiveValue, Stream};
+use std::sync::{Arc, Mutex};
+
+/// One step in turning one version of a list into the next, as produced by
+/// `ReactiveValue::diffs`. Edits are meant to be applied in order, and each index refers to the
+/// list as it is after all of the previous edits have been applied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VecEdit<T> {
+    /// A value was inserted at `index`.
+    Insert { index: usize, value: T },
+
+    /// The value at `index` was removed.
+    Remove { index: usize },
+
+    /// The value at `from` was removed and inserted again at `to`.
+    Move { from: usize, to: usize },
+}
+
+impl<T: Clone> VecEdit<T> {
+    /// Applies the edit to a list. Applying every edit of a diff to the previous version of a
+    /// list produces the next version.
+    pub fn apply_to(&self, list: &mut Vec<T>) {
+        match self {
+            VecEdit::Insert { index, value } => list.insert(*index, value.clone()),
+            VecEdit::Remove { index } => {
+                list.remove(*index);
+            }
+            VecEdit::Move { from, to } => {
+                let value = list.remove(*from);
+                list.insert(*to, value);
+            }
+        }
+    }
+}
+
+/// Where each value of the new list comes from.
+enum Source {
+    Kept(usize),
+    Moved(usize),
+    Inserted,
+}
+
+/// Computes the edits that turn `old` into `new`. Values that keep their relative order are left
+/// in place, which keeps the number of insertions and removals minimal. Removed values that are
+/// inserted again elsewhere are reported as moves.
+///
+/// Runs in O(n * m) time in the size of the changed region of the list (everything between the
+/// common prefix and the common suffix).
+pub(crate) fn diff<T: Clone + PartialEq>(old: &[T], new: &[T]) -> Vec<VecEdit<T>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // Longest common subsequence of the changed region.
+    let mut lengths = vec![vec![0_usize; new_middle.len() + 1]; old_middle.len() + 1];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lengths[i][j] = if old_middle[i] == new_middle[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut sources: Vec<Option<Source>> = (0..new_middle.len()).map(|_| None).collect();
+    let mut is_kept = vec![false; old_middle.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() && j < new_middle.len() {
+        if old_middle[i] == new_middle[j] {
+            sources[j] = Some(Source::Kept(i));
+            is_kept[i] = true;
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    // Pair up values that were removed in one place and inserted in another.
+    let mut is_moved = vec![false; old_middle.len()];
+    for (j, source) in sources.iter_mut().enumerate() {
+        if source.is_some() {
+            continue;
+        }
+        let moved_from = (0..old_middle.len())
+            .find(|&i| !is_kept[i] && !is_moved[i] && old_middle[i] == new_middle[j]);
+        *source = Some(match moved_from {
+            Some(i) => {
+                is_moved[i] = true;
+                Source::Moved(i)
+            }
+            None => Source::Inserted,
+        });
+    }
+
+    let mut edits = vec![];
+
+    // Removals go from the back to the front, so that each index is still valid.
+    for i in (0..old_middle.len()).rev() {
+        if !is_kept[i] && !is_moved[i] {
+            edits.push(VecEdit::Remove { index: prefix + i });
+        }
+    }
+
+    // Then the list is rebuilt from the front to the back. `working` tracks which old value is
+    // at each index of the changed region, and everything before `j` is already in place.
+    let mut working: Vec<Option<usize>> = (0..old_middle.len())
+        .filter(|&i| is_kept[i] || is_moved[i])
+        .map(Some)
+        .collect();
+    for (j, source) in sources.into_iter().enumerate() {
+        let old_index = match source {
+            Some(Source::Kept(i)) | Some(Source::Moved(i)) => i,
+            _ => {
+                edits.push(VecEdit::Insert {
+                    index: prefix + j,
+                    value: new_middle[j].clone(),
+                });
+                working.insert(j, None);
+                continue;
+            }
+        };
+        let from = match working.iter().position(|entry| *entry == Some(old_index)) {
+            Some(from) => from,
+            None => continue,
+        };
+        if from != j {
+            edits.push(VecEdit::Move {
+                from: prefix + from,
+                to: prefix + j,
+            });
+            let entry = working.remove(from);
+            working.insert(j, entry);
+        }
+    }
+    edits
+}
+
+impl<T> dyn ReactiveValue<Vec<T>>
+where
+    T: 'static + Send + Sync + Clone + PartialEq,
+{
+    /// Returns a stream that emits the edits between consecutive values of a ReactiveValue that
+    /// holds a list. This lets list UIs update incrementally, even when the list is stored as a
+    /// plain Vec. Changes that leave the list equal to its previous version are not emitted.
+    ///
+    /// # Examples
+    /// ```
+    /// use epoxy_streams::{ReactiveCache, ReactiveValue, VecEdit};
+    ///
+    /// let list = ReactiveValue::new(vec!["a", "b", "c"]);
+    /// let diffs = ReactiveCache::from_stream(ReactiveValue::diffs(&list));
+    ///
+    /// list.set(vec!["b", "c", "d"]);
+    /// list.set(vec!["c", "b", "d"]);
+    /// assert_eq!(
+    ///     diffs.get_cloned(),
+    ///     vec![
+    ///         vec![
+    ///             VecEdit::Remove { index: 0 },
+    ///             VecEdit::Insert { index: 2, value: "d" },
+    ///         ],
+    ///         vec![VecEdit::Move { from: 1, to: 0 }],
+    ///     ]
+    /// );
+    ///
+    /// let mut replayed = vec!["a", "b", "c"];
+    /// for edit in diffs.get().iter().flat_map(|edits| edits.iter()) {
+    ///     edit.apply_to(&mut replayed);
+    /// }
+    /// assert_eq!(replayed, *list.get());
+    /// ```
+    pub fn diffs(value: &dyn ReactiveValue<Vec<T>>) -> Stream<Vec<VecEdit<T>>> {
+        let previous = Mutex::new(value.get());
+        value
+            .as_stream()
+            .map_rc(move |next| {
+                let mut previous = match previous.lock() {
+                    Ok(previous) => previous,
+                    Err(err) => panic!("Diff mutex poisoned: {}", err),
+                };
+                let edits = diff(&previous, &next);
+                *previous = next;
+                Arc::new(edits)
+            })
+            .filter(|edits| !edits.is_empty())
+    }
+}
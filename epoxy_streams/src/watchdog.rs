@@ -0,0 +1,185 @@
+//! Liveness monitoring for streams that are expected to emit regularly, such as heartbeats or
+//! sensor readings. `monitor` watches a stream and reports when it goes silent and when it comes
+//! back, so applications can raise (and clear) alerts.
+//!
+//! # Examples
+//! ```
+//! use epoxy_streams::watchdog::{self, LivenessEvent};
+//! use epoxy_streams::ReactiveCache;
+//! use std::time::Duration;
+//!
+//! let heartbeats: epoxy_streams::Sink<()> = epoxy_streams::Sink::new();
+//! let liveness = watchdog::monitor(&heartbeats.get_stream(), Duration::from_millis(50));
+//! let events = ReactiveCache::from_stream(liveness.map(|event| match event {
+//!     LivenessEvent::Stalled { .. } => "stalled",
+//!     LivenessEvent::Recovered { .. } => "recovered",
+//! }));
+//!
+//! heartbeats.emit(());
+//! assert!(events.get_cloned().is_empty());
+//!
+//! std::thread::sleep(Duration::from_millis(200));
+//! assert_eq!(events.get_cloned(), vec!["stalled"]);
+//!
+//! heartbeats.emit(());
+//! assert_eq!(events.get_cloned(), vec!["stalled", "recovered"]);
+//! ```
+use super::scheduler::Scheduler;
+use super::{Stream, Subscription};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A change in the liveness of a monitored stream, see `monitor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LivenessEvent {
+    /// The stream has not emitted anything for longer than the expected interval.
+    Stalled {
+        /// How long the stream had been silent when the stall was detected.
+        silent_for: Duration,
+    },
+
+    /// The stream emitted a value after it had stalled.
+    Recovered {
+        /// How long the stream was silent in total.
+        silent_for: Duration,
+    },
+}
+
+struct WatchdogFields<T> {
+    last_value_at: Instant,
+    is_stalled: bool,
+    is_check_scheduled: bool,
+    is_complete: bool,
+
+    #[allow(dead_code)]
+    subscription: Option<Subscription<T>>,
+}
+
+/// Returns a stream of `LivenessEvent`s for `stream`. A `Stalled` event is emitted once the
+/// stream has gone `expected_interval` without emitting a value (counting from when `monitor` was
+/// called), and a `Recovered` event is emitted with the next value after that. The events
+/// alternate, so a stream that stays silent only produces one `Stalled` event.
+///
+/// The silence is measured with the stream's scheduler (see `Stream::with_scheduler`). Only one
+/// timer is pending at a time, no matter how often the stream emits. When the monitored stream
+/// completes, the liveness stream completes too.
+pub fn monitor<T>(stream: &Stream<T>, expected_interval: Duration) -> Stream<LivenessEvent>
+where
+    T: Send,
+    T: Sync,
+    T: 'static,
+{
+    let scheduler = stream.scheduler();
+    let derived_stream: Stream<LivenessEvent> = stream.derive_with_fields(WatchdogFields::<T> {
+        last_value_at: scheduler.now(),
+        is_stalled: false,
+        is_check_scheduled: true,
+        is_complete: false,
+        subscription: None,
+    });
+    let weak_stream_ref = Arc::downgrade(&derived_stream.pointer);
+    let completion_stream_ref = derived_stream.clone();
+
+    let subscription_scheduler = scheduler.clone();
+    let subscription = stream.subscribe_with_completion(
+        move |_| {
+            let stream_ref = match weak_stream_ref.upgrade() {
+                Some(pointer) => Stream { pointer },
+                None => return,
+            };
+
+            let now = subscription_scheduler.now();
+            let mut recovered_after = None;
+            let mut needs_check = false;
+            stream_ref.mutate_extra_fields(|fields: &mut WatchdogFields<T>| {
+                if fields.is_stalled {
+                    fields.is_stalled = false;
+                    recovered_after = Some(now - fields.last_value_at);
+                }
+                fields.last_value_at = now;
+                needs_check = !fields.is_check_scheduled;
+                fields.is_check_scheduled = true;
+            });
+
+            if needs_check {
+                schedule_check::<T>(
+                    &subscription_scheduler,
+                    &stream_ref,
+                    expected_interval,
+                    expected_interval,
+                );
+            }
+            if let Some(silent_for) = recovered_after {
+                stream_ref.emit_rc(Arc::new(LivenessEvent::Recovered { silent_for }));
+            }
+        },
+        move || {
+            completion_stream_ref.mutate_extra_fields(|fields: &mut WatchdogFields<T>| {
+                fields.is_complete = true;
+            });
+            completion_stream_ref.complete();
+        },
+    );
+
+    derived_stream.mutate_extra_fields(move |fields: &mut WatchdogFields<T>| {
+        fields.subscription = Some(subscription);
+    });
+
+    schedule_check::<T>(
+        &scheduler,
+        &derived_stream,
+        expected_interval,
+        expected_interval,
+    );
+    derived_stream
+}
+
+fn schedule_check<T>(
+    scheduler: &Arc<dyn Scheduler>,
+    stream: &Stream<LivenessEvent>,
+    delay: Duration,
+    expected_interval: Duration,
+) where
+    T: Send,
+    T: Sync,
+    T: 'static,
+{
+    let check_scheduler = scheduler.clone();
+    let weak_stream_ref = Arc::downgrade(&stream.pointer);
+    scheduler.schedule_after(
+        delay,
+        Box::new(move || {
+            let stream_ref = match weak_stream_ref.upgrade() {
+                Some(pointer) => Stream { pointer },
+                None => return,
+            };
+
+            // The stream may have emitted since this check was scheduled, in which case the next
+            // check is pushed back to a full interval after the latest value.
+            let now = check_scheduler.now();
+            let mut stalled_for = None;
+            let mut next_check = None;
+            stream_ref.mutate_extra_fields(|fields: &mut WatchdogFields<T>| {
+                if fields.is_complete {
+                    fields.is_check_scheduled = false;
+                    return;
+                }
+                let silent_for = now - fields.last_value_at;
+                if silent_for >= expected_interval {
+                    fields.is_stalled = true;
+                    fields.is_check_scheduled = false;
+                    stalled_for = Some(silent_for);
+                } else {
+                    next_check = Some(expected_interval - silent_for);
+                }
+            });
+
+            if let Some(delay) = next_check {
+                schedule_check::<T>(&check_scheduler, &stream_ref, delay, expected_interval);
+            }
+            if let Some(silent_for) = stalled_for {
+                stream_ref.emit_rc(Arc::new(LivenessEvent::Stalled { silent_for }));
+            }
+        }),
+    );
+}
@@ -1,3 +1,5 @@
+#![allow(bare_trait_objects)]
+
 #[macro_use]
 extern crate epoxy;
 
@@ -30,12 +30,22 @@
 //! |--------------------|------------------------------------------------------------------------|
 //! | map(fn)            | Runs all values from the input stream through a mapper function        |
 //! | map_rc(fn)         | Same as map() but the mapper function takes and returns an Arc          |
+//! | map_values(fn)     | Same as map() but the mapper function takes an owned clone of the value|
 //! | flat_map(fn)       | Similar to map() but iterates through the result of the mapper function|
 //! | filter(fn)         | Returns only input values that pass the given filter function          |
+//! | ok_values()        | Unwraps the Ok values of a stream of Results, skipping errors          |
+//! | err_values()       | Unwraps the Err values of a stream of Results, skipping Ok values      |
+//! | some_values()      | Unwraps the Some values of a stream of Options, skipping None          |
 //! | inspect(method)    | Passes through the original stream, calls a method for each item       |
 //! | scan(fn, default)  | Similar to reduce(), but returns the value after each iteration        |
 //! | count_values()     | Returns the number of times the stream has emitted                     |
 //! | buffer(size)       | Collects emitted values into vectors of length `size`                  |
+//! | debounce(duration) | Emits the latest value once the input has been quiet for `duration`    |
+//! | debounce_by_key    | Same as debounce(), but with a separate timer for each key             |
+//! | audit(duration)    | Emits the latest value at the end of each `duration` long window       |
+//! | rate_limit(...)    | Limits the input to a sustained rate using a bursty token bucket       |
+//! | sample_on(stream)  | Emits the latest input value each time another stream emits            |
+//! | concat(stream)     | Emits the input values, then the values of `stream` once input ends    |
 //! 
 //! ReactiveValues have their own set of operators, although it is also possible to get a reference
 //! to the underlying stream of a ReactiveValue with `.as_stream()` and use any of the above
@@ -46,6 +56,8 @@
 //! | map(fn)               | Runs all values from the input stream through a mapper function     |
 //! | sanitize(fn, default) | Does not change the value if the input does not pass a test fn      |
 //! | fallback(fn, fallback)| Changes the value to `fallback` if the input does not pass a test fn|
+//! | filter(fn)            | Contains Some(value) if the input passes a test fn, otherwise None  |
+//! | debounce(duration)    | Only updates once the input has stopped changing for `duration`     |
 //! 
 //! However, this library also ships with a `computed!` macro that makes dealing with ReactiveValue
 //! just as easy as dealing with any other Rust variable.
@@ -64,6 +76,89 @@
 //! assert_eq!(*score.get(), 8);
 //! ```
 //! 
+//! Values that should be read without becoming a dependency of the computed value can be
+//! accessed with `get_untracked()`. The computed value will not update when they change.
+//!
+//! ```
+//! # #[macro_use] extern crate epoxy;
+//! use epoxy::ReactiveValue;
+//!
+//! let points = epoxy::ReactiveValue::new(4);
+//! let multiplier = epoxy::ReactiveValue::new(1);
+//! let score = computed!(points * *multiplier.get_untracked());
+//!
+//! multiplier.set(2);
+//! assert_eq!(*score.get(), 4);
+//!
+//! points.set(5);
+//! assert_eq!(*score.get(), 10);
+//! ```
+//!
+//! Every variable that a computed expression uses from its surroundings is treated as a
+//! ReactiveValue. Plain values, like configuration, can be listed in square brackets at the start
+//! of the expression. They are cloned into the computed value as they are, and do not become
+//! dependencies.
+//!
+//! ```
+//! # #[macro_use] extern crate epoxy;
+//! use epoxy::ReactiveValue;
+//!
+//! #[derive(Clone)]
+//! struct Config {
+//!     bonus: i32,
+//! }
+//!
+//! let config = Config { bonus: 10 };
+//! let points = epoxy::ReactiveValue::new(4);
+//! let score = computed!([config] points + config.bonus);
+//! assert_eq!(*score.get(), 14);
+//!
+//! points.set(5);
+//! assert_eq!(*score.get(), 15);
+//! ```
+//!
+//! Computed expressions can also be fallible. The `?` operator works inside them, producing a
+//! ReactiveValue that holds a `Result`. ReactiveValues of Results have their own operators:
+//! `map_ok`, `and_then`, `last_ok_or` and `error_transitions`.
+//!
+//! ```
+//! # #[macro_use] extern crate epoxy;
+//! use epoxy::ReactiveValue;
+//! use std::num::ParseIntError;
+//!
+//! let input = epoxy::ReactiveValue::new("4".to_string());
+//! let doubled = computed!({
+//!     let number = input.trim().parse::<i32>()?;
+//!     Ok::<_, ParseIntError>(number * 2)
+//! });
+//! let label = ReactiveValue::map_ok(&doubled, |number| format!("Doubled: {}", number));
+//! assert_eq!(*label.get(), Ok("Doubled: 8".to_string()));
+//!
+//! input.set("four".to_string());
+//! assert!(label.get().is_err());
+//! ```
+//!
+//! Computed values keep statistics about themselves, which helps to find expensive ones.
+//!
+//! ```
+//! # #[macro_use] extern crate epoxy;
+//! use epoxy::ReactiveValue;
+//!
+//! let points = epoxy::ReactiveValue::new(4);
+//! let multiplier = epoxy::ReactiveValue::new(1);
+//! let score = computed!(points * multiplier);
+//!
+//! multiplier.set(2);
+//! let stats = score.stats().unwrap();
+//! assert_eq!(stats.recompute_count, 1);
+//! assert_eq!(stats.dependencies.len(), 2);
+//! ```
+//!
+//! Computed expressions should not set ReactiveValues themselves (for example through a helper
+//! function), since setting one of their own dependencies would make them recompute forever. By
+//! default such writes panic with a `ReentrantWriteError`, see
+//! `config::set_reentrant_write_policy` for how to defer them instead.
+//!
 //! ## Comparisons to other FRP Libraries
 //! 
 //! ### Carboxyl / Frappe
@@ -89,11 +184,15 @@
 //! ### ReactiveX
 //! 
 //! These streams are intended to be substantially simpler than those in the ReactiveX family of
-//! libraries. The most significant difference is that this library has no concept of a 'cold'
-//! stream, meaning no streams will ever emit a value immediately upon subscription. Streams
-//! in this library also never close, as they are intended to model long-term asynchronous data
-//! flows (of course it is possible to make a stream 'closeable' by making a stream of Option
-//! enums and unsubscribing on `None`, that just isn't built in to the library). Finally, where
+//! libraries. The most significant difference is that streams are 'hot' by default: a new
+//! subscriber only receives the values emitted after it subscribed. The exceptions are the
+//! streams that exist to replay values, which emit to a subscriber as soon as it subscribes:
+//! `Stream::from_values` and `Stream::once`, `Stream::compact_latest` (the latest value for
+//! each key), and `Journal::subscribe_from` (the values recorded in a journal). Other than
+//! `from_values` and `once`, which complete right away, streams only close when their Sink is
+//! closed with `Sink::close` or dropped, as they are intended to model long-term asynchronous
+//! data flows. Completion can be observed with `Stream::subscribe_with_completion` or
+//! `Stream::on_complete`. Finally, where
 //! Rx subscriptions live until explicitly unsubscribed, Rust Reactive subscriptions only live
 //! as long as they are in scope.
 //! 
@@ -106,13 +205,171 @@ extern crate epoxy_streams;
 
 use proc_macro_hack::proc_macro_hack;
 
+#[cfg(feature = "timers")]
+pub use epoxy_streams::Ack;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::AckConsumer;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::AckQueue;
+pub use epoxy_streams::ArcPool;
+#[cfg(feature = "std")]
+pub use epoxy_streams::BidirectionalPipe;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::BreakerState;
+pub use epoxy_streams::CancellationToken;
+#[cfg(feature = "checkpoint")]
+pub use epoxy_streams::CheckpointError;
+pub use epoxy_streams::ChunkBoundary;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::CircuitBreaker;
+#[cfg(feature = "std")]
+pub use epoxy_streams::ComputedDependency;
+#[cfg(feature = "std")]
+pub use epoxy_streams::ComputedStats;
+pub use epoxy_streams::ConnectableStream;
+pub use epoxy_streams::Connection;
+#[cfg(feature = "std")]
+pub use epoxy_streams::ConsistentRead;
+pub use epoxy_streams::CountedBroadcast;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Delivery;
+#[cfg(feature = "std")]
+pub use epoxy_streams::DeliveryMode;
+pub use epoxy_streams::DeliveryReport;
+pub use epoxy_streams::EmitNext;
+pub use epoxy_streams::EmitPermit;
+pub use epoxy_streams::EventTimeWindow;
+pub use epoxy_streams::EventTimeWindows;
+pub use epoxy_streams::EventTimed;
+pub use epoxy_streams::FinishingSink;
+pub use epoxy_streams::FinishingStream;
+pub use epoxy_streams::FromStream;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Interner;
+#[cfg(feature = "std")]
+pub use epoxy_streams::InternerStats;
+pub use epoxy_streams::IntoStream;
+#[cfg(feature = "std")]
+pub use epoxy_streams::InvalidTransition;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Mailbox;
+pub use epoxy_streams::MembershipChange;
+pub use epoxy_streams::MailboxError;
+pub use epoxy_streams::Notification;
+pub use epoxy_streams::Observable;
+pub use epoxy_streams::OrderViolation;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::RateLimitOverflow;
+#[cfg(feature = "std")]
 pub use epoxy_streams::ReactiveValue;
+pub use epoxy_streams::Recycle;
+#[cfg(feature = "std")]
+pub use epoxy_streams::ReactiveValueReadGuard;
+#[cfg(feature = "std")]
 pub use epoxy_streams::ReadonlyReactiveValue;
+pub use epoxy_streams::ReentrantWriteError;
+#[cfg(feature = "std")]
+pub use epoxy_streams::PendingResponse;
+pub use epoxy_streams::PermitError;
+pub use epoxy_streams::PermitRevoker;
+pub use epoxy_streams::Pipeline;
+pub use epoxy_streams::PipelineBuilder;
+#[cfg(feature = "pipeline_config")]
+pub use epoxy_streams::PipelineConfigError;
+pub use epoxy_streams::PipelineStep;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Request;
+pub use epoxy_streams::RequestError;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Requester;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Responder;
+pub use epoxy_streams::RetainedPayload;
+pub use epoxy_streams::RetentionReport;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::RetryPolicy;
+#[cfg(feature = "std")]
+pub use epoxy_streams::RouterHandle;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Selector;
+pub use epoxy_streams::Sequenced;
+pub use epoxy_streams::SetError;
 pub use epoxy_streams::Stream;
+pub use epoxy_streams::StreamClosed;
+#[cfg(feature = "std")]
+pub use epoxy_streams::ShardedSink;
 pub use epoxy_streams::Sink;
+pub use epoxy_streams::SinkProducer;
+#[cfg(feature = "std")]
+pub use epoxy_streams::SpawnOptions;
+#[cfg(feature = "std")]
+pub use epoxy_streams::StateMachine;
+pub use epoxy_streams::SubscriberGroup;
 pub use epoxy_streams::Subscription;
+#[cfg(feature = "std")]
+pub use epoxy_streams::TaskGroup;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::ThresholdAlert;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::ThresholdBound;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::ThresholdConfig;
+#[cfg(feature = "std")]
+pub use epoxy_streams::Timestamped;
+#[cfg(feature = "std")]
+pub use epoxy_streams::ValidatedReactiveValue;
+#[cfg(feature = "std")]
+pub use epoxy_streams::ValueHistory;
+pub use epoxy_streams::ValuePoisoned;
+#[cfg(feature = "std")]
+pub use epoxy_streams::VecEdit;
+#[cfg(feature = "std")]
 pub use epoxy_streams::WriteableReactiveValue;
 
+#[cfg(feature = "bevy")]
+pub use epoxy_streams::bevy;
+pub use epoxy_streams::bus;
+#[cfg(feature = "checkpoint")]
+pub use epoxy_streams::checkpoint;
+#[cfg(feature = "std")]
+pub use epoxy_streams::clock;
+#[cfg(feature = "std")]
+pub use epoxy_streams::config;
+pub use epoxy_streams::dead_letters;
+#[cfg(feature = "ffi")]
+pub use epoxy_streams::epoxy_ffi;
+#[cfg(all(feature = "ipc", unix))]
+pub use epoxy_streams::ipc;
+#[cfg(feature = "journal")]
+pub use epoxy_streams::journal;
+#[cfg(feature = "std")]
+pub use epoxy_streams::pipe_into_bidirectional;
+pub use epoxy_streams::pipeline;
+#[cfg(feature = "pipeline_config")]
+pub use epoxy_streams::pipeline_config;
+#[cfg(feature = "std")]
+pub use epoxy_streams::read_consistent;
+#[cfg(any(feature = "csv", feature = "parquet"))]
+pub use epoxy_streams::recording;
+#[cfg(feature = "std")]
+pub use epoxy_streams::request_channel;
+#[cfg(feature = "std")]
+pub use epoxy_streams::runtime;
+#[cfg(feature = "std")]
+pub use epoxy_streams::scheduler;
+#[cfg(feature = "std")]
+pub use epoxy_streams::scope;
+#[cfg(feature = "std")]
+pub use epoxy_streams::scope::scope;
+#[cfg(feature = "std")]
+pub use epoxy_streams::selector;
+#[cfg(feature = "std")]
+pub use epoxy_streams::store;
+#[cfg(feature = "std")]
+pub use epoxy_streams::transaction;
+#[cfg(feature = "timers")]
+pub use epoxy_streams::watchdog;
+
 /// Add one to an expression.
 #[proc_macro_hack]
 pub use epoxy_macros::computed;